@@ -0,0 +1,41 @@
+//! Decode an APE file to WAV.
+//!
+//! Usage: `ape2wav [--canonical] <input.ape> [output.wav]`
+//!
+//! Restores the original file byte-for-byte when the APE file stores its
+//! WAV header; otherwise, or with `--canonical`, writes a standard 44-byte
+//! header instead.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use ape_rs::ApeReader;
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let canonical = args.iter().any(|a| a == "--canonical");
+    args.retain(|a| a != "--canonical");
+    let (input, output) = match args.as_slice() {
+        [input] => (PathBuf::from(input), PathBuf::from(input).with_extension("wav")),
+        [input, output] => (PathBuf::from(input), PathBuf::from(output)),
+        _ => {
+            eprintln!("usage: ape2wav [--canonical] <input.ape> [output.wav]");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = ApeReader::open(&input).and_then(|mut reader| {
+        if canonical || reader.header().header_data_range().is_none() {
+            reader.save_wav(&output)
+        } else {
+            reader.restore_original(&output)
+        }
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {e}", input.display());
+            ExitCode::FAILURE
+        }
+    }
+}
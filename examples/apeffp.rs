@@ -0,0 +1,57 @@
+//! Write or check audio checksum sidecars for APE files.
+//!
+//! Usage: `apeffp [--md5] <file.ape>...` prints an `.ffp` (or, with
+//! `--md5`, an `md5sum`-style) listing of the files' audio MD5s;
+//! `apeffp --verify <sidecar>` checks the files a sidecar lists.
+
+use std::process::ExitCode;
+
+use ape_rs::sidecar::{self, CheckResult, SidecarFormat};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [flag, path] if flag == "--verify" => verify(path),
+        [flag, files @ ..] if flag == "--md5" && !files.is_empty() => generate(files, SidecarFormat::Md5),
+        [first, ..] if !first.starts_with("--") => generate(&args, SidecarFormat::Ffp),
+        _ => {
+            eprintln!("usage: apeffp [--md5] <file.ape>... | apeffp --verify <sidecar>");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn generate(files: &[String], format: SidecarFormat) -> ExitCode {
+    let result = sidecar::generate(files).and_then(|entries| sidecar::write(&entries, format, std::io::stdout().lock()));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn verify(path: &str) -> ExitCode {
+    let checks = match sidecar::verify(path) {
+        Ok(checks) => checks,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = 0;
+    for check in &checks {
+        match &check.result {
+            CheckResult::Ok => println!("{}: OK", check.name),
+            CheckResult::Mismatch { .. } => println!("{}: MISMATCH", check.name),
+            CheckResult::Failed(e) => println!("{}: FAILED ({e})", check.name),
+        }
+        failed += usize::from(!check.is_ok());
+    }
+    if failed > 0 {
+        eprintln!("{failed} of {} files failed", checks.len());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,42 @@
+//! Remove ID3v2, APEv2 and ID3v1 tags from APE files, in place.
+//!
+//! Usage: `apestrip [--dry-run] <file.ape>...`. With `--dry-run`, only
+//! reports what would be removed.
+
+use std::fs::File;
+use std::process::ExitCode;
+
+use ape_rs::strip;
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    args.retain(|a| a != "--dry-run");
+    if args.is_empty() {
+        eprintln!("usage: apestrip [--dry-run] <file.ape>...");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    for path in &args {
+        let result = if dry_run {
+            File::open(path).map_err(Into::into).and_then(strip::inspect)
+        } else {
+            strip::strip_tags(path)
+        };
+        match result {
+            Ok(summary) => {
+                let verb = if dry_run { "would remove" } else { "removed" };
+                println!("{path}: {verb} {} bytes of tags", summary.removed());
+                if summary.unaccounted > 0 {
+                    eprintln!("{path}: kept {} unaccounted bytes before the tags", summary.unaccounted);
+                }
+            }
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                failed = true;
+            }
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
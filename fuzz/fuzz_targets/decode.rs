@@ -0,0 +1,24 @@
+//! Full decode of raw bytes, in both strict and lenient modes.
+
+#![no_main]
+
+use ape_rs::{ApeReaderBuilder, FrameErrorMode};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+/// Upper bound on samples pulled per input, to keep iterations fast.
+const MAX_SAMPLES: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    for mode in [FrameErrorMode::Strict, FrameErrorMode::Silence] {
+        let Ok(mut reader) = ApeReaderBuilder::new().frame_error_mode(mode).build(Cursor::new(data))
+        else {
+            return;
+        };
+        for sample in reader.samples().take(MAX_SAMPLES) {
+            if sample.is_err() {
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,28 @@
+//! Full decode of a structurally generated file, so mutations land in the
+//! header fields, seek table and frame data rather than the magic.
+
+#![no_main]
+
+use ape_rs::fuzzing::FuzzFile;
+use ape_rs::{ApeReaderBuilder, FrameErrorMode};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+/// Upper bound on samples pulled per input, to keep iterations fast.
+const MAX_SAMPLES: usize = 1 << 20;
+
+fuzz_target!(|file: FuzzFile| {
+    let bytes = file.to_bytes();
+    let Ok(mut reader) = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Silence)
+        .recover_frame_layout(true)
+        .build(Cursor::new(bytes))
+    else {
+        return;
+    };
+    for sample in reader.samples().take(MAX_SAMPLES) {
+        if sample.is_err() {
+            break;
+        }
+    }
+});
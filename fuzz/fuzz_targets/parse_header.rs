@@ -0,0 +1,12 @@
+//! Header parsing only: descriptor, header, seek table and layout checks.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(reader) = ape_rs::ApeReader::new(Cursor::new(data)) {
+        let _ = reader.info();
+    }
+});
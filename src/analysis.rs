@@ -0,0 +1,109 @@
+//! Level analysis over decoded audio: per-channel peak, RMS and DC offset.
+//!
+//! Levels are reported relative to digital full scale, 2^(bits - 1), so
+//! 1.0 is the largest magnitude the bit depth can represent. The running
+//! sums are exact integers, so the result does not depend on how the
+//! stream is split into frames.
+
+/// Levels of one channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelLevels {
+    /// Largest absolute sample value.
+    pub peak: u32,
+    /// `peak` relative to full scale.
+    pub peak_level: f64,
+    /// Root mean square relative to full scale.
+    pub rms: f64,
+    /// Mean sample value relative to full scale.
+    pub dc_offset: f64,
+}
+
+impl ChannelLevels {
+    /// Peak in dBFS (negative infinity for digital silence).
+    pub fn peak_dbfs(&self) -> f64 {
+        20.0 * self.peak_level.log10()
+    }
+
+    /// RMS in dBFS (negative infinity for digital silence).
+    pub fn rms_dbfs(&self) -> f64 {
+        20.0 * self.rms.log10()
+    }
+}
+
+/// Result of [`ApeReader::analyze`](crate::ApeReader::analyze).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Analysis {
+    /// Levels per channel, in stream order.
+    pub channels: Vec<ChannelLevels>,
+    /// Number of blocks (samples per channel) analyzed.
+    pub blocks: u64,
+}
+
+/// Single-pass accumulator behind [`Analysis`].
+///
+/// Feed it interleaved samples in any chunking, then call `finish`.
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    bits_per_sample: u16,
+    peaks: Vec<u32>,
+    sums: Vec<i64>,
+    sums_of_squares: Vec<u128>,
+    samples: u64,
+}
+
+impl Analyzer {
+    /// Create an analyzer for interleaved audio with the given layout.
+    pub fn new(channels: u16, bits_per_sample: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Analyzer {
+            bits_per_sample,
+            peaks: vec![0; channels],
+            sums: vec![0; channels],
+            sums_of_squares: vec![0; channels],
+            samples: 0,
+        }
+    }
+
+    /// Add interleaved samples. A chunk may end mid-block; the next one
+    /// continues with the following channel.
+    pub fn update(&mut self, samples: &[i32]) {
+        let channels = self.peaks.len();
+        for &s in samples {
+            let ch = (self.samples % channels as u64) as usize;
+            self.peaks[ch] = self.peaks[ch].max(s.unsigned_abs());
+            self.sums[ch] += s as i64;
+            self.sums_of_squares[ch] += (s as i64 * s as i64) as u128;
+            self.samples += 1;
+        }
+    }
+
+    /// Levels of everything fed so far.
+    pub fn finish(&self) -> Analysis {
+        let channels = self.peaks.len();
+        let full_scale = crate::convert::full_scale(self.bits_per_sample);
+        let per_channel = |ch: usize| {
+            // Channels before the partial block's cut have one extra sample
+            let n = self.samples / channels as u64 + u64::from((ch as u64) < self.samples % channels as u64);
+            if n == 0 {
+                return ChannelLevels {
+                    peak: 0,
+                    peak_level: 0.0,
+                    rms: 0.0,
+                    dc_offset: 0.0,
+                };
+            }
+            ChannelLevels {
+                peak: self.peaks[ch],
+                peak_level: self.peaks[ch] as f64 / full_scale,
+                rms: (self.sums_of_squares[ch] as f64 / n as f64).sqrt() / full_scale,
+                dc_offset: self.sums[ch] as f64 / n as f64 / full_scale,
+            }
+        };
+        Analysis {
+            channels: (0..channels).map(per_channel).collect(),
+            blocks: self.samples / channels as u64,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Monkey's Audio link files (`.apl`).
+//!
+//! An APL file names an APE image file and a block range within it, so one
+//! track of a single-file album can be handled as a file of its own:
+//!
+//! ```text
+//! [Monkey's Audio Image Link File]
+//! Image File=album.ape
+//! Start Block=1234800
+//! Finish Block=2469600
+//! ----- APE TAG (DO NOT TOUCH!!!) -----
+//! ```
+//!
+//! The finish block is exclusive. A relative image path is resolved from
+//! the directory holding the link file. The APEv2 tag after the marker line
+//! describes the track, not the audio, and is not read here.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{BufReader, Read, Seek};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use crate::error::ApeError;
+#[cfg(feature = "std")]
+use crate::cue::TrackSamples;
+#[cfg(feature = "std")]
+use crate::{ApeInfo, ApeReader};
+
+/// First line of every link file.
+const SIGNATURE: &str = "[Monkey's Audio Image Link File]";
+
+/// Start of the tag section that follows the link fields.
+const TAG_MARKER: &[u8] = b"----- APE TAG";
+
+/// The contents of an APL link file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApeLink {
+    /// Image file path, as written in the link file.
+    pub image_file: String,
+    /// First block of the linked region.
+    pub start_block: u64,
+    /// Block after the last one of the region.
+    pub finish_block: u64,
+}
+
+impl ApeLink {
+    /// Parse the bytes of a link file.
+    ///
+    /// Fails with [`ErrorKind::InvalidLink`](crate::ErrorKind::InvalidLink)
+    /// if the signature or a field is missing, or the block range is empty.
+    pub fn parse(data: &[u8]) -> Result<Self, ApeError> {
+        let end = data
+            .windows(TAG_MARKER.len())
+            .position(|w| w == TAG_MARKER)
+            .unwrap_or(data.len());
+        let text = String::from_utf8_lossy(&data[..end]);
+        let mut lines = text.lines().map(|l| l.trim().trim_start_matches('\u{feff}'));
+        if lines.next() != Some(SIGNATURE) {
+            return Err(ApeError::InvalidLink("not a Monkey's Audio link file".into()));
+        }
+
+        let (mut image_file, mut start_block, mut finish_block) = (None, None, None);
+        for line in lines {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let value = value.trim();
+            let block = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| ApeError::InvalidLink(format!("bad {}: {value:?}", key.trim())))
+            };
+            match key.trim() {
+                "Image File" => image_file = Some(value.to_string()),
+                "Start Block" => start_block = Some(block()?),
+                "Finish Block" => finish_block = Some(block()?),
+                _ => {}
+            }
+        }
+
+        let missing = |field: &str| ApeError::InvalidLink(format!("no {field}"));
+        let link = ApeLink {
+            image_file: image_file.filter(|f| !f.is_empty()).ok_or_else(|| missing("Image File"))?,
+            start_block: start_block.ok_or_else(|| missing("Start Block"))?,
+            finish_block: finish_block.ok_or_else(|| missing("Finish Block"))?,
+        };
+        if link.finish_block <= link.start_block {
+            return Err(ApeError::InvalidLink(format!(
+                "empty block range {}..{}",
+                link.start_block, link.finish_block
+            )));
+        }
+        Ok(link)
+    }
+
+    /// The image file's path, resolved against the directory of the link
+    /// file at `link_path`.
+    #[cfg(feature = "std")]
+    pub fn image_path(&self, link_path: &Path) -> PathBuf {
+        // Link files written on Windows use backslashes
+        let image = self.image_file.replace('\\', "/");
+        match link_path.parent() {
+            Some(dir) => dir.join(image),
+            None => PathBuf::from(image),
+        }
+    }
+}
+
+/// Reader over the region of an image file that a link file points to.
+///
+/// ```no_run
+/// use ape_rs::ApeReader;
+///
+/// let mut track = ApeReader::open_apl("03 - Track.apl").unwrap();
+/// println!("{} samples", track.info().total_samples);
+/// let samples: Vec<i32> = track.samples().collect::<Result<_, _>>().unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub struct ApeLinkReader<R: Read + Seek> {
+    reader: ApeReader<R>,
+    link: ApeLink,
+    /// Image format; `total_samples` covers only the region.
+    info: ApeInfo,
+    /// Interleaved samples of the region not yet returned.
+    remaining: u64,
+}
+
+#[cfg(feature = "std")]
+impl ApeLinkReader<BufReader<File>> {
+    /// Read the link file at `path` and open the image it names.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ApeError> {
+        let path = path.as_ref();
+        let link = ApeLink::parse(&std::fs::read(path)?)?;
+        let reader = ApeReader::open(link.image_path(path))?;
+        Self::new(reader, link)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeLinkReader<R> {
+    /// Restrict `reader`, the image file, to the region of `link`, and seek
+    /// to its start.
+    ///
+    /// The region ends at the end of the stream if the link runs past it;
+    /// a region starting past the end fails with
+    /// [`ErrorKind::InvalidLink`](crate::ErrorKind::InvalidLink).
+    pub fn new(mut reader: ApeReader<R>, link: ApeLink) -> Result<Self, ApeError> {
+        let total_blocks = reader.header().total_blocks();
+        if link.start_block >= total_blocks {
+            return Err(ApeError::InvalidLink(format!(
+                "region starts at block {}, the image has {total_blocks}",
+                link.start_block
+            )));
+        }
+        let blocks = link.finish_block.min(total_blocks) - link.start_block;
+        let info = ApeInfo {
+            total_samples: blocks * reader.info().channels as u64,
+            ..reader.info().clone()
+        };
+        reader.seek_to_block(link.start_block)?;
+        Ok(ApeLinkReader {
+            reader,
+            link,
+            remaining: info.total_samples,
+            info,
+        })
+    }
+
+    /// The link file's contents.
+    pub fn link(&self) -> &ApeLink {
+        &self.link
+    }
+
+    /// Image format, with `total_samples` counting only the linked region.
+    pub fn info(&self) -> &ApeInfo {
+        &self.info
+    }
+
+    /// Iterator over the region's remaining samples, interleaved as
+    /// [`ApeReader::samples`].
+    pub fn samples(&mut self) -> TrackSamples<'_, R> {
+        TrackSamples {
+            reader: &mut self.reader,
+            remaining: &mut self.remaining,
+        }
+    }
+
+    /// The reader over the whole image.
+    pub fn into_inner(self) -> ApeReader<R> {
+        self.reader
+    }
+}
@@ -0,0 +1,78 @@
+//! Wrapping integer arithmetic for the predictor and NNFilter, optionally
+//! checked.
+//!
+//! The reference decoder's arithmetic wraps on overflow, and ours must wrap
+//! the same way to stay bit-exact. A valid stream should never actually
+//! overflow, though, so an overflow points at a decoder bug or a format
+//! variant we do not model. With the `strict-math` feature every operation
+//! is also checked: the first overflow is recorded (the result still wraps,
+//! so output is unchanged) and the packet decoder fails the frame with
+//! [`ApeError::ArithmeticOverflow`](crate::ApeError::ArithmeticOverflow) at
+//! the block where it happened. Without the feature the methods compile to
+//! the plain wrapping operations.
+
+/// Wrapping add, subtract and multiply, recording overflows under
+/// `strict-math`.
+pub(crate) trait Wrap: Sized {
+    fn wrap_add(self, rhs: Self) -> Self;
+    fn wrap_sub(self, rhs: Self) -> Self;
+    fn wrap_mul(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_wrap {
+    ($($t:ty),*) => {$(
+        impl Wrap for $t {
+            #[inline(always)]
+            fn wrap_add(self, rhs: Self) -> Self {
+                #[cfg(feature = "strict-math")]
+                if self.checked_add(rhs).is_none() {
+                    record(concat!(stringify!($t), " addition"));
+                }
+                self.wrapping_add(rhs)
+            }
+
+            #[inline(always)]
+            fn wrap_sub(self, rhs: Self) -> Self {
+                #[cfg(feature = "strict-math")]
+                if self.checked_sub(rhs).is_none() {
+                    record(concat!(stringify!($t), " subtraction"));
+                }
+                self.wrapping_sub(rhs)
+            }
+
+            #[inline(always)]
+            fn wrap_mul(self, rhs: Self) -> Self {
+                #[cfg(feature = "strict-math")]
+                if self.checked_mul(rhs).is_none() {
+                    record(concat!(stringify!($t), " multiplication"));
+                }
+                self.wrapping_mul(rhs)
+            }
+        }
+    )*};
+}
+
+impl_wrap!(i16, i32, i64);
+
+#[cfg(feature = "strict-math")]
+std::thread_local! {
+    /// The first overflow since the last `take_overflow` on this thread.
+    static OVERFLOW: core::cell::Cell<Option<&'static str>> = const { core::cell::Cell::new(None) };
+}
+
+#[cfg(feature = "strict-math")]
+#[cold]
+fn record(operation: &'static str) {
+    OVERFLOW.with(|o| {
+        if o.get().is_none() {
+            o.set(Some(operation));
+        }
+    });
+}
+
+/// The operation that overflowed first since the last call, if any, and
+/// clear it.
+#[cfg(feature = "strict-math")]
+pub(crate) fn take_overflow() -> Option<&'static str> {
+    OVERFLOW.with(|o| o.take())
+}
@@ -2,6 +2,9 @@
 //!
 //! Handles frame-boundary buffering and stereo interleaving.
 
+use alloc::vec::Vec;
+use core::fmt;
+
 /// Buffer that accumulates decoded samples from a frame and yields them
 /// one at a time through the iterator interface.
 pub struct SampleBuffer {
@@ -11,6 +14,17 @@ pub struct SampleBuffer {
     pos: usize,
 }
 
+impl fmt::Debug for SampleBuffer {
+    /// Sizes only, not the samples.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampleBuffer")
+            .field("len", &self.samples.len())
+            .field("pos", &self.pos)
+            .field("capacity", &self.samples.capacity())
+            .finish()
+    }
+}
+
 impl SampleBuffer {
     pub fn new() -> Self {
         SampleBuffer {
@@ -19,6 +33,12 @@ impl SampleBuffer {
         }
     }
 
+    /// Append to `samples`, which count as already consumed.
+    pub fn from_vec(samples: Vec<i32>) -> Self {
+        let pos = samples.len();
+        SampleBuffer { samples, pos }
+    }
+
     /// Push a mono sample.
     pub fn push(&mut self, sample: i32) {
         self.samples.push(sample);
@@ -30,6 +50,50 @@ impl SampleBuffer {
         self.samples.push(right);
     }
 
+    /// All samples held, including already-consumed ones, in the buffer's
+    /// own allocation: with [`from_vec`](Self::from_vec) this lends a
+    /// caller's `Vec` to the decoder and back without copying.
+    pub fn into_vec(self) -> Vec<i32> {
+        self.samples
+    }
+
+    /// All samples currently held, including already-consumed ones.
+    pub fn as_slice(&self) -> &[i32] {
+        &self.samples
+    }
+}
+
+/// Reading and reuse, for the streaming decoder.
+#[cfg(feature = "std")]
+impl SampleBuffer {
+    /// An empty buffer with room for `capacity` samples.
+    pub fn with_capacity(capacity: usize) -> Self {
+        SampleBuffer {
+            samples: Vec::with_capacity(capacity),
+            pos: 0,
+        }
+    }
+
+    /// Push `samples`, in order.
+    pub fn extend_from_slice(&mut self, samples: &[i32]) {
+        self.samples.extend_from_slice(samples);
+    }
+
+    /// Push `n` zero-valued samples.
+    pub fn push_silence(&mut self, n: usize) {
+        self.samples.resize(self.samples.len() + n, 0);
+    }
+
+    /// Samples the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.samples.capacity()
+    }
+
+    /// Make room for at least `capacity` samples in total.
+    pub fn reserve(&mut self, capacity: usize) {
+        self.samples.reserve(capacity.saturating_sub(self.samples.len()));
+    }
+
     /// Get the next sample, or None if buffer is exhausted.
     pub fn next_sample(&mut self) -> Option<i32> {
         if self.pos < self.samples.len() {
@@ -41,7 +105,40 @@ impl SampleBuffer {
         }
     }
 
-    /// Clear the buffer for reuse.
+    /// Take all remaining samples at once, marking them consumed.
+    pub fn take_remaining(&mut self) -> &[i32] {
+        let start = self.pos;
+        self.pos = self.samples.len();
+        &self.samples[start..]
+    }
+
+    /// Copy as many remaining samples as fit into `out`, marking them
+    /// consumed, and return how many were copied.
+    pub fn drain_into(&mut self, out: &mut [i32]) -> usize {
+        let n = out.len().min(self.remaining());
+        out[..n].copy_from_slice(&self.samples[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+
+    /// Take up to `n` remaining samples, marking them consumed.
+    pub fn take(&mut self, n: usize) -> &[i32] {
+        let start = self.pos;
+        self.pos = (start + n).min(self.samples.len());
+        &self.samples[start..self.pos]
+    }
+
+    /// Number of samples already consumed.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Mark up to `n` more samples consumed.
+    pub fn skip(&mut self, n: usize) {
+        self.pos = (self.pos + n).min(self.samples.len());
+    }
+
+    /// Clear the buffer for reuse, keeping its allocation.
     pub fn clear(&mut self) {
         self.samples.clear();
         self.pos = 0;
@@ -0,0 +1,167 @@
+//! Gapless decoding of several files as one stream.
+//!
+//! A CD ripped track-per-file splits the audio at arbitrary sample
+//! positions, so players must join the tracks without inserting or losing
+//! a sample. APE frames never straddle files and every stream ends exactly
+//! at its header's sample count, so concatenating the decoded streams is
+//! sample-accurate; [`ChainedReader`] does that and keeps track of where
+//! each file starts.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use crate::error::ApeError;
+use crate::snapshot::DecoderSnapshot;
+use crate::{ApeInfo, ApeReader};
+
+/// An ordered list of [`ApeReader`]s presented as one continuous stream.
+///
+/// All streams must have the same sample rate, channel count and bit
+/// depth.
+///
+/// ```no_run
+/// use ape_rs::chain::ChainedReader;
+///
+/// let mut album = ChainedReader::open(["01.ape", "02.ape", "03.ape"]).unwrap();
+/// println!("{} samples in {} tracks", album.info().total_samples, album.track_count());
+/// let samples: Vec<i32> = album.samples().collect::<Result<_, _>>().unwrap();
+/// ```
+pub struct ChainedReader<R: Read + Seek> {
+    readers: Vec<ApeReader<R>>,
+    /// Interleaved sample index where each track starts, then the total.
+    starts: Vec<u64>,
+    /// Track the next sample comes from.
+    current: usize,
+    info: ApeInfo,
+}
+
+impl ChainedReader<BufReader<File>> {
+    /// Open each file in order and chain them.
+    pub fn open<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self, ApeError> {
+        let readers = paths.into_iter().map(ApeReader::open).collect::<Result<Vec<_>, _>>()?;
+        Self::new(readers)
+    }
+}
+
+impl<R: Read + Seek> ChainedReader<R> {
+    /// Chain readers, which should all be at the start of their streams.
+    ///
+    /// Fails with [`ErrorKind::FormatMismatch`](crate::ErrorKind::FormatMismatch)
+    /// if `readers` is empty or their formats differ.
+    pub fn new(readers: Vec<ApeReader<R>>) -> Result<Self, ApeError> {
+        let Some(first) = readers.first() else {
+            return Err(ApeError::FormatMismatch("no streams to chain".into()));
+        };
+        let format = |i: &ApeInfo| (i.sample_rate, i.channels, i.bits_per_sample);
+        let mut info = first.info().clone();
+        let mut starts = vec![0];
+        let mut total = 0;
+        for (index, reader) in readers.iter().enumerate() {
+            let track = reader.info();
+            if format(track) != format(&info) {
+                return Err(ApeError::FormatMismatch(format!(
+                    "track {index} is {} Hz, {} channels, {} bits; track 0 is {} Hz, {} channels, {} bits",
+                    track.sample_rate,
+                    track.channels,
+                    track.bits_per_sample,
+                    info.sample_rate,
+                    info.channels,
+                    info.bits_per_sample
+                )));
+            }
+            total += track.total_samples;
+            starts.push(total);
+        }
+        info.total_samples = total;
+        Ok(ChainedReader {
+            readers,
+            starts,
+            current: 0,
+            info,
+        })
+    }
+
+    /// Format of the chained stream. `total_samples` covers every track;
+    /// the compression level and format version are the first track's.
+    pub fn info(&self) -> &ApeInfo {
+        &self.info
+    }
+
+    /// Number of chained tracks.
+    pub fn track_count(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Interleaved sample index in the chained stream where track `index`
+    /// starts.
+    pub fn track_start(&self, index: usize) -> Option<u64> {
+        self.starts.get(index).copied().filter(|_| index < self.readers.len())
+    }
+
+    /// Track the next sample comes from (`track_count()` once the stream
+    /// has ended).
+    pub fn current_track(&self) -> usize {
+        self.current
+    }
+
+    /// The track readers, e.g. for their headers and warnings.
+    pub fn tracks(&self) -> &[ApeReader<R>] {
+        &self.readers
+    }
+
+    /// Continue from the start of track `index`. Returns `false` if there
+    /// is no such track.
+    pub fn seek_to_track(&mut self, index: usize) -> Result<bool, ApeError> {
+        let Some(reader) = self.readers.get_mut(index) else {
+            return Ok(false);
+        };
+        let start = DecoderSnapshot {
+            frame: 0,
+            sample_in_frame: 0,
+            resync_shift: 0,
+            total_blocks: reader.header().total_blocks(),
+        };
+        reader.restore(&start)?;
+        self.current = index;
+        Ok(true)
+    }
+
+    /// Iterator over the samples of every remaining track in turn,
+    /// interleaved as [`ApeReader::samples`].
+    pub fn samples(&mut self) -> ChainedSamples<'_, R> {
+        ChainedSamples { chain: self }
+    }
+
+    /// Unwrap the track readers.
+    pub fn into_tracks(self) -> Vec<ApeReader<R>> {
+        self.readers
+    }
+}
+
+/// Iterator over the samples of a [`ChainedReader`].
+pub struct ChainedSamples<'a, R: Read + Seek> {
+    chain: &'a mut ChainedReader<R>,
+}
+
+impl<R: Read + Seek> Iterator for ChainedSamples<'_, R> {
+    type Item = Result<i32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reader = self.chain.readers.get_mut(self.chain.current)?;
+            if let Some(sample) = reader.samples().next() {
+                return Some(sample);
+            }
+            let next = self.chain.current + 1;
+            if next == self.chain.readers.len() {
+                self.chain.current = next;
+                return None;
+            }
+            // A track may have been read before (after a seek back)
+            if let Err(e) = self.chain.seek_to_track(next) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
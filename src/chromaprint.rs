@@ -0,0 +1,504 @@
+//! Chromaprint audio fingerprints for AcoustID lookups (feature
+//! `chromaprint`).
+//!
+//! Implements Chromaprint's default algorithm (`TEST2`) in the crate:
+//! 1. Downmix to mono 16-bit and resample to 11025 Hz (windowed-sinc
+//!    polyphase filter, as in Chromaprint's bundled resampler)
+//! 2. 4096-point Hamming-windowed FFT every 1365 samples
+//! 3. Fold 28 Hz – 3.5 kHz energy into 12 chroma bands, smooth over five
+//!    frames and normalize
+//! 4. Run 16 Haar-like classifiers over the chroma image, two Gray-coded
+//!    bits each, giving one 32-bit sub-fingerprint per frame
+//! 5. Compress and base64-encode like `fpcalc`
+//!
+//! Floating-point details can flip the odd bit compared with `fpcalc`;
+//! AcoustID matching tolerates that.
+
+use std::f64::consts::PI;
+
+use crate::fft::Fft;
+
+/// Chromaprint's identifier for the `TEST2` algorithm.
+const ALGORITHM: u8 = 1;
+
+/// Sample rate the fingerprint is computed at.
+const SAMPLE_RATE: u32 = 11025;
+
+const FRAME_SIZE: usize = 4096;
+const FRAME_STEP: usize = FRAME_SIZE / 3;
+
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+const BANDS: usize = 12;
+
+/// Temporal smoothing applied to consecutive chroma vectors.
+const CHROMA_FILTER: [f64; 5] = [0.25, 0.75, 1.0, 0.75, 0.25];
+
+/// Chroma vectors with a smaller Euclidean norm are treated as silence.
+const NORM_THRESHOLD: f64 = 0.01;
+
+/// Widest classifier window, in frames.
+const MAX_FILTER_WIDTH: usize = 16;
+
+/// Resampler taps before widening for the cutoff, phases, and cutoff
+/// relative to the output Nyquist frequency.
+const RESAMPLE_TAPS: usize = 16;
+const RESAMPLE_PHASE_SHIFT: u32 = 8;
+const RESAMPLE_CUTOFF: f64 = 0.8;
+/// Kaiser window beta of the resampling filter.
+const RESAMPLE_KAISER_BETA: f64 = 9.0;
+/// Fixed-point precision of the resampling filter.
+const RESAMPLE_FILTER_SHIFT: u32 = 15;
+
+/// A classifier: a Haar-like filter over the chroma image
+/// (`kind`, first band, bands, frames) and its three quantizer thresholds.
+struct Classifier {
+    kind: u8,
+    y: usize,
+    height: usize,
+    width: usize,
+    thresholds: [f64; 3],
+}
+
+const fn classifier(kind: u8, y: usize, height: usize, width: usize, thresholds: [f64; 3]) -> Classifier {
+    Classifier {
+        kind,
+        y,
+        height,
+        width,
+        thresholds,
+    }
+}
+
+/// Chromaprint's trained classifiers for `TEST2`.
+const CLASSIFIERS: [Classifier; 16] = [
+    classifier(0, 4, 3, 15, [1.98215, 2.35817, 2.63523]),
+    classifier(4, 4, 6, 15, [-1.03809, -0.651211, -0.282167]),
+    classifier(1, 0, 4, 16, [-0.298702, 0.119262, 0.558497]),
+    classifier(3, 8, 2, 12, [-0.105439, 0.0153946, 0.135898]),
+    classifier(3, 4, 4, 8, [-0.142891, 0.0258736, 0.200632]),
+    classifier(4, 0, 3, 5, [-0.826319, -0.590612, -0.368214]),
+    classifier(1, 2, 2, 9, [-0.557409, -0.233035, 0.0534525]),
+    classifier(2, 7, 3, 4, [-0.0646826, 0.00620476, 0.0784847]),
+    classifier(2, 6, 2, 16, [-0.192387, -0.029699, 0.215855]),
+    classifier(2, 1, 3, 2, [-0.0397818, -0.00568076, 0.0292026]),
+    classifier(5, 10, 1, 15, [-0.53823, -0.369934, -0.190235]),
+    classifier(3, 6, 2, 10, [-0.124877, 0.0296483, 0.139239]),
+    classifier(2, 1, 1, 14, [-0.101475, 0.0225617, 0.231971]),
+    classifier(3, 5, 6, 4, [-0.0799915, -0.00729616, 0.063262]),
+    classifier(1, 9, 2, 12, [-0.272556, 0.019424, 0.302559]),
+    classifier(3, 4, 2, 14, [-0.164292, -0.0321188, 0.0846339]),
+];
+
+/// A computed fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint {
+    /// One 32-bit sub-fingerprint per analysis frame.
+    pub raw: Vec<u32>,
+}
+
+impl Fingerprint {
+    /// The compressed, URL-safe base64 form that `fpcalc` prints and the
+    /// AcoustID lookup API expects.
+    pub fn encode(&self) -> String {
+        // Each sub-fingerprint is stored as the positions of the bits that
+        // changed from the previous one, as gaps terminated by 0.
+        let mut gaps = Vec::new();
+        let mut prev = 0u32;
+        for &fp in &self.raw {
+            let mut x = fp ^ prev;
+            let (mut bit, mut last_bit) = (1u8, 0u8);
+            while x != 0 {
+                if x & 1 != 0 {
+                    gaps.push(bit - last_bit);
+                    last_bit = bit;
+                }
+                x >>= 1;
+                bit += 1;
+            }
+            gaps.push(0);
+            prev = fp;
+        }
+
+        let len = self.raw.len() as u32;
+        let mut out = vec![ALGORITHM, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+        // Gaps up to 6 fit in 3 bits; larger ones store 7 there and the
+        // excess in a second 5-bit array.
+        let normal: Vec<u8> = gaps.iter().map(|&g| g.min(7)).collect();
+        let exceptional: Vec<u8> = gaps.iter().filter(|&&g| g >= 7).map(|&g| g - 7).collect();
+        pack_bits(&normal, 3, &mut out);
+        pack_bits(&exceptional, 5, &mut out);
+        base64_url(&out)
+    }
+}
+
+/// Pack `values` of `bits` width each, least significant bit first.
+fn pack_bits(values: &[u8], bits: u32, out: &mut Vec<u8>) {
+    let mut acc = 0u32;
+    let mut filled = 0;
+    for &v in values {
+        acc |= ((v as u32) & ((1 << bits) - 1)) << filled;
+        filled += bits;
+        while filled >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            filled -= 8;
+        }
+    }
+    if filled > 0 {
+        out.push(acc as u8);
+    }
+}
+
+/// URL-safe base64 without padding.
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Modified Bessel function of the first kind, order 0.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_sq = x * x / 4.0;
+    for k in 1..50 {
+        term *= half_sq / (k * k) as f64;
+        sum += term;
+        if term < sum * 1e-17 {
+            break;
+        }
+    }
+    sum
+}
+
+/// Streaming polyphase resampler to 11025 Hz over 16-bit samples.
+#[derive(Debug, Clone)]
+struct Resampler {
+    taps: usize,
+    /// One row of `taps` fixed-point coefficients per phase.
+    filters: Vec<i32>,
+    /// Input position of the next output, in 1/phases of a sample,
+    /// counted from the first sample ever fed.
+    index: i64,
+    /// Fractional remainder of `index`, in units of 1/out_rate.
+    frac: u64,
+    step: i64,
+    step_frac: u64,
+    out_rate: u64,
+    input: Vec<i16>,
+    /// Input samples already dropped from the front of `input`.
+    dropped: i64,
+}
+
+impl Resampler {
+    fn new(in_rate: u32) -> Self {
+        let phases = 1usize << RESAMPLE_PHASE_SHIFT;
+        let factor = (SAMPLE_RATE as f64 * RESAMPLE_CUTOFF / in_rate as f64).min(1.0);
+        let taps = ((RESAMPLE_TAPS as f64 / factor).ceil() as usize).max(1);
+        let center = (taps as i64 - 1) / 2;
+
+        let mut filters = Vec::with_capacity(phases * taps);
+        let mut row = vec![0.0; taps];
+        for phase in 0..phases {
+            for (i, tap) in row.iter_mut().enumerate() {
+                let x = PI * ((i as i64 - center) as f64 - phase as f64 / phases as f64) * factor;
+                let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+                let w = 2.0 * x / (factor * taps as f64 * PI);
+                *tap = sinc * bessel_i0(RESAMPLE_KAISER_BETA * (1.0 - w * w).max(0.0).sqrt());
+            }
+            let norm: f64 = row.iter().sum();
+            let scale = (1 << RESAMPLE_FILTER_SHIFT) as f64 / norm;
+            filters.extend(row.iter().map(|&t| ((t * scale).round() as i32).clamp(i16::MIN as i32, i16::MAX as i32)));
+        }
+
+        let dst_incr = in_rate as u64 * phases as u64;
+        let out_rate = SAMPLE_RATE as u64;
+        Resampler {
+            taps,
+            filters,
+            index: -(phases as i64) * center,
+            frac: 0,
+            step: (dst_incr / out_rate) as i64,
+            step_frac: dst_incr % out_rate,
+            out_rate,
+            input: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Add input and append every output sample it completes.
+    fn process(&mut self, samples: &[i16], out: &mut Vec<i16>) {
+        self.input.extend_from_slice(samples);
+        let phase_mask = (1i64 << RESAMPLE_PHASE_SHIFT) - 1;
+        loop {
+            let first = self.index >> RESAMPLE_PHASE_SHIFT;
+            if first + self.taps as i64 > self.dropped + self.input.len() as i64 {
+                break;
+            }
+            let phase = (self.index & phase_mask) as usize;
+            let filter = &self.filters[phase * self.taps..(phase + 1) * self.taps];
+            // Positions before the first sample mirror the input, as in the
+            // reference; they only occur before anything has been dropped.
+            let val: i64 = filter
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| {
+                    let pos = (first + i as i64).abs() - self.dropped;
+                    c as i64 * self.input[pos as usize] as i64
+                })
+                .sum();
+            let rounded = (val + (1 << (RESAMPLE_FILTER_SHIFT - 1))) >> RESAMPLE_FILTER_SHIFT;
+            out.push(rounded.clamp(i16::MIN as i64, i16::MAX as i64) as i16);
+
+            self.index += self.step;
+            self.frac += self.step_frac;
+            if self.frac >= self.out_rate {
+                self.frac -= self.out_rate;
+                self.index += 1;
+            }
+        }
+
+        // Drop input the next output no longer reaches
+        let first = self.index >> RESAMPLE_PHASE_SHIFT;
+        if first > self.dropped {
+            let drop = ((first - self.dropped) as usize).min(self.input.len());
+            self.input.drain(..drop);
+            self.dropped += drop as i64;
+        }
+    }
+}
+
+/// Integral image of chroma rows, for constant-time rectangle sums.
+#[derive(Debug, Clone, Default)]
+struct IntegralImage {
+    /// Row `r` holds sums over rows `0..r` (row 0 is all zero).
+    rows: Vec<[f64; BANDS + 1]>,
+}
+
+impl IntegralImage {
+    fn push(&mut self, features: &[f64; BANDS]) {
+        let prev = self.rows.last().copied().unwrap_or([0.0; BANDS + 1]);
+        if self.rows.is_empty() {
+            self.rows.push(prev);
+        }
+        let mut row = [0.0; BANDS + 1];
+        let mut running = 0.0;
+        for b in 0..BANDS {
+            running += features[b];
+            row[b + 1] = prev[b + 1] + running;
+        }
+        self.rows.push(row);
+    }
+
+    fn num_rows(&self) -> usize {
+        self.rows.len().saturating_sub(1)
+    }
+
+    /// Sum over frames `x1..x2` and bands `y1..y2`.
+    fn area(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> f64 {
+        let (a, b) = (&self.rows[x1], &self.rows[x2]);
+        b[y2] - b[y1] - a[y2] + a[y1]
+    }
+}
+
+impl Classifier {
+    /// Two Gray-coded bits for the window starting at frame `x`.
+    fn classify(&self, image: &IntegralImage, x: usize) -> u32 {
+        let (y, w, h) = (self.y, self.width, self.height);
+        let area = |x1, y1, x2, y2| image.area(x1, y1, x2, y2);
+        let (a, b) = match self.kind {
+            0 => (area(x, y, x + w, y + h), 0.0),
+            1 => {
+                let h2 = h / 2;
+                (area(x, y + h2, x + w, y + h), area(x, y, x + w, y + h2))
+            }
+            2 => {
+                let w2 = w / 2;
+                (area(x + w2, y, x + w, y + h), area(x, y, x + w2, y + h))
+            }
+            3 => {
+                let (w2, h2) = (w / 2, h / 2);
+                (
+                    area(x, y + h2, x + w2, y + h) + area(x + w2, y, x + w, y + h2),
+                    area(x, y, x + w2, y + h2) + area(x + w2, y + h2, x + w, y + h),
+                )
+            }
+            4 => {
+                let h3 = h / 3;
+                (
+                    area(x, y + h3, x + w, y + 2 * h3),
+                    area(x, y, x + w, y + h3) + area(x, y + 2 * h3, x + w, y + h),
+                )
+            }
+            _ => {
+                let w3 = w / 3;
+                (
+                    area(x + w3, y, x + 2 * w3, y + h),
+                    area(x, y, x + w3, y + h) + area(x + 2 * w3, y, x + w, y + h),
+                )
+            }
+        };
+        let value = ((1.0 + a) / (1.0 + b)).ln();
+        let [t0, t1, t2] = self.thresholds;
+        let quantized = if value < t1 {
+            if value < t0 { 0 } else { 1 }
+        } else if value < t2 {
+            2
+        } else {
+            3
+        };
+        // Gray code
+        [0, 1, 3, 2][quantized]
+    }
+}
+
+/// Streaming fingerprint calculator.
+///
+/// Feed it interleaved samples in any chunking, then call `finish`.
+#[derive(Debug, Clone)]
+pub struct Fingerprinter {
+    channels: usize,
+    /// Right shift (negative: left shift) bringing samples to 16 bits.
+    shift: i32,
+    /// Samples of a block split across chunks.
+    partial: Vec<i32>,
+    resampler: Option<Resampler>,
+    /// Resampled audio not yet consumed by a full frame.
+    audio: Vec<i16>,
+    fft: Fft,
+    window: Vec<f64>,
+    spectrum: Vec<f64>,
+    /// Chroma band of each FFT bin in the analysed range.
+    notes: Vec<(usize, usize)>,
+    /// Last `CHROMA_FILTER.len()` chroma vectors, oldest first.
+    recent: Vec<[f64; BANDS]>,
+    image: IntegralImage,
+    raw: Vec<u32>,
+}
+
+impl Fingerprinter {
+    /// Create a fingerprinter for interleaved audio with the given layout.
+    pub fn new(channels: u16, bits_per_sample: u16, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let freq_to_index = |f: f64| (FRAME_SIZE as f64 * f / SAMPLE_RATE as f64).round() as usize;
+        let min_index = freq_to_index(MIN_FREQ).max(1);
+        let max_index = freq_to_index(MAX_FREQ).min(FRAME_SIZE / 2);
+        let notes = (min_index..max_index)
+            .map(|i| {
+                let freq = i as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+                let octave = (freq / (440.0 / 16.0)).log2();
+                (i, (BANDS as f64 * (octave - octave.floor())) as usize)
+            })
+            .collect();
+
+        Fingerprinter {
+            channels: channels.max(1) as usize,
+            shift: bits_per_sample as i32 - 16,
+            partial: Vec::new(),
+            resampler: (sample_rate != SAMPLE_RATE).then(|| Resampler::new(sample_rate)),
+            audio: Vec::new(),
+            fft: Fft::new(FRAME_SIZE),
+            window: (0..FRAME_SIZE)
+                .map(|i| {
+                    let hamming = 0.54 - 0.46 * (2.0 * PI * i as f64 / (FRAME_SIZE - 1) as f64).cos();
+                    hamming / i16::MAX as f64
+                })
+                .collect(),
+            spectrum: Vec::new(),
+            notes,
+            recent: Vec::new(),
+            image: IntegralImage::default(),
+            raw: Vec::new(),
+        }
+    }
+
+    /// Add interleaved samples. A chunk may end mid-block; the next one
+    /// continues with the following channel.
+    pub fn update(&mut self, samples: &[i32]) {
+        let mut mono = Vec::with_capacity(samples.len() / self.channels + 1);
+        for &s in samples {
+            self.partial.push(s);
+            if self.partial.len() == self.channels {
+                let sum: i64 = self.partial.iter().map(|&s| self.to_i16(s) as i64).sum();
+                mono.push((sum / self.channels as i64) as i16);
+                self.partial.clear();
+            }
+        }
+
+        match &mut self.resampler {
+            Some(resampler) => resampler.process(&mono, &mut self.audio),
+            None => self.audio.extend_from_slice(&mono),
+        }
+
+        let mut start = 0;
+        while self.audio.len() - start >= FRAME_SIZE {
+            self.process_frame(start);
+            start += FRAME_STEP;
+        }
+        self.audio.drain(..start);
+    }
+
+    fn to_i16(&self, s: i32) -> i16 {
+        let s = if self.shift >= 0 { s >> self.shift } else { s << -self.shift };
+        s.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    }
+
+    /// Analyse the frame at `audio[start..start + FRAME_SIZE]`.
+    fn process_frame(&mut self, start: usize) {
+        let frame: Vec<f64> = self.audio[start..start + FRAME_SIZE]
+            .iter()
+            .zip(&self.window)
+            .map(|(&s, w)| s as f64 * w)
+            .collect();
+        self.fft.power_spectrum(&frame, &mut self.spectrum);
+
+        let mut chroma = [0.0; BANDS];
+        for &(bin, note) in &self.notes {
+            chroma[note] += self.spectrum[bin];
+        }
+        if self.recent.len() == CHROMA_FILTER.len() {
+            self.recent.remove(0);
+        }
+        self.recent.push(chroma);
+        if self.recent.len() < CHROMA_FILTER.len() {
+            return;
+        }
+
+        let mut smoothed = [0.0; BANDS];
+        for (features, &c) in self.recent.iter().zip(&CHROMA_FILTER) {
+            for (s, f) in smoothed.iter_mut().zip(features) {
+                *s += f * c;
+            }
+        }
+        let norm = smoothed.iter().map(|x| x * x).sum::<f64>().sqrt();
+        for s in &mut smoothed {
+            *s = if norm < NORM_THRESHOLD { 0.0 } else { *s / norm };
+        }
+
+        self.image.push(&smoothed);
+        let rows = self.image.num_rows();
+        if rows >= MAX_FILTER_WIDTH {
+            let x = rows - MAX_FILTER_WIDTH;
+            let bits = CLASSIFIERS
+                .iter()
+                .fold(0u32, |bits, c| (bits << 2) | c.classify(&self.image, x));
+            self.raw.push(bits);
+        }
+    }
+
+    /// The fingerprint of everything fed so far.
+    pub fn finish(self) -> Fingerprint {
+        Fingerprint { raw: self.raw }
+    }
+}
@@ -0,0 +1,130 @@
+//! Clipping detection: runs of consecutive full-scale samples per channel,
+//! with an optional inter-sample (true) peak estimate.
+//!
+//! A sample is full scale when it equals the largest or smallest value the
+//! bit depth can hold. A single full-scale sample is common in loud but
+//! clean masters, so only runs of at least `min_run` samples are reported.
+
+use crate::convert;
+use crate::true_peak::TruePeak;
+
+/// A run of consecutive full-scale samples on one channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipRun {
+    /// Channel index (0-based).
+    pub channel: u16,
+    /// First block of the run, counted from the first sample analyzed.
+    pub start: u64,
+    /// Number of consecutive full-scale samples.
+    pub len: u64,
+}
+
+/// Result of [`ApeReader::detect_clipping`](crate::ApeReader::detect_clipping).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClippingReport {
+    /// Runs of at least `min_run` full-scale samples, ordered by start.
+    pub runs: Vec<ClipRun>,
+    /// Per channel, the number of samples inside reported runs.
+    pub clipped_samples: Vec<u64>,
+    /// Per channel, the 4x-oversampled peak relative to full scale, if
+    /// requested. Values above 1.0 indicate inter-sample overs.
+    pub true_peak: Option<Vec<f64>>,
+}
+
+impl ClippingReport {
+    /// Whether any clipping run was found.
+    pub fn is_clipped(&self) -> bool {
+        !self.runs.is_empty()
+    }
+}
+
+/// Single-pass accumulator behind [`ClippingReport`].
+///
+/// Feed it interleaved samples in any chunking, then call `finish`.
+#[derive(Debug, Clone)]
+pub struct ClippingDetector {
+    max: i32,
+    min: i32,
+    full_scale: f64,
+    min_run: u64,
+    /// Start block and length of the current full-scale run, per channel.
+    current: Vec<Option<(u64, u64)>>,
+    runs: Vec<ClipRun>,
+    clipped_samples: Vec<u64>,
+    true_peaks: Option<Vec<TruePeak>>,
+    samples: u64,
+}
+
+impl ClippingDetector {
+    /// Create a detector for interleaved audio, reporting runs of at least
+    /// `min_run` full-scale samples (0 is treated as 1) and, if
+    /// `inter_sample` is set, per-channel true peaks.
+    pub fn new(channels: u16, bits_per_sample: u16, min_run: u32, inter_sample: bool) -> Self {
+        let channels = channels.max(1) as usize;
+        let range = convert::sample_range(bits_per_sample);
+        ClippingDetector {
+            max: *range.end(),
+            min: *range.start(),
+            full_scale: convert::full_scale(bits_per_sample),
+            min_run: min_run.max(1) as u64,
+            current: vec![None; channels],
+            runs: Vec::new(),
+            clipped_samples: vec![0; channels],
+            true_peaks: inter_sample.then(|| vec![TruePeak::new(); channels]),
+            samples: 0,
+        }
+    }
+
+    /// Add interleaved samples. A chunk may end mid-block; the next one
+    /// continues with the following channel.
+    pub fn update(&mut self, samples: &[i32]) {
+        let channels = self.current.len() as u64;
+        for &s in samples {
+            let ch = (self.samples % channels) as usize;
+            let block = self.samples / channels;
+            if s >= self.max || s <= self.min {
+                match &mut self.current[ch] {
+                    Some((_, len)) => *len += 1,
+                    run => *run = Some((block, 1)),
+                }
+            } else {
+                self.end_run(ch);
+            }
+            if let Some(true_peaks) = &mut self.true_peaks {
+                true_peaks[ch].push(s as f64 / self.full_scale);
+            }
+            self.samples += 1;
+        }
+    }
+
+    /// Close channel `ch`'s current run, recording it if long enough.
+    fn end_run(&mut self, ch: usize) {
+        if let Some((start, len)) = self.current[ch].take()
+            && len >= self.min_run
+        {
+            self.runs.push(ClipRun {
+                channel: ch as u16,
+                start,
+                len,
+            });
+            self.clipped_samples[ch] += len;
+        }
+    }
+
+    /// Report for everything fed so far.
+    pub fn finish(mut self) -> ClippingReport {
+        for ch in 0..self.current.len() {
+            self.end_run(ch);
+        }
+        self.runs.sort_by_key(|r| (r.start, r.channel));
+        ClippingReport {
+            runs: self.runs,
+            clipped_samples: self.clipped_samples,
+            true_peak: self
+                .true_peaks
+                .map(|peaks| peaks.iter().map(TruePeak::peak).collect()),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Bit-exact comparison of a decode against a reference WAV file.
+//!
+//! Validating the decoder means decoding the same file with another tool
+//! (`ffmpeg -i track.ape track.wav`, or the original WAV before encoding)
+//! and checking every sample. [`compare_to_wav`] does that, reporting where
+//! the first difference is and how large the differences get, rather than
+//! just whether the files match.
+//!
+//! [`diff_streams`] compares a decode with a second stream, another APE
+//! decode or any sample iterator, in lockstep, so neither side is held in
+//! memory: differences come back as ranges of blocks.
+
+use std::fs;
+use std::io::{Read, Seek};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::ApeReader;
+use crate::error::ApeError;
+use crate::riff::WaveFormat;
+use crate::wav_reader::WavReader;
+
+/// The first sample that differs, from [`CompareReport::first_mismatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mismatch {
+    /// Index among interleaved samples.
+    pub index: u64,
+    /// Block (sample frame) index.
+    pub block: u64,
+    /// Channel index (0-based).
+    pub channel: u16,
+    /// The decoded sample.
+    pub decoded: i32,
+    /// The reference sample.
+    pub reference: i32,
+}
+
+/// Result of [`compare_to_wav`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompareReport {
+    /// Interleaved samples decoded from the APE file.
+    pub decoded_samples: u64,
+    /// Interleaved samples in the reference's `data` chunk.
+    pub reference_samples: u64,
+    /// Samples that differ, among those both streams have.
+    pub mismatches: u64,
+    /// The first sample that differs, if any.
+    pub first_mismatch: Option<Mismatch>,
+    /// Largest absolute difference between two samples.
+    pub max_diff: u32,
+}
+
+impl CompareReport {
+    /// Whether both streams hold the same samples, and the same number of
+    /// them.
+    pub fn is_bit_exact(&self) -> bool {
+        self.mismatches == 0 && self.decoded_samples == self.reference_samples
+    }
+}
+
+/// PCM audio read from a WAV file by [`parse_wav`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WavAudio {
+    /// The file's `fmt ` chunk.
+    pub format: WaveFormat,
+    /// Interleaved samples, signed at every bit depth (8-bit samples are
+    /// re-centered on zero, as the decoder outputs them).
+    pub samples: Vec<i32>,
+}
+
+/// Read the samples of an integer PCM WAV file, plain or
+/// `WAVE_FORMAT_EXTENSIBLE`, with 1- to 4-byte containers.
+///
+/// A `data` chunk that claims more than the file holds (as streaming
+/// writers such as `ffmpeg` to a pipe leave it) is read to the end.
+pub fn parse_wav(data: &[u8]) -> Result<WavAudio, ApeError> {
+    let mut reader = WavReader::new(data)?;
+    let samples = reader.read_all_samples()?;
+    Ok(WavAudio { format: *reader.format(), samples })
+}
+
+/// Decode `ape_path` and compare it sample by sample with the WAV file
+/// at `wav_path`.
+///
+/// ```no_run
+/// let report = ape_rs::compare::compare_to_wav("track.ape", "track.wav").unwrap();
+/// if let Some(m) = report.first_mismatch {
+///     println!("first difference at block {} channel {}", m.block, m.channel);
+/// }
+/// ```
+pub fn compare_to_wav<P: AsRef<Path>, Q: AsRef<Path>>(ape_path: P, wav_path: Q) -> Result<CompareReport, ApeError> {
+    let mut reader = ApeReader::open(ape_path)?;
+    let wav = parse_wav(&fs::read(wav_path)?)?;
+    reader.compare(&wav)
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// Decode the rest of the stream and compare it with `reference`, as
+    /// [`compare_to_wav`].
+    ///
+    /// Fails with [`ErrorKind::FormatMismatch`](crate::ErrorKind::FormatMismatch)
+    /// if the reference has a different channel count or sample size.
+    /// Frames are handled per the reader's frame error mode.
+    pub fn compare(&mut self, reference: &WavAudio) -> Result<CompareReport, ApeError> {
+        let format = &reference.format;
+        let bytes_per_sample = self.info.bits_per_sample.div_ceil(8);
+        if format.channels != self.info.channels || format.container_bytes() != bytes_per_sample {
+            return Err(ApeError::FormatMismatch(format!(
+                "reference is {} channels of {}-byte samples, the stream {} of {}-byte samples",
+                format.channels,
+                format.container_bytes(),
+                self.info.channels,
+                bytes_per_sample
+            )));
+        }
+
+        let channels = self.info.channels as u64;
+        let mut report = CompareReport {
+            reference_samples: reference.samples.len() as u64,
+            ..CompareReport::default()
+        };
+        self.for_each_chunk(|samples| {
+            let start = report.decoded_samples as usize;
+            report.decoded_samples += samples.len() as u64;
+            let expected = reference.samples.get(start..).unwrap_or_default();
+            for (i, (&decoded, &reference)) in samples.iter().zip(expected).enumerate() {
+                if decoded == reference {
+                    continue;
+                }
+                let index = (start + i) as u64;
+                report.mismatches += 1;
+                report.max_diff = report.max_diff.max(decoded.abs_diff(reference));
+                report.first_mismatch.get_or_insert(Mismatch {
+                    index,
+                    block: index / channels,
+                    channel: (index % channels) as u16,
+                    decoded,
+                    reference,
+                });
+            }
+        })?;
+        Ok(report)
+    }
+}
+
+/// A run of blocks in which the streams differ, from [`StreamDiff::ranges`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffRange {
+    /// Blocks (samples per channel), end-exclusive. Every block in the
+    /// range has at least one differing sample.
+    pub blocks: Range<u64>,
+    /// Differing samples in the range.
+    pub mismatches: u64,
+    /// Largest absolute difference in the range.
+    pub max_diff: u32,
+}
+
+/// Result of [`diff_streams`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamDiff {
+    /// Interleaved samples in the first stream.
+    pub a_samples: u64,
+    /// Interleaved samples in the second stream.
+    pub b_samples: u64,
+    /// Samples that differ, among those both streams have.
+    pub mismatches: u64,
+    /// Runs of differing blocks, in order.
+    pub ranges: Vec<DiffRange>,
+    /// Largest absolute difference between two samples.
+    pub max_diff: u32,
+}
+
+impl StreamDiff {
+    /// Whether both streams hold the same samples, and the same number of
+    /// them.
+    pub fn is_identical(&self) -> bool {
+        self.mismatches == 0 && self.a_samples == self.b_samples
+    }
+}
+
+/// Decode `a` and compare it with the samples of `b` in lockstep.
+///
+/// `b` is any stream of interleaved samples in `a`'s channel layout:
+/// another reader's [`samples`](ApeReader::samples) to compare two APE
+/// files, or `samples.iter().map(|&s| Ok(s))` for audio already in memory.
+/// Only one sample of each is held at a time, so files of any length can
+/// be compared. The first error from either side is returned.
+///
+/// ```no_run
+/// use ape_rs::ApeReader;
+///
+/// let mut original = ApeReader::open("original.ape").unwrap();
+/// let mut repaired = ApeReader::open("repaired.ape").unwrap();
+/// let diff = ape_rs::compare::diff_streams(&mut original, repaired.samples()).unwrap();
+/// for range in &diff.ranges {
+///     println!("blocks {:?} differ", range.blocks);
+/// }
+/// ```
+pub fn diff_streams<R, B>(a: &mut ApeReader<R>, b: B) -> Result<StreamDiff, ApeError>
+where
+    R: Read + Seek,
+    B: IntoIterator<Item = Result<i32, ApeError>>,
+{
+    let channels = a.info.channels.max(1) as u64;
+    let mut diff = StreamDiff::default();
+    let mut a = a.samples();
+    let mut b = b.into_iter();
+    loop {
+        let (x, y) = match (a.next().transpose()?, b.next().transpose()?) {
+            (Some(x), Some(y)) => (x, y),
+            (Some(_), None) => {
+                diff.a_samples += 1 + a.try_fold(0, |n, s| s.map(|_| n + 1))?;
+                break;
+            }
+            (None, Some(_)) => {
+                diff.b_samples += 1 + b.try_fold(0, |n, s| s.map(|_| n + 1))?;
+                break;
+            }
+            (None, None) => break,
+        };
+        let index = diff.a_samples;
+        diff.a_samples += 1;
+        diff.b_samples += 1;
+        if x == y {
+            continue;
+        }
+
+        let d = x.abs_diff(y);
+        let block = index / channels;
+        diff.mismatches += 1;
+        diff.max_diff = diff.max_diff.max(d);
+        match diff.ranges.last_mut() {
+            Some(range) if range.blocks.end >= block => {
+                range.blocks.end = block + 1;
+                range.mismatches += 1;
+                range.max_diff = range.max_diff.max(d);
+            }
+            _ => diff.ranges.push(DiffRange {
+                blocks: block..block + 1,
+                mismatches: 1,
+                max_diff: d,
+            }),
+        }
+    }
+    Ok(diff)
+}
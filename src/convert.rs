@@ -0,0 +1,205 @@
+//! Sample format conversion: scaling between bit depths, normalizing to
+//! and from floating point, clamping, and packing to and from PCM bytes.
+//!
+//! Decoded samples are `i32`s holding `bits_per_sample`-bit values, 8-bit
+//! ones signed (re-centered on zero). These functions are what the
+//! conversions elsewhere in the crate (the `samples_*` iterators, the WAV
+//! writer, PCM digests, the analysis meters) are built on, so they all
+//! agree. Available without `std`.
+//!
+//! Bit depths are clamped to 1..=32.
+
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::error::ApeError;
+
+/// Byte layout of packed PCM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PcmLayout {
+    /// WAV data chunk layout: little-endian, with 8-bit samples unsigned.
+    /// Matches a hash of the `data` chunk of the decoded WAV file.
+    #[default]
+    Wav,
+    /// Little-endian and signed at every bit depth, as hashed for a FLAC
+    /// file's MD5 signature.
+    Flac,
+    /// Big-endian and signed, as in an AIFF file.
+    Aiff,
+}
+
+fn clamp_bits(bits: u16) -> u32 {
+    bits.clamp(1, 32) as u32
+}
+
+/// Magnitude of the most negative `bits`-bit sample: the value that
+/// normalizes to -1.0.
+pub fn full_scale(bits: u16) -> f64 {
+    (1u64 << (clamp_bits(bits) - 1)) as f64
+}
+
+/// The values a `bits`-bit sample can take.
+pub fn sample_range(bits: u16) -> RangeInclusive<i32> {
+    let max = ((1i64 << (clamp_bits(bits) - 1)) - 1) as i32;
+    -max - 1..=max
+}
+
+/// Clamp `sample` to the `bits`-bit range.
+pub fn clamp(sample: i64, bits: u16) -> i32 {
+    let range = sample_range(bits);
+    sample.clamp(*range.start() as i64, *range.end() as i64) as i32
+}
+
+/// Convert a sample from `from_bits` to `to_bits` by shifting: exact when
+/// widening, truncating (rounding down) when narrowing. See
+/// [`dither`](crate::dither) for narrowing without truncation distortion.
+pub fn rescale(sample: i32, from_bits: u16, to_bits: u16) -> i32 {
+    let (from, to) = (clamp_bits(from_bits), clamp_bits(to_bits));
+    if to >= from { sample << (to - from) } else { sample >> (from - to) }
+}
+
+/// A `bits`-bit sample as 16 bits, as [`rescale`] converts it.
+pub fn to_i16(sample: i32, bits: u16) -> i16 {
+    rescale(sample, bits, 16) as i16
+}
+
+/// A `bits`-bit sample as 24 bits (in an `i32`), as [`rescale`] converts it.
+pub fn to_i24(sample: i32, bits: u16) -> i32 {
+    rescale(sample, bits, 24)
+}
+
+/// A `bits`-bit sample left-justified in 32 bits: shifted so its most
+/// significant bit is bit 31, the low bits zero, as ALSA's `S24_LE` in a
+/// 32-bit container (`S32_LE` with 24 valid bits) or ASIO's `Int32` formats
+/// expect. Same as [`rescale`] to 32 bits.
+pub fn left_justify(sample: i32, bits: u16) -> i32 {
+    rescale(sample, bits, 32)
+}
+
+/// [`left_justify`] `samples` of `bits` bits in place, e.g. a chunk or
+/// channel slice from a decode into caller buffers.
+pub fn left_justify_in_place(samples: &mut [i32], bits: u16) {
+    let shift = 32 - clamp_bits(bits);
+    for s in samples {
+        *s <<= shift;
+    }
+}
+
+/// A `bits`-bit sample normalized to [-1.0, 1.0).
+pub fn to_f64(sample: i32, bits: u16) -> f64 {
+    sample as f64 / full_scale(bits)
+}
+
+/// A `bits`-bit sample normalized to [-1.0, 1.0). Exact up to 24 bits.
+pub fn to_f32(sample: i32, bits: u16) -> f32 {
+    to_f64(sample, bits) as f32
+}
+
+/// A normalized value as a `bits`-bit sample, rounded to the nearest and
+/// clamped to the range, so 1.0 gives the largest sample. NaN gives 0.
+pub fn from_f64(value: f64, bits: u16) -> i32 {
+    let scaled = value * full_scale(bits);
+    // `f64::round` needs `std`; `as` truncates toward zero and saturates
+    let rounded = (if scaled < 0.0 { scaled - 0.5 } else { scaled + 0.5 }) as i64;
+    clamp(rounded, bits)
+}
+
+/// A normalized value as a `bits`-bit sample, as [`from_f64`].
+pub fn from_f32(value: f32, bits: u16) -> i32 {
+    from_f64(value as f64, bits)
+}
+
+/// Container size of a `bits`-bit sample in packed PCM: 1 to 4 bytes.
+pub fn bytes_per_sample(bits: u16) -> usize {
+    (bits as usize).div_ceil(8).clamp(1, 4)
+}
+
+/// Append `samples` to `out` as `bytes_per_sample`-byte values (1 to 4) in
+/// `layout`.
+pub fn pack(samples: &[i32], bytes_per_sample: usize, layout: PcmLayout, out: &mut Vec<u8>) {
+    let n = bytes_per_sample;
+    out.reserve(samples.len() * n);
+    for &s in samples {
+        match layout {
+            PcmLayout::Wav if n == 1 => out.push((s + 0x80) as u8),
+            PcmLayout::Wav | PcmLayout::Flac => out.extend_from_slice(&s.to_le_bytes()[..n]),
+            PcmLayout::Aiff => out.extend_from_slice(&s.to_be_bytes()[4 - n..]),
+        }
+    }
+}
+
+/// Read one packed sample of `bytes.len()` bytes (1 to 4) in `layout`,
+/// sign-extended.
+pub fn unpack_sample(bytes: &[u8], layout: PcmLayout) -> i32 {
+    let n = bytes.len().clamp(1, 4);
+    let bytes = &bytes[..n];
+    if layout == PcmLayout::Wav && n == 1 {
+        return bytes[0] as i32 - 0x80;
+    }
+    let mut be = [0; 4];
+    match layout {
+        PcmLayout::Aiff => be[..n].copy_from_slice(bytes),
+        _ => bytes.iter().rev().zip(&mut be).for_each(|(&b, o)| *o = b),
+    }
+    i32::from_be_bytes(be) >> (8 * (4 - n))
+}
+
+/// Append the samples in `bytes`, packed as `bytes_per_sample`-byte values
+/// in `layout`, to `out`. A partial sample at the end is ignored.
+pub fn unpack(bytes: &[u8], bytes_per_sample: usize, layout: PcmLayout, out: &mut Vec<i32>) {
+    let n = bytes_per_sample.clamp(1, 4);
+    out.extend(bytes.chunks_exact(n).map(|c| unpack_sample(c, layout)));
+}
+
+/// Iterator adapter normalizing samples to `f32` in [-1.0, 1.0), from
+/// [`ApeReader::samples_f32`](crate::ApeReader::samples_f32).
+#[derive(Debug)]
+pub struct SamplesF32<I> {
+    inner: I,
+    bits_per_sample: u16,
+}
+
+impl<I> SamplesF32<I> {
+    /// Normalize `samples` of `bits_per_sample` bits.
+    pub fn new(samples: I, bits_per_sample: u16) -> Self {
+        SamplesF32 {
+            inner: samples,
+            bits_per_sample,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<i32, ApeError>>> Iterator for SamplesF32<I> {
+    type Item = Result<f32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(|s| to_f32(s, self.bits_per_sample)))
+    }
+}
+
+/// Iterator adapter left-justifying samples in 32 bits, from
+/// [`ApeReader::samples_left_justified`](crate::ApeReader::samples_left_justified).
+#[derive(Debug)]
+pub struct LeftJustified<I> {
+    inner: I,
+    bits_per_sample: u16,
+}
+
+impl<I> LeftJustified<I> {
+    /// Left-justify `samples` of `bits_per_sample` bits.
+    pub fn new(samples: I, bits_per_sample: u16) -> Self {
+        LeftJustified {
+            inner: samples,
+            bits_per_sample,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<i32, ApeError>>> Iterator for LeftJustified<I> {
+    type Item = Result<i32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(|s| left_justify(s, self.bits_per_sample)))
+    }
+}
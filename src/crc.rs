@@ -0,0 +1,104 @@
+//! CRC-32 used by Monkey's Audio to protect decoded frame output.
+//!
+//! The checksum is the standard reflected CRC-32 (polynomial 0xEDB88320)
+//! computed over the frame's PCM output in WAV byte layout. For v3.99+ the
+//! stored value is the finalized CRC shifted right by one bit, because the
+//! top bit of the frame CRC word is reused as the "frame flags present" marker.
+//!
+//! [`frame_crc`] gives the stored value for a frame's samples; [`FrameCrc`]
+//! computes it incrementally, and [`crc32`] is the plain checksum over any
+//! buffer.
+//!
+//! ```
+//! use ape_rs::crc::{self, FrameCrc};
+//!
+//! assert_eq!(crc::crc32(b"123456789"), 0xCBF4_3926);
+//!
+//! let samples = [0, -1, 1000, -1000];
+//! let mut crc = FrameCrc::new();
+//! crc.update_samples(&samples[..2], 16);
+//! crc.update_samples(&samples[2..], 16);
+//! assert_eq!(crc.finish(), crc::frame_crc(&samples, 16));
+//! ```
+
+/// Reflected CRC-32 lookup table, built at compile time.
+const CRC_TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// The standard CRC-32 of `bytes` (as zlib and PNG compute it).
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = FrameCrc::new();
+    crc.update(bytes);
+    crc.checksum()
+}
+
+/// The CRC a v3.99+ frame header stores for the interleaved `samples` it
+/// decodes to (8, 16 or 24 bits).
+pub fn frame_crc(samples: &[i32], bits_per_sample: u16) -> u32 {
+    let mut crc = FrameCrc::new();
+    crc.update_samples(samples, bits_per_sample);
+    crc.finish()
+}
+
+/// Incremental frame CRC over PCM output.
+#[derive(Debug, Clone)]
+pub struct FrameCrc {
+    state: u32,
+}
+
+impl Default for FrameCrc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCrc {
+    pub fn new() -> Self {
+        FrameCrc { state: 0xFFFFFFFF }
+    }
+
+    /// Feed raw bytes.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state = CRC_TABLE[((self.state ^ b as u32) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Feed decoded samples, serialized exactly as they appear in a WAV file:
+    /// unsigned for 8-bit, little-endian signed for 16/24-bit.
+    pub fn update_samples(&mut self, samples: &[i32], bits_per_sample: u16) {
+        for &s in samples {
+            match bits_per_sample {
+                8 => self.update(&[(s + 0x80) as u8]),
+                16 => self.update(&(s as i16).to_le_bytes()),
+                _ => self.update(&s.to_le_bytes()[..3]),
+            }
+        }
+    }
+
+    /// The standard CRC-32 of the bytes so far.
+    pub fn checksum(&self) -> u32 {
+        self.state ^ 0xFFFFFFFF
+    }
+
+    /// Finalize into the value stored in v3.99+ frame headers: the
+    /// checksum without its lowest bit.
+    pub fn finish(&self) -> u32 {
+        self.checksum() >> 1
+    }
+}
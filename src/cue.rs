@@ -0,0 +1,273 @@
+//! Cue sheets, for playing a single-file album image as separate tracks.
+//!
+//! [`CueSheet::parse`] reads the text of a cue sheet, whether from a
+//! `.cue` file next to the image or from the image's embedded `Cuesheet`
+//! tag. [`ApeReader::track`](crate::ApeReader::track) then gives a reader
+//! over one track's samples.
+//!
+//! Each track runs from its `INDEX 01` to the next track's `INDEX 01`, so a
+//! pregap (`INDEX 00`) stays at the end of the previous track, as CD players
+//! and most splitters do. The last track runs to the end of the stream.
+//! Cue times are in CD frames of 1/75 s; at 44.1 kHz that is 588 samples,
+//! so boundaries are sample-accurate.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek};
+
+use crate::error::ApeError;
+#[cfg(feature = "std")]
+use crate::{ApeInfo, ApeReader};
+
+/// CD frames per second, the unit of cue sheet times.
+pub const CD_FRAMES_PER_SECOND: u64 = 75;
+
+/// A parsed cue sheet.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CueSheet {
+    /// Album title (`TITLE` before the first track).
+    pub title: Option<String>,
+    /// Album performer.
+    pub performer: Option<String>,
+    /// The first `FILE` the sheet names.
+    pub file: Option<String>,
+    /// Tracks in sheet order.
+    pub tracks: Vec<CueTrack>,
+}
+
+/// One `TRACK` entry of a cue sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CueTrack {
+    /// Track number (1–99).
+    pub number: u8,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Pregap start (`INDEX 00`), in CD frames from the start of the file.
+    pub pregap: Option<u64>,
+    /// Track start (`INDEX 01`), in CD frames from the start of the file.
+    pub start: u64,
+}
+
+impl CueTrack {
+    /// `start` as a block (sample per channel) index at `sample_rate`.
+    /// Starts so late that `start × sample_rate` overflows give
+    /// `u64::MAX`, past the end of any stream.
+    pub fn start_block(&self, sample_rate: u32) -> u64 {
+        self.start
+            .checked_mul(sample_rate as u64)
+            .map_or(u64::MAX, |frames| frames / CD_FRAMES_PER_SECOND)
+    }
+}
+
+impl CueSheet {
+    /// Parse cue sheet text. Commands other than `FILE`, `TRACK`, `INDEX`,
+    /// `TITLE` and `PERFORMER` are ignored.
+    ///
+    /// Fails with [`ErrorKind::InvalidCueSheet`](crate::ErrorKind::InvalidCueSheet)
+    /// on a malformed `TRACK` or `INDEX` line, a track without `INDEX 01`,
+    /// or tracks whose starts go backwards.
+    pub fn parse(text: &str) -> Result<Self, ApeError> {
+        let mut sheet = CueSheet::default();
+        // The track being read, with its INDEX 01 once seen
+        let mut current: Option<(CueTrack, bool)> = None;
+
+        for (n, line) in text.lines().enumerate() {
+            let invalid = |msg: &str| ApeError::InvalidCueSheet(format!("line {}: {msg}", n + 1));
+            let line = line.trim().trim_start_matches('\u{feff}');
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" if sheet.file.is_none() => sheet.file = Some(file_name(rest)),
+                "TRACK" => {
+                    if let Some(track) = current.take() {
+                        sheet.push_track(track)?;
+                    }
+                    let number = rest
+                        .split_whitespace()
+                        .next()
+                        .and_then(|t| t.parse::<u8>().ok())
+                        .ok_or_else(|| invalid("bad track number"))?;
+                    let track = CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        pregap: None,
+                        start: 0,
+                    };
+                    current = Some((track, false));
+                }
+                "INDEX" => {
+                    let Some((track, has_start)) = current.as_mut() else {
+                        return Err(invalid("INDEX outside a track"));
+                    };
+                    let mut fields = rest.split_whitespace();
+                    let index = fields.next().and_then(|t| t.parse::<u8>().ok());
+                    let time = fields.next().and_then(parse_time);
+                    match (index, time) {
+                        (Some(0), Some(time)) => track.pregap = Some(time),
+                        (Some(1), Some(time)) => {
+                            track.start = time;
+                            *has_start = true;
+                        }
+                        (Some(_), Some(_)) => {}
+                        _ => return Err(invalid("bad INDEX")),
+                    }
+                }
+                "TITLE" | "PERFORMER" => {
+                    let value = Some(unquote(rest).to_string());
+                    let is_title = command.eq_ignore_ascii_case("TITLE");
+                    match (current.as_mut(), is_title) {
+                        (Some((track, _)), true) => track.title = value,
+                        (Some((track, _)), false) => track.performer = value,
+                        (None, true) => sheet.title = value,
+                        (None, false) => sheet.performer = value,
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(track) = current {
+            sheet.push_track(track)?;
+        }
+        Ok(sheet)
+    }
+
+    /// The track numbered `number`.
+    pub fn track(&self, number: u8) -> Option<&CueTrack> {
+        self.tracks.iter().find(|t| t.number == number)
+    }
+
+    /// Block range `[start, end)` of track `number` in a stream of
+    /// `total_blocks` blocks at `sample_rate`. The end is the next track's
+    /// start, or the end of the stream for the last track.
+    pub fn track_blocks(&self, number: u8, sample_rate: u32, total_blocks: u64) -> Option<(u64, u64)> {
+        let i = self.tracks.iter().position(|t| t.number == number)?;
+        let start = self.tracks[i].start_block(sample_rate).min(total_blocks);
+        let end = self
+            .tracks
+            .get(i + 1)
+            .map_or(total_blocks, |next| next.start_block(sample_rate).min(total_blocks));
+        Some((start, end))
+    }
+
+    fn push_track(&mut self, (track, has_start): (CueTrack, bool)) -> Result<(), ApeError> {
+        if !has_start {
+            return Err(ApeError::InvalidCueSheet(format!("track {} has no INDEX 01", track.number)));
+        }
+        if let Some(prev) = self.tracks.last()
+            && track.start < prev.start
+        {
+            return Err(ApeError::InvalidCueSheet(format!(
+                "track {} starts before track {}",
+                track.number, prev.number
+            )));
+        }
+        self.tracks.push(track);
+        Ok(())
+    }
+}
+
+/// Parse `mm:ss:ff` into CD frames.
+fn parse_time(time: &str) -> Option<u64> {
+    let mut parts = time.split(':').map(|p| p.parse::<u64>().ok());
+    let (Some(Some(m)), Some(Some(s)), Some(Some(f)), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    if s >= 60 || f >= CD_FRAMES_PER_SECOND {
+        return None;
+    }
+    m.checked_mul(60)?
+        .checked_add(s)?
+        .checked_mul(CD_FRAMES_PER_SECOND)?
+        .checked_add(f)
+}
+
+/// Strip surrounding double quotes, if any.
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s)
+}
+
+/// The file name of a `FILE` line's arguments, without its type.
+fn file_name(rest: &str) -> String {
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted.split('"').next().unwrap_or_default().to_string();
+    }
+    // Unquoted: everything but a trailing type keyword
+    match rest.rsplit_once(char::is_whitespace) {
+        Some((name, _)) => name.trim().to_string(),
+        None => rest.to_string(),
+    }
+}
+
+/// Reader over one cue sheet track of an [`ApeReader`], from
+/// [`ApeReader::track`].
+///
+/// Samples start at the track's first block and stop at its last.
+#[cfg(feature = "std")]
+pub struct ApeTrackReader<'a, R: Read + Seek> {
+    pub(crate) reader: &'a mut ApeReader<R>,
+    pub(crate) track: CueTrack,
+    /// Block range of the track in the stream.
+    pub(crate) blocks: (u64, u64),
+    /// Format of the track; `total_samples` covers only the track.
+    pub(crate) info: ApeInfo,
+    /// Interleaved samples of the track not yet returned.
+    pub(crate) remaining: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeTrackReader<'_, R> {
+    /// The cue sheet entry.
+    pub fn track(&self) -> &CueTrack {
+        &self.track
+    }
+
+    /// Stream format, with `total_samples` counting only this track.
+    pub fn info(&self) -> &ApeInfo {
+        &self.info
+    }
+
+    /// First block of the track in the stream, and the block after its last.
+    pub fn block_range(&self) -> (u64, u64) {
+        self.blocks
+    }
+
+    /// Iterator over the track's remaining samples, interleaved as
+    /// [`ApeReader::samples`].
+    pub fn samples(&mut self) -> TrackSamples<'_, R> {
+        TrackSamples {
+            reader: self.reader,
+            remaining: &mut self.remaining,
+        }
+    }
+}
+
+/// Iterator over the samples of an [`ApeTrackReader`] or an
+/// [`ApeLinkReader`](crate::apl::ApeLinkReader).
+#[cfg(feature = "std")]
+pub struct TrackSamples<'a, R: Read + Seek> {
+    pub(crate) reader: &'a mut ApeReader<R>,
+    /// Interleaved samples left in the region.
+    pub(crate) remaining: &'a mut u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Iterator for TrackSamples<'_, R> {
+    type Item = Result<i32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if *self.remaining == 0 {
+            return None;
+        }
+        let sample = self.reader.samples().next()?;
+        if sample.is_ok() {
+            *self.remaining -= 1;
+        }
+        Some(sample)
+    }
+}
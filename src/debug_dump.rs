@@ -0,0 +1,180 @@
+//! Per-frame dumps of the decoder's internal state (feature `debug-dump`).
+//!
+//! When a file decodes differently here than in a reference decoder, the
+//! output alone rarely says why. [`FrameStats`] records how the adaptive
+//! parts of the codec evolved through a frame: each channel's Rice
+//! parameter `k` and its running sum `ksum`, the NNFilter stages' input
+//! averages, and how many escape symbols (values too large for the
+//! frequency model) the range coder decoded. Comparing a dump against
+//! the same values from an instrumented reference decoder points at the
+//! first block where the two diverge.
+//!
+//! [`ApeReader::dump_decode_state`] writes a dump of every frame of a file;
+//! [`PacketDecoder::record_stats`] collects stats for individual packets.
+
+use std::fmt;
+use std::io::{Read, Seek, Write};
+
+use crate::ApeReader;
+use crate::error::ApeError;
+use crate::nnfilter::NNFilter;
+use crate::packet::PacketDecoder;
+use crate::range_coder::RiceState;
+
+/// Blocks between [`TracePoint`]s.
+pub const TRACE_INTERVAL: u32 = 4096;
+
+/// Decoder state through one frame.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameStats {
+    /// Frame index.
+    pub frame: u32,
+    /// Blocks the frame was decoded with.
+    pub blocks: u32,
+    /// Blocks whose residuals were decoded before the frame finished or
+    /// failed.
+    pub decoded: u32,
+    /// Per coded channel; for stereo, the Y (mid) channel then X (side).
+    pub channels: Vec<ChannelStats>,
+    /// Each channel's last recorded block, `k` and `ksum`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last: Vec<Option<(u32, u32, u32)>>,
+}
+
+/// One coded channel's state through a frame.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelStats {
+    /// Escape symbols decoded.
+    pub escapes: u32,
+    /// Smallest Rice `k` after any block.
+    pub k_min: u32,
+    /// Largest Rice `k` after any block.
+    pub k_max: u32,
+    /// State after the first block, every [`TRACE_INTERVAL`] blocks after
+    /// it, and after the last block decoded.
+    pub trace: Vec<TracePoint>,
+}
+
+/// A channel's state after decoding one block.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TracePoint {
+    pub block: u32,
+    /// Rice parameter.
+    pub k: u32,
+    /// Running sum the Rice parameter adapts from.
+    pub ksum: u32,
+    /// Each NNFilter stage's running average of absolute input values,
+    /// first stage applied first; empty at Fast.
+    pub filter_avg: Vec<u32>,
+}
+
+impl FrameStats {
+    /// Start recording a frame of `blocks` blocks.
+    pub(crate) fn new(blocks: u32, channels: usize) -> Self {
+        FrameStats {
+            frame: 0,
+            blocks,
+            decoded: 0,
+            channels: vec![ChannelStats { k_min: u32::MAX, ..Default::default() }; channels],
+            last: vec![None; channels],
+        }
+    }
+
+    /// Record `channel`'s state after decoding `block`, with `escapes` the
+    /// range coder's escape count so far (for all channels).
+    pub(crate) fn record(&mut self, channel: usize, block: u32, rice: &RiceState, escapes: u32, filter: &NNFilter) {
+        let earlier: u32 = self.channels.iter().map(|c| c.escapes).sum();
+        let stats = &mut self.channels[channel];
+        stats.escapes += escapes - earlier;
+        stats.k_min = stats.k_min.min(rice.k);
+        stats.k_max = stats.k_max.max(rice.k);
+        if block.is_multiple_of(TRACE_INTERVAL) {
+            stats.trace.push(TracePoint { block, k: rice.k, ksum: rice.ksum, filter_avg: filter.averages() });
+        }
+        self.last[channel] = Some((block, rice.k, rice.ksum));
+        self.decoded = block + 1;
+    }
+
+    /// Trace each channel's last decoded block, whether the frame
+    /// finished or failed. The filters still hold their final state.
+    pub(crate) fn finish(&mut self, filters: &[NNFilter]) {
+        for ((stats, last), filter) in self.channels.iter_mut().zip(&self.last).zip(filters) {
+            if let Some((block, k, ksum)) = *last
+                && stats.trace.last().is_none_or(|p| p.block != block)
+            {
+                stats.trace.push(TracePoint { block, k, ksum, filter_avg: filter.averages() });
+            }
+            if stats.trace.is_empty() {
+                stats.k_min = 0;
+            }
+        }
+    }
+}
+
+impl fmt::Display for FrameStats {
+    /// One line for the frame, then per channel a summary line and a table
+    /// of its trace.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "frame {}: {} of {} blocks decoded", self.frame, self.decoded, self.blocks)?;
+        let names: &[&str] = if self.channels.len() == 2 { &["Y", "X"] } else { &["mono"] };
+        for (stats, name) in self.channels.iter().zip(names) {
+            if stats.trace.is_empty() {
+                writeln!(f, "  {name}: nothing decoded")?;
+                continue;
+            }
+            writeln!(f, "  {name}: k {}..={}, escapes {}", stats.k_min, stats.k_max, stats.escapes)?;
+            writeln!(f, "    {:>8} {:>3} {:>10}  filter avg", "block", "k", "ksum")?;
+            for point in &stats.trace {
+                write!(f, "    {:>8} {:>3} {:>10} ", point.block, point.k, point.ksum)?;
+                for avg in &point.filter_avg {
+                    write!(f, " {avg}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// Decode every frame on its own and write its [`FrameStats`] to
+    /// `out`, as text. A frame that fails is dumped up to the block that
+    /// failed, followed by the error; the dump goes on with the next frame.
+    ///
+    /// Like [`read_raw_frame`](Self::read_raw_frame), this does not disturb
+    /// decoding.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("mismatch.ape").unwrap();
+    /// reader.dump_decode_state(std::io::stderr()).unwrap();
+    /// ```
+    pub fn dump_decode_state<W: Write>(&mut self, mut out: W) -> Result<(), ApeError> {
+        let h = &self.decoder.header.header;
+        let mut packet = PacketDecoder::from_header(h.channels, h.bits_per_sample, h.compression_level);
+        packet.record_stats(true);
+        for index in 0..self.frame_count() {
+            let result = match self.read_raw_frame(index) {
+                Ok(Some(frame)) => packet.decode_packet(&frame.data, frame.info.skip, frame.info.blocks).map(drop),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+            match packet.take_stats() {
+                Some(mut stats) => {
+                    stats.frame = index;
+                    write!(out, "{stats}")?;
+                }
+                None => writeln!(out, "frame {index}: not decoded")?,
+            }
+            if let Err(e) = result {
+                writeln!(out, "  error: {e}")?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
@@ -9,17 +9,27 @@
 //! 6. Channel decorrelation inverse (mid/side → L/R)
 //! 7. Output interleaved PCM samples
 
+use std::fmt;
 use std::io::{Read, Seek, SeekFrom};
 
+use crate::{FrameErrorAction, FrameErrorHandler, FrameErrorMode, Progress, ProgressHandler};
 use crate::buffer::SampleBuffer;
-use crate::error::ApeError;
+use crate::crc::{self, FrameCrc};
+use crate::diag::diag;
+use crate::error::{ApeError, DecodeWarning, ErrorContext};
+use crate::frame_cache::FrameCache;
 use crate::header::ApeFileHeader;
-use crate::nnfilter::NNFilter;
-use crate::predictor::Predictor;
-use crate::range_coder::{RangeCoder, RiceState};
+use crate::memory::MemoryUsage;
+use crate::packet::{self, PacketDecoder, skip_frame_header};
 
-/// Number of blocks decoded per inner loop iteration.
-const BLOCKS_PER_LOOP: u32 = 4608;
+/// How many bytes past a bad seek-table position to search for the real
+/// start of a frame (see `decode_frame`).
+const RESYNC_WINDOW: u64 = 64;
+
+/// Most bytes to reserve up front for each of the frame input and output
+/// buffers. Frames of valid files fit well within this; a corrupt header
+/// claiming more only grows the buffers as frames actually need it.
+const PREALLOC_LIMIT_BYTES: usize = 16 << 20;
 
 /// Frame decoder state.
 pub struct Decoder<R: Read + Seek> {
@@ -27,39 +37,99 @@ pub struct Decoder<R: Read + Seek> {
     pub header: ApeFileHeader,
     /// Current frame index (0-based).
     current_frame: u32,
+    /// Blocks accounted for so far (decoded, concealed or skipped).
+    blocks_decoded: u64,
     /// Whether all frames have been decoded.
     pub finished: bool,
     /// Output sample buffer.
     pub buffer: SampleBuffer,
-    /// NNFilter instances — one per channel.
-    filters: Vec<NNFilter>,
-    /// Predictor.
-    predictor: Predictor,
-    /// Compression level set index: (level / 1000) - 1.
-    fset: usize,
+    /// Compressed bytes of the frame being decoded, reused across frames.
+    frame_data: Vec<u8>,
+    /// Per-frame decode pipeline (range coder, filters, predictor).
+    packet: PacketDecoder,
+    /// What to do when a frame fails to decode.
+    pub frame_error_mode: FrameErrorMode,
+    /// Per-frame override of `frame_error_mode`, if installed.
+    pub frame_error_handler: Option<FrameErrorHandler>,
+    /// Called after each frame with the blocks decoded so far.
+    pub progress_handler: Option<ProgressHandler>,
+    /// Non-fatal problems encountered so far.
+    pub warnings: Vec<DecodeWarning>,
+    /// Where the next frame starts (position, skip), if the previous frame
+    /// decoded cleanly.
+    next_frame: Option<(u64, u32)>,
+    /// Offset applied to seek-table positions after a resync.
+    resync_shift: i64,
+    /// Recently decoded frames, if caching is on.
+    pub frame_cache: Option<FrameCache>,
+}
+
+impl<R: Read + Seek> fmt::Debug for Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("total_frames", &self.header.header.total_frames)
+            .field("current_frame", &self.current_frame)
+            .field("blocks_decoded", &self.blocks_decoded)
+            .field("finished", &self.finished)
+            .field("buffer", &self.buffer)
+            .field("packet", &self.packet)
+            .field("frame_error_mode", &self.frame_error_mode)
+            .field("frame_error_handler", &self.frame_error_handler.as_ref().map(|_| ".."))
+            .field("progress_handler", &self.progress_handler.as_ref().map(|_| ".."))
+            .field("warnings", &self.warnings.len())
+            .field("next_frame", &self.next_frame)
+            .field("resync_shift", &self.resync_shift)
+            .field("frame_cache", &self.frame_cache.as_ref().map(FrameCache::stats))
+            .finish_non_exhaustive()
+    }
 }
 
 impl<R: Read + Seek> Decoder<R> {
     /// Create a new decoder from a reader and parsed header.
-    pub fn new(reader: R, header: ApeFileHeader) -> Self {
-        let fset = (header.header.compression_level / 1000 - 1) as usize;
-        let channels = header.header.channels as usize;
-
-        // Create one NNFilter per channel
-        let mut filters = Vec::with_capacity(channels);
-        for _ in 0..channels {
-            filters.push(NNFilter::new(fset));
-        }
+    pub fn new(reader: R, header: ApeFileHeader, frame_error_mode: FrameErrorMode) -> Self {
+        let h = &header.header;
+        let packet = PacketDecoder::from_header(h.channels, h.bits_per_sample, h.compression_level);
+        let buffer = SampleBuffer::with_capacity(output_capacity(&header));
+        let frame_data = Vec::with_capacity(input_capacity(&header));
 
         Decoder {
             reader,
             header,
             current_frame: 0,
+            blocks_decoded: 0,
             finished: false,
-            buffer: SampleBuffer::new(),
-            filters,
-            predictor: Predictor::new(),
-            fset,
+            buffer,
+            frame_data,
+            packet,
+            frame_error_mode,
+            frame_error_handler: None,
+            progress_handler: None,
+            warnings: Vec::new(),
+            next_frame: None,
+            resync_shift: 0,
+            frame_cache: None,
+        }
+    }
+
+    /// Start over on another stream, keeping the error handling settings
+    /// and, where the format allows, the frame decoder's allocations.
+    pub fn reset_for(&mut self, reader: R, header: ApeFileHeader) {
+        let h = &header.header;
+        self.packet.reuse_for(h.channels, h.bits_per_sample, h.compression_level);
+        self.reader = reader;
+        self.header = header;
+        self.current_frame = 0;
+        self.blocks_decoded = 0;
+        self.finished = false;
+        self.buffer.clear();
+        self.buffer.reserve(output_capacity(&self.header));
+        self.frame_data.clear();
+        self.frame_data.reserve(input_capacity(&self.header));
+        self.warnings.clear();
+        self.next_frame = None;
+        self.resync_shift = 0;
+        if let Some(cache) = &mut self.frame_cache {
+            cache.clear();
         }
     }
 
@@ -70,6 +140,10 @@ impl<R: Read + Seek> Decoder<R> {
 
     /// Decode the next frame, filling the sample buffer.
     /// Returns true if samples were decoded, false if stream ended.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "decode_frame", level = "debug", skip_all, fields(frame = self.current_frame))
+    )]
     pub fn decode_next_frame(&mut self) -> Result<bool, ApeError> {
         if self.current_frame >= self.header.header.total_frames {
             self.finished = true;
@@ -77,185 +151,510 @@ impl<R: Read + Seek> Decoder<R> {
         }
 
         // How many blocks (samples per channel) in this frame?
-        let nblocks = if self.current_frame == self.header.header.total_frames - 1 {
-            self.header.header.final_frame_blocks
-        } else {
-            self.header.header.blocks_per_frame
-        };
+        let nblocks = self.header.frame_blocks(self.current_frame as usize);
 
         if nblocks == 0 {
             self.finished = true;
             return Ok(false);
         }
 
-        // Read compressed frame data
-        let frame_data = self.read_frame_data()?;
+        // Never emit more than the header advertises, whatever the frame
+        // table says — downstream buffers are sized from total_samples().
+        let total_blocks = self.header.total_blocks();
+        if self.blocks_decoded + nblocks as u64 > total_blocks {
+            self.finished = true;
+            return Err(ApeError::SampleOverrun(format!(
+                "frame {} would bring the block count to {}, header declares {total_blocks}",
+                self.current_frame,
+                self.blocks_decoded + nblocks as u64
+            ))
+            .with_context(self.error_context()));
+        }
+
+        // Retries must start from the same resync hint as the first attempt
+        let hint = self.next_frame;
+        let mut attempt = 0;
+        let result = loop {
+            attempt += 1;
+            self.next_frame = hint;
+            match self.decode_frame(nblocks) {
+                Ok(()) => break Ok(()),
+                Err(e) => {
+                    let e = e.with_context(self.error_context());
+                    match self.frame_error_action(&e, attempt) {
+                        FrameErrorAction::Retry => continue,
+                        action => break Err((e, action)),
+                    }
+                }
+            }
+        };
+        let frame = self.current_frame;
+        self.current_frame += 1;
+        self.blocks_decoded += nblocks as u64;
+        if let Some(handler) = &self.progress_handler {
+            handler(Progress {
+                blocks_decoded: self.blocks_decoded,
+                total_blocks,
+            });
+        }
 
-        // Reset filter and predictor state for this frame
-        for f in &mut self.filters {
-            f.reset();
+        match result {
+            Ok(()) => Ok(true),
+            Err((e, FrameErrorAction::Abort)) => {
+                diag!(error, frame = frame; "frame decode failed: {e}");
+                // The error stands in for the frame's samples
+                self.buffer.clear();
+                Err(e)
+            }
+            Err((e, action)) => {
+                diag!(warn, frame = frame; "frame dropped ({action:?}): {e}");
+                // Drop whatever was partially decoded; the next frame starts
+                // from freshly reset filter and predictor state anyway.
+                self.buffer.clear();
+                if action == FrameErrorAction::FillSilence {
+                    let channels = self.header.header.channels as usize;
+                    self.buffer.push_silence(nblocks as usize * channels);
+                }
+                self.warnings.push(DecodeWarning::FrameDropped { frame, error: e });
+                Ok(true)
+            }
         }
-        self.predictor.reset();
-        self.buffer.clear();
+    }
 
-        // Decode the frame
-        let channels = self.header.header.channels;
-        if channels == 1 {
-            self.decode_frame_mono(&frame_data, nblocks)?;
+    /// Where the next sample comes from: the frame it belongs to, the
+    /// samples of that frame already returned, and the seek-table offset
+    /// learned from resyncs so far.
+    pub fn position(&self) -> (u32, usize, i64) {
+        if self.buffer.is_empty() {
+            (self.current_frame, 0, self.resync_shift)
         } else {
-            self.decode_frame_stereo(&frame_data, nblocks)?;
+            (self.current_frame - 1, self.buffer.position(), self.resync_shift)
         }
+    }
 
-        self.current_frame += 1;
-        Ok(true)
+    /// Heap bytes held now.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let packet = self.packet.memory_bytes();
+        let filters = self.packet.filter_memory_bytes();
+        MemoryUsage {
+            filters,
+            predictor: packet - filters,
+            output: self.buffer.capacity() * size_of::<i32>(),
+            frame_input: self.frame_data.capacity(),
+            seek_table: self.header.seek_table.capacity() * size_of::<u32>(),
+        }
+    }
+
+    /// Stream index (interleaved, from 0) of the next sample, counted from
+    /// its frame's nominal start, so it stays right across seeks and
+    /// dropped frames.
+    pub fn sample_index(&self) -> u64 {
+        let (frame, samples, _) = self.position();
+        let h = &self.header.header;
+        frame as u64 * h.blocks_per_frame as u64 * h.channels as u64 + samples as u64
+    }
+
+    /// Continue from a position returned by `position`: decode `frame` and
+    /// drop its first `samples` samples.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "seek", level = "debug", skip(self), err))]
+    pub fn restore_position(&mut self, frame: u32, samples: usize, resync_shift: i64) -> Result<(), ApeError> {
+        self.current_frame = frame;
+        self.blocks_decoded = frame as u64 * self.header.header.blocks_per_frame as u64;
+        self.resync_shift = resync_shift;
+        self.next_frame = None;
+        self.finished = false;
+        self.buffer.clear();
+        if samples > 0 {
+            self.decode_next_frame()?;
+            self.buffer.skip(samples);
+        }
+        Ok(())
+    }
+
+    /// How to handle a failed frame: ask the installed handler, or else
+    /// apply the frame error mode to recoverable errors.
+    fn frame_error_action(&self, err: &ApeError, attempt: u32) -> FrameErrorAction {
+        if let Some(handler) = &self.frame_error_handler {
+            return handler(err, attempt);
+        }
+        if !err.is_recoverable() {
+            return FrameErrorAction::Abort;
+        }
+        match self.frame_error_mode {
+            FrameErrorMode::Strict => FrameErrorAction::Abort,
+            FrameErrorMode::Silence => FrameErrorAction::FillSilence,
+            FrameErrorMode::Skip => FrameErrorAction::Skip,
+        }
     }
 
-    /// Read compressed data for the current frame.
+    /// Location of the current frame, for error reporting.
+    fn error_context(&self) -> ErrorContext {
+        let frame = self.current_frame;
+        let sample = frame as u64
+            * self.header.header.blocks_per_frame as u64
+            * self.header.header.channels as u64;
+        let byte_offset = self
+            .header
+            .frame_position(frame as usize)
+            .unwrap_or(self.header.data_offset);
+        ErrorContext {
+            frame,
+            sample,
+            byte_offset,
+        }
+    }
+
+    /// Decode and CRC-check the current frame into the sample buffer, or
+    /// copy it from the frame cache.
+    fn decode_frame(&mut self, nblocks: u32) -> Result<(), ApeError> {
+        let frame = self.current_frame;
+        if let Some(entry) = self.frame_cache.as_mut().and_then(|cache| cache.get(frame)) {
+            self.buffer.clear();
+            self.buffer.extend_from_slice(&entry.samples);
+            self.next_frame = Some(entry.next);
+            self.resync_shift = entry.resync_shift;
+            return Ok(());
+        }
+        self.decode_frame_uncached(nblocks)?;
+        if let (Some(cache), Some(next)) = (&mut self.frame_cache, self.next_frame) {
+            cache.insert(frame, self.buffer.as_slice(), next, self.resync_shift);
+        }
+        Ok(())
+    }
+
+    /// Decode and CRC-check the current frame into the sample buffer.
     ///
-    /// Reads from a 4-byte-aligned file position (matching FFmpeg's bswap_buf
-    /// alignment) and byte-swaps each 4-byte group so the range coder sees
-    /// bytes in the correct order.
-    fn read_frame_data(&mut self) -> Result<Vec<u8>, ApeError> {
-        let frame_idx = self.current_frame as usize;
-        let seek_table = &self.header.seek_table;
-
-        if frame_idx >= seek_table.len() {
-            return Err(ApeError::InvalidSeekTable);
+    /// If the frame fails at its seek-table position, look for where it
+    /// really starts: where the previous frame ended, if that one decoded
+    /// cleanly, or otherwise a bounded window past the table position.
+    /// A position that decodes with a matching CRC is taken as the frame,
+    /// and its offset from the table is applied to later frames too.
+    fn decode_frame_uncached(&mut self, nblocks: u32) -> Result<(), ApeError> {
+        let frame = self.current_frame as usize;
+        let table_pos = self
+            .header
+            .frame_position(frame)
+            .ok_or(ApeError::InvalidSeekTable)?;
+        let pos = table_pos.saturating_add_signed(self.resync_shift);
+        let skip = self.header.frame_skip(frame);
+
+        let err = match self.decode_frame_at(pos, skip, nblocks) {
+            Ok(next) => {
+                self.next_frame = Some(next);
+                return Ok(());
+            }
+            Err(e) if !e.is_recoverable() => return Err(e),
+            // The frame was found and decoded; it just holds too many blocks
+            Err(e @ ApeError::SampleOverrun(_)) => return Err(e),
+            Err(e) => e,
+        };
+
+        let candidates: Vec<(u64, u32)> = match self.next_frame.take() {
+            // The previous frame ends exactly here: the data itself is bad
+            Some((next_pos, _)) if next_pos == pos => Vec::new(),
+            Some(next) => vec![next],
+            None => (1..=RESYNC_WINDOW).map(|d| (pos + d, skip)).collect(),
+        };
+        for (candidate, candidate_skip) in candidates {
+            // Non-local errors (I/O) at a speculative position just rule it out
+            if let Ok(next) = self.decode_frame_at(candidate, candidate_skip, nblocks) {
+                self.resync_shift = candidate as i64 - table_pos as i64;
+                self.next_frame = Some(next);
+                diag!(
+                    warn,
+                    frame = self.current_frame,
+                    table_offset = table_pos,
+                    actual_offset = candidate;
+                    "frame resynced"
+                );
+                self.warnings.push(DecodeWarning::FrameResynced {
+                    frame: self.current_frame,
+                    table_offset: table_pos,
+                    actual_offset: candidate,
+                });
+                return Ok(());
+            }
         }
 
-        // Start at 4-byte aligned position (low 2 bits are alignment skip)
-        let start = (seek_table[frame_idx] & !3) as u64;
+        Err(err)
+    }
 
+    /// Decode and CRC-check one frame starting at file offset `pos`, which
+    /// is `skip` bytes into a 32-bit word.
+    ///
+    /// Returns the position and skip of the byte after the frame, which is
+    /// where the next frame starts.
+    fn decode_frame_at(&mut self, pos: u64, skip: u32, nblocks: u32) -> Result<(u64, u32), ApeError> {
+        self.buffer.clear();
+        // When looking past the table position, read correspondingly further,
+        // but never into the trailing tags
+        let table_pos = self.header.frame_position(self.current_frame as usize).unwrap_or(pos);
+        let start = pos - skip as u64;
+        // Round up to whole words (as FFmpeg does): the frame's last bytes
+        // share a word with the next frame and are only in order once the
+        // whole word is swapped
+        let end = self.frame_end(pos) + pos.saturating_sub(table_pos);
+        let end = (start + end.saturating_sub(start).next_multiple_of(4)).min(self.header.tag_offset);
+        let frame_data = self.read_frame_bytes(start, end)?;
+
+        let result = self
+            .packet
+            .decode_frame(&frame_data, skip, nblocks, self.current_frame, &mut self.buffer);
+        self.frame_data = frame_data;
+        let frame_len = result?;
+        diag!(debug, offset = pos, bytes = frame_len, blocks = nblocks; "decoded frame");
+        Ok((start + frame_len as u64, (frame_len & 3) as u32))
+    }
+
+    /// File offset where the current frame's data ends, per the seek table.
+    ///
+    /// If the next entry does not lie past `pos` (a corrupt table), fall
+    /// back to the end of the frame data region.
+    fn frame_end(&self, pos: u64) -> u64 {
         // End is either the next frame's offset or end of frame data.
         // Use total_frames (not seek_table.len()) — the seek table may be
         // pre-allocated to a maximum size with zero-filled trailing entries.
+        let frame = self.current_frame as usize;
         let total_frames = self.header.header.total_frames as usize;
-        let end = if frame_idx + 1 < total_frames {
-            seek_table[frame_idx + 1] as u64
+        let last_frame_end = self.header.frame_data_end();
+        if frame + 1 < total_frames {
+            self.header
+                .frame_position(frame + 1)
+                .filter(|&next| next > pos)
+                .unwrap_or(last_frame_end)
         } else {
-            // Last frame: compute from total frame data size
-            let total_data = self.header.descriptor.ape_frame_data_bytes as u64
-                | ((self.header.descriptor.ape_frame_data_bytes_high as u64) << 32);
-            self.header.data_offset + total_data
-        };
+            last_frame_end
+        }
+    }
 
-        let size = (end - start) as usize;
+    /// Read compressed frame bytes from word-aligned file offset `start` up
+    /// to file offset `end` (or end of stream, if sooner).
+    ///
+    /// Byte-swaps each 4-byte group (matching FFmpeg's bswap_buf) so the
+    /// range coder sees bytes in the correct order. A short read is not an
+    /// error here: a truncated frame shows up as a range coder overrun.
+    ///
+    /// The bytes are read into the decoder's reused input buffer, which is
+    /// lent out: callers hand it back in `frame_data` when done with it.
+    fn read_frame_bytes(&mut self, start: u64, end: u64) -> Result<Vec<u8>, ApeError> {
+        let size = end.saturating_sub(start);
         if size == 0 {
             return Err(ApeError::UnexpectedEof);
         }
 
-        // Seek and read
-        self.reader.seek(SeekFrom::Start(start))?;
-        let mut data = vec![0u8; size];
-        self.reader.read_exact(&mut data)?;
-
-        // Byte-swap each 4-byte group (matching FFmpeg's bswap_buf).
-        // APE stores data as little-endian 32-bit words; the range coder
-        // expects the bytes in big-endian order within each word.
-        let full_words = size / 4;
-        for i in 0..full_words {
-            let off = i * 4;
-            data.swap(off, off + 3);
-            data.swap(off + 1, off + 2);
+        // Seek and read. Read incrementally rather than reserving `size`
+        // bytes: a corrupt seek table can claim gigabytes.
+        let mut data = core::mem::take(&mut self.frame_data);
+        data.clear();
+        let read = self
+            .reader
+            .seek(SeekFrom::Start(start))
+            .and_then(|_| (&mut self.reader).take(size).read_to_end(&mut data));
+        if let Err(e) = read {
+            self.frame_data = data;
+            return Err(e.into());
+        }
+        if data.is_empty() {
+            self.frame_data = data;
+            return Err(ApeError::UnexpectedEof);
         }
 
+        packet::byte_swap_words(&mut data);
+
         Ok(data)
     }
 
-    /// Skip the per-frame header: alignment bytes, CRC, optional frame flags, skip byte.
-    /// Returns a slice pointing to the start of range-coded data.
-    fn skip_frame_header<'a>(&self, frame_data: &'a [u8]) -> Result<&'a [u8], ApeError> {
-        let mut pos = 0usize;
-
-        // Skip byte-alignment padding (low 2 bits of seek table entry)
-        let align_skip = (self.header.seek_table[self.current_frame as usize] & 3) as usize;
-        pos += align_skip;
-
-        if pos + 4 > frame_data.len() {
-            return Err(ApeError::UnexpectedEof);
-        }
-
-        // Read 4-byte big-endian CRC
-        let crc = u32::from_be_bytes([
-            frame_data[pos],
-            frame_data[pos + 1],
-            frame_data[pos + 2],
-            frame_data[pos + 3],
-        ]);
-        pos += 4;
-
-        // If CRC has high bit set, next 4 bytes are frame flags
-        if crc & 0x80000000 != 0 {
-            if pos + 4 > frame_data.len() {
-                return Err(ApeError::UnexpectedEof);
+    /// Rebuild the seek table (and, if the header lacks them, the frame
+    /// counts) by decoding frames sequentially from the start of the data
+    /// region.
+    ///
+    /// Each frame ends where its range coder stops consuming input, which is
+    /// exactly where the next frame's CRC begins, so offsets can be chained
+    /// without a seek table. When `total_frames` is zero, every frame is
+    /// assumed full except the last, whose length is found by matching the
+    /// stored CRC against successively longer prefixes of its output.
+    /// Scanning stops at the first frame that cannot be decoded; the layout
+    /// then covers only the frames recovered before it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn rebuild_frame_layout(&mut self) -> Result<(), ApeError> {
+        let h = &self.header.header;
+        let known_frames = h.total_frames;
+        let blocks_per_frame = h.blocks_per_frame;
+        let final_frame_blocks = h.final_frame_blocks;
+        // Generous bound on a frame's compressed size: twice its raw PCM size
+        let max_frame_bytes = blocks_per_frame as u64
+            * h.channels as u64
+            * (h.bits_per_sample as u64 / 8)
+            * 2
+            + 1024;
+
+        let data_offset = self.header.data_offset;
+        let data_end = self.header.frame_data_end();
+        let mut positions = Vec::new();
+        let mut last_blocks = 0u32;
+        let mut pos = data_offset;
+        let mut skip = 0u32;
+        let mut end_of_data = pos;
+
+        while pos < data_end {
+            let frame = positions.len() as u32;
+            if known_frames > 0 && frame >= known_frames {
+                break;
+            }
+            let nblocks = if known_frames > 0 && frame == known_frames - 1 {
+                final_frame_blocks
+            } else {
+                blocks_per_frame
+            };
+
+            let start = pos - skip as u64;
+            let frame_data = match self.read_frame_bytes(start, data_end.min(start + max_frame_bytes)) {
+                Ok(d) => d,
+                Err(e) if e.is_recoverable() => break,
+                Err(e) => return Err(e),
+            };
+            let Ok((expected_crc, data)) = skip_frame_header(&frame_data, skip) else {
+                break;
+            };
+            let header_len = frame_data.len() - data.len();
+
+            self.buffer.clear();
+            let result = self.packet.decode_samples(data, nblocks, &mut self.buffer);
+            self.frame_data = frame_data;
+            let bits = self.header.header.bits_per_sample;
+
+            match result {
+                Ok(consumed) if crc::frame_crc(self.buffer.as_slice(), bits) == expected_crc => {
+                    positions.push(pos);
+                    last_blocks = nblocks;
+                    let frame_len = header_len + consumed;
+                    pos = start + frame_len as u64;
+                    skip = (frame_len & 3) as u32;
+                    end_of_data = pos;
+                }
+                Err(e) if !e.is_recoverable() => return Err(e),
+                _ => {
+                    // A short final frame of unknown length: find the longest
+                    // prefix whose CRC matches the stored one.
+                    if known_frames == 0
+                        && let Some(matched) = self.crc_matched_blocks(expected_crc)
+                    {
+                        positions.push(pos);
+                        last_blocks = matched;
+                        end_of_data = data_end;
+                    }
+                    break;
+                }
             }
-            // frame flags — we don't use them yet but must skip
-            pos += 4;
         }
 
-        // Skip 1 byte (the first 8 bits of input are ignored by the range coder)
-        if pos >= frame_data.len() {
-            return Err(ApeError::UnexpectedEof);
-        }
-        pos += 1;
+        // Seek-table entries are relative to the descriptor
+        let junk = self.header.junk_bytes;
+        let frame_data_bytes = end_of_data - data_offset;
+        self.header.seek_table = positions.iter().map(|&p| (p - junk) as u32).collect();
+        self.header.header.total_frames = self.header.seek_table.len() as u32;
+        self.header.header.final_frame_blocks = last_blocks;
+        self.header.descriptor.ape_frame_data_bytes = frame_data_bytes as u32;
+        self.header.descriptor.ape_frame_data_bytes_high = (frame_data_bytes >> 32) as u32;
+        diag!(debug, frames = self.header.header.total_frames, final_frame_blocks = last_blocks; "rebuilt frame layout");
 
-        Ok(&frame_data[pos..])
+        self.buffer.clear();
+        self.current_frame = 0;
+        self.finished = false;
+        self.next_frame = None;
+        Ok(())
     }
 
-    /// Decode a mono frame.
-    fn decode_frame_mono(
-        &mut self,
-        frame_data: &[u8],
-        nblocks: u32,
-    ) -> Result<(), ApeError> {
-        let data = self.skip_frame_header(frame_data)?;
-
-        let mut rc = RangeCoder::new(data);
-        let mut rice = RiceState::new();
-
-        for _ in 0..nblocks {
-            // 1. Range decode residual
-            let residual = rc.decode_value(&mut rice);
-
-            // 2. NNFilter inverse
-            let filtered = self.filters[0].decompress(residual);
-
-            // 3. Predictor inverse
-            let sample = self.predictor.decode_mono(filtered);
+    /// Determine the true frame count and final frame length, correcting
+    /// the header if its totals are wrong.
+    ///
+    /// The frame count is the number of seek-table entries that advance
+    /// through the data region. The final frame is decoded for up to a full
+    /// frame of blocks, and its length is the longest prefix of that output
+    /// whose CRC matches the stored one. If no prefix matches, the header is
+    /// left as it is.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn resolve_exact_duration(&mut self) -> Result<(), ApeError> {
+        let declared_blocks = self.header.total_blocks();
+        let data_end = self.header.frame_data_end();
+        let mut frames = 0;
+        let mut prev = None;
+        while let Some(pos) = self.header.frame_position(frames) {
+            if pos >= data_end || prev.is_some_and(|p| pos <= p) {
+                break;
+            }
+            prev = Some(pos);
+            frames += 1;
+        }
+        let Some(pos) = prev else { return Ok(()) };
+
+        let last = frames - 1;
+        let skip = self.header.frame_skip(last);
+        let start = pos - skip as u64;
+        let frame_data = match self.read_frame_bytes(start, data_end) {
+            Ok(d) => d,
+            Err(e) if e.is_recoverable() => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let Ok((expected_crc, data)) = skip_frame_header(&frame_data, skip) else {
+            return Ok(());
+        };
 
-            self.buffer.push(sample);
+        // Decoding past the real end of the frame fails or produces
+        // garbage, but the prefix up to it is intact.
+        self.buffer.clear();
+        let result = self.packet.decode_samples(data, self.header.header.blocks_per_frame, &mut self.buffer);
+        self.frame_data = frame_data;
+        match result {
+            Err(e) if !e.is_recoverable() => return Err(e),
+            _ => {}
         }
+        let matched = self.crc_matched_blocks(expected_crc);
+        self.buffer.clear();
 
+        if let Some(blocks) = matched {
+            self.header.header.total_frames = frames as u32;
+            self.header.header.final_frame_blocks = blocks;
+            let actual_blocks = self.header.total_blocks();
+            if actual_blocks != declared_blocks {
+                diag!(warn, declared_blocks = declared_blocks, actual_blocks = actual_blocks; "duration corrected");
+                self.warnings.push(DecodeWarning::DurationCorrected {
+                    declared_blocks,
+                    actual_blocks,
+                });
+            }
+        }
         Ok(())
     }
 
-    /// Decode a stereo frame.
-    fn decode_frame_stereo(
-        &mut self,
-        frame_data: &[u8],
-        nblocks: u32,
-    ) -> Result<(), ApeError> {
-        let data = self.skip_frame_header(frame_data)?;
-
-        let mut rc = RangeCoder::new(data);
-        let mut rice_y = RiceState::new();
-        let mut rice_x = RiceState::new();
-
-        for _ in 0..nblocks {
-            // Decode Y channel (first in stereo)
-            let residual_y = rc.decode_value(&mut rice_y);
-            let filtered_y = self.filters[0].decompress(residual_y);
-
-            // Decode X channel
-            let residual_x = rc.decode_value(&mut rice_x);
-            let filtered_x = self.filters[1].decompress(residual_x);
-
-            // Predictor inverse + channel decorrelation
-            let (left, right) = self.predictor.decode_stereo(filtered_y, filtered_x);
-
-            self.buffer.push_stereo(left, right);
+    /// The longest run of leading blocks in the buffer whose CRC equals
+    /// `expected_crc`, if any.
+    fn crc_matched_blocks(&self, expected_crc: u32) -> Option<u32> {
+        let channels = self.header.header.channels as usize;
+        let bits = self.header.header.bits_per_sample;
+        let mut crc = FrameCrc::new();
+        let mut matched = None;
+        for (i, block) in self.buffer.as_slice().chunks_exact(channels).enumerate() {
+            crc.update_samples(block, bits);
+            if crc.finish() == expected_crc {
+                matched = Some(i as u32 + 1);
+            }
         }
-
-        Ok(())
+        matched
     }
 }
+
+/// Samples to reserve for the output buffer: one full frame.
+fn output_capacity(header: &ApeFileHeader) -> usize {
+    let h = &header.header;
+    let samples = h.blocks_per_frame as usize * h.channels as usize;
+    samples.min(PREALLOC_LIMIT_BYTES / size_of::<i32>())
+}
+
+/// Bytes to reserve for the compressed input: the largest frame, plus the
+/// word alignment on either side and the read-ahead `read_to_end` does to
+/// detect the end of input.
+fn input_capacity(header: &ApeFileHeader) -> usize {
+    let largest = (0..header.seek_table.len()).filter_map(|f| header.frame_size(f)).max().unwrap_or(0);
+    (largest as usize).saturating_add(64).min(PREALLOC_LIMIT_BYTES)
+}
@@ -0,0 +1,25 @@
+//! Diagnostic events, forwarded to `tracing` (feature `tracing`) and/or
+//! `log` (feature `log`).
+//!
+//! Call sites use [`diag!`] so they need no `cfg` of their own; without
+//! either feature the macro expands to nothing and its arguments are not
+//! evaluated. Fields become `tracing` fields or `log` key-values.
+//!
+//! ```ignore
+//! diag!(warn, frame = 3, offset = pos; "frame resynced");
+//! ```
+//!
+//! Levels follow what a caller can do about the event: `debug` for normal
+//! progress (header fields, frame offsets and sizes), `warn` for problems
+//! the decoder recovered from, `error` for ones it could not.
+
+macro_rules! diag {
+    ($level:ident, $($key:ident = $value:expr),+ ; $($msg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($key = $value,)+ $($msg)+);
+        #[cfg(feature = "log")]
+        log::$level!(target: "ape_rs", $($key = $value),+ ; $($msg)+);
+    };
+}
+
+pub(crate) use diag;
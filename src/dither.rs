@@ -0,0 +1,161 @@
+//! Gain and dither for bit-depth reduction.
+//!
+//! Converting a 24-bit decode to 16 bits by dropping the low byte
+//! truncates: the error is correlated with the signal and audible as
+//! distortion on quiet material. [`Requantizer`] instead rounds after
+//! adding TPDF (triangular) dither of ±1 output LSB, which turns that error
+//! into a constant, signal-independent noise floor, optionally shaped
+//! towards high frequencies where it is less audible. A gain can be applied
+//! first, e.g. to make headroom for a downstream resampler.
+//!
+//! [`ApeReader::samples_i16`](crate::ApeReader::samples_i16) uses it for any
+//! source deeper than 16 bits.
+
+use crate::convert;
+use crate::error::ApeError;
+
+/// Noise shaping applied with the dither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoiseShaping {
+    /// Flat (white) requantization noise.
+    #[default]
+    None,
+    /// First-order error feedback: each sample's requantization error is
+    /// subtracted from the next, moving the noise up in frequency.
+    FirstOrder,
+}
+
+/// How samples are scaled and requantized by a [`Requantizer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DitherOptions {
+    /// Gain in dB applied before requantizing. Results beyond full scale
+    /// are clamped.
+    pub gain_db: f64,
+    /// Add TPDF dither when the output cannot represent the scaled sample
+    /// exactly. Without it samples are rounded to the nearest value.
+    pub dither: bool,
+    pub noise_shaping: NoiseShaping,
+    /// Seed of the dither noise. The same seed gives the same output.
+    pub seed: u64,
+}
+
+impl Default for DitherOptions {
+    fn default() -> Self {
+        DitherOptions {
+            gain_db: 0.0,
+            dither: true,
+            noise_shaping: NoiseShaping::None,
+            seed: 1,
+        }
+    }
+}
+
+/// Converts interleaved samples from one bit depth to another, with the
+/// gain, dither and noise shaping of its [`DitherOptions`].
+///
+/// With no gain and no loss of depth the conversion is exact (a shift for
+/// a deeper output) and no dither is added.
+#[derive(Debug, Clone)]
+pub struct Requantizer {
+    /// Input to output scale, gain included.
+    scale: f64,
+    /// Source and output depth, if the conversion is a lossless shift.
+    exact: Option<(u16, u16)>,
+    min: i32,
+    max: i32,
+    dither: bool,
+    shaping: NoiseShaping,
+    rng: u64,
+    /// Previous requantization error per channel, for noise shaping.
+    error: Vec<f64>,
+    channel: usize,
+}
+
+impl Requantizer {
+    /// Create a requantizer from `from_bits` to `to_bits` (1 to 32) for
+    /// audio with `channels` channels.
+    pub fn new(channels: u16, from_bits: u16, to_bits: u16, options: DitherOptions) -> Self {
+        let (from_bits, to_bits) = (from_bits.clamp(1, 32), to_bits.clamp(1, 32));
+        let shift = to_bits as i32 - from_bits as i32;
+        let exact = (options.gain_db == 0.0 && shift >= 0).then_some((from_bits, to_bits));
+        let range = convert::sample_range(to_bits);
+        Requantizer {
+            scale: 10f64.powf(options.gain_db / 20.0) * 2f64.powi(shift),
+            exact,
+            min: *range.start(),
+            max: *range.end(),
+            dither: options.dither,
+            shaping: options.noise_shaping,
+            rng: options.seed | 1,
+            error: vec![0.0; channels.max(1) as usize],
+            channel: 0,
+        }
+    }
+
+    /// Convert the next sample. Samples are expected interleaved, one
+    /// channel after the other.
+    pub fn next_sample(&mut self, sample: i32) -> i32 {
+        if let Some((from_bits, to_bits)) = self.exact {
+            return convert::rescale(sample, from_bits, to_bits);
+        }
+        let channel = self.channel;
+        self.channel = (channel + 1) % self.error.len();
+
+        let wanted = sample as f64 * self.scale;
+        let target = match self.shaping {
+            NoiseShaping::None => wanted,
+            NoiseShaping::FirstOrder => wanted - self.error[channel],
+        };
+        let noise = if self.dither { self.uniform() + self.uniform() } else { 0.0 };
+        let out = (target + noise).round().clamp(self.min as f64, self.max as f64);
+        // Clamped samples would feed back a huge error; keep it to the
+        // dither's range
+        self.error[channel] = (out - target).clamp(-1.5, 1.5);
+        out as i32
+    }
+
+    /// Convert `samples` in place.
+    pub fn process(&mut self, samples: &mut [i32]) {
+        for s in samples {
+            *s = self.next_sample(*s);
+        }
+    }
+
+    /// Uniform noise in [-0.5, 0.5), from xorshift64*.
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let r = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        r as f64 / (1u64 << 53) as f64 - 0.5
+    }
+}
+
+/// Iterator adapter converting interleaved samples to 16 bits, from
+/// [`ApeReader::samples_i16`](crate::ApeReader::samples_i16).
+#[derive(Debug)]
+pub struct SamplesI16<I> {
+    inner: I,
+    requantizer: Requantizer,
+}
+
+impl<I> SamplesI16<I> {
+    /// Convert `samples` (`channels` channels of `bits_per_sample` bits)
+    /// to 16 bits with `options`.
+    pub fn new(samples: I, channels: u16, bits_per_sample: u16, options: DitherOptions) -> Self {
+        SamplesI16 {
+            inner: samples,
+            requantizer: Requantizer::new(channels, bits_per_sample, 16, options),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<i32, ApeError>>> Iterator for SamplesI16<I> {
+    type Item = Result<i16, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(|s| self.requantizer.next_sample(s) as i16))
+    }
+}
@@ -1,8 +1,20 @@
-use std::fmt;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::io;
 
 /// Errors that can occur while decoding a Monkey's Audio (APE) file.
-#[derive(Debug)]
+///
+/// Match on [`ApeError::kind`] rather than on the variants directly: the
+/// kind looks through any attached [`ErrorContext`], and new variants may be
+/// added in future releases.
+///
+/// Errors are plain data: they can be cloned, compared in assertions and
+/// sent over channels. I/O errors keep their [`io::ErrorKind`] and message
+/// (see [`IoError`]) rather than the original `io::Error`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ApeError {
     /// The file does not start with the APE magic bytes `MAC `.
     InvalidMagic,
@@ -20,8 +32,265 @@ pub enum ApeError {
     RangeCoderError(String),
     /// Unexpected end of data in a compressed frame.
     UnexpectedEof,
-    /// A wrapped I/O error.
-    Io(io::Error),
+    /// The stream holds more audio than the header accounts for.
+    SampleOverrun(String),
+    /// An I/O error from the underlying reader or writer.
+    #[cfg(feature = "std")]
+    Io(IoError),
+    /// A decoder snapshot is malformed or belongs to a different stream.
+    InvalidSnapshot(String),
+    /// A stream or frame needs more memory than a fixed-size decoder was
+    /// created with.
+    CapacityExceeded(String),
+    /// Streams that must share a format (e.g. tracks chained into one
+    /// stream) do not.
+    FormatMismatch(String),
+    /// A cue sheet is malformed or does not fit the stream.
+    InvalidCueSheet(String),
+    /// An APL link file is malformed or does not fit its image file.
+    InvalidLink(String),
+    /// The file's contents do not match the MD5 stored in its descriptor.
+    Md5Mismatch { expected: [u8; 16], actual: [u8; 16] },
+    /// Predictor or NNFilter arithmetic overflowed at block `block` of the
+    /// frame (feature `strict-math`).
+    ArithmeticOverflow { block: u32, operation: &'static str },
+    /// A decode error annotated with where in the stream it occurred.
+    ///
+    /// Its message includes the wrapped error's, so
+    /// [`source`](core::error::Error::source) skips the wrapped error and
+    /// returns that error's own source; [`ApeError::inner`] returns it.
+    WithContext {
+        context: ErrorContext,
+        source: Box<ApeError>,
+    },
+}
+
+/// An I/O error, as kept by [`ApeError::Io`]: the kind and message of the
+/// original [`io::Error`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoError {
+    pub kind: io::ErrorKind,
+    /// The original error, as displayed.
+    pub message: String,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IoError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for IoError {
+    fn from(e: io::Error) -> Self {
+        IoError { kind: e.kind(), message: e.to_string() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<IoError> for io::Error {
+    fn from(e: IoError) -> Self {
+        io::Error::new(e.kind, e.message)
+    }
+}
+
+/// The category of an [`ApeError`], independent of any attached context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input is not a Monkey's Audio file.
+    InvalidMagic,
+    /// The format version is not supported.
+    UnsupportedVersion,
+    /// The compression level is not recognized.
+    UnsupportedCompressionLevel,
+    /// A header field contains an invalid value.
+    InvalidHeader,
+    /// The seek table is missing or corrupt.
+    InvalidSeekTable,
+    /// A frame's decoded output failed its CRC check.
+    CrcMismatch,
+    /// The range coder hit an invalid state (corrupt frame data).
+    RangeCoder,
+    /// A frame's compressed data ended early.
+    UnexpectedEof,
+    /// The stream holds more audio than the header accounts for.
+    SampleOverrun,
+    /// An I/O error from the underlying reader.
+    Io,
+    /// A decoder snapshot is malformed or belongs to a different stream.
+    InvalidSnapshot,
+    /// A fixed-size decoder's limits are too small.
+    CapacityExceeded,
+    /// Streams that must share a format do not.
+    FormatMismatch,
+    /// A cue sheet is malformed or does not fit the stream.
+    InvalidCueSheet,
+    /// An APL link file is malformed or does not fit its image file.
+    InvalidLink,
+    /// The file failed its descriptor MD5 check.
+    Md5Mismatch,
+    /// Decoder arithmetic overflowed (only checked with `strict-math`).
+    ArithmeticOverflow,
+}
+
+impl ErrorKind {
+    /// A stable number for this kind, for bindings and logs that cross a
+    /// language boundary. Codes start at 1, so 0 can mean success; they
+    /// never change or get reused, and new kinds get new codes.
+    pub fn code(self) -> u32 {
+        match self {
+            ErrorKind::InvalidMagic => 1,
+            ErrorKind::UnsupportedVersion => 2,
+            ErrorKind::UnsupportedCompressionLevel => 3,
+            ErrorKind::InvalidHeader => 4,
+            ErrorKind::InvalidSeekTable => 5,
+            ErrorKind::CrcMismatch => 6,
+            ErrorKind::RangeCoder => 7,
+            ErrorKind::UnexpectedEof => 8,
+            ErrorKind::SampleOverrun => 9,
+            ErrorKind::Io => 10,
+            ErrorKind::InvalidSnapshot => 11,
+            ErrorKind::CapacityExceeded => 12,
+            ErrorKind::FormatMismatch => 13,
+            ErrorKind::InvalidCueSheet => 14,
+            ErrorKind::InvalidLink => 15,
+            ErrorKind::Md5Mismatch => 16,
+            ErrorKind::ArithmeticOverflow => 17,
+        }
+    }
+
+    /// The kind with [`code`](Self::code) `code`, or `None` for 0 and
+    /// codes this version does not know.
+    pub fn from_code(code: u32) -> Option<ErrorKind> {
+        Some(match code {
+            1 => ErrorKind::InvalidMagic,
+            2 => ErrorKind::UnsupportedVersion,
+            3 => ErrorKind::UnsupportedCompressionLevel,
+            4 => ErrorKind::InvalidHeader,
+            5 => ErrorKind::InvalidSeekTable,
+            6 => ErrorKind::CrcMismatch,
+            7 => ErrorKind::RangeCoder,
+            8 => ErrorKind::UnexpectedEof,
+            9 => ErrorKind::SampleOverrun,
+            10 => ErrorKind::Io,
+            11 => ErrorKind::InvalidSnapshot,
+            12 => ErrorKind::CapacityExceeded,
+            13 => ErrorKind::FormatMismatch,
+            14 => ErrorKind::InvalidCueSheet,
+            15 => ErrorKind::InvalidLink,
+            16 => ErrorKind::Md5Mismatch,
+            17 => ErrorKind::ArithmeticOverflow,
+            _ => return None,
+        })
+    }
+
+    /// Whether errors of this kind are confined to a single frame, so
+    /// decoding can continue with the next one.
+    pub fn is_recoverable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::CrcMismatch
+                | ErrorKind::RangeCoder
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::SampleOverrun
+                | ErrorKind::ArithmeticOverflow
+        )
+    }
+}
+
+/// Location in the stream where a decode error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorContext {
+    /// Index of the frame being decoded (0-based).
+    pub frame: u32,
+    /// Interleaved sample index of the first sample in that frame.
+    pub sample: u64,
+    /// File byte offset of the frame's compressed data.
+    pub byte_offset: u64,
+}
+
+impl ApeError {
+    /// The category of this error, looking through any attached context.
+    pub fn kind(&self) -> ErrorKind {
+        match self.inner() {
+            ApeError::InvalidMagic => ErrorKind::InvalidMagic,
+            ApeError::UnsupportedVersion(_) => ErrorKind::UnsupportedVersion,
+            ApeError::UnsupportedCompressionLevel(_) => ErrorKind::UnsupportedCompressionLevel,
+            ApeError::InvalidHeader(_) => ErrorKind::InvalidHeader,
+            ApeError::InvalidSeekTable => ErrorKind::InvalidSeekTable,
+            ApeError::InvalidSnapshot(_) => ErrorKind::InvalidSnapshot,
+            ApeError::CapacityExceeded(_) => ErrorKind::CapacityExceeded,
+            ApeError::FormatMismatch(_) => ErrorKind::FormatMismatch,
+            ApeError::InvalidCueSheet(_) => ErrorKind::InvalidCueSheet,
+            ApeError::InvalidLink(_) => ErrorKind::InvalidLink,
+            ApeError::Md5Mismatch { .. } => ErrorKind::Md5Mismatch,
+            ApeError::ArithmeticOverflow { .. } => ErrorKind::ArithmeticOverflow,
+            ApeError::CrcMismatch { .. } => ErrorKind::CrcMismatch,
+            ApeError::RangeCoderError(_) => ErrorKind::RangeCoder,
+            ApeError::UnexpectedEof => ErrorKind::UnexpectedEof,
+            ApeError::SampleOverrun(_) => ErrorKind::SampleOverrun,
+            #[cfg(feature = "std")]
+            ApeError::Io(_) => ErrorKind::Io,
+            ApeError::WithContext { .. } => unreachable!("inner() strips context"),
+        }
+    }
+
+    /// The stable numeric code of this error's [`kind`](Self::kind).
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+
+    /// Whether this error is confined to a single frame, so decoding can
+    /// resume at the next frame. Header, seek-table and I/O errors are fatal.
+    pub fn is_recoverable(&self) -> bool {
+        self.kind().is_recoverable()
+    }
+
+    /// Where the error occurred, if it was raised while decoding a frame.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ApeError::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, with any location context stripped.
+    pub fn inner(&self) -> &ApeError {
+        match self {
+            ApeError::WithContext { source, .. } => source.inner(),
+            other => other,
+        }
+    }
+
+    /// The [`io::ErrorKind`] of an I/O error, looking through any attached
+    /// context, for retry logic that tells e.g. `Interrupted` or
+    /// `TimedOut` from `NotFound`.
+    #[cfg(feature = "std")]
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self.inner() {
+            ApeError::Io(e) => Some(e.kind),
+            _ => None,
+        }
+    }
+
+    /// Attach a frame location to this error (no-op if one is already attached).
+    pub(crate) fn with_context(self, context: ErrorContext) -> Self {
+        match self {
+            ApeError::WithContext { .. } => self,
+            other => ApeError::WithContext {
+                context,
+                source: Box::new(other),
+            },
+        }
+    }
 }
 
 impl fmt::Display for ApeError {
@@ -36,6 +305,17 @@ impl fmt::Display for ApeError {
             }
             ApeError::InvalidHeader(msg) => write!(f, "invalid APE header: {msg}"),
             ApeError::InvalidSeekTable => write!(f, "invalid or missing seek table"),
+            ApeError::InvalidSnapshot(msg) => write!(f, "invalid decoder snapshot: {msg}"),
+            ApeError::CapacityExceeded(msg) => write!(f, "decoder capacity exceeded: {msg}"),
+            ApeError::FormatMismatch(msg) => write!(f, "stream formats differ: {msg}"),
+            ApeError::InvalidCueSheet(msg) => write!(f, "invalid cue sheet: {msg}"),
+            ApeError::InvalidLink(msg) => write!(f, "invalid APE link file: {msg}"),
+            ApeError::Md5Mismatch { expected, actual } => {
+                write!(f, "file MD5 mismatch: expected ")?;
+                write_hex(f, expected)?;
+                write!(f, ", got ")?;
+                write_hex(f, actual)
+            }
             ApeError::CrcMismatch {
                 frame,
                 expected,
@@ -46,24 +326,121 @@ impl fmt::Display for ApeError {
                     "CRC mismatch in frame {frame}: expected {expected:#010x}, got {actual:#010x}"
                 )
             }
+            ApeError::ArithmeticOverflow { block, operation } => {
+                write!(f, "arithmetic overflow ({operation}) at block {block}")
+            }
             ApeError::RangeCoderError(msg) => write!(f, "range coder error: {msg}"),
             ApeError::UnexpectedEof => write!(f, "unexpected end of compressed data"),
+            ApeError::SampleOverrun(msg) => write!(f, "sample count overrun: {msg}"),
+            #[cfg(feature = "std")]
             ApeError::Io(e) => write!(f, "I/O error: {e}"),
+            ApeError::WithContext { context, source } => {
+                write!(
+                    f,
+                    "frame {} (sample {}, byte offset {}): {source}",
+                    context.frame, context.sample, context.byte_offset
+                )
+            }
         }
     }
 }
 
-impl std::error::Error for ApeError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|b| write!(f, "{b:02x}"))
+}
+
+impl core::error::Error for ApeError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
         match self {
+            #[cfg(feature = "std")]
             ApeError::Io(e) => Some(e),
+            // Already part of the message
+            ApeError::WithContext { source, .. } => source.source(),
             _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for ApeError {
     fn from(e: io::Error) -> Self {
-        ApeError::Io(e)
+        ApeError::Io(e.into())
+    }
+}
+
+/// A non-fatal problem encountered while decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeWarning {
+    /// A frame failed to decode in lenient mode and was concealed or skipped.
+    FrameDropped {
+        /// Index of the failed frame (0-based).
+        frame: u32,
+        /// The error that caused the frame to be dropped.
+        error: ApeError,
+    },
+    /// A frame was not at its seek-table position and was found nearby.
+    FrameResynced {
+        /// Index of the frame (0-based).
+        frame: u32,
+        /// File offset the seek table gave.
+        table_offset: u64,
+        /// File offset where the frame was actually found.
+        actual_offset: u64,
+    },
+    /// The header's frame count or final frame length was wrong and has been
+    /// corrected from the audio data.
+    DurationCorrected {
+        /// Total blocks (samples per channel) the header declared.
+        declared_blocks: u64,
+        /// Total blocks actually present.
+        actual_blocks: u64,
+    },
+    /// Bytes after the audio data that are neither the declared terminating
+    /// data nor a recognized tag. They are not fed to the decoder.
+    TrailingData {
+        /// File offset of the first unaccounted byte.
+        offset: u64,
+        /// Number of unaccounted bytes.
+        len: u64,
+    },
+}
+
+impl DecodeWarning {
+    /// The frame the warning concerns, if any.
+    pub fn frame(&self) -> Option<u32> {
+        match self {
+            DecodeWarning::FrameDropped { frame, .. } | DecodeWarning::FrameResynced { frame, .. } => {
+                Some(*frame)
+            }
+            DecodeWarning::DurationCorrected { .. } | DecodeWarning::TrailingData { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::FrameDropped { frame, error } => {
+                write!(f, "frame {frame} dropped: {error}")
+            }
+            DecodeWarning::FrameResynced {
+                frame,
+                table_offset,
+                actual_offset,
+            } => write!(
+                f,
+                "frame {frame} found at byte {actual_offset}, seek table says {table_offset}"
+            ),
+            DecodeWarning::DurationCorrected {
+                declared_blocks,
+                actual_blocks,
+            } => write!(
+                f,
+                "header declares {declared_blocks} blocks, stream holds {actual_blocks}"
+            ),
+            DecodeWarning::TrailingData { offset, len } => {
+                write!(f, "{len} unexpected bytes after the audio data at byte {offset}")
+            }
+        }
     }
 }
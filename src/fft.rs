@@ -0,0 +1,72 @@
+//! Radix-2 FFT for the optional spectral features.
+
+use std::f64::consts::PI;
+
+/// Precomputed plan for transforms of one power-of-two size.
+#[derive(Debug, Clone)]
+pub struct Fft {
+    n: usize,
+    /// `e^(-2πik/n)` for k in 0..n/2.
+    twiddles: Vec<(f64, f64)>,
+    /// Bit-reversed index of each position.
+    bit_reverse: Vec<usize>,
+}
+
+impl Fft {
+    /// Plan a transform of `n` points. `n` must be a power of two.
+    pub fn new(n: usize) -> Self {
+        assert!(n.is_power_of_two(), "FFT size must be a power of two");
+        let bits = n.trailing_zeros();
+        Fft {
+            n,
+            twiddles: (0..n / 2)
+                .map(|k| {
+                    let angle = -2.0 * PI * k as f64 / n as f64;
+                    (angle.cos(), angle.sin())
+                })
+                .collect(),
+            bit_reverse: (0..n)
+                .map(|i| if bits == 0 { 0 } else { i.reverse_bits() >> (usize::BITS - bits) })
+                .collect(),
+        }
+    }
+
+    /// Transform size.
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    /// Power spectrum `|X[k]|²` of real `input` for k in 0..=n/2.
+    ///
+    /// `input` shorter than the transform is zero-padded.
+    pub fn power_spectrum(&self, input: &[f64], output: &mut Vec<f64>) {
+        let n = self.n;
+        let mut re = vec![0.0; n];
+        let mut im = vec![0.0; n];
+        for (i, &x) in input.iter().take(n).enumerate() {
+            re[self.bit_reverse[i]] = x;
+        }
+
+        let mut size = 2;
+        while size <= n {
+            let half = size / 2;
+            let step = n / size;
+            for start in (0..n).step_by(size) {
+                for k in 0..half {
+                    let (wr, wi) = self.twiddles[k * step];
+                    let (a, b) = (start + k, start + k + half);
+                    let tr = re[b] * wr - im[b] * wi;
+                    let ti = re[b] * wi + im[b] * wr;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            size *= 2;
+        }
+
+        output.clear();
+        output.extend((0..=n / 2).map(|k| re[k] * re[k] + im[k] * im[k]));
+    }
+}
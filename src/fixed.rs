@@ -0,0 +1,126 @@
+//! Fixed-memory frame decoding for firmware-style players.
+//!
+//! [`FixedDecoder`] allocates everything it will ever use when it is
+//! created: filter and predictor state for the stream, a frame input
+//! buffer and an output buffer, each sized from limits the caller gives.
+//! Decoding a frame then allocates nothing (an error message aside), and
+//! frames larger than the limits are rejected instead of growing a buffer.
+//!
+//! State size depends on the compression level; Insane (5000) needs by far
+//! the most. [`FixedDecoder::memory_bytes`] reports the exact total, and
+//! [`FixedDecoder::new`] can refuse levels above a ceiling so a device
+//! never commits to more than it budgeted for.
+
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::buffer::SampleBuffer;
+use crate::error::ApeError;
+use crate::packet::{self, PacketDecoder};
+
+/// Buffer limits for a [`FixedDecoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FixedLimits {
+    /// Largest frame, in blocks (the stream's `blocks_per_frame`).
+    pub max_blocks: u32,
+    /// Largest compressed frame, in bytes, including word alignment.
+    pub max_frame_bytes: usize,
+    /// Highest compression level to accept (1000–5000).
+    pub max_compression_level: u16,
+}
+
+/// Frame decoder whose memory is all allocated up front.
+///
+/// ```no_run
+/// use ape_rs::fixed::{FixedDecoder, FixedLimits};
+///
+/// # let (frame_bytes, skip, blocks): (Vec<u8>, u32, u32) = (vec![], 0, 73728);
+/// let limits = FixedLimits { max_blocks: 73728, max_frame_bytes: 256 * 1024, max_compression_level: 3000 };
+/// let mut decoder = FixedDecoder::new(2, 16, 2000, limits).unwrap();
+/// println!("decoder uses {} bytes", decoder.memory_bytes());
+///
+/// let pcm: &[i32] = decoder.decode(&frame_bytes, skip, blocks).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FixedDecoder {
+    packet: PacketDecoder,
+    channels: usize,
+    limits: FixedLimits,
+    /// Byte-swapped frame data.
+    input: Vec<u8>,
+    /// Decoded samples of the last frame. Moved into a `SampleBuffer` while
+    /// decoding; its capacity is never exceeded.
+    output: Vec<i32>,
+}
+
+impl FixedDecoder {
+    /// Create a decoder for a stream with the given layout and compression
+    /// level, allocating all buffers for `limits`.
+    pub fn new(
+        channels: u16,
+        bits_per_sample: u16,
+        compression_level: u16,
+        limits: FixedLimits,
+    ) -> Result<Self, ApeError> {
+        if compression_level > limits.max_compression_level {
+            return Err(ApeError::CapacityExceeded(format!(
+                "compression level {compression_level} is above the configured maximum of {}",
+                limits.max_compression_level
+            )));
+        }
+        let packet = PacketDecoder::new(channels, bits_per_sample, compression_level)?;
+        let max_samples = limits.max_blocks as usize * channels as usize;
+        Ok(FixedDecoder {
+            packet,
+            channels: channels as usize,
+            limits,
+            input: Vec::with_capacity(limits.max_frame_bytes),
+            output: Vec::with_capacity(max_samples),
+        })
+    }
+
+    /// Total heap bytes held: filter and predictor state plus the input and
+    /// output buffers. This does not change after creation.
+    pub fn memory_bytes(&self) -> usize {
+        self.packet.memory_bytes()
+            + self.input.capacity()
+            + self.output.capacity() * size_of::<i32>()
+    }
+
+    /// The limits the decoder was created with.
+    pub fn limits(&self) -> FixedLimits {
+        self.limits
+    }
+
+    /// Decode and CRC-check one frame, as
+    /// [`PacketDecoder::decode_packet`], returning its interleaved samples.
+    ///
+    /// The samples stay valid until the next call.
+    pub fn decode(&mut self, data: &[u8], skip: u32, nblocks: u32) -> Result<&[i32], ApeError> {
+        if nblocks > self.limits.max_blocks {
+            return Err(ApeError::CapacityExceeded(format!(
+                "frame of {nblocks} blocks, decoder holds {}",
+                self.limits.max_blocks
+            )));
+        }
+        if data.len() > self.limits.max_frame_bytes {
+            return Err(ApeError::CapacityExceeded(format!(
+                "frame of {} bytes, decoder holds {}",
+                data.len(),
+                self.limits.max_frame_bytes
+            )));
+        }
+        self.input.clear();
+        self.input.extend_from_slice(data);
+        packet::byte_swap_words(&mut self.input);
+
+        self.output.clear();
+        let mut buffer = SampleBuffer::from_vec(core::mem::take(&mut self.output));
+        let result = self.packet.decode_frame(&self.input, skip, nblocks, 0, &mut buffer);
+        self.output = buffer.into_vec();
+        debug_assert!(self.output.len() <= self.limits.max_blocks as usize * self.channels);
+        result?;
+        Ok(&self.output)
+    }
+}
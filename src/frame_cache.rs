@@ -0,0 +1,104 @@
+//! A cache of recently decoded frames.
+//!
+//! Editors and waveform views seek back and forth over the same stretch of
+//! a file, and every seek into a frame normally decodes it again from the
+//! start. With a cache (see
+//! [`ApeReaderBuilder::frame_cache`](crate::ApeReaderBuilder::frame_cache)),
+//! the decoder keeps the output of the last few frames it decoded and
+//! copies a frame from there instead of range-decoding and filtering it
+//! again. Frames are evicted least recently used first.
+//!
+//! Only frames that decoded cleanly and passed their CRC are cached; a
+//! damaged frame is decoded, and its error handled, every time.
+
+use std::collections::VecDeque;
+
+/// Hits and misses of a reader's frame cache, from
+/// [`ApeReader::frame_cache_stats`](crate::ApeReader::frame_cache_stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameCacheStats {
+    /// Frames served from the cache.
+    pub hits: u64,
+    /// Frames that had to be decoded.
+    pub misses: u64,
+    /// Frames held now.
+    pub frames: usize,
+    /// Most frames held at once.
+    pub capacity: usize,
+}
+
+/// One cached frame.
+pub(crate) struct CachedFrame {
+    pub frame: u32,
+    pub samples: Vec<i32>,
+    /// Where the next frame starts (position, skip).
+    pub next: (u64, u32),
+    /// Seek-table offset the frame was found at.
+    pub resync_shift: i64,
+}
+
+/// Decoded frames, most recently used last.
+pub(crate) struct FrameCache {
+    entries: VecDeque<CachedFrame>,
+    capacity: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl FrameCache {
+    pub fn new(capacity: usize) -> Self {
+        FrameCache {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The cached output of `frame`, marked most recently used.
+    pub fn get(&mut self, frame: u32) -> Option<&CachedFrame> {
+        let Some(i) = self.entries.iter().position(|e| e.frame == frame) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let entry = self.entries.remove(i)?;
+        self.entries.push_back(entry);
+        self.entries.back()
+    }
+
+    /// Cache `samples` as the output of `frame`, evicting the least
+    /// recently used frame if full. The evicted frame's allocation is
+    /// reused.
+    pub fn insert(&mut self, frame: u32, samples: &[i32], next: (u64, u32), resync_shift: i64) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entry = if self.entries.len() >= self.capacity {
+            self.entries.pop_front().expect("a full cache has entries")
+        } else {
+            CachedFrame { frame, samples: Vec::new(), next, resync_shift }
+        };
+        entry.frame = frame;
+        entry.samples.clear();
+        entry.samples.extend_from_slice(samples);
+        entry.next = next;
+        entry.resync_shift = resync_shift;
+        self.entries.push_back(entry);
+    }
+
+    /// Drop every frame, keeping the counts.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> FrameCacheStats {
+        FrameCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            frames: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Frame index sidecars: a file's header, frame layout and verification
+//! state, stored next to it.
+//!
+//! Opening a large file normally parses its header and seek table, and a
+//! player that wants to know a seek will land on an intact frame has to
+//! decode the file first. A [`FrameIndex`] records both once: the parsed
+//! header, each frame's offset and first block, and (from
+//! [`ApeReader::verified_frame_index`]) whether each frame passed its CRC.
+//! [`ApeReaderBuilder::build_with_index`] then opens the file without
+//! reading its header, and [`FrameIndex::frame_check`] says whether a seek
+//! target is known good before decoding it.
+//!
+//! ```no_run
+//! use ape_rs::{ApeReader, ApeReaderBuilder};
+//! use ape_rs::frame_index::FrameIndex;
+//!
+//! // Once, e.g. when a file is added to a library
+//! let mut reader = ApeReader::open("album.ape").unwrap();
+//! reader.verified_frame_index().unwrap().save(FrameIndex::sidecar_path("album.ape")).unwrap();
+//!
+//! // On every later open
+//! let index = FrameIndex::load(FrameIndex::sidecar_path("album.ape")).unwrap();
+//! let file = std::io::BufReader::new(std::fs::File::open("album.ape").unwrap());
+//! let reader = ApeReaderBuilder::new().build_with_index(file, &index).unwrap();
+//! ```
+//!
+//! An index belongs to one file. Opening checks the stream length and the
+//! descriptor's position against it, which catches a replaced or retagged
+//! file but not audio rewritten in place; rebuild the index when a file
+//! changes.
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::error::ApeError;
+use crate::header::{self, ApeFileHeader};
+use crate::{ApeReader, ApeReaderBuilder};
+
+/// Identifies the index encoding; the last byte is the version.
+const INDEX_MAGIC: [u8; 8] = *b"APEIDX\x00\x01";
+
+/// Bytes of a serialized descriptor and header.
+const HEADER_BYTES: usize = 52 + 24;
+
+/// Bytes before the per-frame entries.
+const PREFIX_BYTES: usize = 8 + 4 * 8 + HEADER_BYTES + 4;
+
+/// What is known about a frame's integrity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameCheck {
+    /// Never decoded.
+    #[default]
+    Unchecked,
+    /// Decoded and passed its CRC.
+    Ok,
+    /// Failed to decode or failed its CRC.
+    Damaged,
+}
+
+/// One frame of a [`FrameIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexedFrame {
+    /// File offset of the frame's first byte.
+    pub offset: u64,
+    /// Index of the frame's first block in the stream.
+    pub first_block: u64,
+    pub check: FrameCheck,
+}
+
+/// A file's parsed header and per-frame layout and integrity, from
+/// [`ApeReader::frame_index`] or [`ApeReader::verified_frame_index`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameIndex {
+    pub header: ApeFileHeader,
+    /// Length of the indexed file.
+    pub stream_len: u64,
+    pub frames: Vec<IndexedFrame>,
+}
+
+impl FrameIndex {
+    /// Where the index of the file at `path` is kept by convention:
+    /// `track.ape` → `track.ape.apeidx`.
+    pub fn sidecar_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut name = path.as_ref().as_os_str().to_os_string();
+        name.push(".apeidx");
+        PathBuf::from(name)
+    }
+
+    /// The integrity of frame `frame`, or `None` past the last frame.
+    pub fn frame_check(&self, frame: u32) -> Option<FrameCheck> {
+        self.frames.get(frame as usize).map(|f| f.check)
+    }
+
+    /// The frames recorded as damaged.
+    pub fn damaged_frames(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.frames.len() as u32).filter(|&i| self.frames[i as usize].check == FrameCheck::Damaged)
+    }
+
+    /// Whether every frame was decoded and passed its CRC.
+    pub fn is_verified(&self) -> bool {
+        self.frames.iter().all(|f| f.check == FrameCheck::Ok)
+    }
+
+    /// Encode for storage: a versioned header, then little-endian fields.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = &self.header;
+        let mut out = Vec::with_capacity(PREFIX_BYTES + 4 * header.seek_table.len() + 17 * self.frames.len());
+        out.extend_from_slice(&INDEX_MAGIC);
+        for field in [self.stream_len, header.data_offset, header.junk_bytes, header.tag_offset] {
+            out.extend_from_slice(&field.to_le_bytes());
+        }
+        header.descriptor.write_to(&mut out);
+        header.header.write_to(&mut out);
+        out.extend_from_slice(&(header.seek_table.len() as u32).to_le_bytes());
+        for entry in &header.seek_table {
+            out.extend_from_slice(&entry.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            out.extend_from_slice(&frame.offset.to_le_bytes());
+            out.extend_from_slice(&frame.first_block.to_le_bytes());
+            out.push(frame.check as u8);
+        }
+        out
+    }
+
+    /// Decode bytes produced by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Fails with [`ErrorKind::InvalidSeekTable`](crate::ErrorKind::InvalidSeekTable)
+    /// if they are not an index or the frames disagree with the header.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ApeError> {
+        if bytes.len() < PREFIX_BYTES + 4 || bytes[..8] != INDEX_MAGIC {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        let u32_at = |off: usize| bytes.get(off..off + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+        let u64_at = |off: usize| bytes.get(off..off + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()));
+        let (descriptor, ape_header) = header::read_descriptor_and_header(&bytes[40..40 + HEADER_BYTES])?;
+
+        let entries = u32_at(PREFIX_BYTES - 4).ok_or(ApeError::InvalidSeekTable)? as usize;
+        let frames_at = PREFIX_BYTES + 4 * entries;
+        let count = u32_at(frames_at).ok_or(ApeError::InvalidSeekTable)? as usize;
+        if bytes.len() - frames_at - 4 != 17 * count {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        let seek_table = (0..entries).map(|i| u32_at(PREFIX_BYTES + 4 * i)).collect::<Option<_>>();
+        let frames = (0..count).map(|i| {
+            let at = frames_at + 4 + 17 * i;
+            let check = match bytes[at + 16] {
+                0 => FrameCheck::Unchecked,
+                1 => FrameCheck::Ok,
+                2 => FrameCheck::Damaged,
+                _ => return None,
+            };
+            Some(IndexedFrame { offset: u64_at(at)?, first_block: u64_at(at + 8)?, check })
+        });
+        let index = FrameIndex {
+            header: ApeFileHeader {
+                descriptor,
+                header: ape_header,
+                seek_table: seek_table.ok_or(ApeError::InvalidSeekTable)?,
+                data_offset: u64_at(16).unwrap(),
+                junk_bytes: u64_at(24).unwrap(),
+                tag_offset: u64_at(32).unwrap(),
+            },
+            stream_len: u64_at(8).unwrap(),
+            frames: frames.collect::<Option<_>>().ok_or(ApeError::InvalidSeekTable)?,
+        };
+        if !index.frames_match_header() {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        Ok(index)
+    }
+
+    /// Write the index to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ApeError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    /// Read an index written by [`save`](Self::save).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ApeError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Whether the frame entries are the ones the header describes.
+    fn frames_match_header(&self) -> bool {
+        let header = &self.header;
+        self.frames.len() == header.header.total_frames as usize
+            && self.frames.iter().enumerate().all(|(i, frame)| {
+                header.frame_position(i) == Some(frame.offset)
+                    && frame.first_block == i as u64 * header.header.blocks_per_frame as u64
+            })
+    }
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// An index of the file's header and frame layout, with every frame
+    /// [`Unchecked`](FrameCheck::Unchecked).
+    pub fn frame_index(&mut self) -> Result<FrameIndex, ApeError> {
+        let header = self.decoder.header.clone();
+        let stream_len = self.decoder.reader.seek(SeekFrom::End(0))?;
+        let blocks_per_frame = header.header.blocks_per_frame as u64;
+        let frames = (0..header.header.total_frames as usize)
+            .map_while(|i| {
+                Some(IndexedFrame {
+                    offset: header.frame_position(i)?,
+                    first_block: i as u64 * blocks_per_frame,
+                    check: FrameCheck::Unchecked,
+                })
+            })
+            .collect();
+        Ok(FrameIndex { header, stream_len, frames })
+    }
+
+    /// [`frame_index`](Self::frame_index) with every frame decoded and its
+    /// CRC checked, whatever the frame error mode.
+    ///
+    /// Decodes the whole file, then returns to the position it started
+    /// from.
+    pub fn verified_frame_index(&mut self) -> Result<FrameIndex, ApeError> {
+        let mut index = self.frame_index()?;
+        let position = self.snapshot();
+        let mode = self.decoder.frame_error_mode;
+        self.decoder.frame_error_mode = crate::FrameErrorMode::Silence;
+        let result = self.seek_to_block(0).and_then(|()| {
+            for frame in self.decoded_frames() {
+                match frame {
+                    Ok(frame) => {
+                        if let Some(entry) = index.frames.get_mut(frame.index as usize) {
+                            entry.check = if frame.crc_ok { FrameCheck::Ok } else { FrameCheck::Damaged };
+                        }
+                    }
+                    Err(e) if e.is_recoverable() => {
+                        let frame = e.context().and_then(|c| index.frames.get_mut(c.frame as usize));
+                        if let Some(entry) = frame {
+                            entry.check = FrameCheck::Damaged;
+                        }
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        });
+        self.decoder.frame_error_mode = mode;
+        result?;
+        self.restore(&position)?;
+        Ok(index)
+    }
+}
+
+impl ApeReaderBuilder {
+    /// Create an ApeReader from `index` instead of parsing the header of
+    /// `reader`, the file the index was made from.
+    ///
+    /// Fails with [`ErrorKind::InvalidSeekTable`](crate::ErrorKind::InvalidSeekTable)
+    /// if the stream's length differs from the indexed file's or the
+    /// descriptor is not where the index puts it.
+    pub fn build_with_index<R: Read + Seek>(self, mut reader: R, index: &FrameIndex) -> Result<ApeReader<R>, ApeError> {
+        let mut magic = [0u8; 4];
+        if reader.seek(SeekFrom::End(0))? != index.stream_len {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        reader.seek(SeekFrom::Start(index.header.junk_bytes))?;
+        reader.read_exact(&mut magic)?;
+        if &magic != b"MAC " {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        self.build_with_header(reader, index.header.clone())
+    }
+}
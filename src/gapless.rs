@@ -0,0 +1,241 @@
+//! Gapless playback metadata: encoder delay and padding.
+//!
+//! Lossy encoders add silence at the start (delay) and end (padding) of a
+//! track; a lossless file made from such a decode, or cut from a longer
+//! recording on frame boundaries, inherits it. Players trim both to play
+//! albums without gaps. MP3 and AAC tooling records the amounts in an
+//! `iTunSMPB` item, and [`Gapless`] reads and writes that item in a
+//! trailing APEv2 tag, so the same tools understand APE files.
+//!
+//! The value is hexadecimal fields separated by spaces: a reserved zero,
+//! the delay, the padding and the number of valid blocks, then reserved
+//! zeros:
+//!
+//! ```text
+//!  00000000 00000840 000001CA 00000000003F1F36 00000000 ...
+//! ```
+//!
+//! [`ApeReader::gapless`] reads the item and [`ApeReader::into_gapless`]
+//! trims the stream to the valid blocks. [`Gapless::apev2_tag`] builds a
+//! tag to append to a file without one (see [`strip`](crate::strip) to
+//! remove an existing tag first), and the `testgen` encoder writes one for
+//! [`TestStream::gapless`](crate::testgen::TestStream::gapless).
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek, SeekFrom};
+
+#[cfg(feature = "std")]
+use crate::cue::TrackSamples;
+#[cfg(feature = "std")]
+use crate::error::ApeError;
+use crate::header;
+#[cfg(feature = "std")]
+use crate::{ApeInfo, ApeReader};
+
+/// Key of the APEv2 item holding the delay and padding.
+pub const ITUNSMPB: &str = "iTunSMPB";
+
+/// APEv2 tag format version.
+const APE_TAG_VERSION: u32 = 2000;
+
+/// Size of an APEv2 tag header or footer.
+const APE_TAG_FOOTER_SIZE: usize = header::APE_TAG_FOOTER_SIZE as usize;
+
+/// Blocks of encoder delay and padding to trim from a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gapless {
+    /// Blocks of silence at the start.
+    pub delay: u64,
+    /// Blocks of silence at the end.
+    pub padding: u64,
+}
+
+impl Gapless {
+    /// Parse an `iTunSMPB` value. `None` if it lacks the delay and padding
+    /// fields or they are not hexadecimal.
+    pub fn parse_itunsmpb(value: &str) -> Option<Self> {
+        let mut fields = value.split_whitespace().skip(1);
+        let mut next = || u64::from_str_radix(fields.next()?, 16).ok();
+        Some(Gapless {
+            delay: next()?,
+            padding: next()?,
+        })
+    }
+
+    /// The `iTunSMPB` value for a stream of `total_blocks` blocks.
+    pub fn to_itunsmpb(&self, total_blocks: u64) -> String {
+        let valid = self.valid_blocks(total_blocks);
+        let mut value = format!(" 00000000 {:08X} {:08X} {valid:016X}", self.delay, self.padding);
+        for _ in 0..8 {
+            value.push_str(" 00000000");
+        }
+        value
+    }
+
+    /// Blocks left of `total_blocks` after trimming.
+    pub fn valid_blocks(&self, total_blocks: u64) -> u64 {
+        total_blocks.saturating_sub(self.delay).saturating_sub(self.padding)
+    }
+
+    /// A complete APEv2 tag (header, `iTunSMPB` item and footer) for a
+    /// stream of `total_blocks` blocks, to append to the file.
+    pub fn apev2_tag(&self, total_blocks: u64) -> Vec<u8> {
+        let value = self.to_itunsmpb(total_blocks);
+        let mut item = Vec::new();
+        item.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        item.extend_from_slice(&0u32.to_le_bytes()); // UTF-8 text
+        item.extend_from_slice(ITUNSMPB.as_bytes());
+        item.push(0);
+        item.extend_from_slice(value.as_bytes());
+
+        let size = (item.len() + APE_TAG_FOOTER_SIZE) as u32;
+        let mut tag = Vec::with_capacity(item.len() + 2 * APE_TAG_FOOTER_SIZE);
+        // Bit 31: the tag has a header; bit 29: this is the header
+        tag_frame(&mut tag, size, 1, 1 << 31 | 1 << 29);
+        tag.extend_from_slice(&item);
+        tag_frame(&mut tag, size, 1, 1 << 31);
+        tag
+    }
+}
+
+/// Append an APEv2 tag header or footer.
+fn tag_frame(out: &mut Vec<u8>, size: u32, items: u32, flags: u32) {
+    out.extend_from_slice(b"APETAGEX");
+    for field in [APE_TAG_VERSION, size, items, flags] {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+    out.extend_from_slice(&[0; 8]);
+}
+
+/// The text items of the APEv2 tag at the end of `tags` (the bytes from
+/// [`ApeFileHeader::tag_offset`](crate::ApeFileHeader::tag_offset) to the
+/// end of the stream), in tag order. Binary items and anything malformed
+/// are skipped.
+pub fn apev2_text_items(tags: &[u8]) -> Vec<(String, String)> {
+    let Some((range, count)) = header::find_trailing_tags_in(tags).ape_items else {
+        return Vec::new();
+    };
+    let mut data = &tags[range.start as usize..range.end as usize];
+
+    let mut items = Vec::new();
+    for _ in 0..count {
+        if data.len() < 8 {
+            break;
+        }
+        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let flags = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        let Some(key_len) = data[8..].iter().position(|&b| b == 0) else {
+            break;
+        };
+        let value_start = 8 + key_len + 1;
+        let Some(value) = value_start.checked_add(len).and_then(|end| data.get(value_start..end)) else {
+            break;
+        };
+        // Bits 1-2: 0 for UTF-8 text
+        if flags & 0b110 == 0
+            && let (Ok(key), Ok(value)) = (core::str::from_utf8(&data[8..8 + key_len]), core::str::from_utf8(value))
+        {
+            items.push((key.to_string(), value.to_string()));
+        }
+        data = &data[value_start + len..];
+    }
+    items
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeReader<R> {
+    /// The delay and padding recorded in the file's APEv2 tag, or `None`
+    /// if it has no `iTunSMPB` item. An item that does not parse, or that
+    /// trims more blocks than the stream holds, is ignored.
+    ///
+    /// Like [`read_raw_frame`](Self::read_raw_frame), this does not disturb
+    /// decoding.
+    pub fn gapless(&mut self) -> Result<Option<Gapless>, ApeError> {
+        let tag_offset = self.decoder.header.tag_offset;
+        let stream_len = self.decoder.reader.seek(SeekFrom::End(0))?;
+        let tags = self.read_range(tag_offset, stream_len.saturating_sub(tag_offset))?;
+        let total_blocks = self.decoder.header.total_blocks();
+        Ok(apev2_text_items(&tags)
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(ITUNSMPB))
+            .find_map(|(_, value)| Gapless::parse_itunsmpb(value))
+            .filter(|g| g.delay.saturating_add(g.padding) <= total_blocks))
+    }
+
+    /// Trim the stream to the blocks between the recorded delay and
+    /// padding, seeking past the delay. A file without gapless metadata is
+    /// not trimmed.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut track = ApeReader::open("track.ape").unwrap().into_gapless().unwrap();
+    /// println!("{:?}, {} samples", track.gapless(), track.info().total_samples);
+    /// let samples: Vec<i32> = track.samples().collect::<Result<_, _>>().unwrap();
+    /// ```
+    pub fn into_gapless(mut self) -> Result<GaplessReader<R>, ApeError> {
+        let gapless = self.gapless()?.unwrap_or_default();
+        GaplessReader::new(self, gapless)
+    }
+}
+
+/// Reader over the valid blocks of a stream, from
+/// [`ApeReader::into_gapless`].
+#[cfg(feature = "std")]
+pub struct GaplessReader<R: Read + Seek> {
+    reader: ApeReader<R>,
+    gapless: Gapless,
+    /// Stream format; `total_samples` covers only the valid blocks.
+    info: ApeInfo,
+    /// Interleaved samples of the valid blocks not yet returned.
+    remaining: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> GaplessReader<R> {
+    /// Trim `gapless`'s delay and padding from `reader`, whatever the file
+    /// records, and seek to the first valid block.
+    pub fn new(mut reader: ApeReader<R>, gapless: Gapless) -> Result<Self, ApeError> {
+        let total_blocks = reader.header().total_blocks();
+        let delay = gapless.delay.min(total_blocks);
+        let info = ApeInfo {
+            total_samples: gapless.valid_blocks(total_blocks) * reader.info().channels as u64,
+            ..reader.info().clone()
+        };
+        reader.seek_to_block(delay)?;
+        Ok(GaplessReader {
+            reader,
+            gapless,
+            remaining: info.total_samples,
+            info,
+        })
+    }
+
+    /// The delay and padding trimmed.
+    pub fn gapless(&self) -> Gapless {
+        self.gapless
+    }
+
+    /// Stream format, with `total_samples` counting only the valid blocks.
+    pub fn info(&self) -> &ApeInfo {
+        &self.info
+    }
+
+    /// Iterator over the remaining valid samples, interleaved as
+    /// [`ApeReader::samples`].
+    pub fn samples(&mut self) -> TrackSamples<'_, R> {
+        TrackSamples {
+            reader: &mut self.reader,
+            remaining: &mut self.remaining,
+        }
+    }
+
+    /// The reader over the whole stream.
+    pub fn into_inner(self) -> ApeReader<R> {
+        self.reader
+    }
+}
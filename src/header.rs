@@ -1,6 +1,22 @@
-use std::io::{self, Read, Seek, SeekFrom};
+//! APE descriptor, header and seek table parsing.
+//!
+//! [`parse_header`] reads the structures at the start of a file; the
+//! resulting [`ApeFileHeader`] describes where every frame lies and how
+//! many blocks it holds. All of this is public so tools can inspect a file
+//! without decoding it, or build a reader from a header parsed elsewhere
+//! (see [`ApeReaderBuilder::build_with_header`](crate::ApeReaderBuilder::build_with_header)).
+//!
+//! Without `std`, [`parse_header_slice`] parses a file held in memory.
 
+use alloc::format;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::diag::diag;
 use crate::error::ApeError;
+use crate::packet;
 
 /// APE magic bytes: "MAC " (0x4D 0x41 0x43 0x20)
 const APE_MAGIC: [u8; 4] = [0x4D, 0x41, 0x43, 0x20];
@@ -8,35 +24,106 @@ const APE_MAGIC: [u8; 4] = [0x4D, 0x41, 0x43, 0x20];
 /// Minimum supported format version (v3.99).
 const MIN_VERSION: u16 = 3990;
 
+/// Fixed size of the v3.99+ descriptor, including the magic.
+const DESCRIPTOR_SIZE: u32 = 52;
+
+/// Fixed size of the APE header that follows the descriptor.
+const HEADER_SIZE: u32 = 24;
+
+/// Size of an ID3v1 tag, which starts with "TAG" at 128 bytes from the end.
+pub(crate) const ID3V1_SIZE: u64 = 128;
+
+/// Size of an APEv2 tag header or footer ("APETAGEX" preamble).
+pub(crate) const APE_TAG_FOOTER_SIZE: u64 = 32;
+
 /// APE descriptor — first structure in the file (52 bytes for v3.99+).
+///
+/// The `*_bytes` fields give the sizes of the regions that follow it, in
+/// file order.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ApeDescriptor {
+    /// Format version × 1000 (e.g. 3990 for v3.99).
     pub version: u16,
+    /// Size of the descriptor itself, including the magic.
     pub descriptor_bytes: u32,
+    /// Size of the [`ApeHeader`].
     pub header_bytes: u32,
+    /// Size of the seek table (4 bytes per entry).
     pub seek_table_bytes: u32,
+    /// Size of the original WAV header stored after the seek table.
     pub header_data_bytes: u32,
+    /// Size of the compressed frame data, low 32 bits.
     pub ape_frame_data_bytes: u32,
+    /// Size of the compressed frame data, high 32 bits.
     pub ape_frame_data_bytes_high: u32,
+    /// Size of the original WAV trailer stored after the frame data.
     pub terminating_data_bytes: u32,
+    /// MD5 of the file as written by the encoder (zero if not computed).
     pub file_md5: [u8; 16],
 }
 
 /// APE header — follows the descriptor (24 bytes).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ApeHeader {
+    /// 1000 (Fast) to 5000 (Insane).
     pub compression_level: u16,
+    /// Legacy format flags; unused by v3.99+ streams.
     pub format_flags: u16,
+    /// Blocks in every frame but the last.
     pub blocks_per_frame: u32,
+    /// Blocks in the last frame.
     pub final_frame_blocks: u32,
     pub total_frames: u32,
+    /// 8, 16 or 24.
     pub bits_per_sample: u16,
+    /// 1 or 2.
     pub channels: u16,
+    /// Sample rate in Hz.
     pub sample_rate: u32,
 }
 
+impl ApeDescriptor {
+    /// Serialize to the on-disk layout, including the "MAC " magic.
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&APE_MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&[0, 0]); // padding
+        for field in [
+            self.descriptor_bytes,
+            self.header_bytes,
+            self.seek_table_bytes,
+            self.header_data_bytes,
+            self.ape_frame_data_bytes,
+            self.ape_frame_data_bytes_high,
+            self.terminating_data_bytes,
+        ] {
+            out.extend_from_slice(&field.to_le_bytes());
+        }
+        out.extend_from_slice(&self.file_md5);
+    }
+}
+
+impl ApeHeader {
+    /// Serialize to the on-disk layout (24 bytes).
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.compression_level.to_le_bytes());
+        out.extend_from_slice(&self.format_flags.to_le_bytes());
+        out.extend_from_slice(&self.blocks_per_frame.to_le_bytes());
+        out.extend_from_slice(&self.final_frame_blocks.to_le_bytes());
+        out.extend_from_slice(&self.total_frames.to_le_bytes());
+        out.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+        out.extend_from_slice(&self.channels.to_le_bytes());
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+    }
+}
+
 /// Complete parsed file header: descriptor + header + seek table.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ApeFileHeader {
     pub descriptor: ApeDescriptor,
     pub header: ApeHeader,
@@ -44,6 +131,13 @@ pub struct ApeFileHeader {
     pub seek_table: Vec<u32>,
     /// Byte offset where compressed frame data begins.
     pub data_offset: u64,
+    /// Bytes of leading junk (e.g. an ID3v2 tag) before the descriptor.
+    /// Seek-table entries are relative to the descriptor, not the file.
+    pub junk_bytes: u64,
+    /// File offset where trailing tags (APEv2, ID3v1) begin, or the stream
+    /// length if there are none. Tags are only looked for after the frame
+    /// and terminating data the descriptor declares.
+    pub tag_offset: u64,
 }
 
 impl ApeFileHeader {
@@ -52,6 +146,150 @@ impl ApeFileHeader {
         self.total_blocks() * self.header.channels as u64
     }
 
+    /// Size of the compressed frame data region in bytes.
+    pub fn frame_data_bytes(&self) -> u64 {
+        self.descriptor.ape_frame_data_bytes as u64
+            | ((self.descriptor.ape_frame_data_bytes_high as u64) << 32)
+    }
+
+    /// File offset one past the last byte of compressed frame data.
+    ///
+    /// Uses the descriptor's frame data size, but never runs into the
+    /// trailing tags. Without a size, the data is taken to run up to the
+    /// terminating (WAV trailer) bytes in front of the tags.
+    pub fn frame_data_end(&self) -> u64 {
+        let frame_data_bytes = self.frame_data_bytes();
+        let end = if frame_data_bytes > 0 {
+            self.data_offset.saturating_add(frame_data_bytes)
+        } else {
+            self.tag_offset
+                .saturating_sub(self.descriptor.terminating_data_bytes as u64)
+        };
+        end.min(self.tag_offset).max(self.data_offset)
+    }
+
+    /// File range `(offset, length)` of the stored header of the original
+    /// WAV file (everything before its audio), which sits between the seek
+    /// table and the frame data. `None` if the encoder did not keep it.
+    pub fn header_data_range(&self) -> Option<(u64, u64)> {
+        let len = self.descriptor.header_data_bytes as u64;
+        (len > 0).then(|| (self.data_offset.saturating_sub(len), len))
+    }
+
+    /// File range `(offset, length)` of the terminating data: whatever
+    /// followed the audio in the original WAV file (e.g. trailing RIFF
+    /// chunks), stored after the frame data. `None` if there is none.
+    ///
+    /// The range never runs into the trailing tags.
+    pub fn terminating_data_range(&self) -> Option<(u64, u64)> {
+        let start = self.frame_data_end();
+        let end = start
+            .saturating_add(self.descriptor.terminating_data_bytes as u64)
+            .min(self.tag_offset);
+        (end > start).then(|| (start, end - start))
+    }
+
+    /// Bytes between the terminating data and the trailing tags that no
+    /// part of the format accounts for, as `(offset, length)`.
+    pub(crate) fn unaccounted_tail(&self) -> Option<(u64, u64)> {
+        if self.frame_data_bytes() == 0 {
+            return None;
+        }
+        let tail_start = self
+            .frame_data_end()
+            .saturating_add(self.descriptor.terminating_data_bytes as u64);
+        (self.tag_offset > tail_start).then(|| (tail_start, self.tag_offset - tail_start))
+    }
+
+    /// File offset of frame `frame`'s first byte, per the seek table.
+    pub fn frame_position(&self, frame: usize) -> Option<u64> {
+        self.seek_table
+            .get(frame)
+            .map(|&entry| entry as u64 + self.junk_bytes)
+    }
+
+    /// How many bytes into its first 32-bit word frame `frame` starts.
+    ///
+    /// Compressed data is a stream of 32-bit words counted from the first
+    /// frame, so this is relative to frame 0 rather than to the file.
+    pub fn frame_skip(&self, frame: usize) -> u32 {
+        match (self.seek_table.first(), self.seek_table.get(frame)) {
+            (Some(&first), Some(&entry)) => entry.wrapping_sub(first) & 3,
+            _ => 0,
+        }
+    }
+
+    /// Whether the seek table or frame counts are missing, so the frame
+    /// layout has to be recovered from the data region.
+    #[cfg(feature = "std")]
+    pub(crate) fn needs_layout_recovery(&self) -> bool {
+        self.header.total_frames == 0
+            || (self.seek_table.len() as u64) < self.header.total_frames as u64
+    }
+
+    /// Number of blocks in `frame`: a full frame, or `final_frame_blocks`
+    /// for the last one.
+    pub fn frame_blocks(&self, frame: usize) -> u32 {
+        if frame + 1 == self.header.total_frames as usize {
+            self.header.final_frame_blocks
+        } else {
+            self.header.blocks_per_frame
+        }
+    }
+
+    /// Compressed size of `frame`: up to the next seek-table entry, or to
+    /// the end of the frame data region for the last frame (or when the
+    /// next entry does not lie past this one).
+    pub fn frame_size(&self, frame: usize) -> Option<u64> {
+        let pos = self.frame_position(frame)?;
+        let end = if frame + 1 < self.header.total_frames as usize {
+            self.frame_position(frame + 1).filter(|&next| next > pos)
+        } else {
+            None
+        };
+        Some(end.unwrap_or(self.frame_data_end()).saturating_sub(pos))
+    }
+
+    /// Approximate file offset of block `block`'s compressed data, for
+    /// progress display: its frame's seek-table position plus the block's
+    /// share of the frame's size. `total_blocks()` maps to the end of the
+    /// frame data; `None` past that or the end of the seek table.
+    ///
+    /// Decoding a block needs its whole frame, from
+    /// [`frame_position`](Self::frame_position) of
+    /// `block / blocks_per_frame`.
+    pub fn block_byte_offset(&self, block: u64) -> Option<u64> {
+        let total_blocks = self.total_blocks();
+        if block >= total_blocks {
+            return (block == total_blocks).then(|| self.frame_data_end());
+        }
+        let frame = (block / self.header.blocks_per_frame as u64) as usize;
+        let within = block % self.header.blocks_per_frame as u64;
+        let (pos, size) = (self.frame_position(frame)?, self.frame_size(frame)?);
+        let blocks = self.frame_blocks(frame).max(1) as u128;
+        Some(pos + (size as u128 * within as u128 / blocks) as u64)
+    }
+
+    /// How many blocks, from the start of the stream, can be decoded from
+    /// the first `bytes` bytes of the file: those in frames that end
+    /// (rounded up to a whole 32-bit word) by then. For buffer progress
+    /// while a file downloads.
+    pub fn decodable_blocks(&self, bytes: u64) -> u64 {
+        let first = self.frame_position(0).unwrap_or(self.data_offset);
+        let mut blocks = 0;
+        for frame in 0..self.header.total_frames as usize {
+            let (Some(pos), Some(size)) = (self.frame_position(frame), self.frame_size(frame)) else {
+                break;
+            };
+            let end = first + (pos + size).saturating_sub(first).next_multiple_of(4);
+            if end.min(self.tag_offset) > bytes {
+                break;
+            }
+            blocks += self.frame_blocks(frame) as u64;
+        }
+        blocks
+    }
+
     /// Total number of audio blocks (one block = one sample per channel).
     pub fn total_blocks(&self) -> u64 {
         if self.header.total_frames == 0 {
@@ -63,45 +301,369 @@ impl ApeFileHeader {
     }
 }
 
+/// Identifies the [`SeekTable`] encoding; the last byte is the version.
+const SEEK_TABLE_MAGIC: [u8; 8] = *b"APESEEK\x01";
+
+/// A stream's frame layout: where each frame starts and where the frame
+/// data ends, as taken by [`ApeReader::seek_table`](crate::ApeReader::seek_table).
+///
+/// Caching it lets a file whose layout had to be rebuilt by scanning
+/// (see [`ApeReaderBuilder::recover_frame_layout`](crate::ApeReaderBuilder::recover_frame_layout))
+/// be reopened instantly with
+/// [`ApeReaderBuilder::seek_table`](crate::ApeReaderBuilder::seek_table).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeekTable {
+    /// File offset of each frame's first byte.
+    pub offsets: Vec<u64>,
+    /// Blocks in the last frame.
+    pub final_frame_blocks: u32,
+    /// File offset one past the last byte of frame data.
+    pub frame_data_end: u64,
+}
+
+impl SeekTable {
+    /// Encode for storage: a versioned header, then little-endian fields.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(24 + self.offsets.len() * 8);
+        out.extend_from_slice(&SEEK_TABLE_MAGIC);
+        out.extend_from_slice(&(self.offsets.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.final_frame_blocks.to_le_bytes());
+        out.extend_from_slice(&self.frame_data_end.to_le_bytes());
+        for offset in &self.offsets {
+            out.extend_from_slice(&offset.to_le_bytes());
+        }
+        out
+    }
+
+    /// Decode bytes produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ApeError> {
+        if bytes.len() < 24 || bytes[..8] != SEEK_TABLE_MAGIC {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        let u32_at = |off: usize| u32::from_le_bytes(bytes[off..off + 4].try_into().unwrap());
+        let u64_at = |off: usize| u64::from_le_bytes(bytes[off..off + 8].try_into().unwrap());
+        let count = u32_at(8) as usize;
+        if (bytes.len() - 24) / 8 != count || !(bytes.len() - 24).is_multiple_of(8) {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        Ok(SeekTable {
+            offsets: (0..count).map(|i| u64_at(24 + 8 * i)).collect(),
+            final_frame_blocks: u32_at(12),
+            frame_data_end: u64_at(16),
+        })
+    }
+}
+
+impl ApeFileHeader {
+    /// Header for a bare stream of frames with no `MAC ` header, as carried
+    /// by containers and network protocols that strip it.
+    ///
+    /// The first frame starts at offset 0 and frames run to `stream_len`.
+    /// The seek table is empty, so the layout has to be recovered before
+    /// decoding; [`ApeReaderBuilder::build_raw`](crate::ApeReaderBuilder::build_raw)
+    /// does that. `header.total_frames` and `final_frame_blocks` may be
+    /// zero when unknown.
+    pub fn raw(version: u16, header: ApeHeader, stream_len: u64) -> Self {
+        ApeFileHeader {
+            descriptor: ApeDescriptor {
+                version,
+                descriptor_bytes: 0,
+                header_bytes: 0,
+                seek_table_bytes: 0,
+                header_data_bytes: 0,
+                ape_frame_data_bytes: 0,
+                ape_frame_data_bytes_high: 0,
+                terminating_data_bytes: 0,
+                file_md5: [0; 16],
+            },
+            header,
+            seek_table: Vec::new(),
+            data_offset: 0,
+            junk_bytes: 0,
+            tag_offset: stream_len,
+        }
+    }
+
+    /// This header's frame layout.
+    pub fn seek_table(&self) -> SeekTable {
+        let frames = (self.header.total_frames as usize).min(self.seek_table.len());
+        SeekTable {
+            offsets: (0..frames).filter_map(|i| self.frame_position(i)).collect(),
+            final_frame_blocks: self.header.final_frame_blocks,
+            frame_data_end: self.frame_data_end(),
+        }
+    }
+
+    /// Replace the frame layout with `table`, e.g. one cached from an
+    /// earlier scan of the same file.
+    ///
+    /// Offsets must increase and lie within the frame data region.
+    pub fn apply_seek_table(&mut self, table: &SeekTable) -> Result<(), ApeError> {
+        let in_region = |offset: u64| offset >= self.data_offset && offset < table.frame_data_end;
+        let ascending = table.offsets.windows(2).all(|w| w[0] < w[1]);
+        if table.offsets.is_empty()
+            || !ascending
+            || !table.offsets.iter().all(|&o| in_region(o))
+            || table.frame_data_end > self.tag_offset
+            || table.offsets.len() > u32::MAX as usize
+        {
+            return Err(ApeError::InvalidSeekTable);
+        }
+        let entries: Option<Vec<u32>> = table
+            .offsets
+            .iter()
+            .map(|&o| u32::try_from(o - self.junk_bytes).ok())
+            .collect();
+        self.seek_table = entries.ok_or(ApeError::InvalidSeekTable)?;
+        self.header.total_frames = table.offsets.len() as u32;
+        self.header.final_frame_blocks = table.final_frame_blocks;
+        let frame_data_bytes = table.frame_data_end - self.data_offset;
+        self.descriptor.ape_frame_data_bytes = frame_data_bytes as u32;
+        self.descriptor.ape_frame_data_bytes_high = (frame_data_bytes >> 32) as u32;
+        Ok(())
+    }
+}
+
 /// Parse an APE file header from a reader.
 ///
-/// After this returns, the reader is positioned at the start of compressed
-/// frame data.
+/// Skips a leading ID3v2 tag and looks for trailing APEv2 / ID3v1 tags, so
+/// the reader is seeked to its end first. After this returns, the reader
+/// is positioned at the start of compressed frame data.
+///
+/// The frame counts and seek table are checked against each other, so a
+/// file whose frame layout is missing (an aborted encode) fails here; open
+/// it with [`ApeReaderBuilder::recover_frame_layout`](crate::ApeReaderBuilder::recover_frame_layout).
+#[cfg(feature = "std")]
 pub fn parse_header<R: Read + Seek>(reader: &mut R) -> Result<ApeFileHeader, ApeError> {
+    let header = parse(&mut IoInput(reader))?;
+    validate_frame_layout(&header)?;
+    Ok(header)
+}
+
+/// [`parse_header`] without the frame layout checks, for a reader that
+/// rebuilds the layout or takes it from a [`SeekTable`] and checks the
+/// result itself.
+#[cfg(feature = "std")]
+pub(crate) fn parse_header_for_recovery<R: Read + Seek>(reader: &mut R) -> Result<ApeFileHeader, ApeError> {
+    parse(&mut IoInput(reader))
+}
+
+/// Parse an APE file header from a complete file in memory, like
+/// [`parse_header`]. Offsets in the result index into `data`.
+pub fn parse_header_slice(data: &[u8]) -> Result<ApeFileHeader, ApeError> {
+    let header = parse(&mut SliceInput { data, pos: 0 })?;
+    validate_frame_layout(&header)?;
+    Ok(header)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "parse_header", level = "debug", skip_all, err))]
+fn parse<I: Input>(input: &mut I) -> Result<ApeFileHeader, ApeError> {
+    let stream_len = input.len()?;
+    input.seek_to(0)?;
+
     // Scan for "MAC " magic — there may be leading junk (ID3v2 tag, etc.)
-    let desc_start = find_magic(reader)?;
+    let desc_start = find_magic(input)?;
 
     // Read descriptor (magic already consumed, reads remaining fields)
-    let descriptor = read_descriptor(reader)?;
+    let descriptor = read_descriptor(input)?;
+    validate_descriptor(&descriptor, desc_start, stream_len)?;
 
     // Seek to header start using descriptor_bytes (robust to future extensions)
-    reader.seek(SeekFrom::Start(desc_start + descriptor.descriptor_bytes as u64))?;
-    let header = read_header(reader)?;
+    input.seek_to(desc_start + descriptor.descriptor_bytes as u64)?;
+    let header = read_header(input)?;
 
     // Seek to seek table start
     let seek_table_start = desc_start
         + descriptor.descriptor_bytes as u64
         + descriptor.header_bytes as u64;
-    reader.seek(SeekFrom::Start(seek_table_start))?;
-    let seek_table = read_seek_table(reader, &descriptor)?;
+    input.seek_to(seek_table_start)?;
+    let seek_table = read_seek_table(input, &descriptor)?;
 
     // Data offset: after descriptor + header + seek table + header data
     let data_offset = seek_table_start
         + descriptor.seek_table_bytes as u64
         + descriptor.header_data_bytes as u64;
 
+    // Tags follow the frame data and terminating data; when the descriptor
+    // sizes those, bytes in front of their end are audio, whatever they
+    // happen to contain
+    let frame_data_bytes = descriptor.ape_frame_data_bytes as u64
+        | ((descriptor.ape_frame_data_bytes_high as u64) << 32);
+    let declared_end = data_offset
+        .saturating_add(frame_data_bytes)
+        .saturating_add(descriptor.terminating_data_bytes as u64);
+    let tags_from = if frame_data_bytes > 0 && declared_end <= stream_len {
+        declared_end
+    } else {
+        data_offset
+    };
+    let tag_offset = find_trailing_tags(input, stream_len, tags_from)?.start;
+    input.seek_to(data_offset)?;
+    diag!(
+        debug,
+        version = descriptor.version,
+        level = header.compression_level,
+        frames = header.total_frames,
+        blocks_per_frame = header.blocks_per_frame,
+        data_offset = data_offset,
+        tag_offset = tag_offset;
+        "parsed header"
+    );
+
     Ok(ApeFileHeader {
         descriptor,
         header,
         seek_table,
         data_offset,
+        junk_bytes: desc_start,
+        tag_offset,
     })
 }
 
+/// Where the tags at the end of a stream lie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TrailingTags {
+    /// Offset of the first tag byte, or the stream length if there are no
+    /// tags.
+    pub start: u64,
+    /// The bytes of the APEv2 tag's items and how many it declares, if
+    /// there is an APEv2 tag.
+    pub ape_items: Option<(Range<u64>, u32)>,
+}
+
+/// Find the tags at the end of the stream: an ID3v1 tag and/or an APEv2
+/// tag in front of it. Tags that would reach back past `tags_from`, and
+/// APEv2 footers whose size cannot hold the footer, are ignored.
+fn find_trailing_tags<I: Input>(
+    input: &mut I,
+    stream_len: u64,
+    tags_from: u64,
+) -> Result<TrailingTags, ApeError> {
+    let mut end = stream_len;
+    let mut ape_items = None;
+
+    if end >= tags_from + ID3V1_SIZE {
+        let mut magic = [0u8; 3];
+        input.seek_to(end - ID3V1_SIZE)?;
+        input.read_exact(&mut magic)?;
+        if &magic == b"TAG" {
+            end -= ID3V1_SIZE;
+        }
+    }
+
+    if end >= tags_from + APE_TAG_FOOTER_SIZE {
+        let mut footer = [0u8; APE_TAG_FOOTER_SIZE as usize];
+        input.seek_to(end - APE_TAG_FOOTER_SIZE)?;
+        input.read_exact(&mut footer)?;
+        if &footer[..8] == b"APETAGEX" {
+            // Size covers the items and footer; bit 31 of the flags marks
+            // an additional 32-byte header in front of the items.
+            let size = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as u64;
+            let items = u32::from_le_bytes([footer[16], footer[17], footer[18], footer[19]]);
+            let flags = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+            let header = if flags & (1 << 31) != 0 { APE_TAG_FOOTER_SIZE } else { 0 };
+            let tag_len = size + header;
+            if size >= APE_TAG_FOOTER_SIZE && end >= tags_from + tag_len {
+                ape_items = Some((end - size..end - APE_TAG_FOOTER_SIZE, items));
+                end -= tag_len;
+            }
+        }
+    }
+
+    Ok(TrailingTags { start: end, ape_items })
+}
+
+/// [`find_trailing_tags`] over `bytes`, which hold only tags (e.g. from
+/// [`ApeFileHeader::tag_offset`] to the end of the stream).
+pub(crate) fn find_trailing_tags_in(bytes: &[u8]) -> TrailingTags {
+    let mut input = SliceInput { data: bytes, pos: 0 };
+    // Every read is bounds-checked against the slice first
+    find_trailing_tags(&mut input, bytes.len() as u64, 0).unwrap_or(TrailingTags {
+        start: bytes.len() as u64,
+        ape_items: None,
+    })
+}
+
+/// Check that the descriptor's region sizes are at least the fixed layout
+/// and that the header and seek table they describe lie within the stream.
+fn validate_descriptor(
+    descriptor: &ApeDescriptor,
+    desc_start: u64,
+    stream_len: u64,
+) -> Result<(), ApeError> {
+    if descriptor.descriptor_bytes < DESCRIPTOR_SIZE {
+        return Err(ApeError::InvalidHeader(format!(
+            "descriptor_bytes {} is smaller than the {DESCRIPTOR_SIZE}-byte descriptor",
+            descriptor.descriptor_bytes
+        )));
+    }
+    if descriptor.header_bytes < HEADER_SIZE {
+        return Err(ApeError::InvalidHeader(format!(
+            "header_bytes {} is smaller than the {HEADER_SIZE}-byte header",
+            descriptor.header_bytes
+        )));
+    }
+
+    let header_end =
+        desc_start + descriptor.descriptor_bytes as u64 + descriptor.header_bytes as u64;
+    if header_end > stream_len {
+        return Err(ApeError::InvalidHeader(format!(
+            "header ends at byte {header_end}, past end of stream ({stream_len} bytes)"
+        )));
+    }
+    let seek_table_end = header_end + descriptor.seek_table_bytes as u64;
+    if seek_table_end > stream_len {
+        return Err(ApeError::InvalidHeader(format!(
+            "seek table ends at byte {seek_table_end}, past end of stream ({stream_len} bytes)"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that the frame counts in the header agree with each other and with
+/// the seek table, so inconsistencies are reported up front rather than as
+/// mid-decode failures.
+pub(crate) fn validate_frame_layout(file_header: &ApeFileHeader) -> Result<(), ApeError> {
+    let h = &file_header.header;
+
+    if h.final_frame_blocks > h.blocks_per_frame {
+        return Err(ApeError::InvalidHeader(format!(
+            "final frame has {} blocks, more than blocks_per_frame ({})",
+            h.final_frame_blocks, h.blocks_per_frame
+        )));
+    }
+    if h.total_frames > 0 && h.final_frame_blocks == 0 {
+        return Err(ApeError::InvalidHeader(format!(
+            "final_frame_blocks is 0 with {} total frames",
+            h.total_frames
+        )));
+    }
+    // The seek table may be pre-allocated larger than needed (zero-filled
+    // trailing entries), but it must cover every frame.
+    if (file_header.seek_table.len() as u64) < h.total_frames as u64 {
+        return Err(ApeError::InvalidHeader(format!(
+            "seek table has {} entries but header declares {} frames",
+            file_header.seek_table.len(),
+            h.total_frames
+        )));
+    }
+    if h.total_frames > 0 && file_header.frame_data_bytes() == 0 {
+        return Err(ApeError::InvalidHeader(format!(
+            "header declares {} frames ({} blocks) but no frame data",
+            h.total_frames,
+            file_header.total_blocks()
+        )));
+    }
+
+    Ok(())
+}
+
 /// Scan forward to find the "MAC " magic bytes, returning the byte offset.
-fn find_magic<R: Read + Seek>(reader: &mut R) -> Result<u64, ApeError> {
+fn find_magic<I: Input>(input: &mut I) -> Result<u64, ApeError> {
     let mut buf = [0u8; 4];
-    reader.read_exact(&mut buf)?;
+    input.read_exact(&mut buf)?;
 
     if buf == APE_MAGIC {
         return Ok(0);
@@ -111,15 +673,15 @@ fn find_magic<R: Read + Seek>(reader: &mut R) -> Result<u64, ApeError> {
     if &buf[..3] == b"ID3" {
         // Read the rest of the ID3v2 header to get its size
         let mut id3_header = [0u8; 6]; // 6 more bytes after "ID3" + version byte we already read
-        reader.read_exact(&mut id3_header)?;
+        input.read_exact(&mut id3_header)?;
         // ID3v2 size is a 28-bit syncsafe integer at bytes 6-9 of the header
         let size = ((id3_header[2] as u32) << 21)
             | ((id3_header[3] as u32) << 14)
             | ((id3_header[4] as u32) << 7)
             | (id3_header[5] as u32);
         let offset = 10 + size as u64; // 10-byte header + body
-        reader.seek(SeekFrom::Start(offset))?;
-        reader.read_exact(&mut buf)?;
+        input.seek_to(offset)?;
+        input.read_exact(&mut buf)?;
         if buf == APE_MAGIC {
             return Ok(offset);
         }
@@ -129,26 +691,26 @@ fn find_magic<R: Read + Seek>(reader: &mut R) -> Result<u64, ApeError> {
 }
 
 /// Read the APE descriptor (everything after the 4 magic bytes).
-fn read_descriptor<R: Read>(reader: &mut R) -> Result<ApeDescriptor, ApeError> {
-    let version = read_u16_le(reader)?;
+fn read_descriptor<I: Input>(input: &mut I) -> Result<ApeDescriptor, ApeError> {
+    let version = input.u16_le()?;
     if version < MIN_VERSION {
         return Err(ApeError::UnsupportedVersion(version));
     }
 
     // 2 reserved/padding bytes after version
     let mut _padding = [0u8; 2];
-    reader.read_exact(&mut _padding)?;
+    input.read_exact(&mut _padding)?;
 
-    let descriptor_bytes = read_u32_le(reader)?;
-    let header_bytes = read_u32_le(reader)?;
-    let seek_table_bytes = read_u32_le(reader)?;
-    let header_data_bytes = read_u32_le(reader)?;
-    let ape_frame_data_bytes = read_u32_le(reader)?;
-    let ape_frame_data_bytes_high = read_u32_le(reader)?;
-    let terminating_data_bytes = read_u32_le(reader)?;
+    let descriptor_bytes = input.u32_le()?;
+    let header_bytes = input.u32_le()?;
+    let seek_table_bytes = input.u32_le()?;
+    let header_data_bytes = input.u32_le()?;
+    let ape_frame_data_bytes = input.u32_le()?;
+    let ape_frame_data_bytes_high = input.u32_le()?;
+    let terminating_data_bytes = input.u32_le()?;
 
     let mut file_md5 = [0u8; 16];
-    reader.read_exact(&mut file_md5)?;
+    input.read_exact(&mut file_md5)?;
 
     Ok(ApeDescriptor {
         version,
@@ -164,17 +726,46 @@ fn read_descriptor<R: Read>(reader: &mut R) -> Result<ApeDescriptor, ApeError> {
 }
 
 /// Read the APE header (24 bytes).
-fn read_header<R: Read>(reader: &mut R) -> Result<ApeHeader, ApeError> {
-    let compression_level = read_u16_le(reader)?;
-    let format_flags = read_u16_le(reader)?;
-    let blocks_per_frame = read_u32_le(reader)?;
-    let final_frame_blocks = read_u32_le(reader)?;
-    let total_frames = read_u32_le(reader)?;
-    let bits_per_sample = read_u16_le(reader)?;
-    let channels = read_u16_le(reader)?;
-    let sample_rate = read_u32_le(reader)?;
-
-    // Validate
+fn read_header<I: Input>(input: &mut I) -> Result<ApeHeader, ApeError> {
+    let compression_level = input.u16_le()?;
+    let format_flags = input.u16_le()?;
+    let blocks_per_frame = input.u32_le()?;
+    let final_frame_blocks = input.u32_le()?;
+    let total_frames = input.u32_le()?;
+    let bits_per_sample = input.u16_le()?;
+    let channels = input.u16_le()?;
+    let sample_rate = input.u32_le()?;
+
+    let header = ApeHeader {
+        compression_level,
+        format_flags,
+        blocks_per_frame,
+        final_frame_blocks,
+        total_frames,
+        bits_per_sample,
+        channels,
+        sample_rate,
+    };
+    validate_header(&header)?;
+    Ok(header)
+}
+
+/// Check that the stream parameters are ones the decoder supports.
+pub(crate) fn validate_header(header: &ApeHeader) -> Result<(), ApeError> {
+    let &ApeHeader {
+        sample_rate,
+        blocks_per_frame,
+        channels,
+        bits_per_sample,
+        compression_level,
+        ..
+    } = header;
+    if sample_rate == 0 {
+        return Err(ApeError::InvalidHeader("sample rate is zero".into()));
+    }
+    if blocks_per_frame == 0 {
+        return Err(ApeError::InvalidHeader("blocks_per_frame is zero".into()));
+    }
     if channels == 0 || channels > 2 {
         return Err(ApeError::InvalidHeader(format!(
             "unsupported channel count: {channels}"
@@ -185,46 +776,113 @@ fn read_header<R: Read>(reader: &mut R) -> Result<ApeHeader, ApeError> {
             "unsupported bits per sample: {bits_per_sample}"
         )));
     }
-    match compression_level {
-        1000 | 2000 | 3000 | 4000 | 5000 => {}
-        _ => return Err(ApeError::UnsupportedCompressionLevel(compression_level)),
+    if !packet::is_supported_level(compression_level) {
+        return Err(ApeError::UnsupportedCompressionLevel(compression_level));
     }
 
-    Ok(ApeHeader {
-        compression_level,
-        format_flags,
-        blocks_per_frame,
-        final_frame_blocks,
-        total_frames,
-        bits_per_sample,
-        channels,
-        sample_rate,
-    })
+    Ok(())
+}
+
+/// Read a descriptor and header serialized back to back by their
+/// `write_to` methods, checking them as [`parse_header`] would.
+#[cfg(feature = "std")]
+pub(crate) fn read_descriptor_and_header(bytes: &[u8]) -> Result<(ApeDescriptor, ApeHeader), ApeError> {
+    let mut input = SliceInput { data: bytes, pos: 0 };
+    let mut magic = [0u8; 4];
+    input.read_exact(&mut magic)?;
+    if magic != APE_MAGIC {
+        return Err(ApeError::InvalidMagic);
+    }
+    let descriptor = read_descriptor(&mut input)?;
+    let header = read_header(&mut input)?;
+    Ok((descriptor, header))
+}
+
+/// Check that a header built by hand is one the decoder supports.
+pub(crate) fn validate_file_header(file_header: &ApeFileHeader) -> Result<(), ApeError> {
+    if file_header.descriptor.version < MIN_VERSION {
+        return Err(ApeError::UnsupportedVersion(file_header.descriptor.version));
+    }
+    validate_header(&file_header.header)
 }
 
 /// Read the seek table — array of u32 offsets, one per frame.
-fn read_seek_table<R: Read>(
-    reader: &mut R,
+fn read_seek_table<I: Input>(
+    input: &mut I,
     descriptor: &ApeDescriptor,
 ) -> Result<Vec<u32>, ApeError> {
     let n_entries = descriptor.seek_table_bytes / 4;
     let mut table = Vec::with_capacity(n_entries as usize);
     for _ in 0..n_entries {
-        table.push(read_u32_le(reader)?);
+        table.push(input.u32_le()?);
     }
     Ok(table)
 }
 
-// ── Little-endian helpers ────────────────────────────────────────────
+// ── Input sources ────────────────────────────────────────────────────
+
+/// What the parser reads from: a seekable stream, or a slice without `std`.
+trait Input {
+    fn len(&mut self) -> Result<u64, ApeError>;
+    fn seek_to(&mut self, pos: u64) -> Result<(), ApeError>;
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ApeError>;
+
+    fn u16_le(&mut self) -> Result<u16, ApeError> {
+        let mut buf = [0u8; 2];
+        self.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
 
-fn read_u16_le<R: Read>(r: &mut R) -> Result<u16, io::Error> {
-    let mut buf = [0u8; 2];
-    r.read_exact(&mut buf)?;
-    Ok(u16::from_le_bytes(buf))
+    fn u32_le(&mut self) -> Result<u32, ApeError> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
 }
 
-fn read_u32_le<R: Read>(r: &mut R) -> Result<u32, io::Error> {
-    let mut buf = [0u8; 4];
-    r.read_exact(&mut buf)?;
-    Ok(u32::from_le_bytes(buf))
+#[cfg(feature = "std")]
+struct IoInput<'r, R>(&'r mut R);
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Input for IoInput<'_, R> {
+    fn len(&mut self) -> Result<u64, ApeError> {
+        Ok(self.0.seek(SeekFrom::End(0))?)
+    }
+
+    fn seek_to(&mut self, pos: u64) -> Result<(), ApeError> {
+        self.0.seek(SeekFrom::Start(pos))?;
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ApeError> {
+        Ok(self.0.read_exact(buf)?)
+    }
+}
+
+struct SliceInput<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Input for SliceInput<'_> {
+    fn len(&mut self) -> Result<u64, ApeError> {
+        Ok(self.data.len() as u64)
+    }
+
+    fn seek_to(&mut self, pos: u64) -> Result<(), ApeError> {
+        // Like a file, positions past the end are fine until read from
+        self.pos = usize::try_from(pos).unwrap_or(usize::MAX);
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ApeError> {
+        let bytes = self
+            .data
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..buf.len()))
+            .ok_or_else(|| ApeError::InvalidHeader("unexpected end of data".into()))?;
+        buf.copy_from_slice(bytes);
+        self.pos += buf.len();
+        Ok(())
+    }
 }
@@ -0,0 +1,28 @@
+//! The codec's building blocks, for experiments and tooling (feature
+//! `internals`).
+//!
+//! **No stability guarantee.** These types follow the decoder's needs and
+//! may change in any release, including patch releases. Pin an exact
+//! version if you depend on them.
+//!
+//! A frame decodes as: [`RangeCoder::decode_value`] per channel (with one
+//! [`RiceState`] each), then [`NNFilter::decompress`], then
+//! [`Predictor::decode_mono`] or [`Predictor::decode_stereo`]. Filters and
+//! predictor are reset at the start of every frame. The range coder
+//! expects frame data with each 32-bit word byte-swapped and the frame
+//! header (CRC, optional flags, one skip byte) already removed.
+//!
+//! Misuse panics rather than returning errors: `NNFilter::new` takes
+//! `compression_level / 1000 - 1` and panics outside `0..5`.
+//! [`PacketDecoder`](crate::packet::PacketDecoder) is the supported way to
+//! decode a single frame.
+//!
+//! [`dot_and_adapt`] is a filter stage's inner loop: the simd128 kernel on
+//! `wasm32` builds with SIMD, else [`dot_and_adapt_scalar`], which it must
+//! match exactly.
+
+pub use crate::nnfilter::{
+    FILTER_FRACBITS, FILTER_ORDERS, MAX_STAGES, NNFilter, NNFilterStage, dot_and_adapt, dot_and_adapt_scalar,
+};
+pub use crate::predictor::Predictor;
+pub use crate::range_coder::{RangeCoder, RiceState};
@@ -0,0 +1,125 @@
+//! Byte map of an APE file.
+//!
+//! [`ApeFileHeader::layout`] lists the regions a file is made of, in file
+//! order, from what the header parser already found: leading junk such as
+//! an ID3v2 tag, the descriptor, the header, the seek table, the stored WAV
+//! header, each frame, the terminating data and the trailing tags. Hex
+//! editors, forensic tools and bug reports can label bytes with it.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::ApeFileHeader;
+#[cfg(feature = "std")]
+use crate::ApeReader;
+#[cfg(feature = "std")]
+use crate::error::ApeError;
+
+/// What a [`Region`] of the file holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegionKind {
+    /// Bytes before the descriptor, usually an ID3v2 tag.
+    Junk,
+    /// The `MAC ` descriptor.
+    Descriptor,
+    /// The header with the stream parameters.
+    Header,
+    /// The seek table.
+    SeekTable,
+    /// The original WAV file's header.
+    HeaderData,
+    /// A compressed frame. Frames are packed as 32-bit words, so one may
+    /// share its first and last word with its neighbours.
+    Frame(u32),
+    /// What followed the audio in the original WAV file.
+    TerminatingData,
+    /// Bytes between the terminating data and the tags that no part of
+    /// the format accounts for.
+    Unaccounted,
+    /// Trailing APEv2 and/or ID3v1 tags.
+    Tags,
+}
+
+/// A byte range of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub kind: RegionKind,
+    /// File offset of the first byte.
+    pub offset: u64,
+    /// Length in bytes.
+    pub len: u64,
+}
+
+impl Region {
+    /// File offset one past the last byte.
+    pub fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+impl ApeFileHeader {
+    /// The regions of a file of `stream_len` bytes with this header, in
+    /// file order. Empty regions are left out.
+    ///
+    /// Regions come from the descriptor sizes and the seek table and are
+    /// not checked against each other: in a damaged file they may overlap
+    /// or leave gaps.
+    pub fn layout(&self, stream_len: u64) -> Vec<Region> {
+        let d = &self.descriptor;
+        let mut regions = Vec::new();
+        let mut push = |kind, offset, len| {
+            if len > 0 {
+                regions.push(Region { kind, offset, len });
+            }
+        };
+
+        let header_start = self.junk_bytes + d.descriptor_bytes as u64;
+        let seek_table_start = header_start + d.header_bytes as u64;
+        push(RegionKind::Junk, 0, self.junk_bytes);
+        push(RegionKind::Descriptor, self.junk_bytes, d.descriptor_bytes as u64);
+        push(RegionKind::Header, header_start, d.header_bytes as u64);
+        push(RegionKind::SeekTable, seek_table_start, d.seek_table_bytes as u64);
+        if let Some((offset, len)) = self.header_data_range() {
+            push(RegionKind::HeaderData, offset, len);
+        }
+        let frames = self.seek_table.len().min(self.header.total_frames as usize);
+        for frame in 0..frames {
+            if let (Some(offset), Some(len)) = (self.frame_position(frame), self.frame_size(frame)) {
+                push(RegionKind::Frame(frame as u32), offset, len);
+            }
+        }
+        if let Some((offset, len)) = self.terminating_data_range() {
+            push(RegionKind::TerminatingData, offset, len);
+        }
+        if let Some((offset, len)) = self.unaccounted_tail() {
+            push(RegionKind::Unaccounted, offset, len);
+        }
+        push(RegionKind::Tags, self.tag_offset, stream_len.saturating_sub(self.tag_offset));
+        regions
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeReader<R> {
+    /// The regions of the file, in file order: see
+    /// [`ApeFileHeader::layout`].
+    ///
+    /// This finds the file's length through the underlying reader but does
+    /// not disturb decoding.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// for region in reader.layout().unwrap() {
+    ///     println!("{:>10} {:>8} {:?}", region.offset, region.len, region.kind);
+    /// }
+    /// ```
+    pub fn layout(&mut self) -> Result<Vec<Region>, ApeError> {
+        let stream_len = self.decoder.reader.seek(SeekFrom::End(0))?;
+        Ok(self.decoder.header.layout(stream_len))
+    }
+}
@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 //! Pure Rust decoder for Monkey's Audio (APE) lossless audio files.
 //!
@@ -21,23 +21,200 @@
 //!
 //! let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
 //! ```
+//!
+//! # `no_std`
+//!
+//! With default features off, the crate builds on `core` + `alloc`. The
+//! codec core remains: [`slice::SliceDecoder`] decodes a file held in
+//! memory and [`packet::PacketDecoder`] single frames, alongside the header
+//! models and [`snapshot`]. [`ApeReader`],
+//! header parsing and the analysis modules need the `std` feature.
 
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+pub mod apl;
+mod arith;
 mod buffer;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(feature = "chromaprint")]
+pub mod chromaprint;
+#[cfg(feature = "std")]
+pub mod clipping;
+#[cfg(feature = "compare")]
+pub mod compare;
+pub mod crc;
+pub mod convert;
+pub mod cue;
+#[cfg(feature = "debug-dump")]
+pub mod debug_dump;
+#[cfg(feature = "std")]
 mod decode;
+mod diag;
+#[cfg(feature = "std")]
+pub mod dither;
+#[cfg(feature = "std")]
+pub mod digest;
 pub mod error;
+pub mod fixed;
+#[cfg(any(feature = "chromaprint", feature = "spectral"))]
+mod fft;
+#[cfg(feature = "std")]
+pub mod frame_cache;
+#[cfg(feature = "std")]
+pub mod frame_index;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+pub mod gapless;
 mod header;
+#[cfg(feature = "internals")]
+pub mod internals;
+pub mod layout;
+#[cfg(feature = "loudness")]
+pub mod loudness;
+pub mod memory;
+pub mod metadata;
+pub mod mix;
 mod nnfilter;
+#[cfg(feature = "std")]
+mod original;
+pub mod packet;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod pipeline;
 mod predictor;
 mod range_coder;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "rubato")]
+pub mod resample;
+pub mod riff;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod sidecar;
+#[cfg(feature = "std")]
+pub mod silence;
+#[cfg(feature = "std")]
+pub mod strip;
+pub mod slice;
+pub mod snapshot;
+#[cfg(feature = "spectral")]
+pub mod spectral;
+#[cfg(feature = "testgen")]
+pub mod testgen;
+#[cfg(feature = "std")]
+mod true_peak;
+pub mod wav;
+#[cfg(feature = "std")]
+pub mod wav_reader;
+#[cfg(feature = "std")]
+pub mod waveform;
 
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{BufReader, Read, Seek};
+#[cfg(feature = "std")]
+use std::io::{BufReader, Read, Seek, SeekFrom};
+#[cfg(feature = "std")]
+use std::ops::{ControlFlow, Range};
+#[cfg(feature = "std")]
 use std::path::Path;
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+#[cfg(feature = "std")]
+use diag::diag;
+pub use error::{ApeError, DecodeWarning, ErrorContext, ErrorKind};
+#[cfg(feature = "std")]
+pub use error::IoError;
+pub use header::{ApeDescriptor, ApeFileHeader, ApeHeader, SeekTable, parse_header_slice};
+#[cfg(feature = "std")]
+pub use header::parse_header;
+pub use metadata::Metadata;
+
+/// `Read + Seek` as one trait, so sources of different types can be boxed
+/// as `Box<dyn ReadSeek + Send>`: see [`ApeReader::from_boxed`].
+/// Implemented for every `Read + Seek` type.
+#[cfg(feature = "std")]
+pub trait ReadSeek: Read + Seek {}
+
+#[cfg(feature = "std")]
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// An [`ApeReader`] over a boxed source of any type, from
+/// [`ApeReader::from_boxed`].
+#[cfg(feature = "std")]
+pub type BoxedApeReader = ApeReader<Box<dyn ReadSeek + Send>>;
 
-pub use error::ApeError;
+/// How the decoder reacts when a frame fails its CRC or cannot be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameErrorMode {
+    /// Return the error from the sample iterator (default).
+    #[default]
+    Strict,
+    /// Replace the frame with silence of the same length and keep going.
+    Silence,
+    /// Drop the frame's samples entirely and keep going.
+    Skip,
+}
+
+/// What to do about one failed frame, as chosen by a handler installed with
+/// [`ApeReaderBuilder::on_frame_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameErrorAction {
+    /// Drop the frame's samples and continue with the next frame.
+    Skip,
+    /// Replace the frame with silence of the same length and continue.
+    FillSilence,
+    /// Read and decode the frame again (e.g. after a transient I/O error).
+    Retry,
+    /// Return the error from the sample iterator.
+    Abort,
+}
+
+/// Callback deciding how to handle a failed frame.
+#[cfg(feature = "std")]
+type FrameErrorHandler = Arc<dyn Fn(&ApeError, u32) -> FrameErrorAction + Send + Sync>;
+
+/// How far decoding has got, as passed to a callback installed with
+/// [`ApeReaderBuilder::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Progress {
+    /// Blocks (samples per channel) accounted for so far, including
+    /// concealed or skipped ones.
+    pub blocks_decoded: u64,
+    /// Blocks in the whole stream.
+    pub total_blocks: u64,
+}
+
+impl Progress {
+    /// Fraction of the stream done, from 0.0 to 1.0. An empty stream counts
+    /// as done.
+    pub fn fraction(&self) -> f64 {
+        if self.total_blocks == 0 {
+            1.0
+        } else {
+            (self.blocks_decoded as f64 / self.total_blocks as f64).min(1.0)
+        }
+    }
+}
+
+/// Callback receiving decode progress.
+#[cfg(feature = "std")]
+type ProgressHandler = Arc<dyn Fn(Progress) + Send + Sync>;
 
 /// Metadata about the audio contained in an APE file.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ApeInfo {
     /// Sample rate in Hz (e.g. 44100).
     pub sample_rate: u32,
@@ -51,17 +228,491 @@ pub struct ApeInfo {
     pub compression_level: u16,
     /// Format version (e.g. 3990 for v3.99).
     pub format_version: u16,
+    /// Speaker positions of the channels, when the stored WAV header uses
+    /// `WAVE_FORMAT_EXTENSIBLE` (see [`riff::WaveFormat::channel_mask`]).
+    pub channel_mask: Option<u32>,
+}
+
+impl fmt::Display for ApeInfo {
+    /// A one-line summary, e.g. `44100 Hz, 16-bit stereo, 3:25.120
+    /// (9055392 blocks), Normal (2000), format 3.99`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = match self.channels {
+            1 => "mono",
+            2 => "stereo",
+            _ => "multichannel",
+        };
+        let blocks = self.total_samples / self.channels.max(1) as u64;
+        let millis = blocks * 1000 / self.sample_rate.max(1) as u64;
+        let level = match self.compression_level {
+            1000 => "Fast",
+            2000 => "Normal",
+            3000 => "High",
+            4000 => "Extra High",
+            5000 => "Insane",
+            _ => "level",
+        };
+        write!(
+            f,
+            "{} Hz, {}-bit {channels}, {}:{:02}.{:03} ({blocks} blocks), {level} ({}), format {}.{:02}",
+            self.sample_rate,
+            self.bits_per_sample,
+            millis / 60_000,
+            millis / 1000 % 60,
+            millis % 1000,
+            self.compression_level,
+            self.format_version / 1000,
+            self.format_version % 1000 / 10,
+        )
+    }
+}
+
+/// Location and size of one compressed frame, from the header and seek
+/// table (no decoding involved).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameInfo {
+    /// Frame index (0-based).
+    pub index: u32,
+    /// File offset of the frame's first byte, per the seek table.
+    pub offset: u64,
+    /// Compressed size in bytes, up to the next frame's offset (or the end
+    /// of the frame data for the last frame).
+    pub size: u64,
+    /// Position of `offset` within its 32-bit word, counted from frame 0
+    /// (see [`PacketDecoder::decode_packet`](packet::PacketDecoder::decode_packet)).
+    pub skip: u32,
+    /// Number of blocks (samples per channel) the frame holds.
+    pub blocks: u32,
+    /// Index of the frame's first block in the stream. Multiply by the
+    /// channel count for its index among interleaved samples.
+    pub first_block: u64,
+}
+
+/// One decoded frame, from [`ApeReader::decoded_frames`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frame {
+    /// Frame index (0-based).
+    pub index: u32,
+    /// Index of the first block in `samples` in the stream.
+    pub first_block: u64,
+    /// The frame's interleaved samples: silence, or nothing, for a frame
+    /// the frame error mode concealed or skipped.
+    pub samples: Vec<i32>,
+    /// Whether the frame decoded and passed its CRC check.
+    pub crc_ok: bool,
+}
+
+/// A whole decoded stream, from [`load`] or [`ApeReader::read_all`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedAudio {
+    pub info: ApeInfo,
+    /// All samples, interleaved.
+    pub samples: Vec<i32>,
+    /// Length of `samples` at the stream's sample rate.
+    pub duration: Duration,
+}
+
+#[cfg(feature = "std")]
+impl DecodedAudio {
+    /// Number of blocks (samples per channel).
+    pub fn blocks(&self) -> usize {
+        self.samples.len() / self.info.channels.max(1) as usize
+    }
+
+    /// The samples of channel `channel` (0-based), or `None` if there is no
+    /// such channel.
+    pub fn channel(&self, channel: u16) -> Option<Vec<i32>> {
+        let channels = self.info.channels as usize;
+        (channel < self.info.channels)
+            .then(|| self.samples[channel as usize..].iter().step_by(channels).copied().collect())
+    }
+
+    /// The samples split into one `Vec` per channel.
+    pub fn planar(&self) -> Vec<Vec<i32>> {
+        (0..self.info.channels).filter_map(|ch| self.channel(ch)).collect()
+    }
+}
+
+/// Decode the APE file at `path` in one call: its format, all its samples
+/// and its duration.
+///
+/// For scripts, tests and preprocessing where the whole file fits in
+/// memory; see [`ApeReader`] to stream instead. Any frame that fails to
+/// decode fails the load.
+///
+/// ```no_run
+/// let audio = ape_rs::load("track.ape").unwrap();
+/// println!("{:?} of {} Hz audio", audio.duration, audio.info.sample_rate);
+/// let [left, right] = <[Vec<i32>; 2]>::try_from(audio.planar()).unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<DecodedAudio, ApeError> {
+    ApeReader::open(path)?.read_all()
+}
+
+/// Time of block `block` at `sample_rate`, exact to the nanosecond.
+#[cfg(feature = "std")]
+fn block_time(block: u64, sample_rate: u32) -> Duration {
+    let rate = sample_rate.max(1) as u64;
+    Duration::from_secs(block / rate) + Duration::from_nanos(block % rate * 1_000_000_000 / rate)
+}
+
+/// A frame's compressed bytes, as read by
+/// [`ApeReader::read_raw_frame`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    pub info: FrameInfo,
+    /// File bytes from the start of the 32-bit word holding the frame's
+    /// first byte (`info.skip` bytes before it) to the end of the word
+    /// holding its last, ready for
+    /// [`PacketDecoder::decode_packet`](packet::PacketDecoder::decode_packet).
+    pub data: Vec<u8>,
+}
+
+impl RawFrame {
+    /// Just the frame's own bytes, without the neighbouring frames' bytes
+    /// that share its first and last words.
+    pub fn bytes(&self) -> &[u8] {
+        let skip = self.info.skip as usize;
+        let end = (skip + self.info.size as usize).min(self.data.len());
+        &self.data[skip.min(end)..end]
+    }
 }
 
 /// A reader that decodes Monkey's Audio (APE) files.
 ///
 /// Modeled after `shorten_rs::ShnReader` — open a file, read metadata, then
 /// iterate over decoded PCM samples.
+///
+/// # Allocation
+///
+/// The decoder's buffers are sized for the largest frame when the reader
+/// is created, so decoding frames does not allocate afterwards: pulling
+/// samples through [`samples`](Self::samples),
+/// [`SampleChunks::next_chunk`], [`read_samples`](Self::read_samples) or
+/// [`decode_with`](Self::decode_with) is safe on a realtime audio thread. The exceptions are the error paths
+/// (a damaged frame's resync search, error messages and warnings), filling
+/// a [frame cache](ApeReaderBuilder::frame_cache), the `tracing`, `log`
+/// and `debug-dump` features, and methods that return owned data such as
+/// `Vec`s.
+#[cfg(feature = "std")]
 pub struct ApeReader<R: Read + Seek> {
     decoder: decode::Decoder<R>,
     info: ApeInfo,
+    /// Builder options that apply again in [`reset_for`](Self::reset_for).
+    recover_frame_layout: bool,
+    exact_duration: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> fmt::Debug for ApeReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApeReader")
+            .field("info", &self.info)
+            .field("decoder", &self.decoder)
+            .field("recover_frame_layout", &self.recover_frame_layout)
+            .field("exact_duration", &self.exact_duration)
+            .finish()
+    }
 }
 
+/// Stream metadata from a file header, before any stored WAV header is
+/// looked at.
+#[cfg(feature = "std")]
+fn stream_info(file_header: &ApeFileHeader) -> ApeInfo {
+    ApeInfo {
+        sample_rate: file_header.header.sample_rate,
+        channels: file_header.header.channels,
+        bits_per_sample: file_header.header.bits_per_sample,
+        total_samples: file_header.total_samples(),
+        compression_level: file_header.header.compression_level,
+        format_version: file_header.descriptor.version,
+        channel_mask: None,
+    }
+}
+
+/// Builder for an [`ApeReader`] with non-default decoding options.
+///
+/// ```no_run
+/// use ape_rs::{ApeReaderBuilder, FrameErrorMode};
+///
+/// let mut reader = ApeReaderBuilder::new()
+///     .frame_error_mode(FrameErrorMode::Silence)
+///     .open("damaged.ape")
+///     .unwrap();
+/// let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+/// for w in reader.warnings() {
+///     eprintln!("{w}");
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Default)]
+pub struct ApeReaderBuilder {
+    frame_error_mode: FrameErrorMode,
+    frame_error_handler: Option<FrameErrorHandler>,
+    progress_handler: Option<ProgressHandler>,
+    recover_frame_layout: bool,
+    exact_duration: bool,
+    seek_table: Option<SeekTable>,
+    frame_cache: usize,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for ApeReaderBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApeReaderBuilder")
+            .field("frame_error_mode", &self.frame_error_mode)
+            .field("frame_error_handler", &self.frame_error_handler.as_ref().map(|_| ".."))
+            .field("progress_handler", &self.progress_handler.as_ref().map(|_| ".."))
+            .field("recover_frame_layout", &self.recover_frame_layout)
+            .field("exact_duration", &self.exact_duration)
+            .field("seek_table", &self.seek_table.as_ref().map(|t| t.offsets.len()))
+            .field("frame_cache", &self.frame_cache)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl ApeReaderBuilder {
+    /// Create a builder with default (strict) options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how corrupt frames are handled.
+    pub fn frame_error_mode(mut self, mode: FrameErrorMode) -> Self {
+        self.frame_error_mode = mode;
+        self
+    }
+
+    /// Shorthand for `frame_error_mode(FrameErrorMode::Silence)` when `true`,
+    /// `FrameErrorMode::Strict` when `false`.
+    pub fn lenient(self, lenient: bool) -> Self {
+        self.frame_error_mode(if lenient {
+            FrameErrorMode::Silence
+        } else {
+            FrameErrorMode::Strict
+        })
+    }
+
+    /// Decide per failed frame what to do, overriding `frame_error_mode`.
+    ///
+    /// The handler is called with the error (carrying its frame context) and
+    /// the attempt number, starting at 1 and increasing with each
+    /// [`FrameErrorAction::Retry`]. Unlike `frame_error_mode`, it also sees
+    /// fatal errors such as I/O failures, so a reader over a flaky source
+    /// can retry them.
+    ///
+    /// ```no_run
+    /// use ape_rs::{ApeReaderBuilder, FrameErrorAction};
+    ///
+    /// let reader = ApeReaderBuilder::new()
+    ///     .on_frame_error(|err, attempt| match attempt {
+    ///         1 if !err.is_recoverable() => FrameErrorAction::Retry,
+    ///         _ if err.is_recoverable() => FrameErrorAction::FillSilence,
+    ///         _ => FrameErrorAction::Abort,
+    ///     })
+    ///     .open("track.ape");
+    /// ```
+    pub fn on_frame_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ApeError, u32) -> FrameErrorAction + Send + Sync + 'static,
+    {
+        self.frame_error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Call `handler` after each frame is decoded, with the blocks done so
+    /// far and the stream total.
+    ///
+    /// It runs for every way of decoding: the sample iterator and the
+    /// whole-stream methods such as [`ApeReader::analyze`] and
+    /// [`ApeReader::pcm_digest`]. After a seek, progress continues from the
+    /// new position.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReaderBuilder;
+    ///
+    /// let mut reader = ApeReaderBuilder::new()
+    ///     .on_progress(|p| eprint!("\r{:5.1}%", p.fraction() * 100.0))
+    ///     .open("track.ape")
+    ///     .unwrap();
+    /// let analysis = reader.analyze().unwrap();
+    /// ```
+    pub fn on_progress<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Recover the frame layout from the data region when the header has no
+    /// frame count or the seek table is missing or truncated (typical of
+    /// aborted encodes), instead of rejecting the file.
+    ///
+    /// Recovery decodes the whole file once at open time to locate each
+    /// frame, so it is off by default. Well-formed files are unaffected.
+    pub fn recover_frame_layout(mut self, recover: bool) -> Self {
+        self.recover_frame_layout = recover;
+        self
+    }
+
+    /// Use a frame layout saved from [`ApeReader::seek_table`] instead of
+    /// the file's own seek table, skipping layout recovery.
+    ///
+    /// ```no_run
+    /// use ape_rs::{ApeReaderBuilder, SeekTable};
+    ///
+    /// // First open: scan the damaged file once and cache the result
+    /// let reader = ApeReaderBuilder::new().recover_frame_layout(true).open("aborted.ape").unwrap();
+    /// std::fs::write("aborted.ape.seek", reader.seek_table().to_bytes()).unwrap();
+    ///
+    /// // Later opens: no scan
+    /// let table = SeekTable::from_bytes(&std::fs::read("aborted.ape.seek").unwrap()).unwrap();
+    /// let reader = ApeReaderBuilder::new().seek_table(table).open("aborted.ape").unwrap();
+    /// ```
+    pub fn seek_table(mut self, table: SeekTable) -> Self {
+        self.seek_table = Some(table);
+        self
+    }
+
+    /// Determine the exact duration from the audio data instead of trusting
+    /// the header's frame count and final frame length.
+    ///
+    /// The final frame is decoded once at open time and the totals are
+    /// corrected (with a [`DecodeWarning::DurationCorrected`]) if they are
+    /// wrong, so [`ApeInfo::total_samples`] matches what decoding yields.
+    pub fn exact_duration(mut self, exact: bool) -> Self {
+        self.exact_duration = exact;
+        self
+    }
+
+    /// Keep the output of the `frames` most recently decoded frames, so
+    /// seeking back into one of them copies it instead of decoding it
+    /// again. See [`frame_cache`] for details; 0 (the default) turns the
+    /// cache off.
+    ///
+    /// Each cached frame holds up to `blocks_per_frame × channels` samples
+    /// (over 2 MiB for a stereo Extra High frame), and filling the cache
+    /// allocates, unlike steady-state decoding without one.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReaderBuilder;
+    ///
+    /// // A waveform view scrubbing around the playhead
+    /// let reader = ApeReaderBuilder::new().frame_cache(8).open("track.ape").unwrap();
+    /// ```
+    pub fn frame_cache(mut self, frames: usize) -> Self {
+        self.frame_cache = frames;
+        self
+    }
+
+    /// Open an APE file by path.
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<ApeReader<BufReader<File>>, ApeError> {
+        let file = File::open(path)?;
+        self.build(BufReader::new(file))
+    }
+
+    /// Create an ApeReader from any `Read + Seek` source.
+    pub fn build<R: Read + Seek>(self, mut reader: R) -> Result<ApeReader<R>, ApeError> {
+        let file_header = self.read_header(&mut reader)?;
+        self.build_with_header(reader, file_header)
+    }
+
+    /// Parse the header of `reader` as [`build`](Self::build) does: with
+    /// [`parse_header`]'s frame layout checks, unless the layout is to be
+    /// recovered or replaced, in which case building checks the result.
+    pub(crate) fn read_header<R: Read + Seek>(&self, reader: &mut R) -> Result<ApeFileHeader, ApeError> {
+        if self.recover_frame_layout || self.seek_table.is_some() {
+            header::parse_header_for_recovery(reader)
+        } else {
+            header::parse_header(reader)
+        }
+    }
+
+    /// Create an ApeReader from a header parsed (or built) elsewhere,
+    /// without reading it again from `reader`.
+    ///
+    /// Offsets in `file_header` are positions in `reader`. The stream
+    /// parameters are checked as [`parse_header`] would check them.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    /// use ape_rs::{ApeReaderBuilder, parse_header};
+    ///
+    /// let mut file = BufReader::new(File::open("track.ape").unwrap());
+    /// let header = parse_header(&mut file).unwrap();
+    /// println!("{} frames at offset {}", header.header.total_frames, header.data_offset);
+    /// let reader = ApeReaderBuilder::new().build_with_header(file, header).unwrap();
+    /// ```
+    pub fn build_with_header<R: Read + Seek>(
+        self,
+        reader: R,
+        mut file_header: ApeFileHeader,
+    ) -> Result<ApeReader<R>, ApeError> {
+        header::validate_file_header(&file_header)?;
+        if let Some(table) = &self.seek_table {
+            file_header.apply_seek_table(table)?;
+        }
+
+        let mut decoder = decode::Decoder::new(reader, file_header, self.frame_error_mode);
+        decoder.frame_error_handler = self.frame_error_handler;
+        decoder.progress_handler = self.progress_handler;
+        decoder.frame_cache = (self.frame_cache > 0).then(|| frame_cache::FrameCache::new(self.frame_cache));
+        let mut reader = ApeReader {
+            info: stream_info(&decoder.header),
+            decoder,
+            recover_frame_layout: self.recover_frame_layout,
+            exact_duration: self.exact_duration,
+        };
+        reader.open_stream()?;
+        Ok(reader)
+    }
+
+    /// Build an [`ApeReader`] over a bare stream of frames with no `MAC `
+    /// header, using stream parameters supplied by the caller (e.g. from a
+    /// container's codec private data).
+    ///
+    /// The stream must start with the first frame. Frame boundaries are
+    /// found by scanning, as with [`recover_frame_layout`](Self::recover_frame_layout),
+    /// unless a [`seek_table`](Self::seek_table) is given. Leave
+    /// `header.total_frames` zero if the frame count is unknown.
+    ///
+    /// ```no_run
+    /// use ape_rs::{ApeHeader, ApeReaderBuilder};
+    ///
+    /// let header = ApeHeader {
+    ///     compression_level: 2000,
+    ///     format_flags: 0,
+    ///     blocks_per_frame: 73728,
+    ///     final_frame_blocks: 0,
+    ///     total_frames: 0,
+    ///     bits_per_sample: 16,
+    ///     channels: 2,
+    ///     sample_rate: 44100,
+    /// };
+    /// let frames = std::io::Cursor::new(std::fs::read("track.frames").unwrap());
+    /// let reader = ApeReaderBuilder::new().build_raw(frames, 3990, header).unwrap();
+    /// ```
+    pub fn build_raw<R: Read + Seek>(
+        mut self,
+        mut reader: R,
+        version: u16,
+        header: ApeHeader,
+    ) -> Result<ApeReader<R>, ApeError> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        self.recover_frame_layout = true;
+        self.build_with_header(reader, ApeFileHeader::raw(version, header, stream_len))
+    }
+}
+
+#[cfg(feature = "std")]
 impl ApeReader<BufReader<File>> {
     /// Open an APE file by path.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ApeError> {
@@ -69,28 +720,131 @@ impl ApeReader<BufReader<File>> {
         let reader = BufReader::new(file);
         Self::new(reader)
     }
+
+    /// Open an APE file by path, memory-mapped rather than read through a
+    /// buffer: the fastest way to decode a local file (feature `mmap`).
+    ///
+    /// The file must not be modified or truncated while the reader is
+    /// alive. The decoder checks every frame's CRC, but a file shrinking
+    /// under the map can still fault the process.
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<ApeReader<std::io::Cursor<memmap2::Mmap>>, ApeError> {
+        ApeReader::new(std::io::Cursor::new(shared::map_file(path.as_ref())?))
+    }
+
+    /// Open a Monkey's Audio link file (`.apl`) and the image file it
+    /// names, limited to the linked region.
+    pub fn open_apl<P: AsRef<Path>>(path: P) -> Result<apl::ApeLinkReader<BufReader<File>>, ApeError> {
+        apl::ApeLinkReader::open(path)
+    }
 }
 
+#[cfg(feature = "std")]
+impl BoxedApeReader {
+    /// Create a reader over a boxed source, with default options.
+    ///
+    /// Readers over files, memory and network adapters then all have the
+    /// one type [`BoxedApeReader`] and can share a collection. Pass a boxed
+    /// source to [`ApeReaderBuilder::build`] to set options as well.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::Cursor;
+    /// use ape_rs::{ApeReader, BoxedApeReader};
+    ///
+    /// let readers: Vec<BoxedApeReader> = vec![
+    ///     ApeReader::from_boxed(Box::new(File::open("a.ape").unwrap())).unwrap(),
+    ///     ApeReader::from_boxed(Box::new(Cursor::new(std::fs::read("b.ape").unwrap()))).unwrap(),
+    /// ];
+    /// ```
+    pub fn from_boxed(source: Box<dyn ReadSeek + Send>) -> Result<Self, ApeError> {
+        Self::new(source)
+    }
+}
+
+#[cfg(feature = "std")]
 impl<R: Read + Seek> ApeReader<R> {
     /// Create a new ApeReader from any `Read + Seek` source.
     ///
     /// Parses the APE header immediately. After construction, call `info()`
     /// for metadata and `samples()` for audio.
-    pub fn new(mut reader: R) -> Result<Self, ApeError> {
-        let file_header = header::parse_header(&mut reader)?;
-
-        let info = ApeInfo {
-            sample_rate: file_header.header.sample_rate,
-            channels: file_header.header.channels,
-            bits_per_sample: file_header.header.bits_per_sample,
-            total_samples: file_header.total_samples(),
-            compression_level: file_header.header.compression_level,
-            format_version: file_header.descriptor.version,
-        };
+    pub fn new(reader: R) -> Result<Self, ApeError> {
+        ApeReaderBuilder::new().build(reader)
+    }
 
-        let decoder = decode::Decoder::new(reader, file_header);
+    /// Create an ApeReader from a header returned by [`parse_header`],
+    /// with default options.
+    ///
+    /// Parsing first lets a caller look at the stream (format, length,
+    /// frame layout) and reject it before the decoder allocates its filter
+    /// and predictor state. Use [`ApeReaderBuilder::build_with_header`] to
+    /// set options as well.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    /// use ape_rs::{ApeReader, parse_header};
+    ///
+    /// let mut file = BufReader::new(File::open("track.ape").unwrap());
+    /// let header = parse_header(&mut file).unwrap();
+    /// if header.header.compression_level <= 4000 {
+    ///     let reader = ApeReader::with_header(file, header).unwrap();
+    /// }
+    /// ```
+    pub fn with_header(reader: R, header: ApeFileHeader) -> Result<Self, ApeError> {
+        ApeReaderBuilder::new().build_with_header(reader, header)
+    }
+
+    /// Switch to another file, keeping this reader's options and its
+    /// decoder allocations: with the same channel count and compression
+    /// level, the NNFilter buffers (large at Extra High and Insane) and
+    /// the output buffer are reused rather than allocated again. For batch
+    /// tools that decode thousands of files.
+    ///
+    /// `header` is the new file's, as from [`parse_header`]. Builder
+    /// options apply as when the reader was built, except a
+    /// [`seek_table`](ApeReaderBuilder::seek_table), which belonged to the
+    /// previous file. Warnings start over. If this fails the reader is
+    /// only good for another `reset_for`.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    /// use ape_rs::{ApeReader, parse_header};
+    ///
+    /// let mut reader = ApeReader::open("first.ape").unwrap();
+    /// for path in ["second.ape", "third.ape"] {
+    ///     let mut file = BufReader::new(File::open(path).unwrap());
+    ///     let header = parse_header(&mut file).unwrap();
+    ///     reader.reset_for(header, file).unwrap();
+    ///     let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    /// }
+    /// ```
+    pub fn reset_for(&mut self, header: ApeFileHeader, reader: R) -> Result<(), ApeError> {
+        header::validate_file_header(&header)?;
+        self.decoder.reset_for(reader, header);
+        self.open_stream()
+    }
+
+    /// Settle the frame layout and stream info of a freshly set up decoder.
+    fn open_stream(&mut self) -> Result<(), ApeError> {
+        let decoder = &mut self.decoder;
+        if self.recover_frame_layout && decoder.header.needs_layout_recovery() {
+            decoder.rebuild_frame_layout()?;
+        }
+        if self.exact_duration {
+            decoder.resolve_exact_duration()?;
+        }
+        header::validate_frame_layout(&decoder.header)?;
+        if let Some((offset, len)) = decoder.header.unaccounted_tail() {
+            diag!(warn, offset = offset, len = len; "unaccounted data after the frames");
+            decoder.warnings.push(DecodeWarning::TrailingData { offset, len });
+        }
 
-        Ok(ApeReader { decoder, info })
+        self.info = stream_info(&decoder.header);
+        let header_data = self.header_data()?;
+        self.info.channel_mask = riff::wave_format(&header_data).and_then(|f| f.channel_mask);
+        Ok(())
     }
 
     /// Get metadata about the audio stream.
@@ -98,46 +852,1260 @@ impl<R: Read + Seek> ApeReader<R> {
         &self.info
     }
 
+    /// Sample rate in Hz, as in [`ApeInfo::sample_rate`].
+    pub fn sample_rate(&self) -> u32 {
+        self.info.sample_rate
+    }
+
+    /// Number of channels, as in [`ApeInfo::channels`].
+    pub fn channels(&self) -> u16 {
+        self.info.channels
+    }
+
+    /// Bits per sample, as in [`ApeInfo::bits_per_sample`].
+    pub fn bits_per_sample(&self) -> u16 {
+        self.info.bits_per_sample
+    }
+
+    /// Total interleaved samples in the stream, as in
+    /// [`ApeInfo::total_samples`].
+    pub fn total_samples(&self) -> u64 {
+        self.info.total_samples
+    }
+
+    /// Compression level (1000–5000), as in
+    /// [`ApeInfo::compression_level`].
+    pub fn compression_level(&self) -> u16 {
+        self.info.compression_level
+    }
+
+    /// The parsed file header, including any corrections made at open time
+    /// (a rebuilt seek table, an exact duration).
+    pub fn header(&self) -> &ApeFileHeader {
+        &self.decoder.header
+    }
+
+    /// Record the current position, to resume from later with
+    /// [`restore`](Self::restore) — possibly on a new reader for the same
+    /// file after a restart.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    /// use ape_rs::snapshot::DecoderSnapshot;
+    ///
+    /// let mut reader = ApeReader::open("long.ape").unwrap();
+    /// let _first: Vec<i32> = reader.samples().take(1_000_000).collect::<Result<_, _>>().unwrap();
+    /// std::fs::write("job.checkpoint", reader.snapshot().to_bytes()).unwrap();
+    ///
+    /// // Later, in another process
+    /// let mut reader = ApeReader::open("long.ape").unwrap();
+    /// let snapshot = DecoderSnapshot::from_bytes(&std::fs::read("job.checkpoint").unwrap()).unwrap();
+    /// reader.restore(&snapshot).unwrap();
+    /// ```
+    pub fn snapshot(&self) -> snapshot::DecoderSnapshot {
+        let (frame, sample_in_frame, resync_shift) = self.decoder.position();
+        snapshot::DecoderSnapshot {
+            frame,
+            sample_in_frame: sample_in_frame as u32,
+            resync_shift,
+            total_blocks: self.decoder.header.total_blocks(),
+        }
+    }
+
+    /// Continue decoding from a position taken by
+    /// [`snapshot`](Self::snapshot), re-decoding the snapshot's frame.
+    ///
+    /// Fails with [`ErrorKind::InvalidSnapshot`] if the snapshot does not
+    /// fit this stream, or with the frame's decode error (subject to the
+    /// frame error mode) if it cannot be decoded.
+    pub fn restore(&mut self, snapshot: &snapshot::DecoderSnapshot) -> Result<(), ApeError> {
+        let header = &self.decoder.header;
+        let frame_samples = header.frame_blocks(snapshot.frame as usize) as u64 * header.header.channels as u64;
+        if snapshot.total_blocks != header.total_blocks() {
+            return Err(ApeError::InvalidSnapshot(format!(
+                "snapshot is for a stream of {} blocks, this one has {}",
+                snapshot.total_blocks,
+                header.total_blocks()
+            )));
+        }
+        let in_stream = match snapshot.frame.cmp(&header.header.total_frames) {
+            std::cmp::Ordering::Less => (snapshot.sample_in_frame as u64) < frame_samples,
+            std::cmp::Ordering::Equal => snapshot.sample_in_frame == 0,
+            std::cmp::Ordering::Greater => false,
+        };
+        if !in_stream {
+            return Err(ApeError::InvalidSnapshot(format!(
+                "frame {} sample {} is outside the stream",
+                snapshot.frame, snapshot.sample_in_frame
+            )));
+        }
+        self.decoder
+            .restore_position(snapshot.frame, snapshot.sample_in_frame as usize, snapshot.resync_shift)
+    }
+
+    /// Go back to the start of the stream, so that [`samples`](Self::samples)
+    /// and the other decoding methods make another pass over the file.
+    ///
+    /// Decoding starts over as on a newly opened reader. Warnings
+    /// collected so far are kept, and a second pass adds its own; the
+    /// frame cache is kept too.
+    pub fn rewind(&mut self) -> Result<(), ApeError> {
+        self.decoder.restore_position(0, 0, 0)
+    }
+
+    /// Continue from the block at `time`, rounded to the nearest block,
+    /// and return the index of the next sample in the stream.
+    ///
+    /// The index is the block times the channel count, so decoding resumes
+    /// with the first channel of a block. A time past the end lands at the
+    /// end of the stream. The frame holding the block is decoded at once:
+    /// this fails with its decode error, subject to the frame error mode.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let sample = reader.seek_to_time(Duration::from_secs(90)).unwrap();
+    /// println!("resuming at block {}", sample / reader.channels() as u64);
+    /// ```
+    pub fn seek_to_time(&mut self, time: Duration) -> Result<u64, ApeError> {
+        let rate = self.info.sample_rate as u128;
+        let block = (time.as_nanos() * rate + 500_000_000) / 1_000_000_000;
+        let block = u64::try_from(block).unwrap_or(u64::MAX).min(self.decoder.header.total_blocks());
+        self.seek_to_block(block)?;
+        Ok(block * self.info.channels as u64)
+    }
+
+    /// Stream index (interleaved, from 0) of the next sample
+    /// [`samples`](Self::samples) and the other decoding methods will
+    /// return: the number of samples played so far, counting from the start
+    /// of the stream. Like [`timed_samples`](Self::timed_samples), it
+    /// follows seeks and jumps past frames the [`FrameErrorMode::Skip`]
+    /// mode drops; at the end of the stream it is
+    /// [`total_samples`](Self::total_samples).
+    pub fn sample_position(&self) -> u64 {
+        self.decoder.sample_index().min(self.decoder.header.total_samples())
+    }
+
+    /// Playback time of the next sample's block from the start of the
+    /// stream, as by [`sample_position`](Self::sample_position).
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let mut buf = vec![0i32; 4096];
+    /// while reader.read_samples(&mut buf).unwrap() > 0 {
+    ///     println!("{:.1}s", reader.time_position().as_secs_f64());
+    /// }
+    /// ```
+    pub fn time_position(&self) -> Duration {
+        let block = self.sample_position() / self.info.channels.max(1) as u64;
+        block_time(block, self.info.sample_rate)
+    }
+
+    /// Reader over track `number` of `cue`, a cue sheet for this stream,
+    /// positioned at the track's first sample.
+    ///
+    /// Fails with [`ErrorKind::InvalidCueSheet`] if the sheet has no such
+    /// track or the track starts past the end of the stream.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    /// use ape_rs::cue::CueSheet;
+    ///
+    /// let cue = CueSheet::parse(&std::fs::read_to_string("album.cue").unwrap()).unwrap();
+    /// let mut reader = ApeReader::open("album.ape").unwrap();
+    /// let mut track = reader.track(&cue, 3).unwrap();
+    /// println!("{:?}: {} samples", track.track().title, track.info().total_samples);
+    /// let samples: Vec<i32> = track.samples().collect::<Result<_, _>>().unwrap();
+    /// ```
+    pub fn track(&mut self, cue: &cue::CueSheet, number: u8) -> Result<cue::ApeTrackReader<'_, R>, ApeError> {
+        let info = &self.info;
+        let total_blocks = self.decoder.header.total_blocks();
+        let (Some(entry), Some((start, end))) =
+            (cue.track(number), cue.track_blocks(number, info.sample_rate, total_blocks))
+        else {
+            return Err(ApeError::InvalidCueSheet(format!("no track {number}")));
+        };
+        if start >= total_blocks {
+            return Err(ApeError::InvalidCueSheet(format!(
+                "track {number} starts at block {start}, the stream has {total_blocks}"
+            )));
+        }
+        let track_info = ApeInfo {
+            total_samples: (end - start) * info.channels as u64,
+            ..info.clone()
+        };
+        self.seek_to_block(start)?;
+        Ok(cue::ApeTrackReader {
+            remaining: track_info.total_samples,
+            reader: self,
+            track: entry.clone(),
+            blocks: (start, end),
+            info: track_info,
+        })
+    }
+
+    /// Continue from block `block` (at most the end of the stream).
+    pub(crate) fn seek_to_block(&mut self, block: u64) -> Result<(), ApeError> {
+        let h = &self.decoder.header;
+        let block = block.min(h.total_blocks());
+        let blocks_per_frame = h.header.blocks_per_frame as u64;
+        let frame = (block / blocks_per_frame) as u32;
+        let samples = (block % blocks_per_frame) as usize * h.header.channels as usize;
+        let (_, _, resync_shift) = self.decoder.position();
+        self.decoder.restore_position(frame, samples, resync_shift)
+    }
+
+    /// The stream's frame layout, for caching and reuse with
+    /// [`ApeReaderBuilder::seek_table`].
+    pub fn seek_table(&self) -> SeekTable {
+        self.decoder.header.seek_table()
+    }
+
+    /// Heap bytes the reader's decoder holds, by purpose. See
+    /// [`memory::MemoryUsage::estimate`] to size a decode before opening a
+    /// reader.
+    pub fn memory_footprint(&self) -> memory::MemoryUsage {
+        self.decoder.memory_usage()
+    }
+
+    /// Change the number of decoded frames kept for reuse, as with
+    /// [`ApeReaderBuilder::frame_cache`]. Cached frames are dropped; 0
+    /// turns the cache off.
+    pub fn set_frame_cache(&mut self, frames: usize) {
+        self.decoder.frame_cache = (frames > 0).then(|| frame_cache::FrameCache::new(frames));
+    }
+
+    /// Hits and misses of the frame cache, or `None` if it is off.
+    pub fn frame_cache_stats(&self) -> Option<frame_cache::FrameCacheStats> {
+        self.decoder.frame_cache.as_ref().map(frame_cache::FrameCache::stats)
+    }
+
+    /// Number of compressed frames in the stream.
+    pub fn frame_count(&self) -> u32 {
+        self.decoder.header.header.total_frames
+    }
+
+    /// Blocks in every frame but the last, from the header.
+    pub fn blocks_per_frame(&self) -> u32 {
+        self.decoder.header.header.blocks_per_frame
+    }
+
+    /// The frame the next sample comes from: [`frame_count`](Self::frame_count)
+    /// at the end of the stream.
+    pub fn current_frame(&self) -> u32 {
+        self.decoder.position().0
+    }
+
+    /// Continue from the first sample of frame `index`, the format's
+    /// natural seek granularity: nothing is decoded until the next sample
+    /// is asked for. An index past the last frame lands at the end of the
+    /// stream.
+    pub fn seek_to_frame(&mut self, index: u32) -> Result<(), ApeError> {
+        let index = index.min(self.frame_count());
+        let (_, _, resync_shift) = self.decoder.position();
+        self.decoder.restore_position(index, 0, resync_shift)
+    }
+
+    /// Offset, size and block range of frame `index`, or `None` past the
+    /// last frame or the end of the seek table.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let reader = ApeReader::open("track.ape").unwrap();
+    /// let compressed: u64 = reader.frames().map(|f| f.size).sum();
+    /// println!("{} frames, {compressed} bytes of audio", reader.frame_count());
+    /// ```
+    pub fn frame_info(&self, index: u32) -> Option<FrameInfo> {
+        let header = &self.decoder.header;
+        let frame = index as usize;
+        if index >= header.header.total_frames {
+            return None;
+        }
+        Some(FrameInfo {
+            index,
+            offset: header.frame_position(frame)?,
+            size: header.frame_size(frame)?,
+            skip: header.frame_skip(frame),
+            blocks: header.frame_blocks(frame),
+            first_block: index as u64 * header.header.blocks_per_frame as u64,
+        })
+    }
+
+    /// The frame holding block `block`: the byte range to fetch before
+    /// that block can be decoded. `None` past the end of the stream.
+    ///
+    /// [`ApeFileHeader::block_byte_offset`] gives a finer, approximate
+    /// position within the frame, and [`ApeFileHeader::decodable_blocks`]
+    /// the reverse mapping, through [`header`](Self::header).
+    pub fn frame_at_block(&self, block: u64) -> Option<FrameInfo> {
+        let blocks_per_frame = self.decoder.header.header.blocks_per_frame as u64;
+        let index = u32::try_from(block / blocks_per_frame.max(1)).ok()?;
+        self.frame_info(index)
+    }
+
+    /// Read frame `index`'s compressed bytes without decoding them, or
+    /// `None` if there is no such frame.
+    ///
+    /// This reads through the underlying reader but does not disturb
+    /// decoding, which seeks before every frame anyway.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    /// use ape_rs::packet::PacketDecoder;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let info = reader.info().clone();
+    /// let frame = reader.read_raw_frame(3).unwrap().unwrap();
+    /// let mut decoder = PacketDecoder::new(info.channels, info.bits_per_sample, info.compression_level).unwrap();
+    /// let pcm = decoder.decode_packet(&frame.data, frame.info.skip, frame.info.blocks).unwrap();
+    /// ```
+    pub fn read_raw_frame(&mut self, index: u32) -> Result<Option<RawFrame>, ApeError> {
+        let Some(info) = self.frame_info(index) else {
+            return Ok(None);
+        };
+        // Whole words, counted from frame 0, so the data can be byte-swapped
+        let header = &self.decoder.header;
+        let first = header.frame_position(0).unwrap_or(info.offset);
+        let start = info.offset - info.skip as u64;
+        let end = (first + (info.offset + info.size - first).next_multiple_of(4)).min(header.tag_offset);
+
+        let reader = &mut self.decoder.reader;
+        reader.seek(SeekFrom::Start(start))?;
+        let mut data = Vec::new();
+        reader.take(end.saturating_sub(start)).read_to_end(&mut data)?;
+        if (data.len() as u64) < info.skip as u64 + info.size {
+            return Err(ApeError::UnexpectedEof);
+        }
+        Ok(Some(RawFrame { info, data }))
+    }
+
+    /// The terminating data: bytes that followed the audio in the original
+    /// WAV file, such as trailing RIFF chunks, or an empty `Vec` if the
+    /// file has none. See [`ApeFileHeader::terminating_data_range`].
+    ///
+    /// Like [`read_raw_frame`](Self::read_raw_frame), this does not disturb
+    /// decoding.
+    pub fn terminating_data(&mut self) -> Result<Vec<u8>, ApeError> {
+        match self.decoder.header.terminating_data_range() {
+            Some((offset, len)) => self.read_range(offset, len),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Read `len` bytes at file offset `offset`.
+    fn read_range(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, ApeError> {
+        let reader = &mut self.decoder.reader;
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut data = Vec::new();
+        reader.take(len).read_to_end(&mut data)?;
+        if (data.len() as u64) < len {
+            return Err(ApeError::UnexpectedEof);
+        }
+        Ok(data)
+    }
+
+    /// [`frame_info`](Self::frame_info) for every frame, in order.
+    pub fn frames(&self) -> impl Iterator<Item = FrameInfo> + '_ {
+        (0..self.frame_count()).map_while(|index| self.frame_info(index))
+    }
+
+    /// Decode the rest of the stream a frame at a time, each with its index,
+    /// position and integrity: the unit for verification UIs, or for
+    /// handing work to other threads.
+    ///
+    /// A frame that fails is reported per the frame error mode: in
+    /// `Silence` and `Skip` mode as a [`Frame`] with `crc_ok` false, in
+    /// `Strict` mode as an error, after which iteration goes on with the
+    /// next frame. Starting mid-frame, the first item holds the rest of
+    /// that frame.
+    ///
+    /// ```no_run
+    /// use ape_rs::{ApeReaderBuilder, FrameErrorMode};
+    ///
+    /// let mut reader = ApeReaderBuilder::new().frame_error_mode(FrameErrorMode::Silence).open("track.ape").unwrap();
+    /// for frame in reader.decoded_frames() {
+    ///     let frame = frame.unwrap();
+    ///     println!("frame {}: {}", frame.index, if frame.crc_ok { "ok" } else { "damaged" });
+    /// }
+    /// ```
+    pub fn decoded_frames(&mut self) -> DecodedFrames<'_, R> {
+        DecodedFrames {
+            decoder: &mut self.decoder,
+        }
+    }
+
+    /// Non-fatal problems encountered so far: frames silenced or skipped in
+    /// lenient mode, frames found away from their seek-table position, and
+    /// unexpected bytes between the audio data and the trailing tags.
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.decoder.warnings
+    }
+
+    /// Decode the rest of the stream in one pass and report per-channel
+    /// peak, RMS and DC offset.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let analysis = reader.analyze().unwrap();
+    /// for (ch, levels) in analysis.channels.iter().enumerate() {
+    ///     println!("ch{ch}: peak {:.1} dBFS, RMS {:.1} dBFS", levels.peak_dbfs(), levels.rms_dbfs());
+    /// }
+    /// ```
+    pub fn analyze(&mut self) -> Result<analysis::Analysis, ApeError> {
+        let mut analyzer = analysis::Analyzer::new(self.info.channels, self.info.bits_per_sample);
+        self.for_each_chunk(|samples| analyzer.update(samples))?;
+        Ok(analyzer.finish())
+    }
+
+    /// Decode the rest of the stream and measure its EBU R128 integrated
+    /// loudness, loudness range and true peak (feature `loudness`).
+    #[cfg(feature = "loudness")]
+    pub fn loudness(&mut self) -> Result<loudness::Loudness, ApeError> {
+        let info = &self.info;
+        let mut meter = loudness::LoudnessMeter::new(info.channels, info.bits_per_sample, info.sample_rate);
+        self.for_each_chunk(|samples| meter.update(samples))?;
+        Ok(meter.finish())
+    }
+
+    /// Decode the rest of the stream once and build waveform peaks at
+    /// several zoom levels, one per entry of `bucket_sizes` (in blocks).
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let waveform = reader.waveform(&[256, 4096, 65536], false).unwrap();
+    /// let overview = &waveform.levels[2].channels[0];
+    /// println!("{} buckets", overview.len());
+    /// ```
+    pub fn waveform(&mut self, bucket_sizes: &[u64], with_rms: bool) -> Result<waveform::Waveform, ApeError> {
+        let mut generator =
+            waveform::WaveformGenerator::new(self.info.channels, self.info.bits_per_sample, bucket_sizes, with_rms);
+        self.for_each_chunk(|samples| generator.update(samples))?;
+        Ok(generator.finish())
+    }
+
+    /// Decode the rest of the stream and return the ranges where every
+    /// channel stays at or below `threshold_db` dBFS for at least
+    /// `min_duration`.
+    ///
+    /// Ranges are in blocks (samples per channel), end-exclusive, counted
+    /// from the current position — the start of the stream for a fresh
+    /// reader. Divide by `sample_rate` for seconds.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("side_a.ape").unwrap();
+    /// let rate = reader.info().sample_rate as f64;
+    /// for gap in reader.detect_silence(-60.0, Duration::from_secs(2)).unwrap() {
+    ///     println!("silence {:.2}s - {:.2}s", gap.start as f64 / rate, gap.end as f64 / rate);
+    /// }
+    /// ```
+    pub fn detect_silence(&mut self, threshold_db: f64, min_duration: Duration) -> Result<Vec<Range<u64>>, ApeError> {
+        let info = &self.info;
+        let mut detector = silence::SilenceDetector::new(
+            info.channels,
+            info.bits_per_sample,
+            info.sample_rate,
+            threshold_db,
+            min_duration,
+        );
+        self.for_each_chunk(|samples| detector.update(samples))?;
+        Ok(detector.finish())
+    }
+
+    /// Decode the rest of the stream and report runs of at least `min_run`
+    /// consecutive full-scale samples, plus per-channel true peaks if
+    /// `inter_sample` is set.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("transfer.ape").unwrap();
+    /// let report = reader.detect_clipping(3, true).unwrap();
+    /// if report.is_clipped() {
+    ///     println!("{} clipped runs", report.runs.len());
+    /// }
+    /// ```
+    pub fn detect_clipping(&mut self, min_run: u32, inter_sample: bool) -> Result<clipping::ClippingReport, ApeError> {
+        let mut detector =
+            clipping::ClippingDetector::new(self.info.channels, self.info.bits_per_sample, min_run, inter_sample);
+        self.for_each_chunk(|samples| detector.update(samples))?;
+        Ok(detector.finish())
+    }
+
+    /// Decode the rest of the stream and hash the PCM in the given layout,
+    /// like FLAC's MD5 signature.
+    ///
+    /// With [`PcmLayout::Wav`](digest::PcmLayout::Wav) the digest equals
+    /// `md5sum` / `sha256sum` of the decoded WAV file's `data` chunk.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    /// use ape_rs::digest::{HashAlgorithm, PcmLayout};
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let md5 = reader.pcm_digest(HashAlgorithm::Md5, PcmLayout::Flac).unwrap();
+    /// println!("{md5}");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self), err))]
+    pub fn pcm_digest(
+        &mut self,
+        algorithm: digest::HashAlgorithm,
+        layout: digest::PcmLayout,
+    ) -> Result<digest::PcmDigest, ApeError> {
+        let mut hasher = digest::PcmHasher::new(algorithm, self.info.bits_per_sample, layout);
+        self.for_each_chunk(|samples| hasher.update(samples))?;
+        Ok(hasher.finish())
+    }
+
+    /// Decode up to `max_duration` of audio from the current position and
+    /// compute its Chromaprint fingerprint (feature `chromaprint`).
+    ///
+    /// AcoustID fingerprints the first two minutes (`fpcalc`'s default).
+    /// Decoding stops once `max_duration` is covered, so the reader is left
+    /// somewhere past that point.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let fingerprint = reader.fingerprint(Duration::from_secs(120)).unwrap();
+    /// println!("FINGERPRINT={}", fingerprint.encode());
+    /// ```
+    #[cfg(feature = "chromaprint")]
+    pub fn fingerprint(&mut self, max_duration: Duration) -> Result<chromaprint::Fingerprint, ApeError> {
+        let info = &self.info;
+        let mut fingerprinter =
+            chromaprint::Fingerprinter::new(info.channels, info.bits_per_sample, info.sample_rate);
+        let mut remaining =
+            (max_duration.as_secs_f64() * info.sample_rate as f64) as u64 * info.channels.max(1) as u64;
+        self.for_each_chunk_while(|samples| {
+            let take = samples.len().min(remaining as usize);
+            fingerprinter.update(&samples[..take]);
+            remaining -= take as u64;
+            remaining > 0
+        })?;
+        Ok(fingerprinter.finish())
+    }
+
+    /// Decode the rest of the stream into spectrogram frames of
+    /// `window_size` blocks (a power of two), one every `hop` blocks, with
+    /// channels averaged to mono (feature `spectral`).
+    ///
+    /// Every frame is kept in memory: a 5-minute 44.1 kHz track with a
+    /// 4096-point window and a hop of 2048 is about 6,500 frames of 2,049
+    /// levels. Raise `hop` for long files.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    /// use ape_rs::spectral::WindowFunction;
+    ///
+    /// let mut reader = ApeReader::open("rip.ape").unwrap();
+    /// let spectrogram = reader.spectrogram(4096, 4096, WindowFunction::Hann).unwrap();
+    /// if let Some(cutoff) = spectrogram.cutoff_frequency(-90.0) {
+    ///     println!("content up to {:.1} kHz", cutoff / 1000.0);
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `window_size` is not a power of two.
+    #[cfg(feature = "spectral")]
+    pub fn spectrogram(
+        &mut self,
+        window_size: usize,
+        hop: usize,
+        window: spectral::WindowFunction,
+    ) -> Result<spectral::Spectrogram, ApeError> {
+        let info = &self.info;
+        let mut analyzer = spectral::SpectrumAnalyzer::new(
+            info.channels,
+            info.bits_per_sample,
+            info.sample_rate,
+            window_size,
+            hop,
+            window,
+        );
+        self.for_each_chunk(|samples| analyzer.update(samples))?;
+        Ok(analyzer.finish())
+    }
+
+    /// Feed the rest of the stream to `f` a frame's worth of samples at a
+    /// time, without going through the per-sample iterator.
+    fn for_each_chunk(&mut self, mut f: impl FnMut(&[i32])) -> Result<(), ApeError> {
+        self.for_each_chunk_while(|samples| {
+            f(samples);
+            true
+        })
+    }
+
+    /// Like `for_each_chunk`, but stops after a chunk for which `f` returns
+    /// false.
+    fn for_each_chunk_while(&mut self, mut f: impl FnMut(&[i32]) -> bool) -> Result<(), ApeError> {
+        loop {
+            let chunk = self.decoder.buffer.take_remaining();
+            if !chunk.is_empty() && !f(chunk) {
+                return Ok(());
+            }
+            if self.decoder.finished || !self.decoder.decode_next_frame()? {
+                return Ok(());
+            }
+        }
+    }
+
     /// Returns an iterator that yields decoded PCM samples as `Result<i32>`.
     ///
     /// Samples are interleaved for stereo files:
     /// `[L0, R0, L1, R1, ...]`
     ///
     /// Values are native i32 — the consumer should normalize using
-    /// `bits_per_sample` (e.g. divide by 32768 for 16-bit to get f32).
+    /// `bits_per_sample` (e.g. with [`convert::to_f32`], or use
+    /// [`samples_f32`](Self::samples_f32)).
     pub fn samples(&mut self) -> ApeSamples<'_, R> {
         ApeSamples {
             decoder: &mut self.decoder,
         }
     }
+
+    /// Like [`samples`](Self::samples), but one sample per block: stereo
+    /// is downmixed to mono as `(L + R) / 2`, which cannot clip (see
+    /// [`mix`]). Mono streams are unchanged.
+    pub fn mono_samples(&mut self) -> mix::Downmix<ApeSamples<'_, R>> {
+        let channels = self.info.channels;
+        mix::Downmix::new(self.samples(), channels)
+    }
+
+    /// Like [`samples`](Self::samples), but only channel `channel`
+    /// (0-based, left first), one sample per block. Fails with
+    /// [`ErrorKind::FormatMismatch`] if the stream has no such channel.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let right: Vec<i32> = reader.samples_for_channel(1).unwrap().collect::<Result<_, _>>().unwrap();
+    /// ```
+    pub fn samples_for_channel(&mut self, channel: u16) -> Result<mix::Channel<ApeSamples<'_, R>>, ApeError> {
+        let channels = self.info.channels;
+        if channel >= channels {
+            return Err(ApeError::FormatMismatch(format!(
+                "no channel {channel} in a {channels}-channel stream"
+            )));
+        }
+        Ok(mix::Channel::new(self.samples(), channels, channel))
+    }
+
+    /// Returns an iterator over the rest of the stream in chunks of exactly
+    /// `n` interleaved samples, the last one possibly shorter. Chunks cross
+    /// frame boundaries, so `n` need not divide the frame size.
+    ///
+    /// Iterating yields owned `Vec`s; [`SampleChunks::next_chunk`] lends
+    /// each chunk instead, without allocating per chunk, for audio
+    /// callbacks and encoders that consume fixed-size buffers.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is 0.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let mut chunks = reader.samples_chunks(4096);
+    /// while let Some(chunk) = chunks.next_chunk() {
+    ///     let chunk: &[i32] = chunk.unwrap();
+    ///     // process 4096 samples
+    /// }
+    /// ```
+    pub fn samples_chunks(&mut self, n: usize) -> SampleChunks<'_, R> {
+        assert!(n > 0, "chunk size must be non-zero");
+        SampleChunks {
+            decoder: &mut self.decoder,
+            size: n,
+            chunk: Vec::new(),
+            lent: false,
+            start: 0,
+        }
+    }
+
+    /// Like [`samples`](Self::samples), with each sample's index in the
+    /// stream (interleaved, from 0). Indices come from the decoder's
+    /// position, not a count, so they stay right after a seek (e.g.
+    /// [`restore`](Self::restore)) and jump past frames the
+    /// [`FrameErrorMode::Skip`] mode drops.
+    pub fn timed_samples(&mut self) -> TimedSamples<'_, R> {
+        TimedSamples {
+            decoder: &mut self.decoder,
+        }
+    }
+
+    /// Like [`samples_chunks`](Self::samples_chunks), with the time of
+    /// each chunk's first sample from the start of the stream, kept right
+    /// across seeks and dropped frames as by
+    /// [`timed_samples`](Self::timed_samples). A chunk spanning a dropped
+    /// frame is not split.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// for chunk in reader.timed_chunks(4410) {
+    ///     let (time, samples) = chunk.unwrap();
+    ///     println!("{:.1}s: {} samples", time.as_secs_f64(), samples.len());
+    /// }
+    /// ```
+    pub fn timed_chunks(&mut self, n: usize) -> TimedChunks<'_, R> {
+        let (channels, sample_rate) = (self.info.channels, self.info.sample_rate);
+        TimedChunks {
+            chunks: self.samples_chunks(n),
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// Like [`samples`](Self::samples), normalized to `f32` in [-1.0, 1.0)
+    /// by [`convert::to_f32`].
+    pub fn samples_f32(&mut self) -> convert::SamplesF32<ApeSamples<'_, R>> {
+        let bits = self.info.bits_per_sample;
+        convert::SamplesF32::new(self.samples(), bits)
+    }
+
+    /// Like [`samples`](Self::samples), left-justified in 32 bits by
+    /// [`convert::left_justify`], for audio APIs that take any bit depth in
+    /// a 32-bit container aligned to the most significant bit.
+    pub fn samples_left_justified(&mut self) -> convert::LeftJustified<ApeSamples<'_, R>> {
+        let bits = self.info.bits_per_sample;
+        convert::LeftJustified::new(self.samples(), bits)
+    }
+
+    /// Like [`samples`](Self::samples), converted to 16 bits. Deeper
+    /// sources are requantized with TPDF dither rather than truncated;
+    /// 8-bit sources are shifted up and 16-bit ones pass through exactly.
+    pub fn samples_i16(&mut self) -> dither::SamplesI16<ApeSamples<'_, R>> {
+        self.samples_i16_with(dither::DitherOptions::default())
+    }
+
+    /// [`samples_i16`](Self::samples_i16) with a gain, noise shaping or
+    /// no dither, per `options`.
+    pub fn samples_i16_with(&mut self, options: dither::DitherOptions) -> dither::SamplesI16<ApeSamples<'_, R>> {
+        let (channels, bits) = (self.info.channels, self.info.bits_per_sample);
+        dither::SamplesI16::new(self.samples(), channels, bits, options)
+    }
+
+    /// Decode the rest of the stream into a [`DecodedAudio`], as [`load`]
+    /// does for a path. Frames are handled per the frame error mode.
+    pub fn read_all(&mut self) -> Result<DecodedAudio, ApeError> {
+        let mut samples = Vec::with_capacity(self.info.total_samples.min(1 << 28) as usize);
+        self.for_each_chunk(|chunk| samples.extend_from_slice(chunk))?;
+        let blocks = samples.len() as u64 / self.info.channels.max(1) as u64;
+        Ok(DecodedAudio {
+            info: self.info.clone(),
+            duration: block_time(blocks, self.info.sample_rate),
+            samples,
+        })
+    }
+
+    /// Drive the decode of the rest of the stream, handing `f` each
+    /// frame's samples (interleaved) as a borrowed chunk, until the stream
+    /// ends or `f` returns [`ControlFlow::Break`].
+    ///
+    /// For push-style consumers such as encoders, hashes or network
+    /// writers: no per-sample iterator and no copy out of the decoder.
+    /// Returns the `Break` value, or `Continue(())` at the end of the
+    /// stream. The chunk `f` breaks on counts as consumed; a frame partly
+    /// read through [`samples`](Self::samples) is handed over from where
+    /// reading stopped. Failed frames are handled per the frame error mode.
+    ///
+    /// ```no_run
+    /// use std::ops::ControlFlow;
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// // Find the first non-silent sample without decoding the rest
+    /// let mut index = 0;
+    /// let found = reader.decode_with(|chunk| match chunk.iter().position(|&s| s != 0) {
+    ///     Some(i) => ControlFlow::Break(index + i),
+    ///     None => {
+    ///         index += chunk.len();
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    /// ```
+    pub fn decode_with<B>(&mut self, mut f: impl FnMut(&[i32]) -> ControlFlow<B>) -> Result<ControlFlow<B>, ApeError> {
+        let mut result = ControlFlow::Continue(());
+        self.for_each_chunk_while(|samples| {
+            result = f(samples);
+            result.is_continue()
+        })?;
+        Ok(result)
+    }
+
+    /// Decode the next frame into `out`, replacing its contents but
+    /// keeping its allocation, so the caller decides when buffers are
+    /// allocated and freed (e.g. by cycling a few `Vec`s through a pool).
+    ///
+    /// The decoder writes straight into `out`; its own buffer is not used.
+    /// If part of a frame is still buffered from [`samples`](Self::samples),
+    /// `out` receives the rest of it instead. Frames dropped by the frame
+    /// error mode are passed over. Returns `false`, with `out` empty, at
+    /// the end of the stream.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let mut frame = Vec::with_capacity(73728 * 2);
+    /// while reader.decode_next_frame_into(&mut frame).unwrap() {
+    ///     // play `frame`; no allocation once it has grown to a full frame
+    /// }
+    /// ```
+    pub fn decode_next_frame_into(&mut self, out: &mut Vec<i32>) -> Result<bool, ApeError> {
+        out.clear();
+        let buffered = self.decoder.buffer.take_remaining();
+        if !buffered.is_empty() {
+            out.extend_from_slice(buffered);
+            return Ok(true);
+        }
+
+        let mut buffer = buffer::SampleBuffer::from_vec(core::mem::take(out));
+        core::mem::swap(&mut self.decoder.buffer, &mut buffer);
+        let result = loop {
+            match self.decoder.decode_next_frame() {
+                Ok(true) if self.decoder.buffer.is_empty() => {}
+                other => break other,
+            }
+        };
+        core::mem::swap(&mut self.decoder.buffer, &mut buffer);
+        *out = buffer.into_vec();
+        if !matches!(result, Ok(true)) {
+            out.clear();
+        }
+        result
+    }
+
+    /// Decode into one caller-owned slice per channel, filling as many
+    /// blocks as the shortest slice holds, across frames. Returns the
+    /// number of blocks written: fewer only at the end of the stream, and
+    /// 0 once it has ended.
+    ///
+    /// Each channel is copied straight from the decoder's output into its
+    /// slice, for DSP code that works per channel. A block partly read
+    /// through [`samples`](Self::samples) is skipped. Fails with
+    /// [`ErrorKind::FormatMismatch`] if `planes` does not have one slice
+    /// per channel.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let (mut left, mut right) = (vec![0; 4096], vec![0; 4096]);
+    /// loop {
+    ///     let blocks = reader.decode_into_planar(&mut [&mut left, &mut right]).unwrap();
+    ///     if blocks == 0 {
+    ///         break;
+    ///     }
+    ///     // process left[..blocks] and right[..blocks]
+    /// }
+    /// ```
+    pub fn decode_into_planar(&mut self, planes: &mut [&mut [i32]]) -> Result<usize, ApeError> {
+        let channels = self.info.channels as usize;
+        if planes.len() != channels {
+            return Err(ApeError::FormatMismatch(format!(
+                "{} output planes for a {channels}-channel stream",
+                planes.len()
+            )));
+        }
+        let partial = self.decoder.buffer.position() % channels;
+        if partial != 0 {
+            self.decoder.buffer.skip(channels - partial);
+        }
+
+        let capacity = planes.iter().map(|p| p.len()).min().unwrap_or(0);
+        let mut written = 0;
+        while written < capacity {
+            let samples = self.decoder.buffer.take((capacity - written) * channels);
+            if samples.is_empty() {
+                if self.decoder.finished || !self.decoder.decode_next_frame()? {
+                    break;
+                }
+                continue;
+            }
+            for (channel, plane) in planes.iter_mut().enumerate() {
+                let interleaved = samples[channel..].iter().step_by(channels);
+                for (out, &s) in plane[written..].iter_mut().zip(interleaved) {
+                    *out = s;
+                }
+            }
+            written += samples.len() / channels;
+        }
+        Ok(written)
+    }
+
+    /// Fill `out` with the next interleaved samples, decoding frames as
+    /// needed, and return how many were written: `out.len()` unless the
+    /// stream ends first, and 0 once it has ended.
+    ///
+    /// The counterpart of [`Read::read`] for samples: `out` may be any
+    /// length, need not hold whole blocks and is filled with straight
+    /// copies of the decoder's output, so a fixed buffer can be refilled
+    /// in a loop without allocating.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let mut buf = [0i32; 4096];
+    /// loop {
+    ///     let n = reader.read_samples(&mut buf).unwrap();
+    ///     if n == 0 {
+    ///         break;
+    ///     }
+    ///     // process buf[..n]
+    /// }
+    /// ```
+    pub fn read_samples(&mut self, out: &mut [i32]) -> Result<usize, ApeError> {
+        let mut written = 0;
+        while written < out.len() {
+            let n = self.decoder.buffer.drain_into(&mut out[written..]);
+            if n == 0 {
+                if self.decoder.finished || !self.decoder.decode_next_frame()? {
+                    break;
+                }
+                continue;
+            }
+            written += n;
+        }
+        Ok(written)
+    }
+
+    /// Decode at most `blocks` blocks, appending their interleaved samples
+    /// to `out`, and return how many were produced: `blocks` unless the
+    /// stream ends first, and 0 once it has ended.
+    ///
+    /// For cooperative schedulers (GUI event loops, game loops,
+    /// single-threaded executors) that interleave decoding with other work
+    /// in bounded steps. Frames are decoded whole, so a call decodes a new
+    /// frame only when the samples left from the last one run out; with
+    /// `blocks` below the frame size most calls just copy. A block partly
+    /// read through [`samples`](Self::samples) is completed first and
+    /// counts as one.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let mut pcm = Vec::new();
+    /// // once per tick of the host loop:
+    /// let blocks = reader.decode_at_most(1024, &mut pcm).unwrap();
+    /// ```
+    pub fn decode_at_most(&mut self, blocks: usize, out: &mut Vec<i32>) -> Result<usize, ApeError> {
+        let channels = self.info.channels.max(1) as usize;
+        let partial = self.decoder.buffer.position() % channels;
+        let wanted = (blocks * channels).saturating_sub(partial);
+        let mut taken = 0;
+        while taken < wanted {
+            let samples = self.decoder.buffer.take(wanted - taken);
+            if samples.is_empty() {
+                if self.decoder.finished || !self.decoder.decode_next_frame()? {
+                    break;
+                }
+                continue;
+            }
+            out.extend_from_slice(samples);
+            taken += samples.len();
+        }
+        Ok((partial + taken).div_ceil(channels).min(blocks))
+    }
 }
 
 /// Iterator over decoded PCM samples from an APE file.
 ///
 /// Each call to `next()` yields one sample as `Result<i32, ApeError>`.
 /// For stereo files, samples alternate between channels.
+///
+/// Its [`size_hint`](Iterator::size_hint) is exact unless frames may be
+/// dropped, so output buffers can be sized up front. It is not an
+/// [`ExactSizeIterator`]: with [`FrameErrorMode::Skip`] or a frame error
+/// handler, how many samples are left is only known once the damaged
+/// frames have been decoded.
+#[cfg(feature = "std")]
 pub struct ApeSamples<'a, R: Read + Seek> {
     decoder: &'a mut decode::Decoder<R>,
 }
 
+#[cfg(feature = "std")]
+impl<R: Read + Seek> fmt::Debug for ApeSamples<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApeSamples")
+            .field("sample_index", &self.decoder.sample_index())
+            .field("total_samples", &self.decoder.header.total_samples())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Fixed-size chunks of decoded samples, from
+/// [`ApeReader::samples_chunks`].
+#[cfg(feature = "std")]
+pub struct SampleChunks<'a, R: Read + Seek> {
+    decoder: &'a mut decode::Decoder<R>,
+    size: usize,
+    /// A chunk assembled across frames.
+    chunk: Vec<i32>,
+    /// Whether `chunk` was returned, and can be cleared.
+    lent: bool,
+    /// Stream index of the chunk's first sample.
+    start: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> SampleChunks<'_, R> {
+    /// The next chunk, borrowed until the next call. Samples gathered
+    /// before an error are kept for the following chunk.
+    pub fn next_chunk(&mut self) -> Option<Result<&[i32], ApeError>> {
+        if core::mem::take(&mut self.lent) {
+            self.chunk.clear();
+        }
+        // A whole chunk within the current frame needs no copy
+        if self.chunk.is_empty() && self.decoder.buffer.remaining() >= self.size {
+            self.start = self.decoder.sample_index();
+            return Some(Ok(self.decoder.buffer.take(self.size)));
+        }
+        while self.chunk.len() < self.size {
+            if self.chunk.is_empty() {
+                self.start = self.decoder.sample_index();
+            }
+            let part = self.decoder.buffer.take(self.size - self.chunk.len());
+            if !part.is_empty() {
+                self.chunk.extend_from_slice(part);
+                continue;
+            }
+            if self.decoder.finished {
+                break;
+            }
+            match self.decoder.decode_next_frame() {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        if self.chunk.is_empty() {
+            return None;
+        }
+        self.lent = true;
+        Some(Ok(&self.chunk))
+    }
+
+    /// Stream index (interleaved, from 0) of the first sample of the chunk
+    /// last returned.
+    pub fn chunk_start(&self) -> u64 {
+        self.start
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Iterator for SampleChunks<'_, R> {
+    type Item = Result<Vec<i32>, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().map(|chunk| chunk.map(<[i32]>::to_vec))
+    }
+}
+
+/// Iterator over decoded frames, from [`ApeReader::decoded_frames`].
+#[cfg(feature = "std")]
+pub struct DecodedFrames<'a, R: Read + Seek> {
+    decoder: &'a mut decode::Decoder<R>,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Iterator for DecodedFrames<'_, R> {
+    type Item = Result<Frame, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let channels = self.decoder.header.header.channels.max(1) as u64;
+        // The next frame, or the rest of one partly read
+        let (index, _, _) = self.decoder.position();
+        let first_block = self.decoder.sample_index() / channels;
+        let mut crc_ok = true;
+        if self.decoder.buffer.is_empty() {
+            if self.decoder.finished {
+                return None;
+            }
+            let warnings = self.decoder.warnings.len();
+            match self.decoder.decode_next_frame() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+            crc_ok = !self.decoder.warnings[warnings..]
+                .iter()
+                .any(|w| matches!(w, DecodeWarning::FrameDropped { .. }));
+        }
+        Some(Ok(Frame {
+            index,
+            first_block,
+            samples: self.decoder.buffer.take_remaining().to_vec(),
+            crc_ok,
+        }))
+    }
+}
+
+/// Samples with their stream index, from [`ApeReader::timed_samples`].
+#[cfg(feature = "std")]
+pub struct TimedSamples<'a, R: Read + Seek> {
+    decoder: &'a mut decode::Decoder<R>,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Iterator for TimedSamples<'_, R> {
+    type Item = Result<(u64, i32), ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let index = self.decoder.sample_index();
+            if let Some(s) = self.decoder.next_sample() {
+                return Some(Ok((index, s)));
+            }
+            if self.decoder.finished {
+                return None;
+            }
+            match self.decoder.decode_next_frame() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Chunks with the time of their first sample, from
+/// [`ApeReader::timed_chunks`].
+#[cfg(feature = "std")]
+pub struct TimedChunks<'a, R: Read + Seek> {
+    chunks: SampleChunks<'a, R>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Iterator for TimedChunks<'_, R> {
+    type Item = Result<(Duration, Vec<i32>), ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = match self.chunks.next()? {
+            Ok(chunk) => chunk,
+            Err(e) => return Some(Err(e)),
+        };
+        let block = self.chunks.chunk_start() / self.channels.max(1) as u64;
+        Some(Ok((block_time(block, self.sample_rate), chunk)))
+    }
+}
+
+#[cfg(feature = "std")]
 impl<R: Read + Seek> Iterator for ApeSamples<'_, R> {
     type Item = Result<i32, ApeError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Try to get a sample from the current output buffer
-        if let Some(s) = self.decoder.next_sample() {
-            return Some(Ok(s));
+        loop {
+            // Try to get a sample from the current output buffer
+            if let Some(s) = self.decoder.next_sample() {
+                return Some(Ok(s));
+            }
+
+            // Buffer exhausted — decode the next frame
+            if self.decoder.finished {
+                return None;
+            }
+
+            match self.decoder.decode_next_frame() {
+                Ok(true) => {} // Skipped frames leave the buffer empty; keep going
+                Ok(false) => return None, // Stream ended
+                Err(e) => return Some(Err(e)),
+            }
         }
+    }
 
-        // Buffer exhausted — decode the next frame
-        if self.decoder.finished {
-            return None;
+    /// The samples from the decoder's position to the end of the stream.
+    ///
+    /// In the [`Strict`](FrameErrorMode::Strict) and
+    /// [`Silence`](FrameErrorMode::Silence) modes without a frame error
+    /// handler every frame yields all its samples, so the bounds are equal,
+    /// unless an error is returned in place of a frame. Frames that
+    /// [`Skip`](FrameErrorMode::Skip) mode or a handler may drop leave only
+    /// the current frame's samples as the lower bound.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.decoder.header.total_samples().saturating_sub(self.decoder.sample_index());
+        let remaining = usize::try_from(remaining).ok();
+        let may_drop = self.decoder.frame_error_handler.is_some()
+            || self.decoder.frame_error_mode == FrameErrorMode::Skip;
+        match remaining {
+            Some(n) if !may_drop => (n, Some(n)),
+            _ => (self.decoder.buffer.remaining(), remaining),
         }
+    }
 
-        match self.decoder.decode_next_frame() {
-            Ok(true) => self.decoder.next_sample().map(Ok),
-            Ok(false) => None, // Stream ended
+    /// Skips with [`skip_samples`](ApeSamples::skip_samples), so
+    /// `nth` and [`Iterator::skip`] decode only the frame they land in.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        match self.skip_samples(n as u64) {
+            Ok(_) => self.next(),
             Err(e) => Some(Err(e)),
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> core::iter::FusedIterator for ApeSamples<'_, R> {}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeSamples<'_, R> {
+    /// Pass over the next `n` samples without returning them, and return
+    /// how many there were: fewer than `n` only at the end of the stream.
+    ///
+    /// Whole frames are skipped using the seek table, so only the frame
+    /// the next sample belongs to is decoded, and it only if the skip ends
+    /// inside it. Skipped frames are not checked.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("long.ape").unwrap();
+    /// let block = 30 * 60 * reader.sample_rate() as u64;
+    /// let mut samples = reader.samples();
+    /// samples.skip_samples(block * 2).unwrap();
+    /// let rest: Vec<i32> = samples.collect::<Result<_, _>>().unwrap();
+    /// ```
+    pub fn skip_samples(&mut self, n: u64) -> Result<u64, ApeError> {
+        let decoder = &mut *self.decoder;
+        let buffered = decoder.buffer.remaining() as u64;
+        if n <= buffered {
+            decoder.buffer.skip(n as usize);
+            return Ok(n);
+        }
+
+        let start = decoder.sample_index();
+        let total = decoder.header.total_samples();
+        let target = start.saturating_add(n).min(total);
+        let (_, _, resync_shift) = decoder.position();
+        let h = &decoder.header.header;
+        let frame_samples = h.blocks_per_frame as u64 * h.channels as u64;
+        if target == total || frame_samples == 0 {
+            decoder.restore_position(h.total_frames, 0, resync_shift)?;
+        } else {
+            let frame = (target / frame_samples) as u32;
+            decoder.restore_position(frame, (target % frame_samples) as usize, resync_shift)?;
+        }
+        Ok(target.max(start) - start)
+    }
+}
@@ -0,0 +1,54 @@
+//! Memory footprint of a decode.
+//!
+//! A decoder's heap use is fixed by the stream's format: filter state grows
+//! with the compression level (Insane needs by far the most), the output
+//! buffer with the frame size, and the compressed input with the largest
+//! frame. [`MemoryUsage::estimate`] works this out from a parsed header
+//! alone, so a server or device can refuse a file before committing to it;
+//! [`ApeReader::memory_footprint`](crate::ApeReader::memory_footprint)
+//! reports what an open reader holds.
+
+use crate::header::ApeFileHeader;
+use crate::nnfilter::NNFilter;
+use crate::predictor::Predictor;
+
+/// Heap bytes a decoder uses, by purpose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryUsage {
+    /// NNFilter coefficients and history, for all channels.
+    pub filters: usize,
+    /// Predictor history.
+    pub predictor: usize,
+    /// Decoded samples of one frame.
+    pub output: usize,
+    /// Compressed bytes of the largest frame, held while it is decoded.
+    pub frame_input: usize,
+    /// The parsed seek table.
+    pub seek_table: usize,
+}
+
+impl MemoryUsage {
+    /// Heap bytes a decoder for `header`'s stream will use at its peak,
+    /// while decoding the largest frame.
+    ///
+    /// Filter and predictor sizes are exact; buffer sizes assume no
+    /// over-allocation.
+    pub fn estimate(header: &ApeFileHeader) -> Self {
+        let h = &header.header;
+        let fset = (h.compression_level / 1000).clamp(1, 5) as usize - 1;
+        let largest_frame = (0..header.seek_table.len()).filter_map(|f| header.frame_size(f)).max();
+        MemoryUsage {
+            filters: NNFilter::new(fset).memory_bytes() * h.channels as usize,
+            predictor: Predictor::new().memory_bytes(),
+            output: h.blocks_per_frame as usize * h.channels as usize * size_of::<i32>(),
+            frame_input: largest_frame.unwrap_or(0) as usize,
+            seek_table: header.seek_table.len() * size_of::<u32>(),
+        }
+    }
+
+    /// Sum of all parts.
+    pub fn total(&self) -> usize {
+        self.filters + self.predictor + self.output + self.frame_input + self.seek_table
+    }
+}
@@ -0,0 +1,89 @@
+//! Descriptive metadata (title, artist, …) gathered from the file.
+//!
+//! [`Metadata`] is the one place callers look for track information,
+//! whatever part of the file it came from. Currently that is the original
+//! WAV file's `LIST`/`INFO` chunks, preserved in the header or terminating
+//! data.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek};
+
+#[cfg(feature = "std")]
+use crate::ApeReader;
+#[cfg(feature = "std")]
+use crate::error::ApeError;
+#[cfg(feature = "std")]
+use crate::riff;
+use crate::riff::InfoItem;
+
+/// Track information, from [`ApeReader::metadata`](crate::ApeReader::metadata).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    /// Date as written (often just a year).
+    pub date: Option<String>,
+    /// Track number as written (e.g. `3` or `3/12`).
+    pub track: Option<String>,
+    pub comment: Option<String>,
+    /// Every item as read, including those above, in file order, keyed by
+    /// its name in the source (e.g. `INAM`).
+    pub items: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Whether no metadata was found.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Add `LIST`/`INFO` items. The first value for each field wins.
+    pub fn add_riff_info(&mut self, items: &[InfoItem]) {
+        for item in items {
+            let field = match item.id.as_str() {
+                "INAM" => &mut self.title,
+                "IART" => &mut self.artist,
+                "IPRD" => &mut self.album,
+                "IGNR" => &mut self.genre,
+                "ICRD" => &mut self.date,
+                "ITRK" | "IPRT" => &mut self.track,
+                "ICMT" => &mut self.comment,
+                _ => {
+                    self.items.push((item.id.clone(), item.value.clone()));
+                    continue;
+                }
+            };
+            field.get_or_insert_with(|| item.value.clone());
+            self.items.push((item.id.clone(), item.value.clone()));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeReader<R> {
+    /// Track information from the file. Empty if it has none.
+    ///
+    /// `LIST`/`INFO` chunks are read from both the header data and the
+    /// terminating data, header first. Like
+    /// [`read_raw_frame`](Self::read_raw_frame), this does not disturb
+    /// decoding.
+    pub fn metadata(&mut self) -> Result<Metadata, ApeError> {
+        let header = self.header_data()?;
+        let trailer = self.terminating_data()?;
+        let mut metadata = Metadata::default();
+        metadata.add_riff_info(&riff::info_items(&header));
+        // An odd-length `data` chunk's pad byte comes first in the trailer
+        let odd_data = riff::chunks(&header).any(|c| &c.id == b"data" && c.size & 1 == 1);
+        let trailer = match trailer.split_first() {
+            Some((_, rest)) if odd_data => rest,
+            _ => &trailer[..],
+        };
+        metadata.add_riff_info(&riff::info_items(trailer));
+        Ok(metadata)
+    }
+}
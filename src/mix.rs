@@ -0,0 +1,137 @@
+//! Stereo-to-mono downmixing and single-channel extraction.
+//!
+//! The mono signal is the average of the two channels, `(L + R) / 2`,
+//! rounded down. Unlike a plain sum it always fits the source bit depth,
+//! so it never clips and needs no limiter; the cost is 6 dB of level for
+//! a source panned hard to one side. Mono sources pass through unchanged.
+//!
+//! [`Channel`] instead keeps one channel as it is. APE stores stereo as
+//! mid/side, so both channels are still decoded; only the output is
+//! de-interleaved.
+
+use alloc::vec::Vec;
+
+use crate::error::ApeError;
+
+/// The mono sample for one stereo block.
+pub fn downmix_block(left: i32, right: i32) -> i32 {
+    ((left as i64 + right as i64) >> 1) as i32
+}
+
+/// Downmix interleaved samples chunk by chunk.
+///
+/// A chunk may end between the two samples of a block; the left sample is
+/// kept until the next chunk supplies the right one.
+#[derive(Debug, Clone)]
+pub struct Downmixer {
+    stereo: bool,
+    /// Left sample of a block split across chunks.
+    pending: Option<i32>,
+}
+
+impl Downmixer {
+    /// Create a downmixer for a stream with `channels` channels (1 or 2).
+    pub fn new(channels: u16) -> Self {
+        Downmixer {
+            stereo: channels == 2,
+            pending: None,
+        }
+    }
+
+    /// Append the mono samples for `samples` to `out`.
+    pub fn process(&mut self, samples: &[i32], out: &mut Vec<i32>) {
+        if !self.stereo {
+            out.extend_from_slice(samples);
+            return;
+        }
+        let mut samples = samples;
+        if let Some(left) = self.pending.take() {
+            let Some((&right, rest)) = samples.split_first() else {
+                self.pending = Some(left);
+                return;
+            };
+            out.push(downmix_block(left, right));
+            samples = rest;
+        }
+        let blocks = samples.chunks_exact(2);
+        self.pending = blocks.remainder().first().copied();
+        out.extend(blocks.map(|pair| downmix_block(pair[0], pair[1])));
+    }
+}
+
+/// Iterator adapter yielding one mono sample per block of an interleaved
+/// sample iterator, e.g. [`ApeReader::mono_samples`](crate::ApeReader::mono_samples)
+/// or a [`ChainedReader`](crate::chain::ChainedReader)'s samples.
+///
+/// Errors are passed on as they come. An incomplete final block is
+/// dropped.
+#[derive(Debug)]
+pub struct Downmix<I> {
+    inner: I,
+    stereo: bool,
+}
+
+impl<I> Downmix<I> {
+    /// Downmix `samples`, interleaved with `channels` channels (1 or 2).
+    pub fn new(samples: I, channels: u16) -> Self {
+        Downmix {
+            inner: samples,
+            stereo: channels == 2,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<i32, ApeError>>> Iterator for Downmix<I> {
+    type Item = Result<i32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let left = match self.inner.next()? {
+            Ok(left) if self.stereo => left,
+            other => return Some(other),
+        };
+        Some(self.inner.next()?.map(|right| downmix_block(left, right)))
+    }
+}
+
+/// Iterator adapter yielding one channel of an interleaved sample
+/// iterator, e.g. [`ApeReader::samples_for_channel`](crate::ApeReader::samples_for_channel).
+///
+/// Errors are passed on as they come. An incomplete final block yields
+/// its sample for the channel only if the block reaches it.
+#[derive(Debug)]
+pub struct Channel<I> {
+    inner: I,
+    channels: usize,
+    channel: usize,
+    /// Channel of the next sample from `inner`.
+    next: usize,
+}
+
+impl<I> Channel<I> {
+    /// Keep channel `channel` (0-based) of `samples`, interleaved with
+    /// `channels` channels.
+    pub fn new(samples: I, channels: u16, channel: u16) -> Self {
+        Channel {
+            inner: samples,
+            channels: channels.max(1) as usize,
+            channel: channel as usize,
+            next: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<i32, ApeError>>> Iterator for Channel<I> {
+    type Item = Result<i32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let sample = self.inner.next()?;
+            let channel = self.next;
+            self.next = (channel + 1) % self.channels;
+            match sample {
+                Ok(_) if channel != self.channel => {}
+                other => return Some(other),
+            }
+        }
+    }
+}
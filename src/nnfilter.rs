@@ -10,6 +10,11 @@
 //!   Level 4000 (Extra High): 2 stages, 32+256 taps, fracbits=10,13
 //!   Level 5000 (Insane):     3 stages, 16+256+1280 taps, fracbits=11,13,15
 
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::arith::Wrap;
+
 /// Maximum number of filter stages.
 pub const MAX_STAGES: usize = 3;
 
@@ -98,20 +103,19 @@ impl NNFilterStage {
 
         // Dot product: sum(coeffs[i] * delay[dp - order + i])
         // AND adaptation: coeffs[i] += adaptcoeffs[ap - order + i] * sign
-        let mut sum: i64 = 0;
-        for i in 0..order {
-            sum += self.coeffs[i] as i64 * self.historybuffer[dp - order + i] as i64;
-            // Adapt simultaneously
-            self.coeffs[i] = self.coeffs[i]
-                .wrapping_add((self.historybuffer[ap - order + i] as i32 * sign) as i16);
-        }
+        let sum = dot_and_adapt(
+            &mut self.coeffs,
+            &self.historybuffer[dp - order..dp],
+            &self.historybuffer[ap - order..ap],
+            sign,
+        );
 
         // Round and shift
         let rounding = 1i64 << (self.fracbits as i64 - 1);
         let filtered = ((sum + rounding) >> self.fracbits) as i32;
 
         // Add residual
-        let res = filtered.wrapping_add(input);
+        let res = filtered.wrap_add(input);
 
         // Write to delay line (clamped to i16)
         self.historybuffer[dp] = res.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
@@ -130,9 +134,8 @@ impl NNFilterStage {
         self.historybuffer[ap] = adapt_val as i16;
 
         // Update running average
-        self.avg = ((self.avg as i64
-            + (absres as i64 - self.avg as i64) / 16) as u32)
-            .max(0);
+        self.avg = (self.avg as i64
+            + (absres as i64 - self.avg as i64) / 16) as u32;
 
         // Decay old adaptive coefficients
         if ap >= 1 {
@@ -162,6 +165,96 @@ impl NNFilterStage {
 
         res
     }
+
+    /// The value [`decompress`](Self::decompress) adds to its next input.
+    #[cfg(feature = "testgen")]
+    fn prediction(&self) -> i32 {
+        let dp = self.delay_pos;
+        let sum: i64 = self
+            .coeffs
+            .iter()
+            .zip(&self.historybuffer[dp - self.order..dp])
+            .map(|(&c, &d)| c as i64 * d as i64)
+            .sum();
+        let rounding = 1i64 << (self.fracbits as i64 - 1);
+        ((sum + rounding) >> self.fracbits) as i32
+    }
+
+    /// Apply the filter in the compress direction: return the input for
+    /// which `decompress` yields `output`, advancing the state as
+    /// decompressing it would.
+    #[cfg(feature = "testgen")]
+    pub fn compress(&mut self, output: i32) -> i32 {
+        if self.order == 0 {
+            return output;
+        }
+        let input = output.wrap_sub(self.prediction());
+        self.decompress(input);
+        input
+    }
+}
+
+/// Dot product of `coeffs` and `delay`, adapting each coefficient by
+/// `adapt * sign` as it goes (the stage's inner loop).
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+pub fn dot_and_adapt(coeffs: &mut [i16], delay: &[i16], adapt: &[i16], sign: i32) -> i64 {
+    dot_and_adapt_scalar(coeffs, delay, adapt, sign)
+}
+
+/// The portable loop behind [`dot_and_adapt`], for checking the simd128
+/// kernel against.
+pub fn dot_and_adapt_scalar(coeffs: &mut [i16], delay: &[i16], adapt: &[i16], sign: i32) -> i64 {
+    let mut sum: i64 = 0;
+    for ((c, &d), &a) in coeffs.iter_mut().zip(delay).zip(adapt) {
+        sum += *c as i64 * d as i64;
+        *c = c.wrap_add((a as i32 * sign) as i16);
+    }
+    sum
+}
+
+/// simd128 version of [`dot_and_adapt`], eight taps at a time. Products are
+/// widened to i64 before summing, so the result matches the scalar loop
+/// exactly. Every filter order is a multiple of 8; the scalar loop covers
+/// any remainder.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub fn dot_and_adapt(coeffs: &mut [i16], delay: &[i16], adapt: &[i16], sign: i32) -> i64 {
+    use core::arch::wasm32::*;
+
+    fn widen_sum(products: v128) -> v128 {
+        i64x2_add(i64x2_extend_low_i32x4(products), i64x2_extend_high_i32x4(products))
+    }
+
+    // (a * sign) as i16 with sign in -1..=1 is the wrapping i16 product
+    let sign_v = i16x8_splat(sign as i16);
+    let mut acc = i64x2_splat(0);
+    let mut c_chunks = coeffs.chunks_exact_mut(8);
+    let mut d_chunks = delay.chunks_exact(8);
+    let mut a_chunks = adapt.chunks_exact(8);
+    for ((c, d), a) in (&mut c_chunks).zip(&mut d_chunks).zip(&mut a_chunks) {
+        // SAFETY: each chunk is eight i16s, one v128; wasm loads and
+        // stores need no alignment.
+        let (cv, dv, av) = unsafe {
+            (
+                v128_load(c.as_ptr().cast()),
+                v128_load(d.as_ptr().cast()),
+                v128_load(a.as_ptr().cast()),
+            )
+        };
+        acc = i64x2_add(acc, widen_sum(i32x4_extmul_low_i16x8(cv, dv)));
+        acc = i64x2_add(acc, widen_sum(i32x4_extmul_high_i16x8(cv, dv)));
+
+        let adapted = i16x8_add(cv, i16x8_mul(av, sign_v));
+        // SAFETY: as above
+        unsafe { v128_store(c.as_mut_ptr().cast(), adapted) };
+    }
+
+    let sum = i64x2_extract_lane::<0>(acc).wrapping_add(i64x2_extract_lane::<1>(acc));
+    sum.wrapping_add(dot_and_adapt_scalar(
+        c_chunks.into_remainder(),
+        d_chunks.remainder(),
+        a_chunks.remainder(),
+        sign,
+    ))
 }
 
 /// Multi-stage NNFilter — cascades 0-3 filter stages.
@@ -200,8 +293,34 @@ impl NNFilter {
         value
     }
 
+    /// Apply all filter stages to compress one sample, the inverse of
+    /// [`decompress`](Self::decompress).
+    #[cfg(feature = "testgen")]
+    pub fn compress(&mut self, mut value: i32) -> i32 {
+        for stage in self.stages.iter_mut().rev() {
+            value = stage.compress(value);
+        }
+        value
+    }
+
     /// Number of active stages.
+    #[cfg(feature = "internals")]
     pub fn num_stages(&self) -> usize {
         self.stages.len()
     }
+
+    /// Heap bytes held by the coefficient and history buffers.
+    pub fn memory_bytes(&self) -> usize {
+        self.stages
+            .iter()
+            .map(|s| (s.coeffs.capacity() + s.historybuffer.capacity()) * size_of::<i16>())
+            .sum()
+    }
+
+    /// Each stage's running average of absolute input magnitudes, which
+    /// sets its adaptation step size.
+    #[cfg(feature = "debug-dump")]
+    pub(crate) fn averages(&self) -> Vec<u32> {
+        self.stages.iter().map(|s| s.avg).collect()
+    }
 }
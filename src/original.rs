@@ -0,0 +1,172 @@
+//! Reconstruction of the file that was compressed.
+//!
+//! Monkey's Audio keeps everything of a WAV file that is not audio: the
+//! header in front of the `data` chunk ("header data", between the seek
+//! table and the frames) and whatever followed the audio ("terminating
+//! data", after the frames). Writing the header data, the decoded PCM in WAV
+//! byte order and the terminating data reproduces the original file.
+//!
+//! The descriptor's MD5 covers the stored file rather than the WAV: header
+//! data, frame data and terminating data, then the APE header and the seek
+//! table, in that order. Together with each frame's CRC over its decoded
+//! PCM, a passing check means the output is the file that was compressed.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::convert::{self, PcmLayout};
+use crate::digest::Md5;
+use crate::error::ApeError;
+use crate::riff;
+use crate::{ApeReader, FrameErrorMode};
+
+/// Read size when hashing the file.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// The stored header of the original WAV file (`RIFF` … up to the
+    /// `data` chunk's samples), or an empty `Vec` if the encoder did not
+    /// keep it. See [`ApeFileHeader::header_data_range`](crate::ApeFileHeader::header_data_range).
+    ///
+    /// Like [`read_raw_frame`](Self::read_raw_frame), this does not disturb
+    /// decoding.
+    pub fn header_data(&mut self) -> Result<Vec<u8>, ApeError> {
+        match self.decoder.header.header_data_range() {
+            Some((offset, len)) => self.read_range(offset, len),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Check the file against the MD5 stored in its descriptor, without
+    /// decoding.
+    ///
+    /// Returns `false` if the encoder stored no MD5 (it is all zeros), and
+    /// fails with [`ErrorKind::Md5Mismatch`](crate::ErrorKind::Md5Mismatch)
+    /// if the contents do not match it. This does not disturb decoding.
+    pub fn verify_file_md5(&mut self) -> Result<bool, ApeError> {
+        let header = &self.decoder.header;
+        let d = &header.descriptor;
+        let expected = d.file_md5;
+        if expected == [0; 16] {
+            return Ok(false);
+        }
+        let header_start = header.junk_bytes + d.descriptor_bytes as u64;
+        let seek_table_start = header_start + d.header_bytes as u64;
+        let sections = [
+            (seek_table_start + d.seek_table_bytes as u64, d.header_data_bytes as u64),
+            (header.data_offset, header.frame_data_bytes() + d.terminating_data_bytes as u64),
+            (header_start, d.header_bytes as u64),
+            (seek_table_start, d.seek_table_bytes as u64),
+        ];
+
+        let mut md5 = Md5::new();
+        let mut buf = vec![0; CHUNK_BYTES];
+        let reader = &mut self.decoder.reader;
+        for (offset, mut len) in sections {
+            reader.seek(SeekFrom::Start(offset))?;
+            while len > 0 {
+                let n = (len as usize).min(CHUNK_BYTES);
+                reader.read_exact(&mut buf[..n]).map_err(|e| match e.kind() {
+                    std::io::ErrorKind::UnexpectedEof => ApeError::UnexpectedEof,
+                    _ => e.into(),
+                })?;
+                md5.update(&buf[..n]);
+                len -= n as u64;
+            }
+        }
+        let actual = md5.finish();
+        if actual != expected {
+            return Err(ApeError::Md5Mismatch { expected, actual });
+        }
+        Ok(true)
+    }
+
+    /// Write the original file to `out`: the stored WAV header, the whole
+    /// stream's PCM and the terminating data.
+    ///
+    /// The file MD5 is checked first (if there is one), and every frame is
+    /// decoded strictly whatever the reader's frame error mode, so a
+    /// corrupt frame fails instead of being concealed. Fails with
+    /// [`ErrorKind::InvalidHeader`](crate::ErrorKind::InvalidHeader) if the
+    /// file has no stored header, or if its `fmt ` chunk (plain or
+    /// `WAVE_FORMAT_EXTENSIBLE`) disagrees with the stream's channel count
+    /// or sample size; [`write_wav`](Self::write_wav) writes a WAV file
+    /// with a synthesized header instead. Decoding restarts from the
+    /// beginning of the stream and leaves the reader at its end.
+    pub fn write_original<W: Write>(&mut self, mut out: W) -> Result<(), ApeError> {
+        self.verify_file_md5()?;
+        let header_data = self.header_data()?;
+        if header_data.is_empty() {
+            return Err(ApeError::InvalidHeader(
+                "the file stores no WAV header to restore (write_wav synthesizes one)".into(),
+            ));
+        }
+        if let Some(format) = riff::wave_format(&header_data) {
+            let bytes_per_sample = self.info.bits_per_sample.div_ceil(8);
+            if format.channels != self.info.channels || format.container_bytes() != bytes_per_sample {
+                return Err(ApeError::InvalidHeader(format!(
+                    "stored WAV header is {} channels of {}-byte samples, the stream {} of {}-byte samples",
+                    format.channels,
+                    format.container_bytes(),
+                    self.info.channels,
+                    bytes_per_sample
+                )));
+            }
+        }
+        out.write_all(&header_data)?;
+
+        self.seek_to_block(0)?;
+        let mode = std::mem::replace(&mut self.decoder.frame_error_mode, FrameErrorMode::Strict);
+        let handler = self.decoder.frame_error_handler.take();
+        let written = self.write_pcm(&mut out);
+        self.decoder.frame_error_mode = mode;
+        self.decoder.frame_error_handler = handler;
+        written?;
+
+        out.write_all(&self.terminating_data()?)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Decode the rest of the stream into `out` in WAV `data` chunk byte
+    /// order.
+    pub(crate) fn write_pcm<W: Write>(&mut self, out: &mut W) -> Result<(), ApeError> {
+        let bytes_per_sample = convert::bytes_per_sample(self.info.bits_per_sample);
+        let mut bytes = Vec::new();
+        let mut write_error = None;
+        self.for_each_chunk_while(|samples| {
+            bytes.clear();
+            convert::pack(samples, bytes_per_sample, PcmLayout::Wav, &mut bytes);
+            match out.write_all(&bytes) {
+                Ok(()) => true,
+                Err(e) => {
+                    write_error = Some(e);
+                    false
+                }
+            }
+        })?;
+        match write_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Recreate the original file at `path`, as [`write_original`](Self::write_original).
+    /// The file is removed again if restoring fails.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// reader.restore_original("track.wav").unwrap();
+    /// ```
+    pub fn restore_original<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ApeError> {
+        let path = path.as_ref();
+        let result = self.write_original(BufWriter::new(File::create(path)?));
+        if result.is_err() {
+            let _ = std::fs::remove_file(path);
+        }
+        result
+    }
+}
@@ -0,0 +1,404 @@
+//! Packet-level decoding: one frame's compressed bytes in, PCM out,
+//! independent of the file reader.
+//!
+//! [`ApeReader`](crate::ApeReader) handles seeking, the seek table and
+//! error recovery. Container demuxers and custom I/O layers that locate
+//! frames themselves can drive [`PacketDecoder`] directly instead.
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::buffer::SampleBuffer;
+use crate::crc;
+#[cfg(feature = "debug-dump")]
+use crate::debug_dump::FrameStats;
+use crate::diag::diag;
+use crate::error::ApeError;
+use crate::nnfilter::NNFilter;
+use crate::predictor::Predictor;
+use crate::range_coder::{RangeCoder, RiceState};
+
+/// Compressed bytes a frame may leave unread after its last block
+/// (word padding after the final frame). Anything beyond this means the
+/// frame encodes more blocks than the header gives it.
+const FRAME_SLACK_BYTES: usize = 8;
+
+/// Decoder for individual frames of one stream.
+///
+/// ```no_run
+/// use ape_rs::packet::PacketDecoder;
+///
+/// # let (frame_bytes, skip, blocks): (Vec<u8>, u32, u32) = (vec![], 0, 73728);
+/// // Stream parameters come from the container or the file header
+/// let mut decoder = PacketDecoder::new(2, 16, 2000).unwrap();
+/// let pcm = decoder.decode_packet(&frame_bytes, skip, blocks).unwrap();
+/// assert_eq!(pcm.len(), blocks as usize * 2);
+/// ```
+pub struct PacketDecoder {
+    bits_per_sample: u16,
+    compression_level: u16,
+    /// NNFilter instances — one per channel.
+    filters: Vec<NNFilter>,
+    predictor: Predictor,
+    /// Whether to record [`FrameStats`] for each frame.
+    #[cfg(feature = "debug-dump")]
+    record_stats: bool,
+    /// Stats of the frame decoded last.
+    #[cfg(feature = "debug-dump")]
+    stats: Option<FrameStats>,
+}
+
+impl fmt::Debug for PacketDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketDecoder")
+            .field("channels", &self.filters.len())
+            .field("bits_per_sample", &self.bits_per_sample)
+            .field("compression_level", &self.compression_level)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PacketDecoder {
+    /// Create a decoder for a stream with the given layout and compression
+    /// level (1000–5000).
+    pub fn new(channels: u16, bits_per_sample: u16, compression_level: u16) -> Result<Self, ApeError> {
+        if channels == 0 || channels > 2 {
+            return Err(ApeError::InvalidHeader(format!(
+                "unsupported channel count: {channels}"
+            )));
+        }
+        if bits_per_sample != 8 && bits_per_sample != 16 && bits_per_sample != 24 {
+            return Err(ApeError::InvalidHeader(format!(
+                "unsupported bits per sample: {bits_per_sample}"
+            )));
+        }
+        if !is_supported_level(compression_level) {
+            return Err(ApeError::UnsupportedCompressionLevel(compression_level));
+        }
+
+        Ok(Self::from_header(channels, bits_per_sample, compression_level))
+    }
+
+    /// Create a decoder for parameters already validated by the header
+    /// parser.
+    pub(crate) fn from_header(channels: u16, bits_per_sample: u16, compression_level: u16) -> Self {
+        let fset = (compression_level / 1000 - 1) as usize;
+        PacketDecoder {
+            bits_per_sample,
+            compression_level,
+            filters: (0..channels).map(|_| NNFilter::new(fset)).collect(),
+            predictor: Predictor::new(),
+            #[cfg(feature = "debug-dump")]
+            record_stats: false,
+            #[cfg(feature = "debug-dump")]
+            stats: None,
+        }
+    }
+
+    /// Switch to another stream's parameters (already validated), keeping
+    /// the filters if the channel count and compression level match.
+    #[cfg(feature = "std")]
+    pub(crate) fn reuse_for(&mut self, channels: u16, bits_per_sample: u16, compression_level: u16) {
+        if self.filters.len() == channels as usize && self.compression_level == compression_level {
+            self.bits_per_sample = bits_per_sample;
+        } else {
+            *self = Self::from_header(channels, bits_per_sample, compression_level);
+        }
+    }
+
+    /// Decode one frame of `nblocks` blocks and check its CRC, returning
+    /// interleaved samples.
+    ///
+    /// Frames are packed as 32-bit words counted from the first frame, so a
+    /// frame may begin partway into a word; `skip` is that position
+    /// (`(offset - first_frame_offset) % 4`). `data` is the file's bytes
+    /// from the start of that word, `offset - skip`, as FFmpeg's demuxer
+    /// packs them. It may run past the end of the frame.
+    ///
+    /// Frames are independent, so packets can be decoded in any order.
+    /// A `CrcMismatch` from here always reports frame 0.
+    pub fn decode_packet(&mut self, data: &[u8], skip: u32, nblocks: u32) -> Result<Vec<i32>, ApeError> {
+        let mut frame_data = data.to_vec();
+        byte_swap_words(&mut frame_data);
+
+        let mut buffer = SampleBuffer::new();
+        self.decode_frame(&frame_data, skip, nblocks, 0, &mut buffer)?;
+        Ok(buffer.into_vec())
+    }
+
+    /// Decode and CRC-check a word-swapped frame whose header starts
+    /// `skip` bytes into `frame_data`, appending samples to `buffer`.
+    ///
+    /// Returns the frame's length from its header, which is where the
+    /// next frame starts. `frame` is only used for error reporting.
+    pub(crate) fn decode_frame(
+        &mut self,
+        frame_data: &[u8],
+        skip: u32,
+        nblocks: u32,
+        frame: u32,
+        buffer: &mut SampleBuffer,
+    ) -> Result<usize, ApeError> {
+        let (expected_crc, data) = skip_frame_header(frame_data, skip)?;
+        let header_len = frame_data.len() - data.len();
+        let start = buffer.as_slice().len();
+        let consumed = self.decode_samples(data, nblocks, buffer)?;
+
+        // Verify the CRC over the frame's PCM output
+        let actual = crc::frame_crc(&buffer.as_slice()[start..], self.bits_per_sample);
+        if actual != expected_crc {
+            diag!(debug, frame = frame, expected = expected_crc, actual = actual; "frame CRC mismatch");
+            // The range coder should finish where the next frame starts. If
+            // it stopped well short, the frame encodes more than nblocks
+            // blocks (its CRC covers them all, hence the mismatch). With a
+            // matching CRC, unread bytes only mean the next entry is off.
+            let unread = data.len().saturating_sub(consumed);
+            if unread > FRAME_SLACK_BYTES {
+                return Err(ApeError::SampleOverrun(format!(
+                    "{unread} bytes of frame data left after {nblocks} blocks"
+                )));
+            }
+            return Err(ApeError::CrcMismatch {
+                frame,
+                expected: expected_crc,
+                actual,
+            });
+        }
+
+        Ok(header_len + consumed)
+    }
+
+    /// Reset filter and predictor state, then range-decode `nblocks` blocks
+    /// from `data` into `buffer`.
+    ///
+    /// Returns the number of bytes of `data` the range coder consumed,
+    /// which is where the next frame begins.
+    pub(crate) fn decode_samples(
+        &mut self,
+        data: &[u8],
+        nblocks: u32,
+        buffer: &mut SampleBuffer,
+    ) -> Result<usize, ApeError> {
+        // Reset filter and predictor state for this frame
+        for f in &mut self.filters {
+            f.reset();
+        }
+        self.predictor.reset();
+        // An encode on this thread may have left one behind
+        #[cfg(feature = "strict-math")]
+        crate::arith::take_overflow();
+
+        #[cfg(feature = "debug-dump")]
+        {
+            self.stats = self.record_stats.then(|| FrameStats::new(nblocks, self.filters.len()));
+        }
+
+        let result = if self.filters.len() == 1 {
+            self.decode_mono(data, nblocks, buffer)
+        } else {
+            self.decode_stereo(data, nblocks, buffer)
+        };
+        #[cfg(feature = "debug-dump")]
+        if let Some(stats) = &mut self.stats {
+            stats.finish(&self.filters);
+        }
+        result
+    }
+
+    /// Record [`FrameStats`] for each frame decoded from now on, or stop.
+    /// Recording slows decoding down a little and allocates.
+    #[cfg(feature = "debug-dump")]
+    pub fn record_stats(&mut self, record: bool) {
+        self.record_stats = record;
+        if !record {
+            self.stats = None;
+        }
+    }
+
+    /// The stats of the frame decoded last, complete or up to the block
+    /// where it failed, if [`record_stats`](Self::record_stats) is on. The
+    /// frame index in them is always 0.
+    #[cfg(feature = "debug-dump")]
+    pub fn take_stats(&mut self) -> Option<FrameStats> {
+        self.stats.take()
+    }
+
+    /// Add `channel`'s state after `block` to the stats, if recording.
+    #[cfg(feature = "debug-dump")]
+    fn record(&mut self, channel: usize, block: u32, rice: &RiceState, rc: &RangeCoder<'_>) {
+        if let Some(stats) = &mut self.stats {
+            stats.record(channel, block, rice, rc.escapes, &self.filters[channel]);
+        }
+    }
+
+    /// Heap bytes held by filter and predictor state, all allocated by
+    /// [`new`](Self::new). Decoding allocates nothing further except the
+    /// returned samples.
+    pub fn memory_bytes(&self) -> usize {
+        self.filter_memory_bytes() + self.predictor.memory_bytes()
+    }
+
+    /// Heap bytes held by the filters alone.
+    pub(crate) fn filter_memory_bytes(&self) -> usize {
+        self.filters.iter().map(NNFilter::memory_bytes).sum()
+    }
+
+    /// Magnitude bound for valid samples: 2^(bits_per_sample - 1).
+    fn sample_limit(&self) -> i32 {
+        1 << (self.bits_per_sample - 1)
+    }
+
+    /// Decode a mono frame. Returns the number of bytes consumed.
+    fn decode_mono(&mut self, data: &[u8], nblocks: u32, buffer: &mut SampleBuffer) -> Result<usize, ApeError> {
+        let mut rc = RangeCoder::new(data);
+        let mut rice = RiceState::new();
+        let limit = self.sample_limit();
+
+        for block in 0..nblocks {
+            // 1. Range decode residual
+            let residual = rc.decode_value(&mut rice);
+            rc.check(block)?;
+
+            // 2. NNFilter inverse
+            let filtered = self.filters[0].decompress(residual);
+            #[cfg(feature = "debug-dump")]
+            self.record(0, block, &rice, &rc);
+
+            // 3. Predictor inverse
+            let sample = self.predictor.decode_mono(filtered);
+            check_overflow(block)?;
+            check_sample_range(sample, limit, block)?;
+
+            buffer.push(sample);
+        }
+
+        Ok(rc.finish())
+    }
+
+    /// Decode a stereo frame. Returns the number of bytes consumed.
+    fn decode_stereo(&mut self, data: &[u8], nblocks: u32, buffer: &mut SampleBuffer) -> Result<usize, ApeError> {
+        let mut rc = RangeCoder::new(data);
+        let mut rice_y = RiceState::new();
+        let mut rice_x = RiceState::new();
+        let limit = self.sample_limit();
+
+        for block in 0..nblocks {
+            // Decode Y channel (first in stereo)
+            let residual_y = rc.decode_value(&mut rice_y);
+            let filtered_y = self.filters[0].decompress(residual_y);
+            #[cfg(feature = "debug-dump")]
+            self.record(0, block, &rice_y, &rc);
+
+            // Decode X channel
+            let residual_x = rc.decode_value(&mut rice_x);
+            rc.check(block)?;
+            let filtered_x = self.filters[1].decompress(residual_x);
+            #[cfg(feature = "debug-dump")]
+            self.record(1, block, &rice_x, &rc);
+
+            // Predictor inverse + channel decorrelation
+            let (left, right) = self.predictor.decode_stereo(filtered_y, filtered_x);
+            check_overflow(block)?;
+            check_sample_range(left, limit, block)?;
+            check_sample_range(right, limit, block)?;
+
+            buffer.push_stereo(left, right);
+        }
+
+        Ok(rc.finish())
+    }
+}
+
+/// Whether this build decodes `compression_level`.
+///
+/// Fast (1000) and Normal (2000) always are. High (3000), Extra High (4000)
+/// and Insane (5000) can be compiled out by disabling the `level-high`,
+/// `level-extra-high` and `level-insane` features, so their filter stages
+/// and history buffers are never built; such files then fail with
+/// [`ApeError::UnsupportedCompressionLevel`].
+pub fn is_supported_level(compression_level: u16) -> bool {
+    // Indexed like FILTER_ORDERS: (compression_level / 1000) - 1
+    const ENABLED: [bool; 5] = [
+        true,
+        true,
+        cfg!(feature = "level-high"),
+        cfg!(feature = "level-extra-high"),
+        cfg!(feature = "level-insane"),
+    ];
+    compression_level.is_multiple_of(1000)
+        && (1000..=5000).contains(&compression_level)
+        && ENABLED[(compression_level / 1000 - 1) as usize]
+}
+
+/// Byte-swap each 4-byte group (matching FFmpeg's bswap_buf).
+/// APE stores data as little-endian 32-bit words; the range coder
+/// expects the bytes in big-endian order within each word.
+pub(crate) fn byte_swap_words(data: &mut [u8]) {
+    for word in data.chunks_exact_mut(4) {
+        word.reverse();
+    }
+}
+
+/// A losslessly reconstructed sample always fits the declared bit depth;
+/// one that doesn't means the range coder has lost sync with the stream.
+fn check_sample_range(sample: i32, limit: i32, block: u32) -> Result<(), ApeError> {
+    if sample < -limit || sample >= limit {
+        return Err(ApeError::RangeCoderError(format!(
+            "decoded sample {sample} out of range at block {block}"
+        )));
+    }
+    Ok(())
+}
+
+/// Fail the frame if arithmetic overflowed while decoding `block` (see
+/// [`arith`](crate::arith)). Without `strict-math` nothing is checked.
+#[inline(always)]
+fn check_overflow(block: u32) -> Result<(), ApeError> {
+    #[cfg(feature = "strict-math")]
+    if let Some(operation) = crate::arith::take_overflow() {
+        return Err(ApeError::ArithmeticOverflow { block, operation });
+    }
+    let _ = block;
+    Ok(())
+}
+
+/// Skip the per-frame header: alignment bytes, CRC, optional frame flags, skip byte.
+/// `align` is the low 2 bits of the frame's seek-table entry.
+/// Returns the stored CRC and a slice pointing to the start of range-coded data.
+pub(crate) fn skip_frame_header(frame_data: &[u8], align: u32) -> Result<(u32, &[u8]), ApeError> {
+    let mut pos = 0usize;
+
+    // Skip byte-alignment padding (low 2 bits of seek table entry)
+    pos += align as usize;
+
+    if pos + 4 > frame_data.len() {
+        return Err(ApeError::UnexpectedEof);
+    }
+
+    // Read 4-byte big-endian CRC
+    let crc = u32::from_be_bytes([
+        frame_data[pos],
+        frame_data[pos + 1],
+        frame_data[pos + 2],
+        frame_data[pos + 3],
+    ]);
+    pos += 4;
+
+    // If CRC has high bit set, next 4 bytes are frame flags
+    if crc & 0x80000000 != 0 {
+        if pos + 4 > frame_data.len() {
+            return Err(ApeError::UnexpectedEof);
+        }
+        // frame flags — we don't use them yet but must skip
+        pos += 4;
+    }
+
+    // Skip 1 byte (the first 8 bits of input are ignored by the range coder)
+    if pos >= frame_data.len() {
+        return Err(ApeError::UnexpectedEof);
+    }
+    pos += 1;
+
+    Ok((crc & 0x7FFFFFFF, &frame_data[pos..]))
+}
@@ -0,0 +1,419 @@
+//! Decoding on several threads.
+//!
+//! [`decode_many`] decodes a batch of files concurrently, a bounded number
+//! at a time, and hands each result to a callback on the calling thread as
+//! it completes: the engine a library converter or verifier needs.
+//!
+//! [`ApeReader::decode_parallel_into`] splits one file's frames across
+//! threads instead. Frames are independent (each starts from reset filter
+//! and predictor state), so the speedup is close to linear.
+//!
+//! [`scan_dir`] indexes a music library: it finds the APE files in a
+//! directory tree and parses each one's header and metadata, without
+//! decoding, for a media server's database.
+//!
+//! All take a [`Parallelism`]: scoped threads started for the call, or,
+//! with feature `rayon`, an application's own rayon pool, so decoding
+//! stays within the CPU budget the application already manages.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use crate::convert::{self, PcmLayout};
+use crate::error::{ApeError, DecodeWarning};
+use crate::metadata::Metadata;
+use crate::packet::PacketDecoder;
+use crate::{ApeInfo, ApeReader, ApeReaderBuilder, block_time};
+
+/// Where the parallel APIs run their workers.
+///
+/// `From<usize>` gives [`Threads`](Self::Threads), with 0 meaning
+/// [`Auto`](Self::Auto).
+#[derive(Debug, Clone, Default)]
+pub enum Parallelism {
+    /// Scoped threads, one per available CPU.
+    #[default]
+    Auto,
+    /// Scoped threads, at most this many.
+    Threads(NonZeroUsize),
+    /// Tasks on a rayon pool, one per pool thread (feature `rayon`). The
+    /// calling thread coordinates; if it is one of the pool's own threads,
+    /// the pool needs at least one more.
+    #[cfg(feature = "rayon")]
+    Rayon(std::sync::Arc<rayon::ThreadPool>),
+}
+
+impl From<usize> for Parallelism {
+    fn from(threads: usize) -> Self {
+        NonZeroUsize::new(threads).map_or(Parallelism::Auto, Parallelism::Threads)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl From<std::sync::Arc<rayon::ThreadPool>> for Parallelism {
+    fn from(pool: std::sync::Arc<rayon::ThreadPool>) -> Self {
+        Parallelism::Rayon(pool)
+    }
+}
+
+impl Parallelism {
+    /// Workers to start for `jobs` pieces of work.
+    fn workers(&self, jobs: usize) -> usize {
+        let threads = match self {
+            Parallelism::Auto => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            Parallelism::Threads(n) => n.get(),
+            #[cfg(feature = "rayon")]
+            Parallelism::Rayon(pool) => pool.current_num_threads(),
+        };
+        threads.min(jobs).max(1)
+    }
+
+    /// Run a clone of `worker` on each of up to `jobs` workers while
+    /// `main` runs on the calling thread, and return `main`'s result once
+    /// all have finished. Workers must stop once `main` has returned
+    /// (dropping the channel ends it owns).
+    fn run<W, T>(&self, jobs: usize, worker: W, main: impl FnOnce() -> T) -> T
+    where
+        W: FnOnce() + Send + Clone,
+    {
+        let workers = self.workers(jobs);
+        match self {
+            #[cfg(feature = "rayon")]
+            Parallelism::Rayon(pool) => pool.in_place_scope(|s| {
+                for _ in 0..workers {
+                    let worker = worker.clone();
+                    s.spawn(move |_| worker());
+                }
+                drop(worker);
+                main()
+            }),
+            _ => thread::scope(|s| {
+                for _ in 0..workers {
+                    s.spawn(worker.clone());
+                }
+                drop(worker);
+                main()
+            }),
+        }
+    }
+}
+
+/// Options for [`decode_many`].
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Where files are decoded, and so how many at once.
+    pub parallelism: Parallelism,
+    /// Reader options applied to every file.
+    pub builder: ApeReaderBuilder,
+}
+
+/// A completely decoded file, from [`decode_many`].
+#[derive(Debug)]
+pub struct DecodedFile {
+    /// The stream's format and length.
+    pub info: ApeInfo,
+    /// Interleaved samples.
+    pub samples: Vec<i32>,
+    /// Problems the decoder recovered from, per the builder's options.
+    pub warnings: Vec<DecodeWarning>,
+}
+
+/// One file's outcome, passed to the [`decode_many`] callback.
+#[derive(Debug)]
+pub struct BatchItem {
+    /// Position of the file in the input list.
+    pub index: usize,
+    /// The file's path.
+    pub path: PathBuf,
+    /// The decoded file, or the error that stopped it.
+    pub result: Result<DecodedFile, ApeError>,
+}
+
+/// Decode every file in `paths`, one per worker at a time, calling
+/// `callback` on the calling thread with each file's outcome as it
+/// completes (so not necessarily in input order).
+///
+/// A failing file does not stop the others. At most one decoded file per
+/// worker waits for the callback, which bounds memory use, and each worker
+/// reuses its decoder from file to file (see [`ApeReader::reset_for`]).
+///
+/// ```no_run
+/// use ape_rs::parallel::{BatchOptions, decode_many};
+///
+/// let paths = ["a.ape", "b.ape", "c.ape"];
+/// decode_many(&paths, &BatchOptions::default(), |item| match item.result {
+///     Ok(file) => println!("{}: {} samples", item.path.display(), file.samples.len()),
+///     Err(e) => eprintln!("{}: {e}", item.path.display()),
+/// });
+/// ```
+pub fn decode_many<P, F>(paths: &[P], options: &BatchOptions, mut callback: F)
+where
+    P: AsRef<Path> + Sync,
+    F: FnMut(BatchItem),
+{
+    let next = AtomicUsize::new(0);
+    let workers = options.parallelism.workers(paths.len());
+    let (tx, rx) = mpsc::sync_channel(workers);
+    let next = &next;
+    let worker = move || {
+        let mut reader = None;
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(path) = paths.get(index) else { break };
+            let path = path.as_ref();
+            let item = BatchItem {
+                index,
+                path: path.to_path_buf(),
+                result: decode_file(path, &options.builder, &mut reader),
+            };
+            if tx.send(item).is_err() {
+                break;
+            }
+        }
+    };
+    options.parallelism.run(paths.len(), worker, || {
+        for item in rx {
+            callback(item);
+        }
+    });
+}
+
+/// Decode the file at `path`, reusing the worker's previous reader (and
+/// its decoder allocations) if there is one.
+fn decode_file(
+    path: &Path,
+    builder: &ApeReaderBuilder,
+    reader: &mut Option<ApeReader<BufReader<File>>>,
+) -> Result<DecodedFile, ApeError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let header = builder.read_header(&mut file)?;
+    let reader = match reader {
+        Some(reader) => {
+            reader.reset_for(header, file)?;
+            reader
+        }
+        None => reader.insert(builder.clone().build_with_header(file, header)?),
+    };
+    let samples = reader.samples().collect::<Result<_, _>>()?;
+    Ok(DecodedFile {
+        info: reader.info.clone(),
+        samples,
+        warnings: std::mem::take(&mut reader.decoder.warnings),
+    })
+}
+
+/// What [`scan_dir`] found out about one file without decoding it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScannedFile {
+    pub info: ApeInfo,
+    /// Track information from the file.
+    pub metadata: Metadata,
+    /// Length of the stream as the header declares it.
+    pub duration: Duration,
+    /// Size of the file in bytes.
+    pub file_size: u64,
+}
+
+/// One file's outcome in [`scan_dir`].
+#[derive(Debug)]
+pub struct ScanItem {
+    /// The file's path (or a directory's, for a directory that could not
+    /// be read).
+    pub path: PathBuf,
+    pub result: Result<ScannedFile, ApeError>,
+}
+
+/// Find every `.ape` file under `root`, in all subdirectories, and parse
+/// its header and metadata on the workers `parallelism` gives, for
+/// indexing a music library.
+///
+/// Nothing is decoded, so this reads little more than each file's header
+/// and tags. Items come back sorted by path. A file that fails to parse,
+/// or a directory that cannot be listed, is an item with the error; it
+/// does not stop the scan. Symbolic links to directories are not followed.
+///
+/// ```no_run
+/// use ape_rs::parallel::scan_dir;
+///
+/// for item in scan_dir("/music", 0) {
+///     match item.result {
+///         Ok(file) => println!("{}: {:?} {:?}", item.path.display(), file.metadata.title, file.duration),
+///         Err(e) => eprintln!("{}: {e}", item.path.display()),
+///     }
+/// }
+/// ```
+pub fn scan_dir<P: AsRef<Path>>(root: P, parallelism: impl Into<Parallelism>) -> Vec<ScanItem> {
+    let parallelism = parallelism.into();
+    let mut items = Vec::new();
+    let mut files = Vec::new();
+    let mut dirs = vec![root.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                items.push(ScanItem { path: dir, result: Err(e.into()) });
+                continue;
+            }
+        };
+        for entry in entries {
+            let (path, file_type) = match entry.and_then(|e| Ok((e.path(), e.file_type()?))) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    items.push(ScanItem { path: dir.clone(), result: Err(e.into()) });
+                    continue;
+                }
+            };
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ape")) {
+                files.push(path);
+            }
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::sync_channel(parallelism.workers(files.len()));
+    let (next, files) = (&next, &files);
+    let worker = move || {
+        while let Some(path) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+            let item = ScanItem { path: path.clone(), result: scan_file(path) };
+            if tx.send(item).is_err() {
+                break;
+            }
+        }
+    };
+    parallelism.run(files.len(), worker, || items.extend(rx));
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+    items
+}
+
+/// Parse the header and metadata of the file at `path`.
+fn scan_file(path: &Path) -> Result<ScannedFile, ApeError> {
+    let mut reader = ApeReader::open(path)?;
+    let file_size = reader.decoder.reader.get_ref().metadata()?.len();
+    let info = reader.info.clone();
+    Ok(ScannedFile {
+        duration: block_time(info.total_samples / info.channels.max(1) as u64, info.sample_rate),
+        metadata: reader.metadata()?,
+        info,
+        file_size,
+    })
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// Decode the rest of the stream on the workers `parallelism` gives
+    /// (a thread count, 0 meaning one per CPU, or a [`Parallelism`]) and
+    /// write it to `out` in WAV `data` chunk byte order, in stream order,
+    /// as [`write_wav`](Self::write_wav) writes the samples.
+    ///
+    /// This thread reads compressed frames and writes the output; workers
+    /// decode. A frame that fails on a worker is decoded again here with
+    /// the reader's own error handling (resync, frame error mode,
+    /// handler), so the output is what sequential decoding would give.
+    /// Afterwards the reader is at the end of the stream.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// use ape_rs::{ApeReader, wav};
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// let mut out = BufWriter::new(File::create("track.wav").unwrap());
+    /// std::io::Write::write_all(&mut out, &wav::canonical_header(reader.info())).unwrap();
+    /// reader.decode_parallel_into(&mut out, 0).unwrap();
+    /// ```
+    pub fn decode_parallel_into<W: Write>(
+        &mut self,
+        mut out: W,
+        parallelism: impl Into<Parallelism>,
+    ) -> Result<(), ApeError> {
+        let bytes_per_sample = convert::bytes_per_sample(self.info.bits_per_sample);
+        let mut bytes = Vec::new();
+        let mut write = |samples: &[i32], out: &mut W| -> Result<(), ApeError> {
+            bytes.clear();
+            convert::pack(samples, bytes_per_sample, PcmLayout::Wav, &mut bytes);
+            Ok(out.write_all(&bytes)?)
+        };
+
+        // The rest of a partly consumed frame first
+        write(self.decoder.buffer.take_remaining(), &mut out)?;
+        let (first, _, _) = self.decoder.position();
+        let total = self.frame_count();
+        let parallelism = parallelism.into();
+        let jobs = total.saturating_sub(first) as usize;
+        let workers = parallelism.workers(jobs);
+        let info = self.info.clone();
+
+        let (job_tx, job_rx) = mpsc::channel::<(u32, Vec<u8>, u32, u32)>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_rx = Mutex::new(job_rx);
+        let job_rx = &job_rx;
+        let info = &info;
+        let worker = move || {
+            let mut decoder = PacketDecoder::from_header(info.channels, info.bits_per_sample, info.compression_level);
+            loop {
+                let Ok((frame, data, skip, blocks)) = job_rx.lock().unwrap().recv() else { break };
+                let result = decoder.decode_packet(&data, skip, blocks).ok();
+                if result_tx.send((frame, result)).is_err() {
+                    break;
+                }
+            }
+        };
+        parallelism.run(jobs, worker, || -> Result<(), ApeError> {
+            // Owned here so that returning early also stops the workers
+            let job_tx = job_tx;
+
+            // Keep a few frames per worker in flight, and write results
+            // as soon as the next one in order is in
+            let window = workers as u32 * 2;
+            let (mut next_read, mut next_write) = (first, first);
+            let mut done: BTreeMap<u32, Option<Vec<i32>>> = BTreeMap::new();
+            while next_write < total {
+                while next_read < total && next_read - next_write < window {
+                    match self.read_raw_frame(next_read) {
+                        Ok(Some(raw)) => {
+                            // Workers only stop if this thread has returned
+                            let _ = job_tx.send((next_read, raw.data, raw.info.skip, raw.info.blocks));
+                        }
+                        _ => {
+                            done.insert(next_read, None);
+                        }
+                    }
+                    next_read += 1;
+                }
+                while let Some(samples) = done.remove(&next_write) {
+                    match samples {
+                        Some(samples) => write(&samples, &mut out)?,
+                        None => {
+                            // Decode it here, with resync and error handling
+                            let (_, _, shift) = self.decoder.position();
+                            self.decoder.restore_position(next_write, 0, shift)?;
+                            if self.decoder.decode_next_frame()? {
+                                write(self.decoder.buffer.take_remaining(), &mut out)?;
+                            }
+                        }
+                    }
+                    next_write += 1;
+                }
+                if next_write < next_read {
+                    let Ok((frame, samples)) = result_rx.recv() else { break };
+                    done.insert(frame, samples);
+                }
+            }
+            Ok(())
+        })?;
+
+        let (_, _, shift) = self.decoder.position();
+        self.decoder.restore_position(total, 0, shift)?;
+        out.flush()?;
+        Ok(())
+    }
+}
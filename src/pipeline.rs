@@ -0,0 +1,475 @@
+//! Streaming transcodes: decoder → stages → sink.
+//!
+//! [`ApeReader::pipeline`] feeds the decoded stream, one frame at a time,
+//! through any number of [`Stage`]s (gain and dither, channel operations,
+//! sample rate conversion) into a [`Sink`] such as a [`WavSink`]. Memory
+//! stays bounded by the frame size whatever the length of the file: each
+//! stage keeps one output buffer, reused from chunk to chunk.
+//!
+//! Stages and sinks are traits, so formats and processing this crate does
+//! not provide (a FLAC encoder, a limiter) plug in the same way.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufWriter;
+//! use ape_rs::ApeReader;
+//! use ape_rs::dither::DitherOptions;
+//! use ape_rs::pipeline::{Requantize, ToMono, WavSink};
+//!
+//! let mut reader = ApeReader::open("track.ape").unwrap();
+//! let mut sink = WavSink::new(BufWriter::new(File::create("mono16.wav").unwrap()));
+//! reader
+//!     .pipeline()
+//!     .stage(ToMono::new())
+//!     .stage(Requantize::new(16, DitherOptions::default()))
+//!     .run(&mut sink)
+//!     .unwrap();
+//! ```
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::dither::{DitherOptions, Requantizer};
+use crate::error::ApeError;
+use crate::mix::Downmixer;
+use crate::{ApeInfo, ApeReader, convert, wav};
+
+#[cfg(feature = "rubato")]
+use rubato::{FftFixedIn, Resampler};
+
+/// Input blocks the resampler takes per call.
+#[cfg(feature = "rubato")]
+const RESAMPLE_CHUNK_BLOCKS: usize = 1024;
+
+/// Format of the samples passed between stages: interleaved signed
+/// integers, as the decoder produces them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PcmFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Significant bits per sample (1 to 32).
+    pub bits_per_sample: u16,
+}
+
+impl From<&ApeInfo> for PcmFormat {
+    fn from(info: &ApeInfo) -> Self {
+        PcmFormat {
+            sample_rate: info.sample_rate,
+            channels: info.channels,
+            bits_per_sample: info.bits_per_sample,
+        }
+    }
+}
+
+/// One processing step of a pipeline.
+pub trait Stage {
+    /// Prepare for input in `format` and return the format of the output.
+    /// Called once, before any samples; fails if the stage cannot handle
+    /// the format.
+    fn configure(&mut self, format: PcmFormat) -> Result<PcmFormat, ApeError>;
+
+    /// Process `input`, appending the output to `out`. Chunks may end
+    /// between the samples of a block.
+    fn process(&mut self, input: &[i32], out: &mut Vec<i32>) -> Result<(), ApeError>;
+
+    /// Append whatever the stage still holds at the end of the stream.
+    fn finish(&mut self, out: &mut Vec<i32>) -> Result<(), ApeError> {
+        let _ = out;
+        Ok(())
+    }
+}
+
+/// Where a pipeline's output goes.
+pub trait Sink {
+    /// Called once, with the format of the samples to come.
+    fn begin(&mut self, format: PcmFormat) -> Result<(), ApeError>;
+
+    /// Take the next interleaved samples.
+    fn write(&mut self, samples: &[i32]) -> Result<(), ApeError>;
+
+    /// Called once after the last samples.
+    fn finish(&mut self) -> Result<(), ApeError>;
+}
+
+/// Collects the samples, for tests and short streams.
+impl Sink for Vec<i32> {
+    fn begin(&mut self, _format: PcmFormat) -> Result<(), ApeError> {
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[i32]) -> Result<(), ApeError> {
+        self.extend_from_slice(samples);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ApeError> {
+        Ok(())
+    }
+}
+
+/// A decoder feeding a chain of stages, from [`ApeReader::pipeline`].
+pub struct Pipeline<'a, R: Read + Seek> {
+    reader: &'a mut ApeReader<R>,
+    stages: Vec<Box<dyn Stage + 'a>>,
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// Start a pipeline over the rest of the stream: add stages with
+    /// [`Pipeline::stage`], then [`run`](Pipeline::run) it into a sink.
+    pub fn pipeline(&mut self) -> Pipeline<'_, R> {
+        Pipeline { reader: self, stages: Vec::new() }
+    }
+}
+
+impl<'a, R: Read + Seek> Pipeline<'a, R> {
+    /// Append a stage; stages run in the order added.
+    pub fn stage(mut self, stage: impl Stage + 'a) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Decode the rest of the stream through the stages into `sink`, and
+    /// return the format the sink received.
+    ///
+    /// Frames are handled per the reader's frame error mode. On an error
+    /// the sink is left unfinished.
+    pub fn run<S: Sink + ?Sized>(mut self, sink: &mut S) -> Result<PcmFormat, ApeError> {
+        let mut format = PcmFormat::from(self.reader.info());
+        for stage in &mut self.stages {
+            format = stage.configure(format)?;
+        }
+        sink.begin(format)?;
+
+        let mut buffers = vec![Vec::new(); self.stages.len()];
+        let mut error = None;
+        self.reader.for_each_chunk_while(|chunk| {
+            let result = push(&mut self.stages, &mut buffers, chunk, sink);
+            result.map_err(|e| error = Some(e)).is_ok()
+        })?;
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        // Flush each stage in turn through the ones after it
+        for i in 0..self.stages.len() {
+            let mut held = Vec::new();
+            self.stages[i].finish(&mut held)?;
+            push(&mut self.stages[i + 1..], &mut buffers[i + 1..], &held, sink)?;
+        }
+        sink.finish()?;
+        Ok(format)
+    }
+}
+
+/// Run `input` through `stages` into `sink`, with one output buffer per
+/// stage.
+fn push<S: Sink + ?Sized>(
+    stages: &mut [Box<dyn Stage + '_>],
+    buffers: &mut [Vec<i32>],
+    input: &[i32],
+    sink: &mut S,
+) -> Result<(), ApeError> {
+    let mut data = input;
+    for (stage, buffer) in stages.iter_mut().zip(buffers.iter_mut()) {
+        buffer.clear();
+        stage.process(data, buffer)?;
+        data = buffer;
+    }
+    if data.is_empty() { Ok(()) } else { sink.write(data) }
+}
+
+/// Gain, dither and bit-depth conversion with a [`Requantizer`].
+#[derive(Debug, Clone)]
+pub struct Requantize {
+    bits: Option<u16>,
+    options: DitherOptions,
+    requantizer: Option<Requantizer>,
+}
+
+impl Requantize {
+    /// Convert to `bits` bits per sample, with `options`' gain and dither.
+    pub fn new(bits: u16, options: DitherOptions) -> Self {
+        Requantize { bits: Some(bits), options, requantizer: None }
+    }
+
+    /// Apply `gain_db` at the input's bit depth, with TPDF dither.
+    pub fn gain(gain_db: f64) -> Self {
+        Requantize { bits: None, options: DitherOptions { gain_db, ..DitherOptions::default() }, requantizer: None }
+    }
+}
+
+impl Stage for Requantize {
+    fn configure(&mut self, format: PcmFormat) -> Result<PcmFormat, ApeError> {
+        let bits = self.bits.unwrap_or(format.bits_per_sample);
+        if !(1..=32).contains(&bits) {
+            return Err(ApeError::FormatMismatch(format!("cannot requantize to {bits} bits")));
+        }
+        self.requantizer = Some(Requantizer::new(format.channels, format.bits_per_sample, bits, self.options));
+        Ok(PcmFormat { bits_per_sample: bits, ..format })
+    }
+
+    fn process(&mut self, input: &[i32], out: &mut Vec<i32>) -> Result<(), ApeError> {
+        let requantizer = self.requantizer.as_mut().expect("configured before use");
+        out.extend(input.iter().map(|&s| requantizer.next_sample(s)));
+        Ok(())
+    }
+}
+
+/// Stereo to mono as `(L + R) / 2` (see [`mix`](crate::mix)). Mono passes
+/// through.
+#[derive(Debug, Clone, Default)]
+pub struct ToMono {
+    downmixer: Option<Downmixer>,
+}
+
+impl ToMono {
+    pub fn new() -> Self {
+        ToMono::default()
+    }
+}
+
+impl Stage for ToMono {
+    fn configure(&mut self, format: PcmFormat) -> Result<PcmFormat, ApeError> {
+        if format.channels > 2 {
+            return Err(ApeError::FormatMismatch(format!("cannot downmix {} channels", format.channels)));
+        }
+        self.downmixer = Some(Downmixer::new(format.channels));
+        Ok(PcmFormat { channels: 1, ..format })
+    }
+
+    fn process(&mut self, input: &[i32], out: &mut Vec<i32>) -> Result<(), ApeError> {
+        self.downmixer.as_mut().expect("configured before use").process(input, out);
+        Ok(())
+    }
+}
+
+/// Keep one channel (0-based) and drop the others.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractChannel {
+    channel: u16,
+    channels: u16,
+    /// Index within the current block of the next input sample.
+    position: u16,
+}
+
+impl ExtractChannel {
+    /// Keep channel `channel`, counting from 0.
+    pub fn new(channel: u16) -> Self {
+        ExtractChannel { channel, channels: 1, position: 0 }
+    }
+}
+
+impl Stage for ExtractChannel {
+    fn configure(&mut self, format: PcmFormat) -> Result<PcmFormat, ApeError> {
+        if self.channel >= format.channels {
+            return Err(ApeError::FormatMismatch(format!(
+                "no channel {} in a {}-channel stream",
+                self.channel, format.channels
+            )));
+        }
+        self.channels = format.channels;
+        Ok(PcmFormat { channels: 1, ..format })
+    }
+
+    fn process(&mut self, input: &[i32], out: &mut Vec<i32>) -> Result<(), ApeError> {
+        for &sample in input {
+            if self.position == self.channel {
+                out.push(sample);
+            }
+            self.position = (self.position + 1) % self.channels;
+        }
+        Ok(())
+    }
+}
+
+/// Sample rate conversion with [`rubato`]'s FFT resampler (feature
+/// `rubato`), time-aligned as [`ApeReader::resampled`] is.
+#[cfg(feature = "rubato")]
+pub struct Resample {
+    sample_rate: u32,
+    state: Option<ResampleState>,
+}
+
+#[cfg(feature = "rubato")]
+struct ResampleState {
+    resampler: FftFixedIn<f32>,
+    bits_per_sample: u16,
+    input_rate: u32,
+    input: Vec<Vec<f32>>,
+    output: Vec<Vec<f32>>,
+    /// Channel of the next input sample.
+    channel: usize,
+    /// Output blocks of resampler delay still to drop.
+    skip: usize,
+    /// Input blocks taken, and output blocks emitted.
+    blocks_in: u64,
+    blocks_out: u64,
+}
+
+#[cfg(feature = "rubato")]
+impl Resample {
+    /// Resample to `sample_rate` Hz.
+    pub fn new(sample_rate: u32) -> Self {
+        Resample { sample_rate, state: None }
+    }
+}
+
+#[cfg(feature = "rubato")]
+impl ResampleState {
+    /// Interleave the `blocks` blocks in `output`, less any delay still to
+    /// drop and anything past `limit` blocks, into `out`.
+    fn emit(&mut self, blocks: usize, limit: u64, out: &mut Vec<i32>) {
+        let skipped = self.skip.min(blocks);
+        self.skip -= skipped;
+        let blocks = (blocks - skipped).min((limit - self.blocks_out.min(limit)) as usize);
+        for block in skipped..skipped + blocks {
+            out.extend(self.output.iter().map(|ch| convert::from_f32(ch[block], self.bits_per_sample)));
+        }
+        self.blocks_out += blocks as u64;
+    }
+}
+
+#[cfg(feature = "rubato")]
+impl Stage for Resample {
+    fn configure(&mut self, format: PcmFormat) -> Result<PcmFormat, ApeError> {
+        let channels = format.channels.max(1) as usize;
+        let (from, to) = (format.sample_rate, self.sample_rate);
+        let resampler = FftFixedIn::new(from as usize, to as usize, RESAMPLE_CHUNK_BLOCKS, 2, channels)
+            .map_err(|e| ApeError::FormatMismatch(format!("cannot resample {from} Hz to {to} Hz: {e}")))?;
+        self.state = Some(ResampleState {
+            bits_per_sample: format.bits_per_sample,
+            input_rate: from,
+            input: vec![Vec::with_capacity(RESAMPLE_CHUNK_BLOCKS); channels],
+            output: resampler.output_buffer_allocate(true),
+            skip: resampler.output_delay(),
+            resampler,
+            channel: 0,
+            blocks_in: 0,
+            blocks_out: 0,
+        });
+        Ok(PcmFormat { sample_rate: to, ..format })
+    }
+
+    fn process(&mut self, input: &[i32], out: &mut Vec<i32>) -> Result<(), ApeError> {
+        let state = self.state.as_mut().expect("configured before use");
+        for &sample in input {
+            state.input[state.channel].push(convert::to_f32(sample, state.bits_per_sample));
+            state.channel += 1;
+            if state.channel < state.input.len() {
+                continue;
+            }
+            state.channel = 0;
+            if state.input[0].len() == state.resampler.input_frames_next() {
+                let (_, blocks) = state
+                    .resampler
+                    .process_into_buffer(&state.input, &mut state.output, None)
+                    .expect("resampler buffers have the sizes it asked for");
+                state.blocks_in += state.input[0].len() as u64;
+                state.input.iter_mut().for_each(Vec::clear);
+                state.emit(blocks, u64::MAX, out);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut Vec<i32>) -> Result<(), ApeError> {
+        let sample_rate = self.sample_rate as u64;
+        let state = self.state.as_mut().expect("configured before use");
+        // An incomplete final block is dropped
+        let blocks_read = state.input.iter().map(Vec::len).min().unwrap_or(0);
+        state.input.iter_mut().for_each(|ch| ch.truncate(blocks_read));
+        state.blocks_in += blocks_read as u64;
+        let due = (state.blocks_in * sample_rate).div_ceil(state.input_rate as u64);
+
+        let mut rest = (blocks_read > 0).then(|| core::mem::take(&mut state.input));
+        while state.blocks_out < due {
+            let (_, blocks) = state
+                .resampler
+                .process_partial_into_buffer(rest.take().as_deref(), &mut state.output, None)
+                .expect("resampler buffers have the sizes it asked for");
+            state.emit(blocks, due, out);
+        }
+        Ok(())
+    }
+}
+
+/// Writes a WAV file with a canonical header, whose sizes are filled in
+/// by [`Sink::finish`]. An odd-sized `data` chunk gets the pad byte RIFF
+/// requires.
+#[derive(Debug)]
+pub struct WavSink<W: Write + Seek> {
+    out: W,
+    format: Option<PcmFormat>,
+    samples: u64,
+    bytes: Vec<u8>,
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    /// Write to `out`, from its current position.
+    pub fn new(out: W) -> Self {
+        WavSink { out, format: None, samples: 0, bytes: Vec::new() }
+    }
+
+    /// The writer, after the pipeline has run.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn data_bytes(&self) -> u64 {
+        let bits = self.format.map_or(16, |f| f.bits_per_sample);
+        self.samples * convert::bytes_per_sample(bits) as u64
+    }
+
+    /// The RIFF pad byte after an odd-sized `data` chunk: 0 or 1 bytes.
+    fn pad_bytes(&self) -> u64 {
+        self.data_bytes() & 1
+    }
+
+    fn header(&self) -> [u8; wav::CANONICAL_HEADER_LEN] {
+        let format = self.format.expect("begun before use");
+        let mut header = wav::canonical_header(&ApeInfo {
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            bits_per_sample: format.bits_per_sample,
+            total_samples: self.samples,
+            compression_level: 0,
+            format_version: 0,
+            channel_mask: None,
+        });
+        // The RIFF size covers the pad byte; the data size does not
+        let riff_len = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        header[4..8].copy_from_slice(&riff_len.saturating_add(self.pad_bytes() as u32).to_le_bytes());
+        header
+    }
+}
+
+impl<W: Write + Seek> Sink for WavSink<W> {
+    fn begin(&mut self, format: PcmFormat) -> Result<(), ApeError> {
+        self.format = Some(format);
+        self.samples = 0;
+        self.out.write_all(&self.header())?;
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[i32]) -> Result<(), ApeError> {
+        let bits = self.format.expect("begun before use").bits_per_sample;
+        self.bytes.clear();
+        convert::pack(samples, convert::bytes_per_sample(bits), convert::PcmLayout::Wav, &mut self.bytes);
+        self.out.write_all(&self.bytes)?;
+        self.samples += samples.len() as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ApeError> {
+        if self.pad_bytes() == 1 {
+            self.out.write_all(&[0])?;
+        }
+        let end = self.out.stream_position()?;
+        let len = wav::CANONICAL_HEADER_LEN as u64 + self.data_bytes() + self.pad_bytes();
+        self.out.seek(SeekFrom::Start(end - len))?;
+        self.out.write_all(&self.header())?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
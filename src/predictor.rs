@@ -6,6 +6,11 @@
 //! Based on FFmpeg's predictor_decode_mono_3950 / predictor_update_filter
 //! (v3990 uses the v3950 predictor).
 
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::arith::Wrap;
+
 const HISTORY_SIZE: usize = 512;
 const PREDICTOR_SIZE: usize = 50;
 
@@ -43,6 +48,12 @@ pub struct Predictor {
     coeffs_b: [[i64; 5]; 2],
 }
 
+impl Default for Predictor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Predictor {
     pub fn new() -> Self {
         Predictor {
@@ -56,6 +67,11 @@ impl Predictor {
         }
     }
 
+    /// Heap bytes held by the history buffer.
+    pub fn memory_bytes(&self) -> usize {
+        self.buf.capacity() * size_of::<i64>()
+    }
+
     /// Reset predictor state at frame boundaries.
     pub fn reset(&mut self) {
         self.buf.fill(0);
@@ -69,24 +85,48 @@ impl Predictor {
 
     /// Decode a mono sample.
     pub fn decode_mono(&mut self, input: i32) -> i32 {
-        let a = input as i64;
+        let prediction = self.predict_mono();
+        self.update_mono(input as i64, prediction)
+    }
+
+    /// Encode a mono sample: the input for which [`decode_mono`](Self::decode_mono)
+    /// returns `sample`, advancing the state as decoding it would.
+    #[cfg(feature = "testgen")]
+    pub fn encode_mono(&mut self, sample: i32) -> i32 {
+        let prediction = self.predict_mono();
+        let input = (sample as i64)
+            .wrap_sub(self.filter_a[0].wrap_mul(31) >> 5)
+            .wrap_sub(prediction) as i32;
+        self.update_mono(input as i64, prediction);
+        input
+    }
+
+    /// Fill the delay line for the next mono sample and return the
+    /// prediction added to its input (none of this depends on the input).
+    fn predict_mono(&mut self) -> i64 {
         let bp = self.buf_pos;
 
         // Write current prediction to delay line
         self.buf[bp + YDELAYA] = self.last_a[0];
         // Compute delta (overwrites previous value at that position)
         self.buf[bp + YDELAYA - 1] =
-            self.buf[bp + YDELAYA].wrapping_sub(self.buf[bp + YDELAYA - 1]);
+            self.buf[bp + YDELAYA].wrap_sub(self.buf[bp + YDELAYA - 1]);
 
         // Prediction from 4 delayed values
         let prediction_a: i64 =
-            self.buf[bp + YDELAYA]     .wrapping_mul(self.coeffs_a[0][0])
-            .wrapping_add(self.buf[bp + YDELAYA - 1].wrapping_mul(self.coeffs_a[0][1]))
-            .wrapping_add(self.buf[bp + YDELAYA - 2].wrapping_mul(self.coeffs_a[0][2]))
-            .wrapping_add(self.buf[bp + YDELAYA - 3].wrapping_mul(self.coeffs_a[0][3]));
+            self.buf[bp + YDELAYA]     .wrap_mul(self.coeffs_a[0][0])
+            .wrap_add(self.buf[bp + YDELAYA - 1].wrap_mul(self.coeffs_a[0][1]))
+            .wrap_add(self.buf[bp + YDELAYA - 2].wrap_mul(self.coeffs_a[0][2]))
+            .wrap_add(self.buf[bp + YDELAYA - 3].wrap_mul(self.coeffs_a[0][3]));
+        prediction_a >> 10
+    }
+
+    /// Reconstruct a mono sample from its input and prediction, then adapt.
+    fn update_mono(&mut self, a: i64, prediction: i64) -> i32 {
+        let bp = self.buf_pos;
 
-        // Reconstruct: output = input + (prediction >> 10)
-        let current_a = a.wrapping_add(prediction_a >> 10);
+        // Reconstruct: output = input + prediction
+        let current_a = a.wrap_add(prediction);
         self.last_a[0] = current_a;
 
         // Write adaptation signs
@@ -97,13 +137,13 @@ impl Predictor {
         let sign = apesign(a);
         if sign != 0 {
             self.coeffs_a[0][0] = self.coeffs_a[0][0]
-                .wrapping_add(self.buf[bp + YADAPTCOEFFSA].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + YADAPTCOEFFSA].wrap_mul(sign));
             self.coeffs_a[0][1] = self.coeffs_a[0][1]
-                .wrapping_add(self.buf[bp + YADAPTCOEFFSA - 1].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + YADAPTCOEFFSA - 1].wrap_mul(sign));
             self.coeffs_a[0][2] = self.coeffs_a[0][2]
-                .wrapping_add(self.buf[bp + YADAPTCOEFFSA - 2].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + YADAPTCOEFFSA - 2].wrap_mul(sign));
             self.coeffs_a[0][3] = self.coeffs_a[0][3]
-                .wrapping_add(self.buf[bp + YADAPTCOEFFSA - 3].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + YADAPTCOEFFSA - 3].wrap_mul(sign));
         }
 
         // Advance buffer
@@ -124,7 +164,7 @@ impl Predictor {
 
         // IIR feedback filter: filterA = currentA + (filterA * 31) >> 5
         self.filter_a[0] = current_a
-            .wrapping_add(self.filter_a[0].wrapping_mul(31) >> 5);
+            .wrap_add(self.filter_a[0].wrap_mul(31) >> 5);
 
         self.filter_a[0] as i32
     }
@@ -132,14 +172,53 @@ impl Predictor {
     /// Decode a stereo sample pair. Returns (left, right).
     pub fn decode_stereo(&mut self, input_y: i32, input_x: i32) -> (i32, i32) {
         // Y channel (channel 0)
-        let decoded_y = self.update_filter(input_y as i64, 0, YDELAYA, YDELAYB,
-                                           YADAPTCOEFFSA, YADAPTCOEFFSB);
+        let prediction = self.predict_channel(0, YDELAYA, YDELAYB, YADAPTCOEFFSA, YADAPTCOEFFSB);
+        let decoded_y = self.update_channel(input_y as i64, prediction, 0, YADAPTCOEFFSA, YADAPTCOEFFSB);
 
         // X channel (channel 1)
-        let decoded_x = self.update_filter(input_x as i64, 1, XDELAYA, XDELAYB,
-                                           XADAPTCOEFFSA, XADAPTCOEFFSB);
+        let prediction = self.predict_channel(1, XDELAYA, XDELAYB, XADAPTCOEFFSA, XADAPTCOEFFSB);
+        let decoded_x = self.update_channel(input_x as i64, prediction, 1, XADAPTCOEFFSA, XADAPTCOEFFSB);
 
-        // Advance buffer
+        self.advance_stereo();
+
+        // Inverse channel decorrelation
+        let left = decoded_x.wrap_sub(decoded_y / 2) as i32;
+        let right = left.wrap_add(decoded_y as i32);
+
+        (left, right)
+    }
+
+    /// Encode a stereo sample pair: the inputs `(y, x)` for which
+    /// [`decode_stereo`](Self::decode_stereo) returns `(left, right)`,
+    /// advancing the state as decoding them would.
+    #[cfg(feature = "testgen")]
+    pub fn encode_stereo(&mut self, left: i32, right: i32) -> (i32, i32) {
+        // Channel decorrelation
+        let decoded_y = (right as i64).wrap_sub(left as i64);
+        let decoded_x = (left as i64).wrap_add(decoded_y / 2);
+
+        let prediction = self.predict_channel(0, YDELAYA, YDELAYB, YADAPTCOEFFSA, YADAPTCOEFFSB);
+        let input_y = self.channel_input(decoded_y, prediction, 0);
+        self.update_channel(input_y as i64, prediction, 0, YADAPTCOEFFSA, YADAPTCOEFFSB);
+
+        let prediction = self.predict_channel(1, XDELAYA, XDELAYB, XADAPTCOEFFSA, XADAPTCOEFFSB);
+        let input_x = self.channel_input(decoded_x, prediction, 1);
+        self.update_channel(input_x as i64, prediction, 1, XADAPTCOEFFSA, XADAPTCOEFFSB);
+
+        self.advance_stereo();
+        (input_y, input_x)
+    }
+
+    /// The input for which `update_channel` returns `decoded`.
+    #[cfg(feature = "testgen")]
+    fn channel_input(&self, decoded: i64, prediction: i64, ch: usize) -> i32 {
+        decoded
+            .wrap_sub(self.filter_a[ch].wrap_mul(31) >> 5)
+            .wrap_sub(prediction) as i32
+    }
+
+    /// Advance the shared history buffer after both stereo channels.
+    fn advance_stereo(&mut self) {
         self.buf_pos += 1;
         if self.buf_pos >= HISTORY_SIZE {
             for i in 0..PREDICTOR_SIZE {
@@ -150,18 +229,13 @@ impl Predictor {
             }
             self.buf_pos = 0;
         }
-
-        // Inverse channel decorrelation
-        let left = decoded_x.wrapping_sub(decoded_y / 2) as i32;
-        let right = left.wrapping_add(decoded_y as i32);
-
-        (left, right)
     }
 
-    /// Apply prediction filter for one sample on one channel (stereo path).
-    fn update_filter(
+    /// Fill the delay lines for one channel's next sample (stereo path)
+    /// and return the prediction added to its input (none of this depends
+    /// on the input).
+    fn predict_channel(
         &mut self,
-        decoded: i64,
         ch: usize,
         delay_a: usize,
         delay_b: usize,
@@ -174,63 +248,70 @@ impl Predictor {
         self.buf[bp + delay_a] = self.last_a[ch];
         self.buf[bp + adapt_a] = apesign(self.buf[bp + delay_a]);
         self.buf[bp + delay_a - 1] =
-            self.buf[bp + delay_a].wrapping_sub(self.buf[bp + delay_a - 1]);
+            self.buf[bp + delay_a].wrap_sub(self.buf[bp + delay_a - 1]);
         self.buf[bp + adapt_a - 1] = apesign(self.buf[bp + delay_a - 1]);
 
         let prediction_a: i64 =
-            self.buf[bp + delay_a]    .wrapping_mul(self.coeffs_a[ch][0])
-            .wrapping_add(self.buf[bp + delay_a - 1].wrapping_mul(self.coeffs_a[ch][1]))
-            .wrapping_add(self.buf[bp + delay_a - 2].wrapping_mul(self.coeffs_a[ch][2]))
-            .wrapping_add(self.buf[bp + delay_a - 3].wrapping_mul(self.coeffs_a[ch][3]));
+            self.buf[bp + delay_a]    .wrap_mul(self.coeffs_a[ch][0])
+            .wrap_add(self.buf[bp + delay_a - 1].wrap_mul(self.coeffs_a[ch][1]))
+            .wrap_add(self.buf[bp + delay_a - 2].wrap_mul(self.coeffs_a[ch][2]))
+            .wrap_add(self.buf[bp + delay_a - 3].wrap_mul(self.coeffs_a[ch][3]));
 
         // Filter B: cross-channel prediction
         // B delay stores: filterA of the OTHER channel - IIR(filterB)
         self.buf[bp + delay_b] = self.filter_a[ch ^ 1]
-            .wrapping_sub(self.filter_b[ch].wrapping_mul(31) >> 5);
+            .wrap_sub(self.filter_b[ch].wrap_mul(31) >> 5);
         self.buf[bp + adapt_b] = apesign(self.buf[bp + delay_b]);
         self.buf[bp + delay_b - 1] =
-            self.buf[bp + delay_b].wrapping_sub(self.buf[bp + delay_b - 1]);
+            self.buf[bp + delay_b].wrap_sub(self.buf[bp + delay_b - 1]);
         self.buf[bp + adapt_b - 1] = apesign(self.buf[bp + delay_b - 1]);
         self.filter_b[ch] = self.filter_a[ch ^ 1];
 
         let prediction_b: i64 =
-            self.buf[bp + delay_b]    .wrapping_mul(self.coeffs_b[ch][0])
-            .wrapping_add(self.buf[bp + delay_b - 1].wrapping_mul(self.coeffs_b[ch][1]))
-            .wrapping_add(self.buf[bp + delay_b - 2].wrapping_mul(self.coeffs_b[ch][2]))
-            .wrapping_add(self.buf[bp + delay_b - 3].wrapping_mul(self.coeffs_b[ch][3]))
-            .wrapping_add(self.buf[bp + delay_b - 4].wrapping_mul(self.coeffs_b[ch][4]));
+            self.buf[bp + delay_b]    .wrap_mul(self.coeffs_b[ch][0])
+            .wrap_add(self.buf[bp + delay_b - 1].wrap_mul(self.coeffs_b[ch][1]))
+            .wrap_add(self.buf[bp + delay_b - 2].wrap_mul(self.coeffs_b[ch][2]))
+            .wrap_add(self.buf[bp + delay_b - 3].wrap_mul(self.coeffs_b[ch][3]))
+            .wrap_add(self.buf[bp + delay_b - 4].wrap_mul(self.coeffs_b[ch][4]));
+
+        (prediction_a.wrap_add(prediction_b >> 1)) >> 10
+    }
+
+    /// Reconstruct one channel's sample from its input and prediction,
+    /// then adapt (stereo path).
+    fn update_channel(&mut self, decoded: i64, prediction: i64, ch: usize, adapt_a: usize, adapt_b: usize) -> i64 {
+        let bp = self.buf_pos;
 
         // Reconstruct
-        self.last_a[ch] = decoded
-            .wrapping_add((prediction_a.wrapping_add(prediction_b >> 1)) >> 10);
+        self.last_a[ch] = decoded.wrap_add(prediction);
 
         // IIR feedback
         self.filter_a[ch] = self.last_a[ch]
-            .wrapping_add(self.filter_a[ch].wrapping_mul(31) >> 5);
+            .wrap_add(self.filter_a[ch].wrap_mul(31) >> 5);
 
         // Adapt coefficients A
         let sign = apesign(decoded);
         if sign != 0 {
             self.coeffs_a[ch][0] = self.coeffs_a[ch][0]
-                .wrapping_add(self.buf[bp + adapt_a].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_a].wrap_mul(sign));
             self.coeffs_a[ch][1] = self.coeffs_a[ch][1]
-                .wrapping_add(self.buf[bp + adapt_a - 1].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_a - 1].wrap_mul(sign));
             self.coeffs_a[ch][2] = self.coeffs_a[ch][2]
-                .wrapping_add(self.buf[bp + adapt_a - 2].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_a - 2].wrap_mul(sign));
             self.coeffs_a[ch][3] = self.coeffs_a[ch][3]
-                .wrapping_add(self.buf[bp + adapt_a - 3].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_a - 3].wrap_mul(sign));
 
             // Adapt coefficients B
             self.coeffs_b[ch][0] = self.coeffs_b[ch][0]
-                .wrapping_add(self.buf[bp + adapt_b].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_b].wrap_mul(sign));
             self.coeffs_b[ch][1] = self.coeffs_b[ch][1]
-                .wrapping_add(self.buf[bp + adapt_b - 1].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_b - 1].wrap_mul(sign));
             self.coeffs_b[ch][2] = self.coeffs_b[ch][2]
-                .wrapping_add(self.buf[bp + adapt_b - 2].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_b - 2].wrap_mul(sign));
             self.coeffs_b[ch][3] = self.coeffs_b[ch][3]
-                .wrapping_add(self.buf[bp + adapt_b - 3].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_b - 3].wrap_mul(sign));
             self.coeffs_b[ch][4] = self.coeffs_b[ch][4]
-                .wrapping_add(self.buf[bp + adapt_b - 4].wrapping_mul(sign));
+                .wrap_add(self.buf[bp + adapt_b - 4].wrap_mul(sign));
         }
 
         self.filter_a[ch]
@@ -4,6 +4,18 @@
 //! - normalize() is called at the START of each decode (culfreq/culshift)
 //! - update() does NOT normalize
 //! - The buffer register carries bits between bytes via EXTRA_BITS mechanism
+//!
+//! Desynchronization (reading past the end of the frame, or decoding a value
+//! outside the current interval) is latched rather than returned from every
+//! call; the frame decoder polls `check()` once per block.
+
+use alloc::format;
+#[cfg(feature = "testgen")]
+use alloc::vec;
+#[cfg(feature = "testgen")]
+use alloc::vec::Vec;
+
+use crate::error::ApeError;
 
 // ── Range coder constants ────────────────────────────────────────────
 
@@ -37,6 +49,12 @@ pub struct RiceState {
     pub ksum: u32,
 }
 
+impl Default for RiceState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RiceState {
     pub fn new() -> Self {
         RiceState {
@@ -84,6 +102,13 @@ pub struct RangeCoder<'a> {
     pub low: u32,
     pub range: u32,
     help: u32,
+    /// Set once a byte past the end of `data` has been requested.
+    overrun: bool,
+    /// Set once a decoded value fell outside the coding interval.
+    invalid_symbol: bool,
+    /// Escape symbols decoded so far (values too large for the model).
+    #[cfg(feature = "debug-dump")]
+    pub(crate) escapes: u32,
 }
 
 impl<'a> RangeCoder<'a> {
@@ -97,6 +122,10 @@ impl<'a> RangeCoder<'a> {
             low: 0,
             range: 1u32 << EXTRA_BITS,
             help: 0,
+            overrun: false,
+            invalid_symbol: false,
+            #[cfg(feature = "debug-dump")]
+            escapes: 0,
         };
 
         // Read first byte into buffer, extract EXTRA_BITS for low
@@ -107,17 +136,37 @@ impl<'a> RangeCoder<'a> {
         rc
     }
 
-    /// Read the next byte, returning 0 on EOF.
+    /// Read the next byte, returning 0 (and flagging overrun) on EOF.
     fn read_byte(&mut self) -> u8 {
         if self.pos < self.data.len() {
             let b = self.data[self.pos];
             self.pos += 1;
             b
         } else {
+            self.overrun = true;
             0
         }
     }
 
+    /// Report desynchronization detected so far, tagged with the block index
+    /// being decoded when it was noticed.
+    pub fn check(&self, block: u32) -> Result<(), ApeError> {
+        if self.overrun {
+            Err(ApeError::RangeCoderError(format!(
+                "read past end of frame data ({} bytes) at block {block}",
+                self.data.len()
+            )))
+        } else if self.invalid_symbol {
+            Err(ApeError::RangeCoderError(format!(
+                "decoded value out of range at block {block} (byte {} of {})",
+                self.pos,
+                self.data.len()
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Renormalize: expand range by reading bytes until range > BOTTOM_VALUE.
     fn normalize(&mut self) {
         while self.range <= BOTTOM_VALUE {
@@ -127,6 +176,13 @@ impl<'a> RangeCoder<'a> {
         }
     }
 
+    /// Final renormalization at the end of a frame. Returns the number of
+    /// bytes consumed, which is the offset of the next frame's header.
+    pub fn finish(&mut self) -> usize {
+        self.normalize();
+        self.pos
+    }
+
     /// FFmpeg's range_decode_culshift: normalize, then decode uniform in [0, 2^shift).
     fn culshift(&mut self, shift: u32) -> u32 {
         self.normalize();
@@ -138,7 +194,11 @@ impl<'a> RangeCoder<'a> {
     fn culfreq(&mut self, tot_f: u32) -> u32 {
         self.normalize();
         self.help = self.range / tot_f;
-        self.low / self.help
+        let value = self.low / self.help;
+        if value >= tot_f {
+            self.invalid_symbol = true;
+        }
+        value
     }
 
     /// FFmpeg's range_decode_update: update state (does NOT normalize).
@@ -158,6 +218,10 @@ impl<'a> RangeCoder<'a> {
     /// Matches FFmpeg's range_get_symbol exactly.
     fn get_symbol(&mut self) -> u32 {
         let cf = self.culshift(16);
+        if cf > 0xFFFF {
+            // low >= range: the stream no longer describes a valid interval
+            self.invalid_symbol = true;
+        }
 
         // FFmpeg's fast path for rare/overflow symbols (cf > 65492)
         if cf > 65492 {
@@ -172,7 +236,7 @@ impl<'a> RangeCoder<'a> {
         let mut lo = 0usize;
         let mut hi = MODEL_ELEMENTS - 1;
         while lo < hi {
-            let mid = (lo + hi + 1) / 2;
+            let mid = (lo + hi).div_ceil(2);
             if (COUNTS_3980[mid] as u32) <= cf {
                 lo = mid;
             } else {
@@ -196,6 +260,10 @@ impl<'a> RangeCoder<'a> {
         // In our table, symbols 21-62 come from the cf>65492 fast path,
         // symbol 63 triggers the escape.
         if overflow == 63 {
+            #[cfg(feature = "debug-dump")]
+            {
+                self.escapes += 1;
+            }
             overflow = (self.decode_bits(16)) << 16;
             overflow |= self.decode_bits(16);
         }
@@ -230,3 +298,135 @@ impl<'a> RangeCoder<'a> {
         }
     }
 }
+
+// ── Range encoder ────────────────────────────────────────────────────
+
+/// Bits below the byte the encoder shifts out next.
+#[cfg(feature = "testgen")]
+const SHIFT_BITS: u32 = CODE_BITS - 9; // = 23
+
+/// Range encoder producing the byte stream [`RangeCoder`] decodes, in the
+/// same (word-swapped) byte order.
+///
+/// The first byte is the one the decoder skips; carries are propagated
+/// back into bytes already written.
+#[cfg(feature = "testgen")]
+pub struct RangeEncoder {
+    out: Vec<u8>,
+    low: u32,
+    range: u32,
+}
+
+#[cfg(feature = "testgen")]
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "testgen")]
+impl RangeEncoder {
+    pub fn new() -> Self {
+        RangeEncoder {
+            out: vec![0],
+            low: 0,
+            range: TOP_VALUE,
+        }
+    }
+
+    /// Shift out bytes until the range is wider than BOTTOM_VALUE, as the
+    /// decoder's normalize reads them.
+    fn normalize(&mut self) {
+        while self.range <= BOTTOM_VALUE {
+            self.out.push((self.low >> SHIFT_BITS) as u8);
+            self.low = (self.low << 8) & (TOP_VALUE - 1);
+            self.range <<= 8;
+        }
+    }
+
+    /// Narrow the interval to `[lt_f, lt_f + sy_f)` in units of `help`.
+    fn update(&mut self, help: u32, sy_f: u32, lt_f: u32) {
+        self.low += help * lt_f;
+        self.range = help * sy_f;
+        if self.low & TOP_VALUE != 0 {
+            self.low &= TOP_VALUE - 1;
+            // The interval never leaves the initial one, so the carry
+            // stops before the skipped first byte
+            for byte in self.out[1..].iter_mut().rev() {
+                *byte = byte.wrapping_add(1);
+                if *byte != 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Inverse of `culshift` + `update`.
+    fn encode_shift(&mut self, sy_f: u32, lt_f: u32, shift: u32) {
+        self.normalize();
+        self.update(self.range >> shift, sy_f, lt_f);
+    }
+
+    /// Inverse of `culfreq` + `update`.
+    fn encode_freq(&mut self, sy_f: u32, lt_f: u32, tot_f: u32) {
+        self.normalize();
+        self.update(self.range / tot_f, sy_f, lt_f);
+    }
+
+    /// Inverse of `get_symbol`.
+    fn encode_symbol(&mut self, symbol: u32) {
+        if symbol < MODEL_ELEMENTS as u32 - 1 {
+            let s = symbol as usize;
+            self.encode_shift(COUNTS_DIFF_3980[s] as u32, COUNTS_3980[s] as u32, 16);
+        } else {
+            // The decoder's fast path: cf = symbol - 63 + 65535
+            self.encode_shift(1, symbol + 65535 - 63, 16);
+        }
+    }
+
+    /// Encode a value for [`RangeCoder::decode_value`] to return.
+    pub fn encode_value(&mut self, value: i32, rice: &mut RiceState) {
+        let pivot = rice.pivot();
+        // Zigzag encode: the inverse of decode_value's
+        let x = if value > 0 {
+            (value as u32).wrapping_mul(2).wrapping_sub(1)
+        } else {
+            value.unsigned_abs().wrapping_mul(2)
+        };
+
+        let overflow = x / pivot;
+        let base = x % pivot;
+        if overflow < 63 {
+            self.encode_symbol(overflow);
+        } else {
+            self.encode_symbol(63);
+            self.encode_shift(1, overflow >> 16, 16);
+            self.encode_shift(1, overflow & 0xFFFF, 16);
+        }
+
+        if pivot < 0x10000 {
+            self.encode_freq(1, base, pivot);
+        } else {
+            let mut base_hi = pivot;
+            let mut bbits = 0u32;
+            while base_hi & !0xFFFF != 0 {
+                base_hi >>= 1;
+                bbits += 1;
+            }
+            self.encode_freq(1, base >> bbits, base_hi + 1);
+            self.encode_freq(1, base & ((1 << bbits) - 1), 1 << bbits);
+        }
+
+        rice.update(x);
+    }
+
+    /// Flush the interval's low end and return the stream. The decoder
+    /// reads exactly these bytes: its [`finish`](RangeCoder::finish)
+    /// lands on the end.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.normalize();
+        let low = self.low;
+        self.out.extend_from_slice(&[(low >> 23) as u8, (low >> 15) as u8, (low >> 7) as u8, (low << 1) as u8]);
+        self.out
+    }
+}
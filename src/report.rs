@@ -0,0 +1,130 @@
+//! One machine-readable summary of a file.
+//!
+//! [`Report`] gathers what tools print about an APE file into one
+//! structure: its format, its metadata, whether it verifies and what
+//! decoding it took. With the `serde` feature it serializes (to JSON or
+//! anything else), so pipelines and command-line `--json` output share
+//! one schema rather than each formatting its own.
+//!
+//! ```no_run
+//! use ape_rs::ApeReader;
+//!
+//! let report = ApeReader::open("track.ape").unwrap().report().unwrap();
+//! if !report.verification.is_ok() {
+//!     eprintln!("damaged frames: {:?}", report.verification.damaged_frames);
+//! }
+//! ```
+
+use std::io::{Read, Seek};
+use std::time::{Duration, Instant};
+
+use crate::error::ApeError;
+use crate::metadata::Metadata;
+use crate::{ApeInfo, ApeReader, block_time};
+
+/// Everything known about a file after decoding it, from
+/// [`ApeReader::report`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Report {
+    pub info: ApeInfo,
+    /// Length of the stream as the header declares it.
+    pub duration: Duration,
+    /// Track information from the file.
+    pub metadata: Metadata,
+    pub verification: Verification,
+    pub stats: DecodeStats,
+}
+
+/// Integrity of a file: frame CRCs and the descriptor's MD5.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Verification {
+    /// Frames decoded.
+    pub frames: u32,
+    /// Frames that failed to decode or failed their CRC check.
+    pub damaged_frames: Vec<u32>,
+    /// The MD5 over the stored file: `Some(true)` if it matched,
+    /// `Some(false)` if not, `None` if the encoder stored none.
+    pub file_md5: Option<bool>,
+    /// Every [`DecodeWarning`](crate::DecodeWarning), as displayed.
+    pub warnings: Vec<String>,
+}
+
+impl Verification {
+    /// Whether every frame decoded and the MD5, if any, matched.
+    pub fn is_ok(&self) -> bool {
+        self.damaged_frames.is_empty() && self.file_md5 != Some(false)
+    }
+}
+
+/// Figures from the decode.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodeStats {
+    /// Blocks (samples per channel) decoded, including concealed ones.
+    pub blocks: u64,
+    /// Bytes of compressed audio (frame data).
+    pub compressed_bytes: u64,
+    /// Compressed size as a fraction of the PCM size.
+    pub compression_ratio: f64,
+    /// Average bitrate of the compressed audio, in kbit/s.
+    pub bitrate_kbps: f64,
+    /// Wall-clock time the decode took.
+    pub decode_time: Duration,
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// Decode the rest of the stream and check the file, summing it all up
+    /// in a [`Report`].
+    ///
+    /// Damaged frames are listed rather than failing the report, whatever
+    /// the frame error mode; only errors that stop decoding altogether
+    /// (such as I/O errors) are returned. Call this on a reader at the
+    /// start to cover the whole file.
+    pub fn report(&mut self) -> Result<Report, ApeError> {
+        let metadata = self.metadata()?;
+        let file_md5 = match self.verify_file_md5() {
+            Ok(true) => Some(true),
+            Ok(false) => None,
+            Err(ApeError::Md5Mismatch { .. }) => Some(false),
+            Err(e) => return Err(e),
+        };
+
+        let start = Instant::now();
+        let channels = self.info.channels.max(1) as u64;
+        let mut verification = Verification { file_md5, ..Verification::default() };
+        let mut blocks = 0;
+        for frame in self.decoded_frames() {
+            match frame {
+                Ok(frame) => {
+                    blocks += frame.samples.len() as u64 / channels;
+                    verification.frames += 1;
+                    if !frame.crc_ok {
+                        verification.damaged_frames.push(frame.index);
+                    }
+                }
+                Err(e) if e.is_recoverable() => {
+                    verification.frames += 1;
+                    verification.damaged_frames.extend(e.context().map(|c| c.frame));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let decode_time = start.elapsed();
+        verification.warnings = self.warnings().iter().map(ToString::to_string).collect();
+
+        let info = &self.info;
+        let compressed_bytes = self.decoder.header.frame_data_bytes();
+        let pcm_bytes = info.total_samples * (info.bits_per_sample as u64).div_ceil(8);
+        let duration = block_time(info.total_samples / channels, info.sample_rate);
+        let stats = DecodeStats {
+            blocks,
+            compressed_bytes,
+            compression_ratio: if pcm_bytes == 0 { 0.0 } else { compressed_bytes as f64 / pcm_bytes as f64 },
+            bitrate_kbps: if duration.is_zero() { 0.0 } else { compressed_bytes as f64 * 8.0 / duration.as_secs_f64() / 1000.0 },
+            decode_time,
+        };
+        Ok(Report { info: info.clone(), duration, metadata, verification, stats })
+    }
+}
@@ -0,0 +1,165 @@
+//! Sample rate conversion of the decoded stream (feature `rubato`).
+//!
+//! Playback stacks often run the output device at one fixed rate.
+//! [`ApeReader::resampled`] converts a file to that rate on the fly with
+//! [`rubato`]'s FFT resampler, yielding interleaved `f32` samples, so a
+//! 44.1 kHz rip can feed a 48 kHz device directly.
+//!
+//! The output is time-aligned with the input: the resampler's delay is
+//! dropped from the start and the end is flushed, so `n` input blocks give
+//! `n * output_rate / input_rate` output blocks, rounded up.
+
+use std::io::{Read, Seek};
+
+use rubato::{FftFixedIn, Resampler};
+
+use crate::convert;
+use crate::error::ApeError;
+use crate::{ApeReader, ApeSamples};
+
+/// Input blocks the resampler takes per call.
+const CHUNK_BLOCKS: usize = 1024;
+
+/// Iterator over the resampled stream, from [`ApeReader::resampled`].
+///
+/// Yields interleaved samples normalized to [-1.0, 1.0] as
+/// [`convert::to_f32`] normalizes them. Filter overshoot can slightly
+/// exceed full scale; the samples are not clamped.
+pub struct Resampled<'a, R: Read + Seek> {
+    samples: ApeSamples<'a, R>,
+    bits_per_sample: u16,
+    sample_rate: u32,
+    input_rate: u32,
+    resampler: FftFixedIn<f32>,
+    input: Vec<Vec<f32>>,
+    output: Vec<Vec<f32>>,
+    /// Next sample to yield from `output`: block and channel.
+    block: usize,
+    channel: usize,
+    /// Valid blocks in `output`.
+    blocks: usize,
+    /// Output blocks of resampler delay still to drop.
+    skip: usize,
+    /// Input blocks read, and output blocks yielded.
+    blocks_in: u64,
+    blocks_out: u64,
+    input_ended: bool,
+}
+
+impl<R: Read + Seek> ApeReader<R> {
+    /// Resample the rest of the stream to `sample_rate` Hz (feature
+    /// `rubato`).
+    ///
+    /// Fails with [`ErrorKind::FormatMismatch`](crate::ErrorKind::FormatMismatch)
+    /// for a rate of 0.
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// for sample in reader.resampled(48_000).unwrap() {
+    ///     let sample: f32 = sample.unwrap();
+    ///     // feed the 48 kHz device
+    /// }
+    /// ```
+    pub fn resampled(&mut self, sample_rate: u32) -> Result<Resampled<'_, R>, ApeError> {
+        let channels = self.info.channels.max(1) as usize;
+        let input_rate = self.info.sample_rate;
+        let resampler = FftFixedIn::new(input_rate as usize, sample_rate as usize, CHUNK_BLOCKS, 2, channels)
+            .map_err(|e| ApeError::FormatMismatch(format!("cannot resample {input_rate} Hz to {sample_rate} Hz: {e}")))?;
+        Ok(Resampled {
+            bits_per_sample: self.info.bits_per_sample,
+            sample_rate,
+            input_rate,
+            input: vec![Vec::with_capacity(CHUNK_BLOCKS); channels],
+            output: resampler.output_buffer_allocate(true),
+            skip: resampler.output_delay(),
+            resampler,
+            samples: self.samples(),
+            block: 0,
+            channel: 0,
+            blocks: 0,
+            blocks_in: 0,
+            blocks_out: 0,
+            input_ended: false,
+        })
+    }
+}
+
+impl<R: Read + Seek> Resampled<'_, R> {
+    /// The output sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Output blocks the input read so far accounts for.
+    fn blocks_due(&self) -> u64 {
+        (self.blocks_in * self.sample_rate as u64).div_ceil(self.input_rate as u64)
+    }
+
+    /// Read the next chunk of input and resample it into `output`.
+    fn refill(&mut self) -> Result<(), ApeError> {
+        let wanted = self.resampler.input_frames_next();
+        for channel in &mut self.input {
+            channel.clear();
+        }
+        while !self.input_ended && self.input[0].len() < wanted {
+            for channel in &mut self.input {
+                match self.samples.next() {
+                    Some(sample) => channel.push(convert::to_f32(sample?, self.bits_per_sample)),
+                    None => {
+                        self.input_ended = true;
+                        break;
+                    }
+                }
+            }
+        }
+        // An incomplete final block is dropped
+        let blocks_read = self.input.iter().map(Vec::len).min().unwrap_or(0);
+        for channel in &mut self.input {
+            channel.truncate(blocks_read);
+        }
+        self.blocks_in += blocks_read as u64;
+
+        // Input and output buffers are sized by the resampler itself
+        let (_, blocks) = match blocks_read {
+            0 => self.resampler.process_partial_into_buffer(None::<&[Vec<f32>]>, &mut self.output, None),
+            n if n == wanted => self.resampler.process_into_buffer(&self.input, &mut self.output, None),
+            _ => self.resampler.process_partial_into_buffer(Some(&self.input), &mut self.output, None),
+        }
+        .expect("resampler buffers have the sizes it asked for");
+
+        let skipped = self.skip.min(blocks);
+        self.skip -= skipped;
+        self.block = skipped;
+        self.blocks = blocks;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Iterator for Resampled<'_, R> {
+    type Item = Result<f32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.input_ended && self.blocks_out >= self.blocks_due() {
+                return None;
+            }
+            if self.block < self.blocks {
+                let sample = self.output[self.channel][self.block];
+                self.channel += 1;
+                if self.channel == self.output.len() {
+                    self.channel = 0;
+                    self.block += 1;
+                    self.blocks_out += 1;
+                }
+                return Some(Ok(sample));
+            }
+            if let Err(e) = self.refill() {
+                self.input_ended = true;
+                self.blocks = 0;
+                return Some(Err(e));
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! RIFF/WAVE structure of the original file's stored header and trailer.
+//!
+//! The header data ([`ApeReader::header_data`](crate::ApeReader::header_data))
+//! is the original WAV file up to the samples: the `RIFF`/`WAVE` preamble,
+//! `fmt `, any other chunks, and the `data` chunk's own header. Chunks after
+//! the samples (often `LIST`, written by recorders that only know the
+//! metadata once recording stops) are in the terminating data. [`chunks`]
+//! walks either region; [`wave_format`] parses the `fmt ` chunk and
+//! [`info_items`] reads `LIST`/`INFO` metadata.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// One RIFF chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    /// Four-character chunk ID, e.g. `*b"fmt "`.
+    pub id: [u8; 4],
+    /// Size from the chunk header.
+    pub size: u32,
+    /// The chunk body, cut short if the region ends first (as it does for
+    /// the `data` chunk in the header data).
+    pub data: &'a [u8],
+}
+
+/// Iterator over the chunks of a RIFF region, from [`chunks`].
+#[derive(Debug, Clone)]
+pub struct Chunks<'a> {
+    rest: &'a [u8],
+}
+
+/// Iterate over the chunks in `region`: the header data (its
+/// `RIFF`/`WAVE` preamble is skipped) or the terminating data.
+///
+/// If the `data` chunk has an odd length, the terminating data starts with
+/// its pad byte; pass the region after it.
+pub fn chunks(region: &[u8]) -> Chunks<'_> {
+    let rest = match region {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', rest @ ..] => rest,
+        _ => region,
+    };
+    Chunks { rest }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Chunk<'a>;
+
+    fn next(&mut self) -> Option<Chunk<'a>> {
+        if self.rest.len() < 8 {
+            return None;
+        }
+        let (head, body) = self.rest.split_at(8);
+        let id = [head[0], head[1], head[2], head[3]];
+        let size = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+        let len = (size as usize).min(body.len());
+        let data = &body[..len];
+        // Chunks are padded to an even length
+        let next = (len + (size as usize & 1)).min(body.len());
+        self.rest = &body[next..];
+        Some(Chunk { id, size, data })
+    }
+}
+
+/// `wFormatTag` of integer PCM.
+pub const WAVE_FORMAT_PCM: u16 = 1;
+
+/// `wFormatTag` of `WAVE_FORMAT_EXTENSIBLE`, whose real format is the
+/// first two bytes of [`WaveFormat::sub_format`].
+pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// The original file's `fmt ` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WaveFormat {
+    /// `wFormatTag`, e.g. [`WAVE_FORMAT_PCM`] or [`WAVE_FORMAT_EXTENSIBLE`].
+    pub format_tag: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub byte_rate: u32,
+    /// Bytes per block (one sample of every channel).
+    pub block_align: u16,
+    /// Container size of one sample, in bits.
+    pub bits_per_sample: u16,
+    /// Bits actually used in each container (e.g. 20 in a 24-bit
+    /// container). Extensible format only.
+    pub valid_bits_per_sample: Option<u16>,
+    /// Speaker positions of the channels, one bit per speaker in
+    /// `dwChannelMask` order (`0x3` is front left + front right).
+    /// Extensible format only.
+    pub channel_mask: Option<u32>,
+    /// Format GUID, as stored. Extensible format only.
+    pub sub_format: Option<[u8; 16]>,
+}
+
+impl WaveFormat {
+    /// Parse a `fmt ` chunk body. `None` if it is too short for its format.
+    pub fn parse(body: &[u8]) -> Option<WaveFormat> {
+        let u16_at = |off: usize| Some(u16::from_le_bytes(body.get(off..off + 2)?.try_into().ok()?));
+        let u32_at = |off: usize| Some(u32::from_le_bytes(body.get(off..off + 4)?.try_into().ok()?));
+        let mut format = WaveFormat {
+            format_tag: u16_at(0)?,
+            channels: u16_at(2)?,
+            sample_rate: u32_at(4)?,
+            byte_rate: u32_at(8)?,
+            block_align: u16_at(12)?,
+            bits_per_sample: u16_at(14)?,
+            valid_bits_per_sample: None,
+            channel_mask: None,
+            sub_format: None,
+        };
+        // cbSize (16), then wValidBitsPerSample, dwChannelMask, SubFormat
+        if format.format_tag == WAVE_FORMAT_EXTENSIBLE {
+            if u16_at(16)? < 22 {
+                return None;
+            }
+            format.valid_bits_per_sample = Some(u16_at(18)?);
+            format.channel_mask = Some(u32_at(20)?);
+            format.sub_format = Some(body.get(24..40)?.try_into().ok()?);
+        }
+        Some(format)
+    }
+
+    /// The format of the samples: `format_tag`, or for the extensible
+    /// format the tag embedded in `sub_format`.
+    pub fn sample_format(&self) -> u16 {
+        match self.sub_format {
+            Some(guid) => u16::from_le_bytes([guid[0], guid[1]]),
+            None => self.format_tag,
+        }
+    }
+
+    /// Bytes per sample container, from the block alignment.
+    pub fn container_bytes(&self) -> u16 {
+        self.block_align.checked_div(self.channels).unwrap_or(0)
+    }
+}
+
+/// The `fmt ` chunk of the header data, if there is a well-formed one.
+pub fn wave_format(header_data: &[u8]) -> Option<WaveFormat> {
+    let fmt = chunks(header_data).find(|c| &c.id == b"fmt ")?;
+    WaveFormat::parse(fmt.data)
+}
+
+/// One `LIST`/`INFO` entry, e.g. `INAM` (title) or `IART` (artist).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InfoItem {
+    /// Four-character ID.
+    pub id: String,
+    /// Text value. Older recorders wrote Latin-1 rather than UTF-8; both
+    /// are decoded.
+    pub value: String,
+}
+
+/// All `LIST`/`INFO` entries in `region` (header or terminating data), in
+/// file order. Empty values are left out.
+pub fn info_items(region: &[u8]) -> Vec<InfoItem> {
+    let mut items = Vec::new();
+    for list in chunks(region).filter(|c| &c.id == b"LIST") {
+        let Some(body) = list.data.strip_prefix(b"INFO") else { continue };
+        for item in (Chunks { rest: body }) {
+            let value = decode_text(item.data);
+            if !value.is_empty() {
+                items.push(InfoItem {
+                    id: item.id.iter().map(|&b| b as char).collect(),
+                    value,
+                });
+            }
+        }
+    }
+    items
+}
+
+/// Decode a NUL-terminated string as UTF-8, or as Latin-1 if it is not
+/// valid UTF-8.
+fn decode_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let bytes = &bytes[..end];
+    let text = match core::str::from_utf8(bytes) {
+        Ok(s) => String::from(s),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    };
+    String::from(text.trim())
+}
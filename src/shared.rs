@@ -0,0 +1,146 @@
+//! One file in memory, decoded by many readers at once.
+//!
+//! [`SharedApeReader`] parses a file held in memory (a `Vec<u8>`, a
+//! `bytes::Bytes`, a memory map, anything that is `AsRef<[u8]>`) once and
+//! hands out independent [`ApeReader`]s over it. Each cursor has its own
+//! decoder state and position; the bytes and the parsed header are shared,
+//! so a server can answer concurrent range requests for the same file
+//! without reopening or re-parsing it. With feature `mmap`,
+//! [`SharedApeReader::open_mmap`] maps a file from disk.
+
+#[cfg(feature = "mmap")]
+use std::fs::File;
+use std::io::Cursor;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::ApeError;
+use crate::header::{ApeFileHeader, parse_header_slice};
+use crate::{ApeInfo, ApeReader, ApeReaderBuilder};
+
+/// Shared file bytes: the source type of a [`SharedApeReader`]'s cursors.
+#[derive(Debug)]
+pub struct SharedBytes<T>(Arc<T>);
+
+impl<T> Clone for SharedBytes<T> {
+    fn clone(&self) -> Self {
+        SharedBytes(Arc::clone(&self.0))
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for SharedBytes<T> {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
+
+/// An [`ApeReader`] over a [`SharedApeReader`]'s bytes.
+pub type SharedCursor<T> = ApeReader<Cursor<SharedBytes<T>>>;
+
+/// A parsed APE file in memory, from which any number of readers can be
+/// created, on any thread.
+///
+/// Cloning is cheap: clones share the bytes and the header.
+///
+/// ```no_run
+/// use ape_rs::shared::SharedApeReader;
+///
+/// let file = SharedApeReader::new(std::fs::read("track.ape").unwrap()).unwrap();
+/// std::thread::scope(|s| {
+///     for start in [0, 44100 * 60] {
+///         let file = &file;
+///         s.spawn(move || {
+///             let mut reader = file.cursor_at(start).unwrap();
+///             let minute: Vec<i32> = reader.samples().take(44100 * 2 * 60).collect::<Result<_, _>>().unwrap();
+///         });
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct SharedApeReader<T> {
+    data: Arc<T>,
+    header: ApeFileHeader,
+    info: ApeInfo,
+}
+
+impl<T> Clone for SharedApeReader<T> {
+    fn clone(&self) -> Self {
+        SharedApeReader {
+            data: Arc::clone(&self.data),
+            header: self.header.clone(),
+            info: self.info.clone(),
+        }
+    }
+}
+
+/// Memory-map the file at `path`, read-only.
+#[cfg(feature = "mmap")]
+pub(crate) fn map_file(path: &Path) -> Result<memmap2::Mmap, ApeError> {
+    let file = File::open(path)?;
+    // SAFETY: the map is only read. Callers are told the file must not
+    // change while it is mapped, which is all memmap2 requires.
+    Ok(unsafe { memmap2::Mmap::map(&file)? })
+}
+
+#[cfg(feature = "mmap")]
+impl SharedApeReader<memmap2::Mmap> {
+    /// Memory-map the file at `path` and parse it (feature `mmap`), with
+    /// the caveats of [`ApeReader::open_mmap`].
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self, ApeError> {
+        Self::new(map_file(path.as_ref())?)
+    }
+}
+
+impl<T: AsRef<[u8]>> SharedApeReader<T> {
+    /// Parse the file in `data`, which must hold all of it.
+    pub fn new(data: T) -> Result<Self, ApeError> {
+        Self::from_arc(Arc::new(data))
+    }
+
+    /// As [`new`](Self::new), for bytes that are already shared.
+    pub fn from_arc(data: Arc<T>) -> Result<Self, ApeError> {
+        let header = parse_header_slice((*data).as_ref())?;
+        let reader = ApeReaderBuilder::new().build_with_header(Cursor::new(SharedBytes(Arc::clone(&data))), header)?;
+        Ok(SharedApeReader {
+            header: reader.header().clone(),
+            info: reader.info().clone(),
+            data,
+        })
+    }
+
+    /// Stream metadata, as [`ApeReader::info`] gives it.
+    pub fn info(&self) -> &ApeInfo {
+        &self.info
+    }
+
+    /// The parsed file header.
+    pub fn header(&self) -> &ApeFileHeader {
+        &self.header
+    }
+
+    /// The file's bytes.
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// A new reader at the start of the stream, with default options.
+    pub fn cursor(&self) -> Result<SharedCursor<T>, ApeError> {
+        self.cursor_with(ApeReaderBuilder::new())
+    }
+
+    /// A new reader positioned at block `block` (samples per channel),
+    /// clamped to the end of the stream.
+    pub fn cursor_at(&self, block: u64) -> Result<SharedCursor<T>, ApeError> {
+        let mut reader = self.cursor()?;
+        reader.seek_to_block(block)?;
+        Ok(reader)
+    }
+
+    /// A new reader at the start of the stream, with the options set on
+    /// `builder` (e.g. a lenient frame error mode). The header is not
+    /// parsed again.
+    pub fn cursor_with(&self, builder: ApeReaderBuilder) -> Result<SharedCursor<T>, ApeError> {
+        builder.build_with_header(Cursor::new(SharedBytes(Arc::clone(&self.data))), self.header.clone())
+    }
+}
@@ -0,0 +1,193 @@
+//! Audio checksum sidecar files, as kept next to lossless rips.
+//!
+//! A FLAC fingerprint (`.ffp`) file lists each track's name and the MD5
+//! of its decoded audio, one `name:md5` line per file; trading and
+//! archiving communities use them to check that audio survived copying
+//! and retagging. The MD5 is the one FLAC stores: of the PCM
+//! little-endian and signed ([`PcmLayout::Flac`]), so the same audio has
+//! the same checksum as APE and as FLAC. An `.md5` sidecar holds the same
+//! checksums in `md5sum`'s `md5  name` format.
+//!
+//! [`generate`] computes the entries for a set of files, [`write`] and
+//! [`parse`] convert them to and from sidecar text, and [`verify`] checks
+//! the files a sidecar lists.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use ape_rs::sidecar::{self, SidecarFormat};
+//!
+//! let entries = sidecar::generate(&["01.ape", "02.ape"]).unwrap();
+//! sidecar::write(&entries, SidecarFormat::Ffp, File::create("album.ffp").unwrap()).unwrap();
+//!
+//! for check in sidecar::verify("album.ffp").unwrap() {
+//!     println!("{}: {}", check.name, if check.is_ok() { "OK" } else { "FAILED" });
+//! }
+//! ```
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+use crate::ApeReader;
+use crate::digest::{HashAlgorithm, PcmLayout};
+use crate::error::ApeError;
+
+/// Text format of a sidecar file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SidecarFormat {
+    /// `name:md5` lines (`.ffp`).
+    Ffp,
+    /// `md5  name` lines, as `md5sum` writes them (`.md5`).
+    Md5,
+}
+
+impl SidecarFormat {
+    /// The format for a sidecar file name's extension, if it is one.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "ffp" => Some(SidecarFormat::Ffp),
+            "md5" => Some(SidecarFormat::Md5),
+            _ => None,
+        }
+    }
+
+    /// The usual file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            SidecarFormat::Ffp => "ffp",
+            SidecarFormat::Md5 => "md5",
+        }
+    }
+}
+
+/// One line of a sidecar: a file name and the MD5 of its decoded audio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SidecarEntry {
+    /// File name, relative to the sidecar's directory.
+    pub name: String,
+    pub md5: [u8; 16],
+}
+
+/// Outcome of checking one file in [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckResult {
+    /// The audio matches.
+    Ok,
+    /// The file decoded to different audio.
+    Mismatch { actual: [u8; 16] },
+    /// The file is missing or did not decode.
+    Failed(ApeError),
+}
+
+/// One file checked by [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SidecarCheck {
+    pub name: String,
+    pub expected: [u8; 16],
+    pub result: CheckResult,
+}
+
+impl SidecarCheck {
+    /// Whether the file matched.
+    pub fn is_ok(&self) -> bool {
+        matches!(self.result, CheckResult::Ok)
+    }
+}
+
+/// MD5 of the audio in the APE file at `path`, as an `.ffp` file records
+/// it. Any frame that fails to decode fails the checksum.
+pub fn audio_md5<P: AsRef<Path>>(path: P) -> Result<[u8; 16], ApeError> {
+    let digest = ApeReader::open(path)?.pcm_digest(HashAlgorithm::Md5, PcmLayout::Flac)?;
+    Ok(digest.bytes.try_into().expect("MD5 digests are 16 bytes"))
+}
+
+/// Entries for `files`, in order, named by file name. Fails on the first
+/// file that does not decode.
+pub fn generate<P: AsRef<Path>>(files: &[P]) -> Result<Vec<SidecarEntry>, ApeError> {
+    files
+        .iter()
+        .map(|path| {
+            let path = path.as_ref();
+            let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+            Ok(SidecarEntry { name, md5: audio_md5(path)? })
+        })
+        .collect()
+}
+
+/// Write `entries` to `out` as sidecar text in `format`.
+pub fn write<W: Write>(entries: &[SidecarEntry], format: SidecarFormat, mut out: W) -> Result<(), ApeError> {
+    for entry in entries {
+        let md5 = to_hex(&entry.md5);
+        match format {
+            SidecarFormat::Ffp => writeln!(out, "{}:{md5}", entry.name)?,
+            SidecarFormat::Md5 => writeln!(out, "{md5}  {}", entry.name)?,
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// The entries in sidecar text, in either format (decided per line).
+///
+/// Blank lines, comments (starting with `;` or `#`) and lines that are in
+/// neither format are skipped. The `*` that `md5sum` puts in front of
+/// names in binary mode is dropped.
+pub fn parse(text: &str) -> Vec<SidecarEntry> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty() && !line.starts_with([';', '#']))
+        .filter_map(|line| {
+            if let Some((name, md5)) = line.rsplit_once(':')
+                && let Some(md5) = from_hex(md5.trim())
+            {
+                return Some(SidecarEntry { name: name.to_owned(), md5 });
+            }
+            let (md5, name) = line.split_once(char::is_whitespace)?;
+            let name = name.trim_start();
+            let name = name.strip_prefix('*').unwrap_or(name);
+            Some(SidecarEntry { name: name.to_owned(), md5: from_hex(md5)? })
+        })
+        .collect()
+}
+
+/// Check every file the sidecar at `path` lists, relative to its
+/// directory. Fails only if the sidecar itself cannot be read.
+pub fn verify<P: AsRef<Path>>(path: P) -> Result<Vec<SidecarCheck>, ApeError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(parse(&text)
+        .into_iter()
+        .map(|entry| {
+            let result = match audio_md5(dir.join(&entry.name)) {
+                Ok(actual) if actual == entry.md5 => CheckResult::Ok,
+                Ok(actual) => CheckResult::Mismatch { actual },
+                Err(e) => CheckResult::Failed(e),
+            };
+            SidecarCheck { name: entry.name, expected: entry.md5, result }
+        })
+        .collect())
+}
+
+/// Lowercase hex of `bytes`.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, b| {
+        let _ = write!(hex, "{b:02x}");
+        hex
+    })
+}
+
+/// An MD5 from 32 hex digits, in either case.
+fn from_hex(hex: &str) -> Option<[u8; 16]> {
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut md5 = [0; 16];
+    for (byte, pair) in md5.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(md5)
+}
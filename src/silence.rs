@@ -0,0 +1,84 @@
+//! Silence detection: find runs of blocks where every channel stays below
+//! a level threshold for at least a minimum duration.
+
+use std::ops::Range;
+use std::time::Duration;
+
+/// Single-pass accumulator behind
+/// [`ApeReader::detect_silence`](crate::ApeReader::detect_silence).
+///
+/// Feed it interleaved samples in any chunking, then call `finish`.
+/// Ranges are in blocks (samples per channel), end-exclusive, counted from
+/// the first sample fed.
+#[derive(Debug, Clone)]
+pub struct SilenceDetector {
+    channels: u64,
+    /// Largest magnitude still counted as silent.
+    threshold: u32,
+    min_blocks: u64,
+    /// Whether every channel of the current block so far is silent.
+    block_silent: bool,
+    /// Start of the current silent run, if in one.
+    run_start: Option<u64>,
+    ranges: Vec<Range<u64>>,
+    samples: u64,
+}
+
+impl SilenceDetector {
+    /// Create a detector for interleaved audio. A block is silent when all
+    /// its samples are at or below `threshold_db` dBFS; runs shorter than
+    /// `min_duration` are ignored.
+    pub fn new(
+        channels: u16,
+        bits_per_sample: u16,
+        sample_rate: u32,
+        threshold_db: f64,
+        min_duration: Duration,
+    ) -> Self {
+        let full_scale = crate::convert::full_scale(bits_per_sample);
+        SilenceDetector {
+            channels: channels.max(1) as u64,
+            threshold: (full_scale * 10f64.powf(threshold_db / 20.0)) as u32,
+            min_blocks: (min_duration.as_secs_f64() * sample_rate as f64).ceil() as u64,
+            block_silent: true,
+            run_start: None,
+            ranges: Vec::new(),
+            samples: 0,
+        }
+    }
+
+    /// Add interleaved samples. A chunk may end mid-block; the next one
+    /// continues with the following channel.
+    pub fn update(&mut self, samples: &[i32]) {
+        for &s in samples {
+            self.block_silent &= s.unsigned_abs() <= self.threshold;
+            self.samples += 1;
+            if !self.samples.is_multiple_of(self.channels) {
+                continue;
+            }
+            let block = self.samples / self.channels - 1;
+            match (self.block_silent, self.run_start) {
+                (true, None) => self.run_start = Some(block),
+                (false, Some(start)) => self.end_run(start, block),
+                _ => {}
+            }
+            self.block_silent = true;
+        }
+    }
+
+    /// Record the run `start..end` if it is long enough.
+    fn end_run(&mut self, start: u64, end: u64) {
+        if end - start >= self.min_blocks.max(1) {
+            self.ranges.push(start..end);
+        }
+        self.run_start = None;
+    }
+
+    /// Silent ranges found so far, including one still running at the end.
+    pub fn finish(mut self) -> Vec<Range<u64>> {
+        if let Some(start) = self.run_start {
+            self.end_run(start, self.samples / self.channels);
+        }
+        self.ranges
+    }
+}
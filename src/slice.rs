@@ -0,0 +1,127 @@
+//! Decoding a complete file held in memory, without `Read` / `Seek`.
+//!
+//! [`SliceDecoder`] borrows the file's bytes and decodes whole frames
+//! straight into a caller-owned `Vec`: there is no reader, no internal
+//! sample buffer and no seeking. It is available without `std`, which
+//! suits WASM and embedded use where the file is already in memory.
+//!
+//! Unlike [`ApeReader`](crate::ApeReader) there is no error recovery: a
+//! frame that fails to decode or fails its CRC is an error.
+
+use alloc::vec::Vec;
+
+use crate::buffer::SampleBuffer;
+use crate::error::{ApeError, ErrorContext};
+use crate::header::{self, ApeFileHeader, parse_header_slice};
+use crate::packet::{self, PacketDecoder};
+
+/// Frame-at-a-time decoder over an APE file in a byte slice.
+///
+/// ```no_run
+/// use ape_rs::slice::SliceDecoder;
+///
+/// let data = std::fs::read("track.ape").unwrap();
+/// let mut decoder = SliceDecoder::new(&data).unwrap();
+/// let mut pcm = Vec::new();
+/// for frame in 0..decoder.frame_count() {
+///     decoder.decode_frame(frame, &mut pcm).unwrap();
+/// }
+/// assert_eq!(pcm.len() as u64, decoder.header().total_samples());
+/// ```
+#[derive(Debug)]
+pub struct SliceDecoder<'a> {
+    data: &'a [u8],
+    header: ApeFileHeader,
+    packet: PacketDecoder,
+    /// Byte-swapped copy of the frame being decoded, reused across frames.
+    swapped: Vec<u8>,
+}
+
+impl<'a> SliceDecoder<'a> {
+    /// Parse the header at the start of `data`, which must hold the whole
+    /// file.
+    pub fn new(data: &'a [u8]) -> Result<Self, ApeError> {
+        let header = parse_header_slice(data)?;
+        Self::with_header(data, header)
+    }
+
+    /// Decode `data` using a header that was already parsed or built by
+    /// hand. Its offsets index into `data`.
+    pub fn with_header(data: &'a [u8], header: ApeFileHeader) -> Result<Self, ApeError> {
+        header::validate_file_header(&header)?;
+        header::validate_frame_layout(&header)?;
+        let h = &header.header;
+        let packet = PacketDecoder::from_header(h.channels, h.bits_per_sample, h.compression_level);
+        Ok(SliceDecoder {
+            data,
+            header,
+            packet,
+            swapped: Vec::new(),
+        })
+    }
+
+    /// The file header.
+    pub fn header(&self) -> &ApeFileHeader {
+        &self.header
+    }
+
+    /// Number of frames in the stream.
+    pub fn frame_count(&self) -> u32 {
+        self.header.header.total_frames
+    }
+
+    /// Decode frame `index` and append its interleaved samples to `out`.
+    ///
+    /// Returns `false` if there is no such frame. On error `out` is left as
+    /// it was. Frames are independent and can be decoded in any order.
+    pub fn decode_frame(&mut self, index: u32, out: &mut Vec<i32>) -> Result<bool, ApeError> {
+        let frame = index as usize;
+        let h = &self.header;
+        let (Some(pos), Some(size)) = (h.frame_position(frame), h.frame_size(frame)) else {
+            return Ok(false);
+        };
+        if index >= self.frame_count() {
+            return Ok(false);
+        }
+        let context = ErrorContext {
+            frame: index,
+            sample: index as u64 * h.header.blocks_per_frame as u64 * h.header.channels as u64,
+            byte_offset: pos,
+        };
+
+        // Whole words from the one holding the frame's first byte
+        let skip = h.frame_skip(frame);
+        let len = self.data.len() as u64;
+        let start = pos.saturating_sub(skip as u64).min(len) as usize;
+        let end = pos.saturating_add(size).min(len) as usize;
+        if start >= end {
+            return Err(ApeError::UnexpectedEof.with_context(context));
+        }
+        self.swapped.clear();
+        self.swapped.extend_from_slice(&self.data[start..end]);
+        packet::byte_swap_words(&mut self.swapped);
+
+        let before = out.len();
+        let mut buffer = SampleBuffer::from_vec(core::mem::take(out));
+        let result = self
+            .packet
+            .decode_frame(&self.swapped, skip, h.frame_blocks(frame), index, &mut buffer);
+        *out = buffer.into_vec();
+        if let Err(e) = result {
+            out.truncate(before);
+            return Err(e.with_context(context));
+        }
+        Ok(true)
+    }
+
+    /// Decode every frame into one interleaved buffer.
+    pub fn decode_all(&mut self) -> Result<Vec<i32>, ApeError> {
+        let total = usize::try_from(self.header.total_samples()).unwrap_or(0);
+        // A corrupt header can claim far more than the data holds
+        let mut out = Vec::with_capacity(total.min(self.data.len() * 8));
+        for index in 0..self.frame_count() {
+            self.decode_frame(index, &mut out)?;
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,79 @@
+//! Decoder position snapshots for checkpoint/resume.
+//!
+//! A snapshot records where in the stream the next sample comes from, not
+//! the filter and predictor state: every frame starts from reset state, so
+//! restoring re-decodes the snapshot's frame (at most one frame of work)
+//! and rebuilds that state exactly.
+//!
+//! Snapshots serialize to a small fixed-size byte string with
+//! [`DecoderSnapshot::to_bytes`], so they can be stored alongside a job's
+//! other progress and restored after a process restart.
+
+use alloc::format;
+
+use crate::error::ApeError;
+
+/// Identifies the encoding; the last byte is the format version.
+const MAGIC: [u8; 8] = *b"APESNAP\x01";
+
+/// Encoded size in bytes.
+pub const SNAPSHOT_LEN: usize = 32;
+
+/// Position of an [`ApeReader`](crate::ApeReader) in its stream, as taken
+/// by [`ApeReader::snapshot`](crate::ApeReader::snapshot).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecoderSnapshot {
+    pub(crate) frame: u32,
+    pub(crate) sample_in_frame: u32,
+    pub(crate) resync_shift: i64,
+    /// Block count of the stream, to catch restoring into the wrong file.
+    pub(crate) total_blocks: u64,
+}
+
+impl DecoderSnapshot {
+    /// Frame the next sample belongs to.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Samples of that frame (interleaved) already returned.
+    pub fn sample_in_frame(&self) -> u32 {
+        self.sample_in_frame
+    }
+
+    /// Encode as [`SNAPSHOT_LEN`] bytes.
+    pub fn to_bytes(&self) -> [u8; SNAPSHOT_LEN] {
+        let mut out = [0; SNAPSHOT_LEN];
+        out[..8].copy_from_slice(&MAGIC);
+        out[8..12].copy_from_slice(&self.frame.to_le_bytes());
+        out[12..16].copy_from_slice(&self.sample_in_frame.to_le_bytes());
+        out[16..24].copy_from_slice(&self.resync_shift.to_le_bytes());
+        out[24..32].copy_from_slice(&self.total_blocks.to_le_bytes());
+        out
+    }
+
+    /// Decode bytes produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ApeError> {
+        if bytes.len() != SNAPSHOT_LEN {
+            return Err(ApeError::InvalidSnapshot(format!(
+                "expected {SNAPSHOT_LEN} bytes, got {}",
+                bytes.len()
+            )));
+        }
+        if bytes[..7] != MAGIC[..7] {
+            return Err(ApeError::InvalidSnapshot("bad magic".into()));
+        }
+        if bytes[7] != MAGIC[7] {
+            return Err(ApeError::InvalidSnapshot(format!("unsupported version {}", bytes[7])));
+        }
+        let u32_at = |off: usize| u32::from_le_bytes(bytes[off..off + 4].try_into().unwrap());
+        let u64_at = |off: usize| u64::from_le_bytes(bytes[off..off + 8].try_into().unwrap());
+        Ok(DecoderSnapshot {
+            frame: u32_at(8),
+            sample_in_frame: u32_at(12),
+            resync_shift: u64_at(16) as i64,
+            total_blocks: u64_at(24),
+        })
+    }
+}
@@ -0,0 +1,200 @@
+//! Spectrogram frames over decoded audio (feature `spectral`).
+//!
+//! Channels are averaged to mono, then each window of `window_size` blocks
+//! (advancing by `hop`) is windowed and transformed. Levels are in dBFS: a
+//! full-scale sine reads about 0 dB in its bin.
+//!
+//! A "lossless" file made from MP3 or AAC typically shows a hard shelf
+//! around 16–20 kHz where genuine CD sources keep content up to Nyquist;
+//! [`Spectrogram::cutoff_frequency`] gives a quick numeric hint of that.
+
+use std::f64::consts::PI;
+
+use crate::fft::Fft;
+
+/// Levels below this are clamped, so silence stays finite.
+const FLOOR_DB: f32 = -200.0;
+
+/// Window applied to each frame before the FFT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindowFunction {
+    /// Good general-purpose choice.
+    #[default]
+    Hann,
+    Hamming,
+    /// Lower sidelobes, for spotting faint content next to loud content.
+    Blackman,
+    /// No windowing.
+    Rectangular,
+}
+
+impl WindowFunction {
+    fn coefficients(self, n: usize) -> Vec<f64> {
+        let denom = n.saturating_sub(1).max(1) as f64;
+        (0..n)
+            .map(|i| {
+                let x = 2.0 * PI * i as f64 / denom;
+                match self {
+                    WindowFunction::Hann => 0.5 - 0.5 * x.cos(),
+                    WindowFunction::Hamming => 0.54 - 0.46 * x.cos(),
+                    WindowFunction::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                    WindowFunction::Rectangular => 1.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Result of [`ApeReader::spectrogram`](crate::ApeReader::spectrogram).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spectrogram {
+    pub sample_rate: u32,
+    /// FFT size in blocks.
+    pub window_size: usize,
+    /// Blocks between the starts of consecutive frames.
+    pub hop: usize,
+    /// One entry per frame, each `window_size / 2 + 1` levels in dBFS from
+    /// 0 Hz to Nyquist. Frame `i` starts at block `i * hop`.
+    pub frames: Vec<Vec<f32>>,
+}
+
+impl Spectrogram {
+    /// Center frequency of bin `bin`, in Hz.
+    pub fn bin_frequency(&self, bin: usize) -> f64 {
+        bin as f64 * self.sample_rate as f64 / self.window_size as f64
+    }
+
+    /// Mean power per bin over all frames, in dBFS.
+    pub fn average(&self) -> Vec<f32> {
+        let bins = self.window_size / 2 + 1;
+        let mut sum = vec![0.0f64; bins];
+        for frame in &self.frames {
+            for (s, &db) in sum.iter_mut().zip(frame) {
+                *s += 10f64.powf(db as f64 / 10.0);
+            }
+        }
+        let n = self.frames.len().max(1) as f64;
+        sum.iter().map(|&p| to_db(p / n)).collect()
+    }
+
+    /// Highest frequency whose average level is above `threshold_db`, or
+    /// `None` if no bin is.
+    ///
+    /// Around -90 dBFS works for 16-bit material: a result well below
+    /// Nyquist (e.g. 16 kHz for a 44.1 kHz file) suggests a lossy source.
+    pub fn cutoff_frequency(&self, threshold_db: f32) -> Option<f64> {
+        self.average()
+            .iter()
+            .rposition(|&db| db > threshold_db)
+            .map(|bin| self.bin_frequency(bin))
+    }
+}
+
+fn to_db(power: f64) -> f32 {
+    ((10.0 * power.log10()) as f32).max(FLOOR_DB)
+}
+
+/// Single-pass accumulator behind [`Spectrogram`].
+///
+/// Feed it interleaved samples in any chunking, then call `finish`.
+#[derive(Debug, Clone)]
+pub struct SpectrumAnalyzer {
+    channels: usize,
+    full_scale: f64,
+    hop: usize,
+    sample_rate: u32,
+    fft: Fft,
+    window: Vec<f64>,
+    /// Converts `|X|²` to power relative to a full-scale sine.
+    scale: f64,
+    /// Channel sum of a block split across chunks, and its sample count.
+    partial: (f64, usize),
+    /// Mono audio not yet consumed by a full frame.
+    audio: Vec<f64>,
+    /// Blocks to drop before the next frame starts, when `hop` exceeds
+    /// the window.
+    skip: usize,
+    spectrum: Vec<f64>,
+    frames: Vec<Vec<f32>>,
+}
+
+impl SpectrumAnalyzer {
+    /// Create an analyzer for interleaved audio.
+    ///
+    /// # Panics
+    ///
+    /// If `window_size` is not a power of two.
+    pub fn new(
+        channels: u16,
+        bits_per_sample: u16,
+        sample_rate: u32,
+        window_size: usize,
+        hop: usize,
+        window: WindowFunction,
+    ) -> Self {
+        let window = window.coefficients(window_size);
+        let gain: f64 = window.iter().sum();
+        SpectrumAnalyzer {
+            channels: channels.max(1) as usize,
+            full_scale: crate::convert::full_scale(bits_per_sample),
+            hop: hop.max(1),
+            sample_rate,
+            fft: Fft::new(window_size),
+            scale: 4.0 / (gain * gain).max(f64::MIN_POSITIVE),
+            window,
+            partial: (0.0, 0),
+            audio: Vec::new(),
+            skip: 0,
+            spectrum: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Add interleaved samples. A chunk may end mid-block; the next one
+    /// continues with the following channel.
+    pub fn update(&mut self, samples: &[i32]) {
+        for &s in samples {
+            self.partial.0 += s as f64;
+            self.partial.1 += 1;
+            if self.partial.1 == self.channels {
+                if self.skip > 0 {
+                    self.skip -= 1;
+                } else {
+                    self.audio.push(self.partial.0 / (self.channels as f64 * self.full_scale));
+                }
+                self.partial = (0.0, 0);
+            }
+        }
+
+        let size = self.fft.size();
+        let mut start = 0;
+        while start + size <= self.audio.len() {
+            let frame: Vec<f64> = self.audio[start..start + size]
+                .iter()
+                .zip(&self.window)
+                .map(|(s, w)| s * w)
+                .collect();
+            self.fft.power_spectrum(&frame, &mut self.spectrum);
+            self.frames
+                .push(self.spectrum.iter().map(|&p| to_db(p * self.scale)).collect());
+            start += self.hop;
+        }
+        // With hop > window_size, `start` can run past the buffered audio
+        let consumed = start.min(self.audio.len());
+        self.audio.drain(..consumed);
+        self.skip += start - consumed;
+    }
+
+    /// Spectrogram of everything fed so far. A trailing partial window is
+    /// dropped.
+    pub fn finish(self) -> Spectrogram {
+        Spectrogram {
+            sample_rate: self.sample_rate,
+            window_size: self.fft.size(),
+            hop: self.hop,
+            frames: self.frames,
+        }
+    }
+}
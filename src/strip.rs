@@ -0,0 +1,132 @@
+//! Removing tags from APE files.
+//!
+//! Taggers put an ID3v2 tag in front of the descriptor and APEv2 and/or
+//! ID3v1 tags after the audio. [`strip_tags`] removes them, leaving the
+//! descriptor, header, seek table, frames and terminating data byte for
+//! byte as they were, so a file checksum taken afterwards changes only
+//! when the audio does. Archives commonly strip tags before checksumming
+//! for that reason.
+//!
+//! Nothing in the descriptor counts the tags, and seek-table entries are
+//! relative to the descriptor rather than the file, so no header field
+//! needs rewriting. The descriptor's terminating data is checked instead:
+//! if it would reach into what looks like a tag, the file is left alone.
+//!
+//! ```no_run
+//! let summary = ape_rs::strip::strip_tags("track.ape").unwrap();
+//! println!("removed {} bytes of tags", summary.removed());
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::error::ApeError;
+use crate::header::{ApeFileHeader, parse_header};
+
+/// What [`strip_tags`] removed, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StripSummary {
+    /// The ID3v2 tag (and any padding after it) in front of the descriptor.
+    pub leading: u64,
+    /// APEv2 and ID3v1 tags after the audio.
+    pub trailing: u64,
+    /// Bytes between the terminating data and the tags that no part of
+    /// the format accounts for. They are not tags, so they are kept.
+    pub unaccounted: u64,
+}
+
+impl StripSummary {
+    /// Total bytes removed.
+    pub fn removed(&self) -> u64 {
+        self.leading + self.trailing
+    }
+}
+
+/// What stripping the tags from the APE stream in `input` would remove,
+/// without writing anything.
+///
+/// Leading junk counts as a tag only if it starts with an ID3v2 header;
+/// other junk is kept. Fails if the declared terminating data runs into
+/// the trailing tags, as removing them would cut it short.
+pub fn inspect<R: Read + Seek>(mut input: R) -> Result<StripSummary, ApeError> {
+    Ok(plan(&mut input)?.1)
+}
+
+/// Copy the APE stream in `input` to `out` without its tags.
+pub fn strip_tags_to<R: Read + Seek, W: Write>(mut input: R, mut out: W) -> Result<StripSummary, ApeError> {
+    let (header, summary) = plan(&mut input)?;
+    let start = summary.leading;
+    input.seek(SeekFrom::Start(start))?;
+    let len = header.tag_offset - start;
+    let copied = io::copy(&mut input.take(len), &mut out)?;
+    if copied < len {
+        return Err(ApeError::UnexpectedEof);
+    }
+    out.flush()?;
+    Ok(summary)
+}
+
+/// Remove the tags from the APE file at `path`, in place.
+///
+/// The stripped file is written next to the original and renamed over it,
+/// so an interrupted strip leaves the original intact. A file without tags
+/// is not rewritten.
+pub fn strip_tags<P: AsRef<Path>>(path: P) -> Result<StripSummary, ApeError> {
+    let path = path.as_ref();
+    let mut input = BufReader::new(File::open(path)?);
+    let summary = plan(&mut input)?.1;
+    if summary.removed() == 0 {
+        return Ok(summary);
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".strip-tmp");
+    let temp = path.with_file_name(temp_name);
+    let result = File::create(&temp).map_err(ApeError::from).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        strip_tags_to(&mut input, &mut out)?;
+        out.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        Ok(fs::rename(&temp, path)?)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.map(|()| summary)
+}
+
+/// Parse the header of the stream in `input` and work out what stripping
+/// its tags removes.
+fn plan<R: Read + Seek>(input: &mut R) -> Result<(ApeFileHeader, StripSummary), ApeError> {
+    let stream_len = input.seek(SeekFrom::End(0))?;
+    input.seek(SeekFrom::Start(0))?;
+    let header = parse_header(input)?;
+
+    let terminating_end = header
+        .frame_data_end()
+        .saturating_add(header.descriptor.terminating_data_bytes as u64);
+    if terminating_end > header.tag_offset {
+        return Err(ApeError::InvalidHeader(format!(
+            "terminating data ends at byte {terminating_end}, past the start of the tags at byte {}",
+            header.tag_offset
+        )));
+    }
+    let summary = StripSummary {
+        leading: if has_id3v2(input, &header)? { header.junk_bytes } else { 0 },
+        trailing: stream_len - header.tag_offset,
+        unaccounted: header.unaccounted_tail().map_or(0, |(_, len)| len),
+    };
+    Ok((header, summary))
+}
+
+/// Whether the leading junk is an ID3v2 tag.
+fn has_id3v2<R: Read + Seek>(input: &mut R, header: &ApeFileHeader) -> Result<bool, ApeError> {
+    if header.junk_bytes < 10 {
+        return Ok(false);
+    }
+    let mut magic = [0u8; 3];
+    input.seek(SeekFrom::Start(0))?;
+    input.read_exact(&mut magic)?;
+    Ok(&magic == b"ID3")
+}
@@ -0,0 +1,304 @@
+//! Synthetic APE files for tests and fuzzing (feature `testgen`).
+//!
+//! [`TestStream`] describes a small stream (compression level, channels,
+//! bit depth, frame size, length and a test signal) and encodes it into a
+//! complete, valid `.ape` file, with a canonical WAV header as header data
+//! and a correct file MD5. Tests can then cover every format the decoder
+//! supports, and edge cases such as one-block final frames, without binary
+//! fixtures: the expected output is [`TestStream::samples`].
+//!
+//! The encoder runs the decoder's stages in reverse: channel decorrelation
+//! and the predictor, the NNFilter stages, then a range encoder. It aims at
+//! valid streams, not compact ones.
+
+use crate::convert;
+use crate::crc;
+use crate::digest::Md5;
+use crate::error::ApeError;
+use crate::gapless::Gapless;
+use crate::header::{self, ApeDescriptor, ApeHeader};
+use crate::nnfilter::NNFilter;
+use crate::packet::{self, byte_swap_words};
+use crate::predictor::Predictor;
+use crate::range_coder::{RangeEncoder, RiceState};
+use crate::{ApeInfo, wav};
+
+/// Format version written to generated files.
+const VERSION: u16 = 3990;
+
+/// Audio content of a [`TestStream`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// All zeros.
+    Silence,
+    /// A sine wave at `frequency` Hz, `amplitude` relative to full scale.
+    /// The second channel is a quarter period ahead of the first.
+    Sine { frequency: f64, amplitude: f64 },
+    /// Uniform white noise up to `amplitude` relative to full scale, from
+    /// a fixed seed, independent per channel.
+    Noise { amplitude: f64, seed: u64 },
+    /// Alternating full-scale maximum and minimum: the largest residuals
+    /// the entropy coder has to handle.
+    FullScale,
+}
+
+/// A synthetic stream to encode. Fields default to 16-bit stereo at
+/// Normal with a 440 Hz sine, spread over several frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestStream {
+    /// 1000 (Fast) to 5000 (Insane).
+    pub compression_level: u16,
+    /// 1 or 2.
+    pub channels: u16,
+    /// 8, 16 or 24.
+    pub bits_per_sample: u16,
+    pub sample_rate: u32,
+    pub blocks_per_frame: u32,
+    /// Total blocks. The last frame holds what is left over, so
+    /// `blocks_per_frame * n + 1` gives a one-block final frame.
+    pub blocks: u64,
+    pub signal: Signal,
+    /// Store a canonical WAV header as the header data.
+    pub wav_header: bool,
+    /// Append an APEv2 tag recording this delay and padding.
+    pub gapless: Option<Gapless>,
+}
+
+impl Default for TestStream {
+    fn default() -> Self {
+        TestStream {
+            compression_level: 2000,
+            channels: 2,
+            bits_per_sample: 16,
+            sample_rate: 44100,
+            blocks_per_frame: 4096,
+            blocks: 10_000,
+            signal: Signal::Sine {
+                frequency: 440.0,
+                amplitude: 0.5,
+            },
+            wav_header: true,
+            gapless: None,
+        }
+    }
+}
+
+impl TestStream {
+    /// One stream per format the build supports: every enabled
+    /// compression level, mono and stereo, 8, 16 and 24 bits, with the
+    /// other fields at their defaults.
+    pub fn all_formats() -> Vec<TestStream> {
+        let mut streams = Vec::new();
+        for compression_level in (1000..=5000).step_by(1000) {
+            if !packet::is_supported_level(compression_level) {
+                continue;
+            }
+            for channels in [1, 2] {
+                for bits_per_sample in [8, 16, 24] {
+                    streams.push(TestStream {
+                        compression_level,
+                        channels,
+                        bits_per_sample,
+                        ..TestStream::default()
+                    });
+                }
+            }
+        }
+        streams
+    }
+
+    /// The stream's interleaved samples: what decoding
+    /// [`encode`](Self::encode)'s output yields.
+    pub fn samples(&self) -> Vec<i32> {
+        let channels = self.channels as usize;
+        let max = *convert::sample_range(self.bits_per_sample).end() as i64;
+        let scale = |amplitude: f64, x: f64| (x * amplitude.clamp(0.0, 1.0) * max as f64).round() as i32;
+        let mut rng = self.signal_seed();
+        let mut samples = Vec::with_capacity(self.blocks as usize * channels);
+        for block in 0..self.blocks {
+            for channel in 0..channels {
+                samples.push(match self.signal {
+                    Signal::Silence => 0,
+                    Signal::Sine { frequency, amplitude } => {
+                        let t = block as f64 / self.sample_rate as f64;
+                        let phase = channel as f64 * core::f64::consts::FRAC_PI_2;
+                        scale(amplitude, (core::f64::consts::TAU * frequency * t + phase).sin())
+                    }
+                    Signal::Noise { amplitude, .. } => {
+                        // xorshift64*, mapped to [-1, 1)
+                        rng ^= rng >> 12;
+                        rng ^= rng << 25;
+                        rng ^= rng >> 27;
+                        let r = rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+                        scale(amplitude, r as f64 / (1u64 << 52) as f64 - 1.0)
+                    }
+                    Signal::FullScale if block % 2 == 0 => max as i32,
+                    Signal::FullScale => -max as i32 - 1,
+                });
+            }
+        }
+        samples
+    }
+
+    fn signal_seed(&self) -> u64 {
+        match self.signal {
+            Signal::Noise { seed, .. } => seed | 1,
+            _ => 1,
+        }
+    }
+
+    /// Encode [`samples`](Self::samples) into a complete `.ape` file.
+    pub fn encode(&self) -> Result<Vec<u8>, ApeError> {
+        self.encode_samples(&self.samples())
+    }
+
+    /// Encode caller-supplied interleaved `samples` in this stream's
+    /// format, ignoring `signal` and `blocks`.
+    ///
+    /// Fails with [`ErrorKind::InvalidHeader`](crate::ErrorKind::InvalidHeader)
+    /// for a format the decoder does not support, a partial block, or a
+    /// sample outside the bit depth's range.
+    pub fn encode_samples(&self, samples: &[i32]) -> Result<Vec<u8>, ApeError> {
+        let channels = self.channels as usize;
+        let mut ape_header = ApeHeader {
+            compression_level: self.compression_level,
+            format_flags: 0,
+            blocks_per_frame: self.blocks_per_frame,
+            final_frame_blocks: 0,
+            total_frames: 0,
+            bits_per_sample: self.bits_per_sample,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        };
+        header::validate_header(&ape_header)?;
+        if !samples.len().is_multiple_of(channels) {
+            return Err(ApeError::InvalidHeader(format!(
+                "{} samples is not a whole number of {channels}-channel blocks",
+                samples.len()
+            )));
+        }
+        let range = convert::sample_range(self.bits_per_sample);
+        if let Some(i) = samples.iter().position(|s| !range.contains(s)) {
+            return Err(ApeError::InvalidHeader(format!(
+                "sample {i} ({}) does not fit in {} bits",
+                samples[i], self.bits_per_sample
+            )));
+        }
+
+        // Frames back to back as the range decoder reads them, then packed
+        // into little-endian words
+        let mut encoder = FrameEncoder::new(&ape_header);
+        let frame_samples = self.blocks_per_frame as usize * channels;
+        let mut frame_data = Vec::new();
+        let mut frame_starts = Vec::new();
+        for frame in samples.chunks(frame_samples) {
+            frame_starts.push(frame_data.len() as u64);
+            encoder.encode(frame, &mut frame_data);
+            ape_header.final_frame_blocks = (frame.len() / channels) as u32;
+        }
+        frame_data.resize(frame_data.len().next_multiple_of(4), 0);
+        byte_swap_words(&mut frame_data);
+        ape_header.total_frames = frame_starts.len() as u32;
+
+        let header_data = if self.wav_header {
+            let info = ApeInfo {
+                sample_rate: self.sample_rate,
+                channels: self.channels,
+                bits_per_sample: self.bits_per_sample,
+                total_samples: samples.len() as u64,
+                compression_level: self.compression_level,
+                format_version: VERSION,
+                channel_mask: None,
+            };
+            wav::canonical_header(&info).to_vec()
+        } else {
+            Vec::new()
+        };
+
+        let mut descriptor = ApeDescriptor {
+            version: VERSION,
+            descriptor_bytes: 52,
+            header_bytes: 24,
+            seek_table_bytes: 4 * frame_starts.len() as u32,
+            header_data_bytes: header_data.len() as u32,
+            ape_frame_data_bytes: frame_data.len() as u32,
+            ape_frame_data_bytes_high: (frame_data.len() as u64 >> 32) as u32,
+            terminating_data_bytes: 0,
+            file_md5: [0; 16],
+        };
+        let data_offset = 52 + 24 + descriptor.seek_table_bytes as u64 + header_data.len() as u64;
+        let mut seek_table = Vec::with_capacity(frame_starts.len() * 4);
+        for start in frame_starts {
+            let offset = u32::try_from(data_offset + start)
+                .map_err(|_| ApeError::InvalidHeader("stream too large for a 32-bit seek table".into()))?;
+            seek_table.extend_from_slice(&offset.to_le_bytes());
+        }
+        let mut header_bytes = Vec::with_capacity(24);
+        ape_header.write_to(&mut header_bytes);
+
+        // The file MD5 covers the header data, frame data and terminating
+        // data, then the APE header and seek table
+        let mut md5 = Md5::new();
+        for part in [&header_data, &frame_data, &header_bytes, &seek_table] {
+            md5.update(part);
+        }
+        descriptor.file_md5 = md5.finish();
+
+        let mut out = Vec::with_capacity(data_offset as usize + frame_data.len());
+        descriptor.write_to(&mut out);
+        out.extend_from_slice(&header_bytes);
+        out.extend_from_slice(&seek_table);
+        out.extend_from_slice(&header_data);
+        out.extend_from_slice(&frame_data);
+        if let Some(gapless) = self.gapless {
+            out.extend_from_slice(&gapless.apev2_tag((samples.len() / channels) as u64));
+        }
+        Ok(out)
+    }
+}
+
+/// Frame encoder: the inverse of [`PacketDecoder`](packet::PacketDecoder).
+struct FrameEncoder {
+    bits_per_sample: u16,
+    filters: Vec<NNFilter>,
+    predictor: Predictor,
+}
+
+impl FrameEncoder {
+    fn new(header: &ApeHeader) -> Self {
+        let fset = (header.compression_level / 1000 - 1) as usize;
+        FrameEncoder {
+            bits_per_sample: header.bits_per_sample,
+            filters: (0..header.channels).map(|_| NNFilter::new(fset)).collect(),
+            predictor: Predictor::new(),
+        }
+    }
+
+    /// Append one frame of interleaved `samples` to `out` in the decoder's
+    /// byte order: CRC, then the range-coded blocks.
+    fn encode(&mut self, samples: &[i32], out: &mut Vec<u8>) {
+        for f in &mut self.filters {
+            f.reset();
+        }
+        self.predictor.reset();
+
+        let mut rc = RangeEncoder::new();
+        if self.filters.len() == 1 {
+            let mut rice = RiceState::new();
+            for &sample in samples {
+                let filtered = self.predictor.encode_mono(sample);
+                rc.encode_value(self.filters[0].compress(filtered), &mut rice);
+            }
+        } else {
+            let (mut rice_y, mut rice_x) = (RiceState::new(), RiceState::new());
+            for pair in samples.chunks_exact(2) {
+                let (filtered_y, filtered_x) = self.predictor.encode_stereo(pair[0], pair[1]);
+                rc.encode_value(self.filters[0].compress(filtered_y), &mut rice_y);
+                rc.encode_value(self.filters[1].compress(filtered_x), &mut rice_x);
+            }
+        }
+
+        out.extend_from_slice(&crc::frame_crc(samples, self.bits_per_sample).to_be_bytes());
+        out.extend_from_slice(&rc.finish());
+    }
+}
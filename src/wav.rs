@@ -0,0 +1,272 @@
+//! Canonical RIFF/WAVE headers.
+//!
+//! Files encoded without their original header (or whose stored header is
+//! unwanted, e.g. one with an oversized `data` length from a streaming
+//! encoder) still decode to a standard WAV file: [`canonical_header`]
+//! builds the 44-byte header of a plain PCM `WAVE_FORMAT_PCM` file for a
+//! stream's format and length.
+//!
+//! [`WavStream`] goes further and presents a whole APE file as that WAV
+//! file, decoding on demand behind `Read + Seek`.
+
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::ApeInfo;
+use crate::riff::WAVE_FORMAT_PCM;
+#[cfg(feature = "std")]
+use crate::ApeReader;
+#[cfg(feature = "std")]
+use crate::convert::{self, PcmLayout};
+#[cfg(feature = "std")]
+use crate::error::ApeError;
+
+/// Size of a canonical header: `RIFF` + `fmt ` (16-byte body) + `data`
+/// chunk headers.
+pub const CANONICAL_HEADER_LEN: usize = 44;
+
+/// Build the canonical 44-byte WAV header for `info`'s format, with chunk
+/// sizes for `info.total_samples` samples.
+///
+/// RIFF sizes are 32-bit: for streams over 4 GiB the sizes saturate at
+/// `u32::MAX`, which most readers take to mean "until the end of the file".
+pub fn canonical_header(info: &ApeInfo) -> [u8; CANONICAL_HEADER_LEN] {
+    let bytes_per_sample = (info.bits_per_sample as u32).div_ceil(8);
+    let block_align = bytes_per_sample * info.channels as u32;
+    let data_bytes = info.total_samples.saturating_mul(bytes_per_sample as u64);
+    let data_len = u32::try_from(data_bytes).unwrap_or(u32::MAX);
+    let riff_len = data_len.saturating_add(CANONICAL_HEADER_LEN as u32 - 8);
+
+    let mut out = [0; CANONICAL_HEADER_LEN];
+    let fields: [&[u8]; 13] = [
+        b"RIFF",
+        &riff_len.to_le_bytes(),
+        b"WAVE",
+        b"fmt ",
+        &16u32.to_le_bytes(),
+        &WAVE_FORMAT_PCM.to_le_bytes(),
+        &info.channels.to_le_bytes(),
+        &info.sample_rate.to_le_bytes(),
+        &(info.sample_rate * block_align).to_le_bytes(),
+        &(block_align as u16).to_le_bytes(),
+        &info.bits_per_sample.to_le_bytes(),
+        b"data",
+        &data_len.to_le_bytes(),
+    ];
+    let mut pos = 0;
+    for field in fields {
+        out[pos..pos + field.len()].copy_from_slice(field);
+        pos += field.len();
+    }
+    out
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> ApeReader<R> {
+    /// Write the rest of the stream to `out` as a WAV file with a
+    /// [`canonical_header`], ignoring any stored header.
+    ///
+    /// The header declares the whole stream, so call this on a reader at
+    /// the start. Frames are handled per the reader's frame error mode.
+    pub fn write_wav<W: Write>(&mut self, mut out: W) -> Result<(), ApeError> {
+        out.write_all(&canonical_header(&self.info))?;
+        self.write_pcm(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Write the stream to a WAV file at `path`, as [`write_wav`](Self::write_wav).
+    ///
+    /// ```no_run
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut reader = ApeReader::open("track.ape").unwrap();
+    /// reader.save_wav("track.wav").unwrap();
+    /// ```
+    pub fn save_wav<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ApeError> {
+        self.write_wav(BufWriter::new(File::create(path)?))
+    }
+
+    /// Present the stream as a WAV file with a [`canonical_header`]: see
+    /// [`WavStream`].
+    ///
+    /// ```no_run
+    /// use std::io::{Read, Seek, SeekFrom};
+    /// use ape_rs::ApeReader;
+    ///
+    /// let mut wav = ApeReader::open("track.ape").unwrap().into_wav_stream();
+    /// let mut block = [0; 4];
+    /// wav.seek(SeekFrom::Start(44 + 4 * 44_100)).unwrap();
+    /// wav.read_exact(&mut block).unwrap(); // the 16-bit stereo block at 1 s
+    /// ```
+    pub fn into_wav_stream(self) -> WavStream<R> {
+        WavStream::new(self)
+    }
+}
+
+/// Samples decoded per refill of a [`WavStream`].
+#[cfg(feature = "std")]
+const STREAM_CHUNK_SAMPLES: usize = 8192;
+
+/// `WavStream::buffer_from` while nothing is buffered.
+#[cfg(feature = "std")]
+const NOT_BUFFERED: u64 = u64::MAX;
+
+/// An APE stream as an uncompressed WAV file, from
+/// [`ApeReader::into_wav_stream`]: `Read + Seek` over a [`canonical_header`]
+/// followed by the PCM data, decoded as it is read.
+///
+/// Sequential reads decode frame after frame. Seeking within the data is
+/// sample-accurate and costs one frame decode when a read follows it.
+/// The file always has the length the header declares: a frame the frame
+/// error mode skips, or samples missing from a truncated stream, read as
+/// silence. Decode errors are returned from `read` as [`io::Error`]s
+/// wrapping the [`ApeError`].
+#[cfg(feature = "std")]
+pub struct WavStream<R: Read + Seek> {
+    reader: ApeReader<R>,
+    header: [u8; CANONICAL_HEADER_LEN],
+    bytes_per_sample: u64,
+    block_align: u64,
+    /// Length of the PCM data.
+    data_len: u64,
+    /// Read position in the virtual file.
+    pos: u64,
+    /// Packed PCM decoded last, starting at data offset `buffer_start`.
+    buffer: Vec<u8>,
+    buffer_start: u64,
+    /// Data offset the last refill was for. Up to `buffer_start` the data
+    /// is silence (a dropped frame), as is all of it after the end of the
+    /// decoded stream.
+    buffer_from: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> WavStream<R> {
+    /// Present `reader`'s stream as a WAV file. The reader may be at any
+    /// position; the file starts at the beginning of the stream.
+    pub fn new(reader: ApeReader<R>) -> Self {
+        let info = &reader.info;
+        let bytes_per_sample = convert::bytes_per_sample(info.bits_per_sample) as u64;
+        WavStream {
+            header: canonical_header(info),
+            bytes_per_sample,
+            block_align: bytes_per_sample * info.channels.max(1) as u64,
+            data_len: info.total_samples * bytes_per_sample,
+            reader,
+            pos: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            buffer_from: NOT_BUFFERED,
+        }
+    }
+
+    /// Length of the virtual file in bytes.
+    pub fn len(&self) -> u64 {
+        CANONICAL_HEADER_LEN as u64 + self.data_len
+    }
+
+    /// Whether the virtual file is empty; never, as it has a header.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The underlying reader.
+    pub fn get_ref(&self) -> &ApeReader<R> {
+        &self.reader
+    }
+
+    /// Unwrap the underlying reader, positioned wherever reading left it.
+    pub fn into_inner(self) -> ApeReader<R> {
+        self.reader
+    }
+
+    /// End of the data `buffer` accounts for.
+    fn buffer_end(&self) -> u64 {
+        if self.buffer.is_empty() { self.data_len } else { self.buffer_start + self.buffer.len() as u64 }
+    }
+
+    /// Decode the next chunk into `buffer`, from data offset `offset` on.
+    /// The buffer may start past `offset` after a dropped frame, and is
+    /// left empty at the end of the stream.
+    fn refill(&mut self, offset: u64) -> Result<(), ApeError> {
+        self.buffer.clear();
+        self.buffer_from = NOT_BUFFERED;
+        let next = self.reader.decoder.sample_index() * self.bytes_per_sample;
+        if next != offset {
+            self.reader.seek_to_block(offset / self.block_align)?;
+        }
+        let decoder = &mut self.reader.decoder;
+        loop {
+            self.buffer_start = decoder.sample_index() * self.bytes_per_sample;
+            let samples = decoder.buffer.take(STREAM_CHUNK_SAMPLES);
+            if !samples.is_empty() {
+                convert::pack(samples, self.bytes_per_sample as usize, PcmLayout::Wav, &mut self.buffer);
+                break;
+            }
+            if decoder.finished || !decoder.decode_next_frame()? {
+                break;
+            }
+        }
+        self.buffer_from = offset;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Read for WavStream<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let header_len = CANONICAL_HEADER_LEN as u64;
+        if self.pos >= self.len() || out.is_empty() {
+            return Ok(0);
+        }
+        if self.pos < header_len {
+            let header = &self.header[self.pos as usize..];
+            let n = header.len().min(out.len());
+            out[..n].copy_from_slice(&header[..n]);
+            self.pos += n as u64;
+            return Ok(n);
+        }
+
+        let offset = self.pos - header_len;
+        if !(self.buffer_from..self.buffer_end()).contains(&offset) {
+            self.refill(offset).map_err(|e| match e {
+                ApeError::Io(e) => e.into(),
+                e => io::Error::other(e),
+            })?;
+        }
+        let n = if offset < self.buffer_start || self.buffer.is_empty() {
+            let end = if self.buffer.is_empty() { self.data_len } else { self.buffer_start };
+            let n = (end - offset).min(out.len() as u64) as usize;
+            out[..n].fill(if self.bytes_per_sample == 1 { 0x80 } else { 0 });
+            n
+        } else {
+            let bytes = &self.buffer[(offset - self.buffer_start) as usize..];
+            let n = bytes.len().min(out.len());
+            out[..n].copy_from_slice(&bytes[..n]);
+            n
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Seek for WavStream<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(d) => self.len().checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        let Some(target) = target else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the file"));
+        };
+        self.pos = target;
+        Ok(target)
+    }
+}
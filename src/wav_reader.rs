@@ -0,0 +1,279 @@
+//! Reading WAV files: the input side of encoding and comparison.
+//!
+//! [`WavReader`] parses the chunks in front of the samples as it meets
+//! them (`fmt `, `fact`, and any others, which are kept but not
+//! interpreted) and then streams the `data` chunk, so it reads from pipes
+//! as well as files and never holds more than one buffer of PCM. It takes
+//! integer PCM in 1- to 4-byte containers, plain or
+//! `WAVE_FORMAT_EXTENSIBLE`, and follows RIFF's padding of odd-sized
+//! chunks.
+//!
+//! A `data` chunk whose size is `0xFFFFFFFF`, as streaming writers leave
+//! it, or larger than the file, is read to the end of the input.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! use ape_rs::wav_reader::WavReader;
+//!
+//! let mut wav = WavReader::new(BufReader::new(File::open("track.wav").unwrap())).unwrap();
+//! println!("{} Hz, {} channels, {} bits", wav.sample_rate(), wav.channels(), wav.bits_per_sample());
+//! let mut buf = [0i32; 4096];
+//! while let n @ 1.. = wav.read_samples(&mut buf).unwrap() {
+//!     // encode buf[..n]
+//! }
+//! ```
+
+use std::io::{self, Read};
+
+use crate::convert::{self, PcmLayout};
+use crate::error::ApeError;
+use crate::riff::{WAVE_FORMAT_PCM, WaveFormat};
+
+/// Bytes of PCM read from the input at a time.
+const READ_BYTES: usize = 64 * 1024;
+
+/// `data` chunk size written by encoders that could not seek back to fill
+/// it in.
+const UNKNOWN_SIZE: u32 = u32::MAX;
+
+/// A streaming reader over the samples of a WAV file.
+#[derive(Debug)]
+pub struct WavReader<R: Read> {
+    input: R,
+    format: WaveFormat,
+    /// Sample count from the `fact` chunk.
+    fact_samples: Option<u32>,
+    /// Everything before the samples, `data` chunk header included.
+    header: Vec<u8>,
+    /// Bytes per sample container.
+    container: usize,
+    /// `data` chunk size, unless unknown.
+    data_len: Option<u64>,
+    /// Bytes of the `data` chunk read so far.
+    data_read: u64,
+    /// Bytes read from the input and not yet unpacked.
+    pending: Vec<u8>,
+    /// Samples unpacked and not yet returned, from `unpacked_pos` on.
+    unpacked: Vec<i32>,
+    unpacked_pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> WavReader<R> {
+    /// Read the chunks in front of the samples from `input`, leaving it at
+    /// the first sample.
+    ///
+    /// Fails with [`ErrorKind::InvalidHeader`](crate::ErrorKind::InvalidHeader)
+    /// if the input is not a RIFF/WAVE file, has no `fmt ` chunk before
+    /// its `data` chunk, or is not integer PCM in 1- to 4-byte containers.
+    pub fn new(mut input: R) -> Result<Self, ApeError> {
+        let mut header = vec![0u8; 12];
+        input.read_exact(&mut header).map_err(eof_as_invalid)?;
+        if !header.starts_with(b"RIFF") || &header[8..12] != b"WAVE" {
+            return Err(ApeError::InvalidHeader("not a RIFF/WAVE file".into()));
+        }
+
+        let mut format = None;
+        let mut fact_samples = None;
+        let data_size = loop {
+            let start = header.len();
+            header.resize(start + 8, 0);
+            input.read_exact(&mut header[start..]).map_err(eof_as_invalid)?;
+            let id: [u8; 4] = header[start..start + 4].try_into().unwrap();
+            let size = u32::from_le_bytes(header[start + 4..start + 8].try_into().unwrap());
+            if &id == b"data" {
+                break size;
+            }
+
+            // Chunks are padded to an even length
+            let padded = size as u64 + (size as u64 & 1);
+            let body_start = header.len();
+            let read = (&mut input).take(padded).read_to_end(&mut header)?;
+            if (read as u64) < padded {
+                return Err(ApeError::InvalidHeader("WAV file ends inside a chunk".into()));
+            }
+            let body = &header[body_start..body_start + size as usize];
+            match &id {
+                b"fmt " => format = WaveFormat::parse(body),
+                b"fact" if body.len() >= 4 => fact_samples = Some(u32::from_le_bytes(body[..4].try_into().unwrap())),
+                _ => {}
+            }
+        };
+
+        let format = format.ok_or_else(|| ApeError::InvalidHeader("WAV file has no valid fmt chunk".into()))?;
+        let container = format.container_bytes() as usize;
+        if format.sample_format() != WAVE_FORMAT_PCM || !(1..=4).contains(&container) {
+            return Err(ApeError::InvalidHeader(format!(
+                "WAV file is format {:#06x} with {container}-byte samples, not integer PCM",
+                format.sample_format()
+            )));
+        }
+        Ok(WavReader {
+            input,
+            format,
+            fact_samples,
+            header,
+            container,
+            data_len: (data_size != UNKNOWN_SIZE).then_some(data_size as u64),
+            data_read: 0,
+            pending: Vec::with_capacity(READ_BYTES),
+            unpacked: Vec::new(),
+            unpacked_pos: 0,
+            eof: false,
+        })
+    }
+
+    /// The `fmt ` chunk.
+    pub fn format(&self) -> &WaveFormat {
+        &self.format
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.format.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.format.sample_rate
+    }
+
+    /// Bits per sample container: 8, 16, 24 or 32. Samples are returned at
+    /// this depth, whatever
+    /// [`valid_bits_per_sample`](Self::valid_bits_per_sample) says.
+    pub fn bits_per_sample(&self) -> u16 {
+        self.container as u16 * 8
+    }
+
+    /// Bits in use in each container: the extensible format's
+    /// `wValidBitsPerSample`, else the `fmt ` chunk's bits per sample.
+    pub fn valid_bits_per_sample(&self) -> u16 {
+        self.format.valid_bits_per_sample.unwrap_or(self.format.bits_per_sample)
+    }
+
+    /// Interleaved samples the `data` chunk declares, or `None` if its
+    /// size was left unknown. The input may end sooner.
+    pub fn total_samples(&self) -> Option<u64> {
+        self.data_len.map(|len| len / self.container as u64)
+    }
+
+    /// Blocks per channel from the `fact` chunk, if there is one.
+    pub fn fact_samples(&self) -> Option<u32> {
+        self.fact_samples
+    }
+
+    /// The file up to the first sample: the `RIFF`/`WAVE` preamble, every
+    /// chunk before the samples and the `data` chunk's header, as an APE
+    /// file stores it for restoring the original.
+    pub fn header_bytes(&self) -> &[u8] {
+        &self.header
+    }
+
+    /// Fill `buf` with the next interleaved samples, sign-extended (8-bit
+    /// samples re-centered on zero). Returns how many were written: fewer
+    /// than `buf.len()` only at the end of the samples, and 0 there.
+    pub fn read_samples(&mut self, buf: &mut [i32]) -> Result<usize, ApeError> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.unpacked_pos == self.unpacked.len() && !self.refill()? {
+                break;
+            }
+            let ready = &self.unpacked[self.unpacked_pos..];
+            let n = ready.len().min(buf.len() - written);
+            buf[written..written + n].copy_from_slice(&ready[..n]);
+            self.unpacked_pos += n;
+            written += n;
+        }
+        Ok(written)
+    }
+
+    /// The rest of the samples, in one `Vec`.
+    pub fn read_all_samples(&mut self) -> Result<Vec<i32>, ApeError> {
+        let mut samples = self.unpacked.split_off(self.unpacked_pos);
+        self.unpacked_pos = self.unpacked.len();
+        while self.refill()? {
+            samples.append(&mut self.unpacked);
+            self.unpacked_pos = 0;
+        }
+        Ok(samples)
+    }
+
+    /// Iterator over the rest of the samples.
+    pub fn samples(&mut self) -> WavSamples<'_, R> {
+        WavSamples { reader: self }
+    }
+
+    /// Skip the remaining samples and return what follows the `data`
+    /// chunk (its pad byte, if its size is odd, then any chunks such as
+    /// `LIST`): an APE file's terminating data. Empty for a `data` chunk
+    /// of unknown size, which runs to the end of the input.
+    pub fn into_trailer(mut self) -> Result<Vec<u8>, ApeError> {
+        let Some(len) = self.data_len else {
+            return Ok(Vec::new());
+        };
+        let skip = len - self.data_read;
+        let skipped = io::copy(&mut (&mut self.input).take(skip), &mut io::sink())?;
+        let mut trailer = Vec::new();
+        if skipped == skip {
+            self.input.read_to_end(&mut trailer)?;
+        }
+        Ok(trailer)
+    }
+
+    /// The input, positioned wherever reading stopped.
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+
+    /// Read and unpack the next buffer of samples into `unpacked`. Returns
+    /// false at the end of the samples.
+    fn refill(&mut self) -> Result<bool, ApeError> {
+        self.unpacked.clear();
+        self.unpacked_pos = 0;
+        while self.unpacked.is_empty() && !self.eof {
+            let want = match self.data_len {
+                Some(len) => (len - self.data_read).min(READ_BYTES as u64),
+                None => READ_BYTES as u64,
+            };
+            let read = (&mut self.input).take(want).read_to_end(&mut self.pending)?;
+            self.data_read += read as u64;
+            self.eof = read == 0;
+
+            // A partial sample waits for the rest, or is dropped at the end
+            let whole = self.pending.len() - self.pending.len() % self.container;
+            convert::unpack(&self.pending[..whole], self.container, PcmLayout::Wav, &mut self.unpacked);
+            self.pending.drain(..whole);
+        }
+        Ok(!self.unpacked.is_empty())
+    }
+}
+
+/// Iterator over a [`WavReader`]'s samples, from [`WavReader::samples`].
+#[derive(Debug)]
+pub struct WavSamples<'a, R: Read> {
+    reader: &'a mut WavReader<R>,
+}
+
+impl<R: Read> Iterator for WavSamples<'_, R> {
+    type Item = Result<i32, ApeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = &mut *self.reader;
+        if reader.unpacked_pos == reader.unpacked.len() {
+            match reader.refill() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        reader.unpacked_pos += 1;
+        Some(Ok(reader.unpacked[reader.unpacked_pos - 1]))
+    }
+}
+
+/// A header cut short is a malformed file rather than an I/O failure.
+fn eof_as_invalid(e: io::Error) -> ApeError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => ApeError::InvalidHeader("WAV file ends inside its header".into()),
+        _ => e.into(),
+    }
+}
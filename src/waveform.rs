@@ -0,0 +1,145 @@
+//! Waveform peak generation: min/max (and optionally RMS) per bucket of
+//! blocks, at several zoom levels from a single decode pass.
+//!
+//! Every level is accumulated independently while the samples stream past,
+//! so bucket sizes need not be multiples of each other. The last bucket of
+//! each level covers whatever blocks remain.
+
+/// Extremes of one channel over one bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Peak {
+    /// Smallest sample value.
+    pub min: i32,
+    /// Largest sample value.
+    pub max: i32,
+    /// Root mean square relative to full scale, if requested.
+    pub rms: Option<f32>,
+}
+
+/// All buckets at one zoom level.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeakLevel {
+    /// Blocks (samples per channel) covered by each bucket.
+    pub blocks_per_bucket: u64,
+    /// Buckets for each channel, in stream order.
+    pub channels: Vec<Vec<Peak>>,
+}
+
+/// Result of [`ApeReader::waveform`](crate::ApeReader::waveform): one
+/// [`PeakLevel`] per requested bucket size, in the order requested.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waveform {
+    /// Zoom levels, finest first if the bucket sizes were given that way.
+    pub levels: Vec<PeakLevel>,
+}
+
+/// In-progress bucket of one channel.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    min: i32,
+    max: i32,
+    sum_of_squares: f64,
+}
+
+impl Bucket {
+    const EMPTY: Bucket = Bucket {
+        min: i32::MAX,
+        max: i32::MIN,
+        sum_of_squares: 0.0,
+    };
+}
+
+/// Single-pass accumulator behind [`Waveform`].
+///
+/// Feed it interleaved samples in any chunking, then call `finish`.
+#[derive(Debug, Clone)]
+pub struct WaveformGenerator {
+    full_scale: f64,
+    with_rms: bool,
+    levels: Vec<PeakLevel>,
+    /// Current bucket per level, per channel.
+    current: Vec<Vec<Bucket>>,
+    /// Blocks in the current bucket, per level.
+    filled: Vec<u64>,
+    /// Interleaved samples seen, to track the channel across chunks.
+    samples: u64,
+}
+
+impl WaveformGenerator {
+    /// Create a generator for interleaved audio with the given layout and
+    /// one zoom level per entry of `bucket_sizes` (in blocks; 0 is treated
+    /// as 1).
+    pub fn new(channels: u16, bits_per_sample: u16, bucket_sizes: &[u64], with_rms: bool) -> Self {
+        let channels = channels.max(1) as usize;
+        WaveformGenerator {
+            full_scale: crate::convert::full_scale(bits_per_sample),
+            with_rms,
+            levels: bucket_sizes
+                .iter()
+                .map(|&size| PeakLevel {
+                    blocks_per_bucket: size.max(1),
+                    channels: vec![Vec::new(); channels],
+                })
+                .collect(),
+            current: vec![vec![Bucket::EMPTY; channels]; bucket_sizes.len()],
+            filled: vec![0; bucket_sizes.len()],
+            samples: 0,
+        }
+    }
+
+    /// Add interleaved samples. A chunk may end mid-block; the next one
+    /// continues with the following channel.
+    pub fn update(&mut self, samples: &[i32]) {
+        let channels = self.current.first().map_or(1, Vec::len);
+        for &s in samples {
+            let ch = (self.samples % channels as u64) as usize;
+            for level in 0..self.levels.len() {
+                let bucket = &mut self.current[level][ch];
+                bucket.min = bucket.min.min(s);
+                bucket.max = bucket.max.max(s);
+                bucket.sum_of_squares += s as f64 * s as f64;
+                if ch == channels - 1 {
+                    self.filled[level] += 1;
+                    if self.filled[level] == self.levels[level].blocks_per_bucket {
+                        self.flush(level);
+                    }
+                }
+            }
+            self.samples += 1;
+        }
+    }
+
+    /// Close the current bucket of `level`.
+    fn flush(&mut self, level: usize) {
+        let blocks = self.filled[level].max(1) as f64;
+        for (ch, bucket) in self.current[level].iter_mut().enumerate() {
+            if bucket.min <= bucket.max {
+                let rms = self
+                    .with_rms
+                    .then(|| ((bucket.sum_of_squares / blocks).sqrt() / self.full_scale) as f32);
+                self.levels[level].channels[ch].push(Peak {
+                    min: bucket.min,
+                    max: bucket.max,
+                    rms,
+                });
+            }
+            *bucket = Bucket::EMPTY;
+        }
+        self.filled[level] = 0;
+    }
+
+    /// Peaks of everything fed so far, including partial final buckets.
+    pub fn finish(mut self) -> Waveform {
+        for level in 0..self.levels.len() {
+            if self.current[level].iter().any(|b| b.min <= b.max) {
+                self.flush(level);
+            }
+        }
+        Waveform {
+            levels: self.levels,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Allocation checks, in their own test binary so the counting allocator
+//! sees nothing but these tests.
+#![cfg(feature = "testgen")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::Cursor;
+use std::ops::ControlFlow;
+
+use ape_rs::ApeReader;
+use ape_rs::testgen::TestStream;
+
+/// The system allocator, counting allocations made on each thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn steady_state_decode_does_not_allocate() {
+    for compression_level in [1000, 2000, 3000, 4000, 5000] {
+        let stream = TestStream { compression_level, blocks: 5 * 4096, ..TestStream::default() };
+        let mut reader = ApeReader::new(Cursor::new(stream.encode().unwrap())).unwrap();
+        let frame_samples = 4096 * stream.channels as usize;
+
+        // Sample by sample, from construction on
+        let before = allocations();
+        let mut samples = reader.samples();
+        for _ in 0..2 * frame_samples {
+            samples.next().unwrap().unwrap();
+        }
+        assert_eq!(allocations() - before, 0, "level {compression_level}: samples()");
+
+        // Lent chunks: after the first, which sizes the chunk buffer
+        let mut chunks = reader.samples_chunks(1000);
+        chunks.next_chunk().unwrap().unwrap();
+        let before = allocations();
+        while let Some(chunk) = chunks.next_chunk() {
+            chunk.unwrap();
+        }
+        assert_eq!(allocations() - before, 0, "level {compression_level}: next_chunk()");
+
+        // Into a fixed slice
+        let mut reader = ApeReader::new(Cursor::new(stream.encode().unwrap())).unwrap();
+        let mut buf = [0i32; 777];
+        let before = allocations();
+        while reader.read_samples(&mut buf).unwrap() > 0 {}
+        assert_eq!(allocations() - before, 0, "level {compression_level}: read_samples()");
+
+        // A callback over a whole stream
+        let mut reader = ApeReader::new(Cursor::new(stream.encode().unwrap())).unwrap();
+        let before = allocations();
+        let mut total = 0;
+        let flow = reader.decode_with(|chunk| {
+            total += chunk.len();
+            ControlFlow::<()>::Continue(())
+        });
+        assert!(flow.unwrap().is_continue());
+        assert_eq!(allocations() - before, 0, "level {compression_level}: decode_with()");
+        assert_eq!(total, 5 * frame_samples);
+    }
+}
@@ -4,7 +4,10 @@
 //!   1. Download APE files to /tmp/ape_test/
 //!   2. cargo test --release -- archive
 
+#![cfg(feature = "std")]
+
 use ape_rs::ApeReader;
+use ape_rs::wav_reader::WavReader;
 use std::path::Path;
 use std::process::Command;
 
@@ -87,7 +90,7 @@ fn test_one_file(ape_path: &str) {
 
     // Step 3: Compare
     let wav_data = std::fs::read(&wav_path).expect("read reference wav");
-    let wav_samples = parse_wav_samples(&wav_data, info.bits_per_sample);
+    let wav_samples = parse_wav_samples(&wav_data);
     let _ = std::fs::remove_file(&wav_path); // clean up
 
     let compare_len = ape_samples.len().min(wav_samples.len());
@@ -120,33 +123,7 @@ fn test_one_file(ape_path: &str) {
         "{ape_path}: {mismatches} samples differ vs ffmpeg (max_diff={max_diff})");
 }
 
-fn parse_wav_samples(data: &[u8], bits_per_sample: u16) -> Vec<i32> {
-    let mut pos = 12;
-    while pos + 8 <= data.len() {
-        let chunk_id = &data[pos..pos + 4];
-        let chunk_size = u32::from_le_bytes([
-            data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7],
-        ]) as usize;
-        pos += 8;
-
-        if chunk_id == b"data" {
-            let sample_data = &data[pos..pos + chunk_size.min(data.len() - pos)];
-            return match bits_per_sample {
-                16 => sample_data.chunks_exact(2)
-                    .map(|c| i16::from_le_bytes([c[0], c[1]]) as i32)
-                    .collect(),
-                24 => sample_data.chunks_exact(3)
-                    .map(|c| {
-                        let raw = (c[0] as i32) | ((c[1] as i32) << 8) | ((c[2] as i32) << 16);
-                        if raw & 0x800000 != 0 { raw | !0xFFFFFF } else { raw }
-                    })
-                    .collect(),
-                _ => panic!("Unsupported bits_per_sample: {bits_per_sample}"),
-            };
-        }
-
-        pos += chunk_size;
-        if chunk_size % 2 != 0 { pos += 1; }
-    }
-    panic!("No 'data' chunk found in WAV");
+/// The samples of a WAV file.
+fn parse_wav_samples(data: &[u8]) -> Vec<i32> {
+    WavReader::new(data).expect("parse WAV").read_all_samples().expect("read WAV samples")
 }
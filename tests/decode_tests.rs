@@ -1,4 +1,7 @@
-use ape_rs::ApeReader;
+#![cfg(feature = "std")]
+
+use ape_rs::{ApeError, ApeReader, ApeReaderBuilder, DecodeWarning, ErrorKind, FrameErrorAction, FrameErrorMode};
+use ape_rs::wav_reader::WavReader;
 use std::path::Path;
 
 const TEST_APE: &str = "tests/data/test.ape";
@@ -113,7 +116,7 @@ fn compare_to_wav_reference() {
     }
 
     let wav_data = std::fs::read(TEST_WAV).expect("Failed to read WAV");
-    let wav_samples = parse_wav_samples(&wav_data, info.bits_per_sample);
+    let wav_samples = parse_wav_samples(&wav_data);
 
     let compare_len = check_samples.min(ape_samples.len()).min(wav_samples.len());
     eprintln!("Comparing {compare_len} samples (APE has {}, WAV has {})",
@@ -250,7 +253,7 @@ fn verify_ape_vs_wav(
         "{ape_path}: decoded {} samples, expected {expected_total}", ape_samples.len());
 
     let wav_data = std::fs::read(wav_path).expect("Failed to read WAV");
-    let wav_samples = parse_wav_samples(&wav_data, info.bits_per_sample);
+    let wav_samples = parse_wav_samples(&wav_data);
 
     let compare_len = expected_total.min(wav_samples.len());
     let label = format!("{ape_path} ({}ch c{})", info.channels, info.compression_level);
@@ -288,38 +291,3530 @@ fn assert_bit_exact(ape: &[i32], wav: &[i32], len: usize, label: &str) {
         "{label}: {mismatches} samples differ (max_diff={max_diff})");
 }
 
-/// Parse PCM samples from a WAV file (16-bit or 24-bit).
-fn parse_wav_samples(data: &[u8], bits_per_sample: u16) -> Vec<i32> {
-    let mut pos = 12; // Skip RIFF header
-    while pos + 8 <= data.len() {
-        let chunk_id = &data[pos..pos + 4];
-        let chunk_size = u32::from_le_bytes([
-            data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7],
-        ]) as usize;
-        pos += 8;
-
-        if chunk_id == b"data" {
-            let sample_data = &data[pos..pos + chunk_size];
-            return match bits_per_sample {
-                16 => sample_data
-                    .chunks_exact(2)
-                    .map(|c| i16::from_le_bytes([c[0], c[1]]) as i32)
-                    .collect(),
-                24 => sample_data
-                    .chunks_exact(3)
-                    .map(|c| {
-                        let raw = (c[0] as i32) | ((c[1] as i32) << 8) | ((c[2] as i32) << 16);
-                        if raw & 0x800000 != 0 { raw | !0xFFFFFF } else { raw }
-                    })
-                    .collect(),
-                _ => panic!("Unsupported bits_per_sample: {bits_per_sample}"),
-            };
-        }
-
-        pos += chunk_size;
-        if chunk_size % 2 != 0 {
-            pos += 1;
-        }
-    }
-    panic!("No 'data' chunk found in WAV file");
+/// The samples of a WAV file.
+fn parse_wav_samples(data: &[u8]) -> Vec<i32> {
+    WavReader::new(data).expect("parse WAV").read_all_samples().expect("read WAV samples")
+}
+
+/// A RIFF/WAVE file of `chunks`, each `(id, body)`, padded as RIFF pads.
+fn riff_wave(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut body = b"WAVE".to_vec();
+    for (id, data) in chunks {
+        body.extend_from_slice(*id);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    [b"RIFF".to_vec(), (body.len() as u32).to_le_bytes().to_vec(), body].concat()
+}
+
+#[test]
+fn wav_reader_streams_pcm_in_every_container() {
+    use ape_rs::riff::WAVE_FORMAT_EXTENSIBLE;
+
+    let fmt = |tag: u16, channels: u16, bits: u16| {
+        let align = channels * bits / 8;
+        let mut fmt = [tag.to_le_bytes(), channels.to_le_bytes()].concat();
+        fmt.extend_from_slice(&48_000u32.to_le_bytes());
+        fmt.extend_from_slice(&(48_000 * align as u32).to_le_bytes());
+        fmt.extend_from_slice(&align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        if tag == WAVE_FORMAT_EXTENSIBLE {
+            fmt.extend_from_slice(&22u16.to_le_bytes());
+            fmt.extend_from_slice(&20u16.to_le_bytes()); // valid bits
+            fmt.extend_from_slice(&3u32.to_le_bytes()); // FL + FR
+            fmt.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xaa, 0, 0x38, 0x9b, 0x71]);
+        }
+        fmt
+    };
+
+    // 8-bit is unsigned on disk; an odd-sized chunk before the samples is
+    // padded, and what follows the samples is the trailer
+    let wav = riff_wave(&[
+        (b"fmt ", fmt(1, 1, 8)),
+        (b"junk", vec![7; 3]),
+        (b"fact", 3u32.to_le_bytes().to_vec()),
+        (b"data", vec![0x80, 0xff, 0x00]),
+        (b"LIST", b"INFO".to_vec()),
+    ]);
+    let mut reader = WavReader::new(&wav[..]).unwrap();
+    assert_eq!((reader.channels(), reader.sample_rate(), reader.bits_per_sample()), (1, 48_000, 8));
+    assert_eq!((reader.total_samples(), reader.fact_samples()), (Some(3), Some(3)));
+    assert_eq!(reader.header_bytes(), &wav[..wav.len() - 3 - 1 - 12]);
+    assert_eq!(reader.samples().collect::<Result<Vec<_>, _>>().unwrap(), [0, 127, -128]);
+    assert_eq!(reader.into_trailer().unwrap(), [&[0][..], b"LIST", &4u32.to_le_bytes(), b"INFO"].concat());
+
+    // 20 valid bits in extensible 24-bit containers, read in small pieces
+    let samples: Vec<i32> = (0..1000).map(|i| (i * 4099 % 1_000_000 - 500_000) << 4).collect();
+    let mut pcm = Vec::new();
+    ape_rs::convert::pack(&samples, 3, ape_rs::convert::PcmLayout::Wav, &mut pcm);
+    let wav = riff_wave(&[(b"fmt ", fmt(WAVE_FORMAT_EXTENSIBLE, 2, 24)), (b"data", pcm)]);
+    let mut reader = WavReader::new(&wav[..]).unwrap();
+    assert_eq!((reader.bits_per_sample(), reader.valid_bits_per_sample()), (24, 20));
+    assert_eq!(reader.format().channel_mask, Some(3));
+    let mut read = Vec::new();
+    let mut buf = [0; 7];
+    while let n @ 1.. = reader.read_samples(&mut buf).unwrap() {
+        read.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(read, samples);
+
+    // 32-bit, with the size a streaming writer leaves: read to the end
+    let samples = [i32::MIN, -1, 0, i32::MAX];
+    let mut pcm = Vec::new();
+    ape_rs::convert::pack(&samples, 4, ape_rs::convert::PcmLayout::Wav, &mut pcm);
+    let mut wav = riff_wave(&[(b"fmt ", fmt(1, 2, 32)), (b"data", pcm)]);
+    let size_at = wav.len() - 16 - 4;
+    wav[size_at..size_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let mut reader = WavReader::new(&wav[..]).unwrap();
+    assert_eq!(reader.total_samples(), None);
+    assert_eq!(reader.read_all_samples().unwrap(), samples);
+
+    // Not integer PCM, no fmt chunk, not a WAV file, cut short
+    for bad in [
+        riff_wave(&[(b"fmt ", fmt(3, 2, 32)), (b"data", vec![0; 8])]),
+        riff_wave(&[(b"data", vec![0; 8])]),
+        b"RIFX\0\0\0\0WAVE".to_vec(),
+        riff_wave(&[(b"fmt ", fmt(1, 2, 16))])[..30].to_vec(),
+    ] {
+        assert_eq!(WavReader::new(&bad[..]).unwrap_err().kind(), ErrorKind::InvalidHeader);
+    }
+}
+
+// ── Lenient decoding ───────────────────────────────────────────────
+
+/// Load test.ape and flip bytes in the middle of frame 0's compressed data.
+fn corrupted_test_file() -> Option<Vec<u8>> {
+    if !Path::new(TEST_APE).exists() {
+        eprintln!("Skipping: test file not found at {TEST_APE}");
+        return None;
+    }
+    let mut data = std::fs::read(TEST_APE).expect("Failed to read APE file");
+    let le32 = |d: &[u8], off: usize| u32::from_le_bytes([d[off], d[off + 1], d[off + 2], d[off + 3]]);
+    // Seek table starts after descriptor + header; entry 0 is frame 0's offset
+    let seek_table_start = (le32(&data, 8) + le32(&data, 12)) as usize;
+    let frame0 = le32(&data, seek_table_start) as usize;
+    for b in &mut data[frame0 + 64..frame0 + 96] {
+        *b ^= 0x5A;
+    }
+    Some(data)
+}
+
+#[test]
+fn strict_mode_reports_corrupt_frame() {
+    let Some(data) = corrupted_test_file() else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    let err = reader.samples().find_map(Result::err).expect("corruption not detected");
+    eprintln!("Strict mode error: {err}");
+    assert!(err.is_recoverable(), "unexpected error kind: {err}");
+
+    let ctx = err.context().expect("decode error carries no frame context");
+    assert_eq!(ctx.frame, 0);
+    assert_eq!(ctx.sample, 0);
+    assert!(ctx.byte_offset > 0);
+
+    // The message carries the cause once, even for reporters walking the
+    // source chain
+    assert!(err.to_string().ends_with(&err.inner().to_string()), "{err}");
+    assert!(std::error::Error::source(&err).is_none());
+}
+
+#[test]
+fn range_coder_desync_detected_before_crc() {
+    let Some(data) = corrupted_test_file() else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    // The garbled bytes should be caught by the range coder mid-frame,
+    // not only by the CRC after emitting a frame of garbage.
+    let err = reader.samples().find_map(Result::err).expect("corruption not detected");
+    assert_eq!(err.kind(), ErrorKind::RangeCoder, "expected range coder error, got: {err}");
+}
+
+#[test]
+fn silence_mode_conceals_corrupt_frame() {
+    let Some(data) = corrupted_test_file() else { return };
+    let mut reader = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Silence)
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+
+    let head: Vec<i32> = reader.samples().take(1024).collect::<Result<_, _>>().unwrap();
+    assert!(head.iter().all(|&s| s == 0), "corrupt frame was not silenced");
+    assert_eq!(reader.warnings().len(), 1);
+    assert_eq!(reader.warnings()[0].frame(), Some(0));
+}
+
+#[test]
+fn skip_mode_drops_corrupt_frame() {
+    let Some(data) = corrupted_test_file() else { return };
+    let mut reader = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Skip)
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+
+    let first = reader.samples().next().expect("no samples after skipped frame");
+    assert!(first.is_ok());
+    assert_eq!(reader.warnings().len(), 1);
+    assert_eq!(reader.warnings()[0].frame(), Some(0));
+}
+
+#[test]
+fn frame_error_handler_retries_then_skips() {
+    use std::sync::{Arc, Mutex};
+
+    let Some(data) = corrupted_test_file() else { return };
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&calls);
+    let mut reader = ApeReaderBuilder::new()
+        .on_frame_error(move |err, attempt| {
+            seen.lock().unwrap().push((err.context().map(|c| c.frame), attempt));
+            if attempt < 2 {
+                FrameErrorAction::Retry
+            } else {
+                FrameErrorAction::Skip
+            }
+        })
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+
+    let first = reader.samples().next().expect("no samples after skipped frame");
+    assert!(first.is_ok());
+    assert_eq!(*calls.lock().unwrap(), [(Some(0), 1), (Some(0), 2)]);
+    assert_eq!(reader.warnings().len(), 1);
+}
+
+#[test]
+fn frame_error_handler_can_abort() {
+    let Some(data) = corrupted_test_file() else { return };
+    let mut reader = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Silence)
+        .on_frame_error(|_, _| FrameErrorAction::Abort)
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+
+    let err = reader.samples().find_map(Result::err).expect("handler did not abort");
+    assert!(err.is_recoverable());
+    assert!(reader.warnings().is_empty());
+}
+
+// ── Header validation ──────────────────────────────────────────────
+
+/// First few KB of test.ape: descriptor, header and seek table.
+fn test_file_head() -> Option<Vec<u8>> {
+    if !Path::new(TEST_APE).exists() {
+        eprintln!("Skipping: test file not found at {TEST_APE}");
+        return None;
+    }
+    let mut data = std::fs::read(TEST_APE).expect("Failed to read APE file");
+    data.truncate(4096);
+    Some(data)
+}
+
+/// `test_file_head()` with a little-endian u32 header field overwritten.
+/// `field` is the byte offset within the 24-byte APE header.
+fn patched_header(field: usize, value: u32) -> Option<Vec<u8>> {
+    let mut data = test_file_head()?;
+    let descriptor_bytes = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+    let off = descriptor_bytes + field;
+    data[off..off + 4].copy_from_slice(&value.to_le_bytes());
+    Some(data)
+}
+
+fn expect_invalid_header(data: Vec<u8>, needle: &str) {
+    match ApeReader::new(std::io::Cursor::new(data)) {
+        Err(e @ ApeError::InvalidHeader(_)) => {
+            assert_eq!(e.kind(), ErrorKind::InvalidHeader);
+            assert!(!e.is_recoverable(), "header errors must be fatal");
+            assert!(e.to_string().contains(needle), "unexpected message: {e}");
+        }
+        Err(e) => panic!("expected InvalidHeader, got: {e}"),
+        Ok(_) => panic!("expected InvalidHeader, header was accepted"),
+    }
+}
+
+#[test]
+fn rejects_final_frame_larger_than_frame_size() {
+    // final_frame_blocks at header offset 8
+    let Some(data) = patched_header(8, 10_000_000) else { return };
+    expect_invalid_header(data, "final frame");
+}
+
+#[test]
+fn rejects_empty_final_frame() {
+    // final_frame_blocks at header offset 8
+    let Some(data) = patched_header(8, 0) else { return };
+    expect_invalid_header(data, "final_frame_blocks is 0 with");
+}
+
+#[test]
+fn rejects_seek_table_shorter_than_frame_count() {
+    // total_frames at header offset 12
+    let Some(data) = patched_header(12, 10_000) else { return };
+    expect_invalid_header(data, "seek table");
+}
+
+#[test]
+fn rejects_zero_sample_rate() {
+    // sample_rate at header offset 20
+    let Some(data) = patched_header(20, 0) else { return };
+    expect_invalid_header(data, "sample rate");
+}
+
+#[test]
+fn rejects_zero_blocks_per_frame() {
+    // blocks_per_frame at header offset 4
+    let Some(data) = patched_header(4, 0) else { return };
+    expect_invalid_header(data, "blocks_per_frame");
+}
+
+#[test]
+fn rejects_undersized_descriptor() {
+    let Some(mut data) = test_file_head() else { return };
+    put_le32(&mut data, 8, 20); // descriptor_bytes
+    expect_invalid_header(data, "descriptor_bytes");
+}
+
+#[test]
+fn rejects_header_past_end_of_stream() {
+    let Some(mut data) = test_file_head() else { return };
+    put_le32(&mut data, 12, 1_000_000); // header_bytes
+    expect_invalid_header(data, "past end of stream");
+}
+
+#[test]
+fn detects_frame_holding_more_blocks_than_declared() {
+    // Shrink blocks_per_frame (header offset 4) below the real 294912, while
+    // keeping it above final_frame_blocks so the header still validates.
+    let Some(mut data) = std::fs::read(TEST_APE).ok() else {
+        eprintln!("Skipping: test file not found at {TEST_APE}");
+        return;
+    };
+    let header_start = le32_at(&data, 8) as usize;
+    put_le32(&mut data, header_start + 4, 290_000);
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let err = reader.samples().find_map(Result::err).expect("overrun not detected");
+    assert_eq!(err.kind(), ErrorKind::SampleOverrun, "expected sample overrun, got: {err}");
+    assert!(err.is_recoverable());
+    assert_eq!(err.context().map(|c| c.frame), Some(0));
+}
+
+#[test]
+fn parse_header_exposes_file_structure() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let mut cursor = std::io::Cursor::new(data.clone());
+    let header = ape_rs::parse_header(&mut cursor).unwrap();
+    assert_eq!(cursor.position(), header.data_offset);
+    assert_eq!(header.descriptor.version, 3990);
+    assert_eq!(header.header.channels, 1);
+    assert_eq!(header.seek_table, [header.data_offset as u32]);
+    assert_eq!(header.tag_offset, data.len() as u64);
+
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReaderBuilder::new().build_with_header(cursor, header.clone()).unwrap();
+    assert_eq!(reader.header().seek_table, header.seek_table);
+    let decoded: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(decoded, expected);
+
+    // Hand-edited headers are validated like parsed ones
+    let build = |header| ApeReader::with_header(std::io::Cursor::new(data.clone()), header);
+    let mut bad = header.clone();
+    bad.header.channels = 3;
+    assert_eq!(build(bad).err().unwrap().kind(), ErrorKind::InvalidHeader);
+    let mut bad = header.clone();
+    bad.descriptor.version = 3980;
+    assert_eq!(build(bad).err().unwrap().kind(), ErrorKind::UnsupportedVersion);
+    let mut bad = header;
+    bad.header.total_frames = 2;
+    assert_eq!(build(bad).err().unwrap().kind(), ErrorKind::InvalidHeader);
+}
+
+#[test]
+fn raw_frame_stream_decodes_with_supplied_parameters() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(data.clone())).unwrap();
+    let frames = data[header.data_offset as usize..].to_vec();
+    let expected = decode_all(data);
+
+    let mut params = header.header.clone();
+    let decode_raw = |params| {
+        let mut reader = ApeReaderBuilder::new()
+            .build_raw(std::io::Cursor::new(frames.clone()), 3990, params)
+            .unwrap();
+        assert_eq!(reader.frame_count(), 2);
+        reader.samples().collect::<Result<Vec<i32>, _>>().unwrap()
+    };
+    assert!(decode_raw(params.clone()) == expected, "raw stream decodes differently");
+
+    // Frame count and final frame length unknown
+    params.total_frames = 0;
+    params.final_frame_blocks = 0;
+    assert!(decode_raw(params.clone()) == expected, "raw stream decodes differently");
+
+    params.compression_level = 2500;
+    let err = ApeReaderBuilder::new()
+        .build_raw(std::io::Cursor::new(frames), 3990, params)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::UnsupportedCompressionLevel);
+}
+
+#[test]
+fn slice_decoder_matches_reader() {
+    use ape_rs::slice::SliceDecoder;
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let parsed = ape_rs::parse_header(&mut std::io::Cursor::new(data.clone())).unwrap();
+    let header = ape_rs::parse_header_slice(&data).unwrap();
+    assert_eq!(header.seek_table, parsed.seek_table);
+    assert_eq!((header.data_offset, header.tag_offset), (parsed.data_offset, parsed.tag_offset));
+
+    let expected = decode_all(data.clone());
+    let mut decoder = SliceDecoder::new(&data).unwrap();
+    assert_eq!(decoder.frame_count(), 2);
+    assert!(decoder.decode_all().unwrap() == expected, "slice decode differs from reader");
+
+    // Frames in any order, appended to the caller's buffer
+    let mut pcm = Vec::new();
+    assert!(decoder.decode_frame(1, &mut pcm).unwrap());
+    assert!(decoder.decode_frame(0, &mut pcm).unwrap());
+    assert!(!decoder.decode_frame(2, &mut pcm).unwrap());
+    let (first, second) = expected.split_at(expected.len() / 2);
+    assert!(pcm == [second, first].concat(), "frames decoded out of order differ");
+
+    // A corrupt frame is an error with its location, and output is untouched
+    let mut damaged = data.clone();
+    let offset = header.frame_position(1).unwrap() as usize + 200;
+    damaged[offset] ^= 0xFF;
+    let mut decoder = SliceDecoder::new(&damaged).unwrap();
+    let mut pcm = vec![7];
+    let err = decoder.decode_frame(1, &mut pcm).unwrap_err();
+    assert!(err.is_recoverable());
+    assert_eq!(err.context().unwrap().frame, 1);
+    assert_eq!(pcm, [7]);
+
+    assert_eq!(ape_rs::parse_header_slice(&data[..40]).err().unwrap().kind(), ErrorKind::InvalidHeader);
+    assert_eq!(ape_rs::parse_header_slice(b"RIFF").err().unwrap().kind(), ErrorKind::InvalidMagic);
+}
+
+#[test]
+fn chained_tracks_play_back_to_back() {
+    use ape_rs::chain::ChainedReader;
+
+    let (Some(one), Some(two)) = (first_frames_of_test_file(1), first_frames_of_test_file(2)) else { return };
+    let mut expected = decode_all(one.clone());
+    let first_len = expected.len() as u64;
+    expected.extend(decode_all(two.clone()));
+
+    let readers = [one, two.clone()]
+        .into_iter()
+        .map(|d| ApeReader::new(std::io::Cursor::new(d)).unwrap())
+        .collect();
+    let mut chain = ChainedReader::new(readers).unwrap();
+    assert_eq!(chain.track_count(), 2);
+    assert_eq!(chain.track_start(1), Some(first_len));
+    assert_eq!(chain.track_start(2), None);
+    assert_eq!(chain.info().total_samples, expected.len() as u64);
+    let samples: Vec<i32> = chain.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, expected);
+    assert_eq!(chain.current_track(), 2);
+
+    assert!(chain.seek_to_track(1).unwrap());
+    assert_eq!(chain.samples().count() as u64, expected.len() as u64 - first_len);
+
+    // A track at another sample rate cannot be joined gaplessly
+    let mut other = two;
+    let rate_offset = le32_at(&other, 8) as usize + 20;
+    put_le32(&mut other, rate_offset, 48000);
+    let readers = vec![
+        ApeReader::new(std::io::Cursor::new(other)).unwrap(),
+        chain.into_tracks().pop().unwrap(),
+    ];
+    let err = ChainedReader::new(readers).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::FormatMismatch);
+}
+
+#[test]
+fn cue_tracks_split_at_index_points() {
+    use ape_rs::cue::CueSheet;
+
+    let cue = CueSheet::parse(
+        "REM GENRE Test\r\n\
+         PERFORMER \"Someone\"\r\n\
+         TITLE \"Album\"\r\n\
+         FILE \"album image.ape\" WAVE\r\n\
+         \x20 TRACK 01 AUDIO\r\n\
+         \x20   TITLE \"One\"\r\n\
+         \x20   INDEX 01 00:00:00\r\n\
+         \x20 TRACK 02 AUDIO\r\n\
+         \x20   TITLE \"Two\"\r\n\
+         \x20   INDEX 00 00:05:00\r\n\
+         \x20   INDEX 01 00:06:30\r\n",
+    )
+    .unwrap();
+    assert_eq!(cue.title.as_deref(), Some("Album"));
+    assert_eq!(cue.performer.as_deref(), Some("Someone"));
+    assert_eq!(cue.file.as_deref(), Some("album image.ape"));
+    assert_eq!(cue.tracks.len(), 2);
+    assert_eq!(cue.tracks[1].title.as_deref(), Some("Two"));
+    assert_eq!((cue.tracks[1].pregap, cue.tracks[1].start), (Some(375), 480));
+
+    assert!(CueSheet::parse("TRACK 01 AUDIO\nINDEX 01 00:61:00\n").is_err());
+    assert!(CueSheet::parse("TRACK 01 AUDIO\nINDEX 00 00:00:00\n").is_err());
+    let backwards = CueSheet::parse("TRACK 01 AUDIO\nINDEX 01 00:02:00\nTRACK 02 AUDIO\nINDEX 01 00:01:00\n");
+    assert_eq!(backwards.err().unwrap().kind(), ErrorKind::InvalidCueSheet);
+
+    // Times too large for the arithmetic
+    let huge = CueSheet::parse("TRACK 01 AUDIO\nINDEX 01 99999999999999999:00:00\n");
+    assert_eq!(huge.err().unwrap().kind(), ErrorKind::InvalidCueSheet);
+    let late = CueSheet::parse("TRACK 01 AUDIO\nINDEX 01 99999999999999:00:00\n").unwrap();
+    assert_eq!(late.tracks[0].start, 99_999_999_999_999 * 60 * 75);
+    assert_eq!(late.tracks[0].start_block(44100), u64::MAX);
+    assert_eq!(late.track_blocks(1, 44100, 1000), Some((1000, 1000)));
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let all = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert_eq!(reader.track(&late, 1).err().unwrap().kind(), ErrorKind::InvalidCueSheet);
+    let channels = reader.info().channels as usize;
+    // 6.4 s at 44.1 kHz, partway through frame 0
+    let split = 480 * 588 * channels;
+
+    let mut two = reader.track(&cue, 2).unwrap();
+    assert_eq!(two.info().total_samples as usize, all.len() - split);
+    let samples: Vec<i32> = two.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, all[split..]);
+
+    let mut one = reader.track(&cue, 1).unwrap();
+    assert_eq!(one.block_range(), (0, 480 * 588));
+    let samples: Vec<i32> = one.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, all[..split]);
+
+    assert_eq!(reader.track(&cue, 3).err().unwrap().kind(), ErrorKind::InvalidCueSheet);
+}
+
+#[test]
+fn apl_link_exposes_only_its_region() {
+    use ape_rs::apl::ApeLink;
+
+    let link = ApeLink::parse(
+        b"[Monkey's Audio Image Link File]\r\n\
+          Image File=sub\\album.ape\r\n\
+          Start Block=1000\r\n\
+          Finish Block=300000\r\n\
+          ----- APE TAG (DO NOT TOUCH!!!) -----\r\nAPETAGEX\xd0\x07\x00\x00",
+    )
+    .unwrap();
+    assert_eq!(link.image_file, "sub\\album.ape");
+    assert_eq!((link.start_block, link.finish_block), (1000, 300000));
+    assert_eq!(link.image_path(Path::new("/music/x.apl")), Path::new("/music/sub/album.ape"));
+    assert!(ApeLink::parse(b"Image File=a.ape\nStart Block=0\nFinish Block=1\n").is_err());
+    let empty = ApeLink::parse(b"[Monkey's Audio Image Link File]\nImage File=a.ape\nStart Block=5\nFinish Block=5\n");
+    assert_eq!(empty.err().unwrap().kind(), ErrorKind::InvalidLink);
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let all = decode_all(data.clone());
+    let dir = std::env::temp_dir().join(format!("ape-rs-apl-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("sub/album.ape"), data).unwrap();
+    let apl = dir.join("track.apl");
+    std::fs::write(&apl, b"[Monkey's Audio Image Link File]\nImage File=sub/album.ape\nStart Block=1000\nFinish Block=300000\n").unwrap();
+
+    let mut track = ApeReader::open_apl(&apl).unwrap();
+    let channels = track.info().channels as usize;
+    assert_eq!(track.info().total_samples as usize, 299000 * channels);
+    let samples: Vec<i32> = track.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, all[1000 * channels..300000 * channels]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn terminating_data_is_exposed() {
+    let Some(mut data) = first_frames_of_test_file(2) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    assert_eq!(reader.header().terminating_data_range(), None);
+    assert!(reader.terminating_data().unwrap().is_empty());
+
+    let trailer = b"LIST\x0c\x00\x00\x00INFOISFT\x00\x00\x00\x00";
+    let end = data.len() as u64;
+    data.extend_from_slice(trailer);
+    put_le32(&mut data, 32, trailer.len() as u32);
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert_eq!(reader.header().terminating_data_range(), Some((end, trailer.len() as u64)));
+    assert_eq!(reader.terminating_data().unwrap(), trailer);
+    // Reading it leaves decoding where it was
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, expected);
+    assert!(reader.warnings().is_empty());
+}
+
+#[test]
+fn riff_info_is_surfaced_as_metadata() {
+    let Some(mut data) = first_frames_of_test_file(1) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    assert!(reader.metadata().unwrap().is_empty());
+
+    // Latin-1 title, NUL-padded artist, and an empty item that is skipped
+    let mut info = b"INFO".to_vec();
+    for (id, value) in [(b"INAM", &b"Caf\xe9\0"[..]), (b"IART", b"Someone\0\0"), (b"ICMT", b"")] {
+        info.extend_from_slice(id);
+        info.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        info.extend_from_slice(value);
+        if value.len() % 2 == 1 {
+            info.push(0);
+        }
+    }
+    let mut trailer = b"LIST".to_vec();
+    trailer.extend_from_slice(&(info.len() as u32).to_le_bytes());
+    trailer.extend_from_slice(&info);
+    data.extend_from_slice(&trailer);
+    put_le32(&mut data, 32, trailer.len() as u32);
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let metadata = reader.metadata().unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("Caf\u{e9}"));
+    assert_eq!(metadata.artist.as_deref(), Some("Someone"));
+    assert_eq!(metadata.comment, None);
+    assert_eq!(
+        metadata.items,
+        [("INAM".to_string(), "Caf\u{e9}".to_string()), ("IART".to_string(), "Someone".to_string())]
+    );
+}
+
+/// `data` (from `first_frames_of_test_file`) with its canonical stored
+/// header swapped for an equivalent `WAVE_FORMAT_EXTENSIBLE` one, and no
+/// file MD5.
+fn with_extensible_header(data: &[u8], channel_mask: u32) -> Vec<u8> {
+    let seek_table_start = (le32_at(data, 8) + le32_at(data, 12)) as usize;
+    let header_start = seek_table_start + le32_at(data, 16) as usize;
+    let header = &data[header_start..header_start + 44];
+    let mut extensible = header[..16].to_vec();
+    extensible.extend_from_slice(&40u32.to_le_bytes());
+    extensible.extend_from_slice(&0xfffeu16.to_le_bytes());
+    extensible.extend_from_slice(&header[22..36]);
+    extensible.extend_from_slice(&22u16.to_le_bytes()); // cbSize
+    extensible.extend_from_slice(&16u16.to_le_bytes()); // wValidBitsPerSample
+    extensible.extend_from_slice(&channel_mask.to_le_bytes());
+    extensible.extend_from_slice(b"\x01\x00\x00\x00\x00\x00\x10\x00\x80\x00\x00\xaa\x00\x38\x9b\x71");
+    extensible.extend_from_slice(&header[36..]);
+    let grown = (extensible.len() - header.len()) as u32;
+
+    let mut out = data[..header_start].to_vec();
+    out.extend_from_slice(&extensible);
+    out.extend_from_slice(&data[header_start + 44..]);
+    let riff_len = le32_at(&out, header_start + 4);
+    put_le32(&mut out, header_start + 4, riff_len + grown);
+    put_le32(&mut out, 20, extensible.len() as u32); // header_data_bytes
+    out[36..52].fill(0); // file MD5
+    for entry in (seek_table_start..header_start).step_by(4) {
+        let offset = le32_at(&out, entry);
+        put_le32(&mut out, entry, offset + grown);
+    }
+    out
+}
+
+#[test]
+fn extensible_wav_header_is_parsed_and_restored() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    assert_eq!(reader.info().channel_mask, None);
+
+    // Front center
+    let data = with_extensible_header(&data, 0x4);
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    assert_eq!(reader.info().channel_mask, Some(0x4));
+    let header = reader.header_data().unwrap();
+    let format = ape_rs::riff::wave_format(&header).unwrap();
+    assert_eq!(format.format_tag, ape_rs::riff::WAVE_FORMAT_EXTENSIBLE);
+    assert_eq!(format.sample_format(), ape_rs::riff::WAVE_FORMAT_PCM);
+    assert_eq!((format.channels, format.bits_per_sample, format.valid_bits_per_sample), (1, 16, Some(16)));
+
+    let mut restored = Vec::new();
+    reader.write_original(&mut restored).unwrap();
+    assert_eq!(restored[..header.len()], header[..]);
+    let pcm: Vec<i32> = restored[header.len()..]
+        .chunks_exact(2)
+        .map(|c| i16::from_le_bytes([c[0], c[1]]) as i32)
+        .collect();
+    assert_eq!(pcm, expected);
+
+    // A stored header that disagrees with the stream is not written out
+    let mut bad = data;
+    let block_align = bad.windows(4).position(|w| w == b"fmt ").unwrap() + 8 + 12;
+    bad[block_align] = 3;
+    let mut reader = ApeReader::new(std::io::Cursor::new(bad)).unwrap();
+    let err = reader.write_original(Vec::new()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+}
+
+#[test]
+fn original_file_is_restored_bit_perfect() {
+    if !Path::new(TEST_APE).exists() || !Path::new(TEST_WAV).exists() {
+        eprintln!("Skipping: test files not found");
+        return;
+    }
+    let data = std::fs::read(TEST_APE).unwrap();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    assert!(reader.verify_file_md5().unwrap());
+    let header = reader.header_data().unwrap();
+    assert_eq!(&header[..4], b"RIFF");
+    let trailer = reader.terminating_data().unwrap();
+
+    let mut restored = Vec::new();
+    reader.write_original(&mut restored).unwrap();
+    // The RIFF size accounts for the whole original file
+    assert_eq!(le32_at(&restored, 4) as usize + 8, restored.len());
+    assert_eq!(restored[..header.len()], header[..]);
+    assert_eq!(restored[restored.len() - trailer.len()..], trailer[..]);
+    let reference = std::fs::read(TEST_WAV).unwrap();
+    assert_eq!(parse_wav_samples(&restored), parse_wav_samples(&reference));
+
+    // One flipped bit in the frame data fails the file MD5 before decoding
+    let mut damaged = data;
+    let offset = reader.header().data_offset as usize + 1000;
+    damaged[offset] ^= 1;
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged)).unwrap();
+    let err = reader.write_original(Vec::new()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Md5Mismatch);
+}
+
+#[cfg(feature = "compare")]
+#[test]
+fn compare_reports_first_mismatch() {
+    use ape_rs::compare;
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut wav = Vec::new();
+    ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().write_wav(&mut wav).unwrap();
+
+    let mut reference = compare::parse_wav(&wav).unwrap();
+    assert_eq!(reference.samples, expected);
+    let report = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().compare(&reference).unwrap();
+    assert!(report.is_bit_exact(), "{report:?}");
+
+    reference.samples[1000] += 3;
+    reference.samples[2000] -= 7;
+    reference.samples.pop();
+    let report = ApeReader::new(std::io::Cursor::new(data)).unwrap().compare(&reference).unwrap();
+    assert!(!report.is_bit_exact());
+    assert_eq!(report.decoded_samples, expected.len() as u64);
+    assert_eq!(report.reference_samples, expected.len() as u64 - 1);
+    assert_eq!((report.mismatches, report.max_diff), (2, 7));
+    let first = report.first_mismatch.unwrap();
+    assert_eq!((first.index, first.decoded, first.reference), (1000, expected[1000], expected[1000] + 3));
+}
+
+#[cfg(feature = "compare")]
+#[test]
+fn diff_streams_reports_differing_ranges() {
+    use ape_rs::compare::diff_streams;
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let open = || ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let expected = decode_all(data.clone());
+
+    let mut b = open();
+    let diff = diff_streams(&mut open(), b.samples()).unwrap();
+    assert!(diff.is_identical(), "{diff:?}");
+    assert_eq!(diff.a_samples, expected.len() as u64);
+
+    // Adjacent blocks merge into one range; the shorter stream's missing
+    // tail is counted but not compared
+    let mut other = expected.clone();
+    other[100] += 1;
+    other[101] -= 5;
+    other[7000] += 2;
+    other.truncate(other.len() - 10);
+    let diff = diff_streams(&mut open(), other.iter().map(|&s| Ok(s))).unwrap();
+    assert!(!diff.is_identical());
+    assert_eq!((diff.a_samples, diff.b_samples), (expected.len() as u64, other.len() as u64));
+    assert_eq!((diff.mismatches, diff.max_diff), (3, 5));
+    let ranges: Vec<_> = diff.ranges.iter().map(|r| (r.blocks.clone(), r.mismatches, r.max_diff)).collect();
+    assert_eq!(ranges, [(100..102, 2, 5), (7000..7001, 1, 2)]);
+}
+
+#[cfg(feature = "compare")]
+#[test]
+fn compare_matches_reference_wav() {
+    if !Path::new(TEST_APE).exists() || !Path::new(TEST_WAV).exists() {
+        eprintln!("Skipping: test files not found");
+        return;
+    }
+    let report = ape_rs::compare::compare_to_wav(TEST_APE, TEST_WAV).unwrap();
+    assert!(report.is_bit_exact(), "{report:?}");
+}
+
+#[test]
+fn canonical_wav_header_matches_format() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    // The encoder kept a canonical header; only the RIFF size differs,
+    // since the original file also had trailing chunks
+    let header = ape_rs::wav::canonical_header(reader.info());
+    let stored = reader.header_data().unwrap();
+    assert_eq!(stored[8..40], header[8..40]);
+    assert_eq!(le32_at(&header, 40) as usize, expected.len() * 2);
+    assert_eq!(le32_at(&header, 4) as usize, expected.len() * 2 + 36);
+
+    let mut wav = Vec::new();
+    reader.write_wav(&mut wav).unwrap();
+    assert_eq!(wav.len(), ape_rs::wav::CANONICAL_HEADER_LEN + expected.len() * 2);
+    assert_eq!(wav[..44], header[..]);
+    assert_eq!(parse_wav_samples(&wav), expected);
+}
+
+#[test]
+fn wav_stream_reads_and_seeks_like_the_wav_file() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let mut wav = Vec::new();
+    ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().write_wav(&mut wav).unwrap();
+
+    // Sequential reads of odd sizes
+    let mut stream = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().into_wav_stream();
+    assert_eq!(stream.len(), wav.len() as u64);
+    let mut read = Vec::new();
+    let mut chunk = [0; 1001];
+    loop {
+        let n = stream.read(&mut chunk).unwrap();
+        if n == 0 {
+            break;
+        }
+        read.extend_from_slice(&chunk[..n]);
+    }
+    assert!(read == wav);
+
+    // Seeks backwards, across frames and into the middle of samples (the
+    // test file is 16-bit mono)
+    let frame_bytes = stream.get_ref().frame_info(0).unwrap().blocks as usize * 2;
+    for start in [wav.len() - 10, 44 + frame_bytes + 3, 7, 44 + 2 * frame_bytes - 5, 44 + 12345] {
+        let mut bytes = vec![0; 10];
+        assert_eq!(stream.seek(SeekFrom::Start(start as u64)).unwrap(), start as u64);
+        stream.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, wav[start..start + 10], "at {start}");
+    }
+    assert_eq!(stream.seek(SeekFrom::End(-4)).unwrap(), wav.len() as u64 - 4);
+    assert_eq!(stream.seek(SeekFrom::Current(2)).unwrap(), wav.len() as u64 - 2);
+    assert!(stream.seek(SeekFrom::Current(-(wav.len() as i64) - 1)).is_err());
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, wav[wav.len() - 2..]);
+
+    // A frame dropped in skip mode reads as silence, keeping the length
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(&data)).unwrap();
+    let mut damaged = data;
+    damaged[header.frame_position(1).unwrap() as usize + 100] ^= 0x40;
+    let reader = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Skip)
+        .build(std::io::Cursor::new(damaged))
+        .unwrap();
+    let mut skipped = Vec::new();
+    reader.into_wav_stream().read_to_end(&mut skipped).unwrap();
+    assert_eq!(skipped.len(), wav.len());
+    assert_eq!(skipped[..44 + frame_bytes], wav[..44 + frame_bytes]);
+    assert!(skipped[44 + frame_bytes..44 + 2 * frame_bytes].iter().all(|&b| b == 0));
+    assert_eq!(skipped[44 + 2 * frame_bytes..], wav[44 + 2 * frame_bytes..]);
+}
+
+#[test]
+fn progress_reports_each_frame() {
+    use std::sync::{Arc, Mutex};
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let mut reader = ApeReaderBuilder::new()
+        .on_progress(move |p| log.lock().unwrap().push(p))
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+    let total = reader.info().total_samples / reader.info().channels as u64;
+    reader.analyze().unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), reader.frame_count() as usize);
+    assert!(seen.windows(2).all(|w| w[0].blocks_decoded < w[1].blocks_decoded));
+    assert!(seen.iter().all(|p| p.total_blocks == total));
+    assert_eq!(seen.last().unwrap().blocks_decoded, total);
+    assert_eq!(seen.last().unwrap().fraction(), 1.0);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trips_metadata() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    let header_json = serde_json::to_string(reader.header()).unwrap();
+    let header: ape_rs::ApeFileHeader = serde_json::from_str(&header_json).unwrap();
+    assert_eq!(header.seek_table, reader.header().seek_table);
+    assert_eq!(serde_json::to_string(&header).unwrap(), header_json);
+
+    let info_json = serde_json::to_string(reader.info()).unwrap();
+    assert!(info_json.contains("\"sample_rate\":44100"), "{info_json}");
+    let info: ape_rs::ApeInfo = serde_json::from_str(&info_json).unwrap();
+    assert_eq!(info.total_samples, reader.info().total_samples);
+
+    let frames: Vec<ape_rs::FrameInfo> = reader.frames().collect();
+    let json = serde_json::to_string(&frames).unwrap();
+    assert_eq!(serde_json::from_str::<Vec<ape_rs::FrameInfo>>(&json).unwrap(), frames);
+    let table = reader.seek_table();
+    assert_eq!(serde_json::from_str::<ape_rs::SeekTable>(&serde_json::to_string(&table).unwrap()).unwrap(), table);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_spans_cover_parse_decode_and_seek() {
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Records the name of every span opened.
+    struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for SpanNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let names = Arc::new(Mutex::new(Vec::new()));
+    tracing::subscriber::with_default(SpanNames(names.clone()), || {
+        let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+        reader.samples().next().unwrap().unwrap();
+        let snapshot = reader.snapshot();
+        reader.restore(&snapshot).unwrap();
+    });
+    let names = names.lock().unwrap();
+    for expected in ["parse_header", "decode_frame", "seek"] {
+        assert!(names.contains(&expected), "no {expected} span in {names:?}");
+    }
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn log_records_dropped_frames() {
+    use std::sync::Mutex;
+
+    /// Keeps this crate's records as (level, message, `frame` key).
+    struct Records(Mutex<Vec<(log::Level, String, Option<String>)>>);
+
+    impl log::Log for Records {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record<'_>) {
+            if record.target() == "ape_rs" {
+                let frame = record.key_values().get("frame".into()).map(|v| v.to_string());
+                self.0.lock().unwrap().push((record.level(), record.args().to_string(), frame));
+            }
+        }
+        fn flush(&self) {}
+    }
+
+    static RECORDS: Records = Records(Mutex::new(Vec::new()));
+    let Some(data) = corrupted_test_file() else { return };
+    log::set_logger(&RECORDS).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let mut reader = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Skip)
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+    reader.samples().next().unwrap().unwrap();
+
+    let records = RECORDS.0.lock().unwrap();
+    assert!(records.iter().any(|(level, msg, _)| *level == log::Level::Debug && msg == "parsed header"));
+    let dropped = records
+        .iter()
+        .find(|(level, msg, _)| *level == log::Level::Warn && msg.starts_with("frame dropped"))
+        .expect("no warning for the dropped frame");
+    assert_eq!(dropped.2.as_deref(), Some("0"));
+}
+
+// ── Frame layout recovery ──────────────────────────────────────────
+
+fn le32_at(data: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([data[off], data[off + 1], data[off + 2], data[off + 3]])
+}
+
+fn put_le32(data: &mut [u8], off: usize, value: u32) {
+    data[off..off + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Build a smaller, valid APE file holding only the first `n` frames of
+/// test.ape, so layout tests don't pay for decoding the whole file.
+fn first_frames_of_test_file(n: usize) -> Option<Vec<u8>> {
+    if !Path::new(TEST_APE).exists() {
+        eprintln!("Skipping: test file not found at {TEST_APE}");
+        return None;
+    }
+    let orig = std::fs::read(TEST_APE).expect("Failed to read APE file");
+    let (desc_bytes, header_bytes) = (le32_at(&orig, 8) as usize, le32_at(&orig, 12) as usize);
+    let seek_table_start = desc_bytes + header_bytes;
+    let (seek_table_bytes, header_data_bytes) = (le32_at(&orig, 16) as usize, le32_at(&orig, 20) as usize);
+    let orig_data_offset = seek_table_start + seek_table_bytes + header_data_bytes;
+    // Frames are whole 32-bit words counted from frame 0; keep the last word
+    let frame_data_len = (le32_at(&orig, seek_table_start + 4 * n) as usize - orig_data_offset).div_ceil(4) * 4;
+
+    let data_offset = seek_table_start + 4 * n + header_data_bytes;
+    let mut out = orig[..seek_table_start].to_vec();
+    for i in 0..n {
+        let entry = le32_at(&orig, seek_table_start + 4 * i) as usize;
+        out.extend_from_slice(&((entry - orig_data_offset + data_offset) as u32).to_le_bytes());
+    }
+    out.extend_from_slice(&orig[seek_table_start + seek_table_bytes..orig_data_offset]);
+    out.extend_from_slice(&orig[orig_data_offset..orig_data_offset + frame_data_len]);
+
+    let blocks_per_frame = le32_at(&orig, desc_bytes + 4);
+    put_le32(&mut out, 16, 4 * n as u32); // seek_table_bytes
+    put_le32(&mut out, 24, frame_data_len as u32); // ape_frame_data_bytes
+    put_le32(&mut out, 32, 0); // terminating_data_bytes
+    put_le32(&mut out, desc_bytes + 8, blocks_per_frame); // final_frame_blocks
+    put_le32(&mut out, desc_bytes + 12, n as u32); // total_frames
+    Some(out)
+}
+
+// ── Exact duration ─────────────────────────────────────────────────
+
+/// Open `damaged` with exact duration on and check it decodes like `intact`.
+fn assert_exact_duration(intact: Vec<u8>, damaged: Vec<u8>) {
+    let expected = decode_all(intact);
+    let mut reader = ApeReaderBuilder::new()
+        .exact_duration(true)
+        .build(std::io::Cursor::new(damaged))
+        .unwrap();
+    assert_eq!(reader.info().total_samples, expected.len() as u64);
+    assert!(
+        matches!(reader.warnings(), [DecodeWarning::DurationCorrected { .. }]),
+        "expected a duration correction, got {:?}",
+        reader.warnings()
+    );
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "corrected file decodes differently");
+}
+
+#[test]
+fn exact_duration_corrects_final_frame_blocks() {
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let mut damaged = intact.clone();
+    let desc_bytes = le32_at(&intact, 8) as usize;
+    let blocks_per_frame = le32_at(&intact, desc_bytes + 4);
+    put_le32(&mut damaged, desc_bytes + 8, blocks_per_frame / 2);
+    assert_exact_duration(intact, damaged);
+}
+
+#[test]
+fn exact_duration_corrects_frame_count() {
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let mut damaged = intact.clone();
+    let desc_bytes = le32_at(&intact, 8) as usize;
+    put_le32(&mut damaged, desc_bytes + 12, 1);
+    assert_exact_duration(intact, damaged);
+}
+
+#[test]
+fn exact_duration_keeps_correct_header() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let declared = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().info().total_samples;
+    let reader = ApeReaderBuilder::new()
+        .exact_duration(true)
+        .build(std::io::Cursor::new(data))
+        .unwrap();
+    assert_eq!(reader.info().total_samples, declared);
+    assert!(reader.warnings().is_empty(), "unexpected warnings: {:?}", reader.warnings());
+}
+
+// ── Analysis ───────────────────────────────────────────────────────
+
+#[test]
+fn analyze_matches_decoded_samples() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let samples = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let channels = reader.info().channels as usize;
+    let full_scale = (1u64 << (reader.info().bits_per_sample - 1)) as f64;
+
+    let analysis = reader.analyze().unwrap();
+    assert_eq!(analysis.blocks, (samples.len() / channels) as u64);
+    assert_eq!(analysis.channels.len(), channels);
+    for (ch, levels) in analysis.channels.iter().enumerate() {
+        let channel: Vec<i64> = samples.iter().skip(ch).step_by(channels).map(|&s| s as i64).collect();
+        let n = channel.len() as f64;
+        let peak = channel.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        let rms = (channel.iter().map(|s| (s * s) as f64).sum::<f64>() / n).sqrt() / full_scale;
+        let dc = channel.iter().sum::<i64>() as f64 / n / full_scale;
+        assert_eq!(levels.peak as u64, peak);
+        assert!((levels.rms - rms).abs() < 1e-9, "ch{ch} rms {} vs {rms}", levels.rms);
+        assert!((levels.dc_offset - dc).abs() < 1e-9, "ch{ch} dc {} vs {dc}", levels.dc_offset);
+        assert!(levels.peak_dbfs() <= 0.0 && levels.rms_dbfs() < levels.peak_dbfs());
+    }
+}
+
+#[test]
+fn waveform_levels_match_decoded_samples() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let samples = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let channels = reader.info().channels as usize;
+    let blocks = samples.len() / channels;
+
+    let waveform = reader.waveform(&[1000, 44100], true).unwrap();
+    assert_eq!(waveform.levels.len(), 2);
+    for level in &waveform.levels {
+        let size = level.blocks_per_bucket as usize;
+        assert_eq!(level.channels.len(), channels);
+        for (ch, peaks) in level.channels.iter().enumerate() {
+            assert_eq!(peaks.len(), blocks.div_ceil(size));
+            let channel: Vec<i32> = samples.iter().skip(ch).step_by(channels).copied().collect();
+            for (peak, bucket) in peaks.iter().zip(channel.chunks(size)) {
+                assert_eq!(peak.min, *bucket.iter().min().unwrap());
+                assert_eq!(peak.max, *bucket.iter().max().unwrap());
+                assert!(peak.rms.is_some_and(|r| (0.0..=1.0).contains(&r)));
+            }
+        }
+    }
+}
+
+#[test]
+fn silence_detector_finds_gaps() {
+    use ape_rs::silence::SilenceDetector;
+    use std::time::Duration;
+
+    // 1 kHz stereo: 1 s tone, 0.5 s near-silence, 1 s tone, 0.1 s silence, 1 s of quiet hiss
+    let rate = 1000;
+    let tone = |n: usize| std::iter::repeat_n([8000, -8000], n).flatten();
+    let samples: Vec<i32> = tone(1000)
+        .chain(std::iter::repeat_n([3, -2], 500).flatten())
+        .chain(tone(1000))
+        .chain(std::iter::repeat_n(0, 200))
+        .chain(std::iter::repeat_n([20, -20], 1000).flatten())
+        .collect();
+
+    let mut detector = SilenceDetector::new(2, 16, rate, -60.0, Duration::from_millis(200));
+    // Split mid-block to exercise channel tracking across chunks
+    let (a, b) = samples.split_at(1001);
+    detector.update(a);
+    detector.update(b);
+    // -60 dBFS is 32 at 16 bits: the hiss counts as silence, the short gap is too short
+    assert_eq!(detector.finish(), vec![1000..1500, 2500..3600]);
+}
+
+#[test]
+fn clipping_detector_reports_full_scale_runs() {
+    use ape_rs::clipping::{ClipRun, ClippingDetector};
+
+    // Stereo 16-bit: left clips for 4 samples at block 2, right touches
+    // full scale once (too short to count), then left clips negative at the end
+    let samples = [
+        0, 0, 100, 0, 32767, 0, 32767, -32768, 32767, 0, 32767, 0, 10, 0, -32768, 5, -32768, 5,
+    ];
+    let mut detector = ClippingDetector::new(2, 16, 2, true);
+    let (a, b) = samples.split_at(7);
+    detector.update(a);
+    detector.update(b);
+    let report = detector.finish();
+
+    assert_eq!(
+        report.runs,
+        [
+            ClipRun { channel: 0, start: 2, len: 4 },
+            ClipRun { channel: 0, start: 7, len: 2 },
+        ]
+    );
+    assert_eq!(report.clipped_samples, [6, 0]);
+    let true_peak = report.true_peak.unwrap();
+    assert!(true_peak[0] > 1.0, "expected an inter-sample over, got {}", true_peak[0]);
+    assert!(true_peak[1] >= 1.0);
+}
+
+#[test]
+fn pcm_hasher_matches_known_vectors() {
+    use ape_rs::digest::{HashAlgorithm, PcmHasher, PcmLayout};
+
+    // 8-bit WAV samples are stored unsigned, so these serialize to "abc"
+    let hash = |algorithm, samples: &[i32]| {
+        let mut hasher = PcmHasher::new(algorithm, 8, PcmLayout::Wav);
+        hasher.update(samples);
+        hasher.finish().to_hex()
+    };
+    let abc = [0x61 - 0x80, 0x62 - 0x80, 0x63 - 0x80];
+    assert_eq!(hash(HashAlgorithm::Md5, &[]), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(hash(HashAlgorithm::Md5, &abc), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(
+        hash(HashAlgorithm::Sha256, &abc),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    // Multi-block message with padding spilling into an extra block
+    let long: Vec<i32> = b"The quick brown fox jumps over the lazy dog. \
+        The quick brown fox jumps over the lazy dog. The quick brown fox jumps, again!"
+        .iter()
+        .map(|&b| b as i32 - 0x80)
+        .collect();
+    assert_eq!(long.len() % 64, 59);
+    assert_eq!(hash(HashAlgorithm::Md5, &long), "ffb52b2a0e94d3cfdba346e0e3b4c32f");
+    assert_eq!(
+        hash(HashAlgorithm::Sha256, &long),
+        "ee884ce69771aa7fece7df9a442df6db9f866cd6ab62283d7df971acf1c4eb59"
+    );
+}
+
+#[test]
+fn pcm_digest_matches_reference_wav() {
+    use ape_rs::digest::{HashAlgorithm, PcmLayout};
+
+    // First frame of test.ape (mono 16-bit) against hashes of the same
+    // 294912 samples taken from the reference WAV's data chunk
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let digest = |algorithm, layout| {
+        let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+        reader.pcm_digest(algorithm, layout).unwrap().to_hex()
+    };
+    assert_eq!(digest(HashAlgorithm::Md5, PcmLayout::Wav), "a143e141ac7533ed8eef547e768fdb49");
+    assert_eq!(
+        digest(HashAlgorithm::Sha256, PcmLayout::Flac),
+        "ac448dc8e9b6530ca7620b1dffb888b208fedff92a920ed0342d97599cc2aa5e"
+    );
+    assert_eq!(digest(HashAlgorithm::Md5, PcmLayout::Aiff), "49b95e18700b5f826aad75cf65ef2270");
+}
+
+#[cfg(any(feature = "loudness", feature = "chromaprint", feature = "spectral"))]
+fn sine(freq: f64, dbfs: f64, seconds: f64, rate: u32, channels: usize) -> Vec<i32> {
+    let amplitude = 32768.0 * 10f64.powf(dbfs / 20.0);
+    let n = (seconds * rate as f64) as usize;
+    (0..n)
+        .flat_map(|i| {
+            let s = (amplitude * (2.0 * std::f64::consts::PI * freq * i as f64 / rate as f64).sin()).round() as i32;
+            std::iter::repeat_n(s, channels)
+        })
+        .collect()
+}
+
+#[cfg(feature = "loudness")]
+#[test]
+fn loudness_of_reference_sine() {
+    use ape_rs::loudness::LoudnessMeter;
+
+    // EBU Tech 3341 case 1: stereo 1 kHz sine at -23 dBFS reads -23 LUFS
+    for rate in [44100, 48000] {
+        let mut meter = LoudnessMeter::new(2, 16, rate);
+        meter.update(&sine(1000.0, -23.0, 20.0, rate, 2));
+        let loudness = meter.finish();
+        assert!((loudness.integrated_lufs + 23.0).abs() < 0.1, "{rate} Hz: {loudness:?}");
+        assert!(loudness.loudness_range_lu.abs() < 0.1, "{rate} Hz: {loudness:?}");
+        assert!((loudness.true_peak_dbtp() + 23.0).abs() < 0.2, "{rate} Hz: {loudness:?}");
+    }
+}
+
+#[cfg(feature = "loudness")]
+#[test]
+fn loudness_range_of_two_level_signal() {
+    use ape_rs::loudness::LoudnessMeter;
+
+    // EBU Tech 3342 case 1: 20 s at -20 dBFS then 20 s at -30 dBFS, LRA 10 LU
+    let mut meter = LoudnessMeter::new(2, 16, 48000);
+    meter.update(&sine(1000.0, -20.0, 20.0, 48000, 2));
+    meter.update(&sine(1000.0, -30.0, 20.0, 48000, 2));
+    let loudness = meter.finish();
+    assert!((loudness.loudness_range_lu - 10.0).abs() < 0.5, "{loudness:?}");
+}
+
+#[cfg(feature = "loudness")]
+#[test]
+fn loudness_of_silence_is_unmeasurable() {
+    let mut meter = ape_rs::loudness::LoudnessMeter::new(2, 16, 44100);
+    meter.update(&vec![0; 44100 * 2 * 5]);
+    let loudness = meter.finish();
+    assert_eq!(loudness.integrated_lufs, f64::NEG_INFINITY);
+    assert_eq!(loudness.true_peak, 0.0);
+}
+
+#[cfg(feature = "chromaprint")]
+#[test]
+fn fingerprint_encoding_matches_fpcalc_format() {
+    use ape_rs::chromaprint::Fingerprint;
+
+    assert_eq!(Fingerprint { raw: vec![1] }.encode(), "AQAAAQE");
+    assert_eq!(Fingerprint { raw: vec![] }.encode(), "AQAAAA");
+}
+
+#[cfg(feature = "chromaprint")]
+#[test]
+fn fingerprinter_is_chunking_invariant() {
+    use ape_rs::chromaprint::Fingerprinter;
+
+    let audio = sine(440.0, -6.0, 30.0, 44100, 2);
+    let mut whole = Fingerprinter::new(2, 16, 44100);
+    whole.update(&audio);
+    let whole = whole.finish();
+
+    let mut chunked = Fingerprinter::new(2, 16, 44100);
+    for chunk in audio.chunks(1001) {
+        chunked.update(chunk);
+    }
+    assert_eq!(chunked.finish(), whole);
+
+    // 4096-sample frames every 1365 samples at 11025 Hz, less the
+    // 5-frame chroma filter and 16-frame classifier warm-up
+    let mut native = Fingerprinter::new(1, 16, 11025);
+    native.update(&sine(440.0, -6.0, 30.0, 11025, 1));
+    assert_eq!(native.finish().raw.len(), (330750 - 4096) / 1365 + 1 - 4 - 15);
+}
+
+#[cfg(feature = "chromaprint")]
+#[test]
+fn fingerprint_of_shorter_duration_is_a_prefix() {
+    // First two frames of test.ape: about 13 s of mono audio
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let fingerprint = |seconds| {
+        let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+        reader.fingerprint(std::time::Duration::from_secs(seconds)).unwrap()
+    };
+    let (short, long) = (fingerprint(4), fingerprint(10));
+    assert!(!short.raw.is_empty());
+    assert!(long.raw.len() > short.raw.len());
+    assert_eq!(long.raw[..short.raw.len()], short.raw[..]);
+}
+
+#[cfg(feature = "spectral")]
+#[test]
+fn spectrogram_locates_sine_and_cutoff() {
+    use ape_rs::spectral::{SpectrumAnalyzer, WindowFunction};
+
+    // 1 kHz sine at -6 dBFS, stereo, fed in odd-sized chunks
+    let audio = sine(1000.0, -6.0, 1.0, 48000, 2);
+    let mut analyzer = SpectrumAnalyzer::new(2, 16, 48000, 1024, 512, WindowFunction::Hann);
+    for chunk in audio.chunks(777) {
+        analyzer.update(chunk);
+    }
+    let spectrogram = analyzer.finish();
+    assert_eq!(spectrogram.frames.len(), (48000 - 1024) / 512 + 1);
+    assert!(spectrogram.frames.iter().all(|f| f.len() == 513));
+
+    let average = spectrogram.average();
+    let peak = (0..average.len()).max_by(|&a, &b| average[a].total_cmp(&average[b])).unwrap();
+    assert!((spectrogram.bin_frequency(peak) - 1000.0).abs() < 48000.0 / 1024.0);
+    assert!((average[peak] + 6.0).abs() < 1.5, "peak level {}", average[peak]);
+
+    let cutoff = spectrogram.cutoff_frequency(-60.0).unwrap();
+    assert!(cutoff > 1000.0 && cutoff < 2000.0, "cutoff {cutoff}");
+
+    // Hops longer than the window skip the blocks in between
+    let mut sparse = SpectrumAnalyzer::new(2, 16, 48000, 1024, 5000, WindowFunction::Hann);
+    for chunk in audio.chunks(777) {
+        sparse.update(chunk);
+    }
+    let sparse = sparse.finish();
+    assert_eq!(sparse.frames.len(), (48000 - 1024) / 5000 + 1);
+    assert_eq!(sparse.frames[2], spectrogram_at(&audio, 10000));
+}
+
+#[cfg(feature = "spectral")]
+fn spectrogram_at(audio: &[i32], block: usize) -> Vec<f32> {
+    use ape_rs::spectral::{SpectrumAnalyzer, WindowFunction};
+
+    let mut analyzer = SpectrumAnalyzer::new(2, 16, 48000, 1024, 1024, WindowFunction::Hann);
+    analyzer.update(&audio[block * 2..(block + 1024) * 2]);
+    analyzer.finish().frames.remove(0)
+}
+
+// ── Packet decoding ────────────────────────────────────────────────
+
+#[test]
+fn compression_levels_follow_features() {
+    use ape_rs::packet::{PacketDecoder, is_supported_level};
+
+    let enabled = [
+        (1000, true),
+        (2000, true),
+        (3000, cfg!(feature = "level-high")),
+        (4000, cfg!(feature = "level-extra-high")),
+        (5000, cfg!(feature = "level-insane")),
+        (2500, false),
+    ];
+    for (level, supported) in enabled {
+        assert_eq!(is_supported_level(level), supported, "level {level}");
+        let result = PacketDecoder::new(2, 16, level);
+        assert_eq!(result.is_ok(), supported, "level {level}");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), ErrorKind::UnsupportedCompressionLevel);
+        }
+    }
+}
+
+#[test]
+fn decode_packet_matches_reader() {
+    use ape_rs::packet::PacketDecoder;
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let info = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().info().clone();
+    let blocks_per_frame = expected.len() / 3;
+
+    let (desc_bytes, header_bytes) = (le32_at(&data, 8) as usize, le32_at(&data, 12) as usize);
+    let offsets: Vec<usize> = (0..3).map(|i| le32_at(&data, desc_bytes + header_bytes + 4 * i) as usize).collect();
+    // Frame 2 of test.ape starts one byte into a word
+    assert_eq!((offsets[2] - offsets[0]) % 4, 1);
+
+    let mut decoder = PacketDecoder::new(info.channels, info.bits_per_sample, info.compression_level).unwrap();
+    // Packets are independent, so decode them out of order
+    for frame in [2, 0, 1] {
+        let skip = ((offsets[frame] - offsets[0]) % 4) as u32;
+        let end = offsets.get(frame + 1).copied().unwrap_or(data.len());
+        let pcm = decoder
+            .decode_packet(&data[offsets[frame] - skip as usize..end], skip, blocks_per_frame as u32)
+            .unwrap();
+        assert_eq!(pcm, expected[frame * blocks_per_frame..(frame + 1) * blocks_per_frame], "frame {frame}");
+    }
+
+    // Without the word's leading byte, the byte order is scrambled
+    let err = decoder
+        .decode_packet(&data[offsets[2]..], 0, blocks_per_frame as u32)
+        .unwrap_err();
+    assert!(err.is_recoverable(), "{err}");
+
+    assert_eq!(PacketDecoder::new(2, 16, 6000).unwrap_err().kind(), ErrorKind::UnsupportedCompressionLevel);
+    assert_eq!(PacketDecoder::new(3, 16, 2000).unwrap_err().kind(), ErrorKind::InvalidHeader);
+}
+
+#[test]
+fn frame_info_matches_seek_table() {
+    if !Path::new(TEST_APE).exists() {
+        eprintln!("Skipping: test file not found at {TEST_APE}");
+        return;
+    }
+    let reader = ApeReader::open(TEST_APE).unwrap();
+    let data = std::fs::read(TEST_APE).unwrap();
+    let frames: Vec<_> = reader.frames().collect();
+    assert_eq!(frames.len(), reader.frame_count() as usize);
+    assert!(reader.frame_info(reader.frame_count()).is_none());
+
+    let seek_table_start = (le32_at(&data, 8) + le32_at(&data, 12)) as usize;
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.index, i as u32);
+        assert_eq!(frame.offset, le32_at(&data, seek_table_start + 4 * i) as u64);
+        assert_eq!(frame.skip as u64, (frame.offset - frames[0].offset) % 4);
+        if let Some(next) = frames.get(i + 1) {
+            assert_eq!(frame.offset + frame.size, next.offset);
+            assert_eq!(frame.first_block + frame.blocks as u64, next.first_block);
+        }
+    }
+    let last = frames.last().unwrap();
+    assert_eq!(
+        (last.first_block + last.blocks as u64) * reader.info().channels as u64,
+        reader.info().total_samples
+    );
+    // The last frame ends with the frame data region, before the terminating bytes
+    let frame_data_bytes = le32_at(&data, 24) as u64;
+    assert_eq!(last.offset + last.size, frames[0].offset + frame_data_bytes);
+}
+
+#[test]
+fn raw_frames_cover_frame_data_and_decode_as_packets() {
+    use ape_rs::packet::PacketDecoder;
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let info = reader.info().clone();
+    let mut decoder = PacketDecoder::new(info.channels, info.bits_per_sample, info.compression_level).unwrap();
+
+    let mut frame_bytes = Vec::new();
+    let mut decoded = Vec::new();
+    for index in 0..reader.frame_count() {
+        let frame = reader.read_raw_frame(index).unwrap().unwrap();
+        assert_eq!(frame.data.len() % 4, 0);
+        assert_eq!(frame.bytes().len() as u64, frame.info.size);
+        frame_bytes.extend_from_slice(frame.bytes());
+        decoded.extend(decoder.decode_packet(&frame.data, frame.info.skip, frame.info.blocks).unwrap());
+    }
+    assert!(reader.read_raw_frame(reader.frame_count()).unwrap().is_none());
+    assert_eq!(decoded, expected);
+
+    let first = reader.frame_info(0).unwrap().offset as usize;
+    assert_eq!(frame_bytes, data[first..first + frame_bytes.len()]);
+    assert_eq!(frame_bytes.len() as u64, le32_at(&data, 24) as u64);
+
+    // Reading raw frames does not disturb decoding
+    let decoded: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn memory_usage_estimate_matches_the_decoder() {
+    use ape_rs::memory::MemoryUsage;
+    use ape_rs::packet::PacketDecoder;
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let info = reader.info().clone();
+    let estimate = MemoryUsage::estimate(reader.header());
+    let packet = PacketDecoder::new(info.channels, info.bits_per_sample, info.compression_level).unwrap();
+    assert_eq!(estimate.filters + estimate.predictor, packet.memory_bytes());
+    let blocks = reader.header().header.blocks_per_frame as usize;
+    assert_eq!(estimate.output, blocks * info.channels as usize * 4);
+    let largest = reader.frames().map(|f| f.size as usize).max().unwrap();
+    assert_eq!((estimate.frame_input, estimate.seek_table), (largest, 8));
+    assert_eq!(estimate.total(), packet.memory_bytes() + estimate.output + largest + 8);
+
+    // An open reader holds the same state, with its buffers already sized
+    let before = reader.memory_footprint();
+    assert_eq!((before.filters, before.predictor, before.output), (estimate.filters, estimate.predictor, estimate.output));
+    assert!(before.frame_input >= largest);
+    reader.samples().next().unwrap().unwrap();
+    let after = reader.memory_footprint();
+    assert!(after.output >= estimate.output && after.total() >= estimate.total());
+
+    // Higher levels need more filter state
+    let fast = PacketDecoder::new(info.channels, 16, 1000).unwrap();
+    assert!(fast.memory_bytes() < packet.memory_bytes());
+}
+
+#[test]
+fn fixed_decoder_reuses_its_buffers() {
+    use ape_rs::fixed::{FixedDecoder, FixedLimits};
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let info = reader.info().clone();
+    let frames: Vec<_> = (0..2).map(|i| reader.read_raw_frame(i).unwrap().unwrap()).collect();
+
+    let limits = FixedLimits {
+        max_blocks: reader.header().header.blocks_per_frame,
+        max_frame_bytes: frames.iter().map(|f| f.data.len()).max().unwrap(),
+        max_compression_level: info.compression_level,
+    };
+    let mut decoder = FixedDecoder::new(info.channels, info.bits_per_sample, info.compression_level, limits).unwrap();
+    let memory = decoder.memory_bytes();
+    assert!(memory >= limits.max_frame_bytes + limits.max_blocks as usize * 4);
+
+    let mut decoded = Vec::new();
+    let mut output_at = None;
+    for frame in &frames {
+        let pcm = decoder.decode(&frame.data, frame.info.skip, frame.info.blocks).unwrap();
+        // Same allocation every frame
+        assert_eq!(*output_at.get_or_insert(pcm.as_ptr()), pcm.as_ptr());
+        decoded.extend_from_slice(pcm);
+    }
+    assert_eq!(decoded, expected);
+    assert_eq!(decoder.memory_bytes(), memory);
+
+    // Anything beyond the limits is refused rather than allocated
+    let frame = frames.iter().max_by_key(|f| f.data.len()).unwrap();
+    let err = decoder.decode(&frame.data, frame.info.skip, limits.max_blocks + 1).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::CapacityExceeded);
+    let oversized = [frame.data.as_slice(), &[0; 4]].concat();
+    let err = decoder.decode(&oversized, frame.info.skip, frame.info.blocks).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::CapacityExceeded);
+    let err = FixedDecoder::new(2, 16, info.compression_level + 1000, limits).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CapacityExceeded);
+}
+
+#[cfg(feature = "internals")]
+#[test]
+fn internals_decode_a_frame_by_hand() {
+    use ape_rs::internals::{NNFilter, Predictor, RangeCoder, RiceState};
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let level = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().info().compression_level;
+
+    let (desc_bytes, header_bytes) = (le32_at(&data, 8) as usize, le32_at(&data, 12) as usize);
+    let mut frame = data[le32_at(&data, desc_bytes + header_bytes) as usize..].to_vec();
+    for word in frame.chunks_exact_mut(4) {
+        word.reverse();
+    }
+    // CRC, frame flags if its top bit is set, then one ignored byte
+    let header_len = if frame[0] & 0x80 != 0 { 9 } else { 5 };
+
+    let mut rc = RangeCoder::new(&frame[header_len..]);
+    let mut rice = RiceState::new();
+    let mut filter = NNFilter::new(level as usize / 1000 - 1);
+    let mut predictor = Predictor::new();
+    let decoded: Vec<i32> = (0..expected.len())
+        .map(|_| predictor.decode_mono(filter.decompress(rc.decode_value(&mut rice))))
+        .collect();
+    rc.check(0).unwrap();
+    assert_eq!(decoded, expected);
+}
+
+/// Run with `RUSTFLAGS="-C target-feature=+simd128"` on a wasm32 target to
+/// check the SIMD kernel; elsewhere both paths are the scalar loop.
+#[cfg(feature = "internals")]
+#[test]
+fn nnfilter_kernel_matches_scalar_loop() {
+    use ape_rs::internals::{dot_and_adapt, dot_and_adapt_scalar};
+
+    let mut seed = 0x2545_f491_u32;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 16) as i16
+    };
+    // Every filter order, plus lengths with a remainder past the last 8 taps
+    for len in [16, 32, 64, 256, 1024, 2048, 13, 7] {
+        for sign in [-1, 0, 1] {
+            for extremes in [false, true] {
+                let mut fill = |n: usize| -> Vec<i16> {
+                    (0..n).map(|i| if extremes { [i16::MIN, i16::MAX][i % 2] } else { next() }).collect()
+                };
+                let (coeffs, delay, adapt) = (fill(len), fill(len), fill(len));
+                let (mut simd, mut scalar) = (coeffs.clone(), coeffs);
+                assert_eq!(
+                    dot_and_adapt(&mut simd, &delay, &adapt, sign),
+                    dot_and_adapt_scalar(&mut scalar, &delay, &adapt, sign),
+                    "{len} taps, sign {sign}"
+                );
+                assert_eq!(simd, scalar, "{len} taps, sign {sign}");
+            }
+        }
+    }
+}
+
+// ── Snapshots ──────────────────────────────────────────────────────
+
+#[test]
+fn snapshot_restores_position_in_new_reader() {
+    use ape_rs::snapshot::DecoderSnapshot;
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let frame_len = expected.len() / 2;
+
+    // Mid-frame, at a frame boundary, at the start and at the end
+    for taken in [frame_len + 12345, frame_len, 0, expected.len()] {
+        let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+        for _ in 0..taken {
+            reader.samples().next().unwrap().unwrap();
+        }
+        let bytes = reader.snapshot().to_bytes();
+
+        let snapshot = DecoderSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.frame() as usize * frame_len + snapshot.sample_in_frame() as usize, taken);
+        let mut resumed = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+        resumed.restore(&snapshot).unwrap();
+        let rest: Vec<i32> = resumed.samples().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rest, expected[taken..], "resumed after {taken} samples");
+    }
+
+    // Wrong stream, corrupt encoding
+    let snapshot = ApeReader::new(std::io::Cursor::new(data)).unwrap().snapshot();
+    let Some(other) = first_frames_of_test_file(1) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(other)).unwrap();
+    assert_eq!(reader.restore(&snapshot).unwrap_err().kind(), ErrorKind::InvalidSnapshot);
+    let mut bytes = snapshot.to_bytes();
+    bytes[7] = 99;
+    assert_eq!(DecoderSnapshot::from_bytes(&bytes).unwrap_err().kind(), ErrorKind::InvalidSnapshot);
+    assert_eq!(DecoderSnapshot::from_bytes(&bytes[..8]).unwrap_err().kind(), ErrorKind::InvalidSnapshot);
+}
+
+// ── Trailing data ──────────────────────────────────────────────────
+
+#[test]
+fn tags_and_terminating_data_are_not_trailing_garbage() {
+    let Some(data) = std::fs::read(TEST_APE).ok() else {
+        eprintln!("Skipping: test file not found at {TEST_APE}");
+        return;
+    };
+    let reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert!(reader.warnings().is_empty(), "unexpected warnings: {:?}", reader.warnings());
+}
+
+#[test]
+fn reports_garbage_between_audio_and_tags() {
+    let Some(clean) = first_frames_of_test_file(1) else { return };
+    let orig = std::fs::read(TEST_APE).unwrap();
+    // test.ape ends in an APEv2 tag (with header) holding its metadata
+    let tag = orig.windows(8).position(|w| w == b"APETAGEX").unwrap();
+
+    let mut data = clean.clone();
+    data.extend_from_slice(&[0xA5; 100]);
+    data.extend_from_slice(&orig[tag..]);
+
+    let mut expected_reader = ApeReader::new(std::io::Cursor::new(clean.clone())).unwrap();
+    let expected: Vec<i32> = expected_reader.samples().collect::<Result<_, _>>().unwrap();
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    match reader.warnings() {
+        [DecodeWarning::TrailingData { offset, len }] => {
+            assert_eq!(*offset, clean.len() as u64);
+            assert_eq!(*len, 100);
+        }
+        other => panic!("expected one TrailingData warning, got {other:?}"),
+    }
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "trailing garbage changed the decoded audio");
+}
+
+#[test]
+fn tag_magic_inside_the_last_frame_is_not_a_tag() {
+    let Some(clean) = first_frames_of_test_file(1) else { return };
+    let mut expected_reader = ApeReader::new(std::io::Cursor::new(clean.clone())).unwrap();
+    let expected: Vec<i32> = expected_reader.samples().collect::<Result<_, _>>().unwrap();
+
+    // Untagged, with "TAG" 128 bytes from the end, inside the declared
+    // frame data
+    let mut data = clean.clone();
+    let mut tail = b"TAG".to_vec();
+    tail.resize(128, 0);
+    data.extend_from_slice(&tail);
+    let frame_data_bytes = le32_at(&data, 24) + tail.len() as u32;
+    put_le32(&mut data, 24, frame_data_bytes);
+
+    let header = ape_rs::parse_header_slice(&data).unwrap();
+    assert_eq!(header.tag_offset, data.len() as u64);
+    assert_eq!(header.frame_data_end(), data.len() as u64);
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert!(reader.warnings().is_empty(), "unexpected warnings: {:?}", reader.warnings());
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "tag magic in the frame data changed the decoded audio");
+}
+
+#[test]
+fn strip_tags_leaves_the_bare_stream() {
+    use ape_rs::strip::{self, StripSummary};
+
+    let Some(clean) = first_frames_of_test_file(1) else { return };
+    let orig = std::fs::read(TEST_APE).unwrap();
+    let ape_tag = &orig[orig.windows(8).position(|w| w == b"APETAGEX").unwrap()..];
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(128, b' ');
+
+    let mut tagged = b"ID3\x04\x00\x00\x00\x00\x00\x15".to_vec();
+    tagged.resize(31, 0);
+    tagged.extend_from_slice(&clean);
+    tagged.extend_from_slice(ape_tag);
+    tagged.extend_from_slice(&id3v1);
+
+    let expected = StripSummary { leading: 31, trailing: ape_tag.len() as u64 + 128, unaccounted: 0 };
+    assert_eq!(strip::inspect(std::io::Cursor::new(&tagged)).unwrap(), expected);
+    let mut out = Vec::new();
+    assert_eq!(strip::strip_tags_to(std::io::Cursor::new(&tagged), &mut out).unwrap(), expected);
+    assert!(out == clean);
+
+    // In place; a second pass finds nothing to do
+    let path = std::env::temp_dir().join(format!("ape-rs-strip-{}.ape", std::process::id()));
+    std::fs::write(&path, &tagged).unwrap();
+    assert_eq!(strip::strip_tags(&path).unwrap().removed(), expected.removed());
+    assert_eq!(strip::strip_tags(&path).unwrap(), StripSummary::default());
+    let stripped = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(stripped == clean);
+
+    // A tag reaching back into the declared terminating data is part of it
+    let mut overlapping = clean.clone();
+    let declared = u32::from_le_bytes(overlapping[32..36].try_into().unwrap());
+    overlapping[32..36].copy_from_slice(&(declared + 10).to_le_bytes());
+    overlapping.extend_from_slice(ape_tag);
+    assert_eq!(strip::inspect(std::io::Cursor::new(&overlapping)).unwrap().trailing, 0);
+
+    // Terminating data running past the end of the stream, into the tags
+    let past_end = declared + ape_tag.len() as u32 + 10;
+    overlapping[32..36].copy_from_slice(&past_end.to_le_bytes());
+    let err = strip::inspect(std::io::Cursor::new(&overlapping)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+}
+
+/// Open `damaged` with layout recovery and check it decodes like `intact`.
+fn assert_recovers_layout(intact: Vec<u8>, damaged: Vec<u8>) {
+    let mut original = ApeReader::new(std::io::Cursor::new(intact)).unwrap();
+    let expected_total = original.info().total_samples;
+    let expected: Vec<i32> = original.samples().collect::<Result<_, _>>().unwrap();
+
+    let mut reader = ApeReaderBuilder::new()
+        .recover_frame_layout(true)
+        .build(std::io::Cursor::new(damaged))
+        .expect("layout recovery failed");
+    assert_eq!(reader.info().total_samples, expected_total);
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "recovered layout decodes differently");
+}
+
+#[test]
+fn recovers_layout_without_frame_count() {
+    // total_frames = 0: frame count and final frame length must be derived
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let mut damaged = intact.clone();
+    let header_start = le32_at(&damaged, 8) as usize;
+    put_le32(&mut damaged, header_start + 12, 0);
+
+    assert_recovers_layout(intact, damaged);
+}
+
+#[test]
+fn recovers_layout_without_seek_table() {
+    // Fold the seek table into header data so data_offset is unchanged
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let mut damaged = intact.clone();
+    let seek_table_bytes = le32_at(&damaged, 16);
+    let header_data_bytes = le32_at(&damaged, 20);
+    put_le32(&mut damaged, 16, 0);
+    put_le32(&mut damaged, 20, header_data_bytes + seek_table_bytes);
+
+    assert!(
+        matches!(ApeReader::new(std::io::Cursor::new(damaged.clone())), Err(ApeError::InvalidHeader(_))),
+        "missing seek table should be rejected without recovery"
+    );
+    // Parsing alone checks the layout too
+    assert_eq!(ape_rs::parse_header_slice(&damaged).unwrap_err().kind(), ErrorKind::InvalidHeader);
+    let err = ape_rs::parse_header(&mut std::io::Cursor::new(&damaged)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+    assert_recovers_layout(intact, damaged);
+}
+
+#[test]
+fn cached_seek_table_replaces_recovery() {
+    use ape_rs::SeekTable;
+
+    // The damaged file from recovers_layout_without_frame_count
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let mut damaged = intact.clone();
+    let header_start = le32_at(&damaged, 8) as usize;
+    put_le32(&mut damaged, header_start + 12, 0);
+
+    let recovered = ApeReaderBuilder::new()
+        .recover_frame_layout(true)
+        .build(std::io::Cursor::new(damaged.clone()))
+        .unwrap();
+    let table = recovered.seek_table();
+    let expected = ApeReader::new(std::io::Cursor::new(intact.clone())).unwrap().seek_table();
+    assert_eq!(table.offsets, expected.offsets);
+
+    let table = SeekTable::from_bytes(&table.to_bytes()).unwrap();
+    let mut reader = ApeReaderBuilder::new()
+        .seek_table(table.clone())
+        .build(std::io::Cursor::new(damaged.clone()))
+        .unwrap();
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == decode_all(intact), "cached layout decodes differently");
+
+    // Tables that cannot belong to the file are rejected
+    let build = |table| ApeReaderBuilder::new().seek_table(table).build(std::io::Cursor::new(damaged.clone()));
+    let mut reversed = table.clone();
+    reversed.offsets.reverse();
+    assert_eq!(build(reversed).err().unwrap().kind(), ErrorKind::InvalidSeekTable);
+    let mut past_end = table.clone();
+    past_end.frame_data_end = damaged.len() as u64 + 1;
+    assert_eq!(build(past_end).err().unwrap().kind(), ErrorKind::InvalidSeekTable);
+    assert_eq!(SeekTable::from_bytes(&table.to_bytes()[..30]).unwrap_err().kind(), ErrorKind::InvalidSeekTable);
+}
+
+// ── Resynchronization ──────────────────────────────────────────────
+
+/// Decode a whole file, panicking on any error.
+fn decode_all(data: Vec<u8>) -> Vec<i32> {
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    reader.samples().collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn resyncs_when_audio_region_is_shifted() {
+    // Insert junk between the header data and frame 0, as a careless tag
+    // editor might: every seek-table entry is now 5 bytes short.
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(intact.clone());
+    let table_pos = le32_at(&intact, 76) as usize;
+    let mut damaged = intact[..table_pos].to_vec();
+    damaged.extend_from_slice(&[0xAA; 5]);
+    damaged.extend_from_slice(&intact[table_pos..]);
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged)).unwrap();
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "resynced decode differs from intact file");
+    // The shifted data also spills 5 bytes past the declared data size
+    match reader.warnings() {
+        [
+            DecodeWarning::TrailingData { len: 5, .. },
+            DecodeWarning::FrameResynced { frame: 0, table_offset, actual_offset },
+        ] => {
+            assert_eq!(*actual_offset, *table_offset + 5);
+        }
+        other => panic!("expected a single resync warning for frame 0, got {other:?}"),
+    }
+}
+
+#[test]
+fn resyncs_past_a_wrong_seek_table_entry() {
+    // Frame 1's entry points 40 bytes too far; frame 0's end gives the truth
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(intact.clone());
+    let mut damaged = intact;
+    let entry = le32_at(&damaged, 80);
+    put_le32(&mut damaged, 80, entry + 40);
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged)).unwrap();
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "resynced decode differs from intact file");
+    assert!(
+        matches!(reader.warnings(), [DecodeWarning::FrameResynced { frame: 1, .. }]),
+        "expected a single resync warning for frame 1, got {:?}",
+        reader.warnings()
+    );
+}
+
+#[test]
+fn seek_table_is_relative_to_leading_id3_tag() {
+    // An odd-sized ID3v2 tag in front shifts the file but not the seek table
+    let Some(intact) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(intact.clone());
+    let body_len = 1001u32;
+    let mut tagged = b"ID3\x04\x00\x00".to_vec();
+    tagged.extend_from_slice(&[
+        (body_len >> 21) as u8 & 0x7F,
+        (body_len >> 14) as u8 & 0x7F,
+        (body_len >> 7) as u8 & 0x7F,
+        body_len as u8 & 0x7F,
+    ]);
+    tagged.resize(10 + body_len as usize, 0);
+    tagged.extend_from_slice(&intact);
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(tagged)).unwrap();
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == expected, "ID3-prefixed decode differs from intact file");
+    assert!(reader.warnings().is_empty(), "unexpected warnings: {:?}", reader.warnings());
+}
+
+// ── Generated streams ──────────────────────────────────────────────
+
+#[cfg(feature = "testgen")]
+fn assert_round_trips(stream: &ape_rs::testgen::TestStream) {
+    let file = stream.encode().unwrap();
+    let mut reader = ApeReader::new(std::io::Cursor::new(file)).unwrap();
+    assert_eq!(reader.info().total_samples, stream.blocks * stream.channels as u64);
+    assert!(reader.verify_file_md5().unwrap() || stream.blocks == 0);
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap_or_else(|e| panic!("{stream:?}: {e}"));
+    assert!(samples == stream.samples(), "{stream:?} decodes differently");
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn generated_formats_round_trip() {
+    for stream in ape_rs::testgen::TestStream::all_formats() {
+        assert_round_trips(&stream);
+    }
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn generated_edge_cases_round_trip() {
+    use ape_rs::testgen::{Signal, TestStream};
+
+    let signals = [
+        Signal::Silence,
+        Signal::Noise { amplitude: 1.0, seed: 7 },
+        Signal::FullScale,
+    ];
+    for compression_level in [1000, 2000] {
+        for signal in signals {
+            for (blocks_per_frame, blocks) in [(1000, 2001), (1000, 1000), (1, 5), (4096, 1), (1000, 0)] {
+                for channels in [1, 2] {
+                    assert_round_trips(&TestStream {
+                        compression_level,
+                        channels,
+                        blocks_per_frame,
+                        blocks,
+                        signal,
+                        ..TestStream::default()
+                    });
+                }
+            }
+        }
+    }
+
+    // No stored WAV header
+    let stream = TestStream {
+        wav_header: false,
+        ..TestStream::default()
+    };
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    assert!(reader.header_data().unwrap().is_empty());
+    assert_round_trips(&stream);
+
+    // Out-of-range input is refused
+    let stream = TestStream {
+        bits_per_sample: 8,
+        ..TestStream::default()
+    };
+    let err = stream.encode_samples(&[0, 128]).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+}
+
+// ── Output conversion ──────────────────────────────────────────────
+
+#[test]
+fn samples_chunks_cross_frame_boundaries() {
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+
+    for size in [7, 1000, 4096, expected.len(), expected.len() + 1] {
+        let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+        let chunks: Vec<Vec<i32>> = reader.samples_chunks(size).collect::<Result<_, _>>().unwrap();
+        assert!(chunks.iter().rev().skip(1).all(|c| c.len() == size));
+        assert!(chunks.concat() == expected, "chunk size {size}");
+    }
+
+    // Lending, after part of a frame was read sample by sample
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let mut all: Vec<i32> = reader.samples().take(5).collect::<Result<_, _>>().unwrap();
+    let mut chunks = reader.samples_chunks(4096);
+    while let Some(chunk) = chunks.next_chunk() {
+        all.extend_from_slice(chunk.unwrap());
+    }
+    assert!(all == expected);
+}
+
+#[test]
+fn decode_into_planar_fills_channel_slices() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let channels = reader.info().channels as usize;
+    let mut planes = vec![vec![0; 10_000]; channels];
+    let err = reader.decode_into_planar(&mut []).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FormatMismatch);
+
+    let mut decoded = vec![Vec::new(); channels];
+    loop {
+        let mut slices: Vec<&mut [i32]> = planes.iter_mut().map(|p| &mut p[..]).collect();
+        let blocks = reader.decode_into_planar(&mut slices).unwrap();
+        if blocks == 0 {
+            break;
+        }
+        for (all, plane) in decoded.iter_mut().zip(&planes) {
+            all.extend_from_slice(&plane[..blocks]);
+        }
+    }
+    for (channel, samples) in decoded.iter().enumerate() {
+        assert!(samples.iter().eq(expected.iter().skip(channel).step_by(channels)));
+    }
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn decode_into_planar_deinterleaves_stereo() {
+    let stream = ape_rs::testgen::TestStream::default();
+    let expected = stream.samples();
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    // Uneven slices: the shorter one sets the block count
+    let (mut left, mut right) = (vec![0; 3000], vec![0; 5000]);
+    let mut blocks = 0;
+    while blocks < stream.blocks as usize {
+        let n = reader.decode_into_planar(&mut [&mut left, &mut right]).unwrap();
+        assert_eq!(n, 3000.min(stream.blocks as usize - blocks));
+        for i in 0..n {
+            assert_eq!([left[i], right[i]], expected[(blocks + i) * 2..][..2]);
+        }
+        blocks += n;
+    }
+    assert_eq!(reader.decode_into_planar(&mut [&mut left, &mut right]).unwrap(), 0);
+}
+
+#[test]
+fn timed_iterators_follow_the_stream_position() {
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let (channels, rate) = (reader.info().channels as u64, reader.info().sample_rate as u64);
+    let frame_samples = reader.frame_info(0).unwrap().blocks as u64 * channels;
+
+    let timed: Vec<(u64, i32)> = reader.timed_samples().collect::<Result<_, _>>().unwrap();
+    assert!(timed.iter().enumerate().all(|(i, &(index, s))| index == i as u64 && s == expected[i]));
+
+    // Across a snapshot restore
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let _: Vec<i32> = reader.samples().take(70_000).collect::<Result<_, _>>().unwrap();
+    let snapshot = reader.snapshot();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    reader.restore(&snapshot).unwrap();
+    assert_eq!(reader.timed_samples().next().unwrap().unwrap(), (70_000, expected[70_000]));
+
+    // Past a dropped frame
+    let mut damaged = data.clone();
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(data)).unwrap();
+    damaged[header.frame_position(1).unwrap() as usize + 100] ^= 0x40;
+    let build = || {
+        ApeReaderBuilder::new()
+            .frame_error_mode(FrameErrorMode::Skip)
+            .build(std::io::Cursor::new(damaged.clone()))
+            .unwrap()
+    };
+    let mut reader = build();
+    let indices: Vec<u64> = reader.timed_samples().map(|t| t.unwrap().0).collect();
+    assert_eq!(indices.len() as u64, expected.len() as u64 - frame_samples);
+    assert_eq!(indices[frame_samples as usize], 2 * frame_samples);
+
+    // Chunk times, including the chunk after the dropped frame
+    let mut reader = build();
+    let chunks: Vec<_> = reader.timed_chunks(frame_samples as usize).collect::<Result<_, _>>().unwrap();
+    let times: Vec<std::time::Duration> = chunks.iter().map(|c| c.0).collect();
+    let at = |samples: u64| std::time::Duration::from_nanos(samples / channels * 1_000_000_000 / rate);
+    assert_eq!(times, [at(0), at(2 * frame_samples)]);
+}
+
+#[test]
+fn decoded_frames_carry_index_position_and_integrity() {
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let infos: Vec<_> = reader.frames().collect();
+    let frames: Vec<_> = reader.decoded_frames().collect::<Result<_, _>>().unwrap();
+    assert_eq!(frames.len(), 3);
+    for (frame, info) in frames.iter().zip(&infos) {
+        assert_eq!((frame.index, frame.first_block, frame.crc_ok), (info.index, info.first_block, true));
+        assert_eq!(frame.samples.len(), info.blocks as usize * reader.info().channels as usize);
+    }
+    assert!(frames.iter().flat_map(|f| f.samples.iter().copied()).eq(expected.iter().copied()));
+
+    // Mid-frame start
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let _: Vec<i32> = reader.samples().take(10).collect::<Result<_, _>>().unwrap();
+    let first = reader.decoded_frames().next().unwrap().unwrap();
+    let channels = reader.info().channels as usize;
+    assert_eq!((first.index, first.first_block), (0, 10 / channels as u64));
+    assert!(first.samples[..] == expected[10..infos[0].blocks as usize * channels]);
+
+    // Damaged frame 1, per frame error mode
+    let mut damaged = data.clone();
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(data)).unwrap();
+    damaged[header.frame_position(1).unwrap() as usize + 100] ^= 0x40;
+    for mode in [FrameErrorMode::Strict, FrameErrorMode::Silence, FrameErrorMode::Skip] {
+        let mut reader =
+            ApeReaderBuilder::new().frame_error_mode(mode).build(std::io::Cursor::new(damaged.clone())).unwrap();
+        let frames: Vec<_> = reader.decoded_frames().collect();
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].as_ref().unwrap().crc_ok && frames[2].as_ref().unwrap().crc_ok);
+        assert_eq!(frames[2].as_ref().unwrap().index, 2);
+        match (mode, &frames[1]) {
+            (FrameErrorMode::Strict, Err(e)) => assert!(e.is_recoverable()),
+            (FrameErrorMode::Silence, Ok(f)) => {
+                assert!(!f.crc_ok && f.samples.len() == infos[1].blocks as usize * channels);
+                assert!(f.samples.iter().all(|&s| s == 0));
+            }
+            (FrameErrorMode::Skip, Ok(f)) => {
+                assert!(!f.crc_ok && f.samples.is_empty());
+                assert_eq!((f.index, f.first_block), (1, infos[1].first_block));
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+}
+
+#[test]
+fn downmixer_averages_without_clipping() {
+    use ape_rs::mix::Downmixer;
+
+    let mut mixer = Downmixer::new(2);
+    let mut out = Vec::new();
+    // Blocks split across chunks, full-scale extremes, odd sums round down
+    mixer.process(&[32767, 32767, -32768], &mut out);
+    mixer.process(&[], &mut out);
+    mixer.process(&[-32768, 3], &mut out);
+    mixer.process(&[-4, 8_388_607, 8_388_607], &mut out);
+    assert_eq!(out, [32767, -32768, -1, 8_388_607]);
+
+    let mut mono = Downmixer::new(1);
+    out.clear();
+    mono.process(&[1, 2, 3], &mut out);
+    assert_eq!(out, [1, 2, 3]);
+
+    // Mono files pass through
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert!(reader.mono_samples().collect::<Result<Vec<_>, _>>().unwrap() == expected);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn mono_samples_downmix_stereo() {
+    use ape_rs::testgen::{Signal, TestStream};
+
+    let stream = TestStream {
+        signal: Signal::FullScale,
+        ..TestStream::default()
+    };
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    let mono: Vec<i32> = reader.mono_samples().collect::<Result<_, _>>().unwrap();
+    let expected: Vec<i32> = stream.samples().chunks(2).map(|b| ape_rs::mix::downmix_block(b[0], b[1])).collect();
+    assert_eq!(mono.len() as u64, stream.blocks);
+    assert!(mono == expected);
+    assert!(mono.iter().all(|&s| (-32768..=32767).contains(&s)));
+}
+
+#[test]
+fn samples_for_channel_extracts_one_channel() {
+    use ape_rs::mix::Channel;
+
+    let channel = |ch| Channel::new((1..=5).map(Ok), 2, ch).map(Result::unwrap).collect::<Vec<i32>>();
+    assert_eq!(channel(0), [1, 3, 5]);
+    assert_eq!(channel(1), [2, 4]);
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let channels = reader.info().channels;
+    assert_eq!(
+        reader.samples_for_channel(channels).err().map(|e| e.kind()),
+        Some(ErrorKind::FormatMismatch)
+    );
+    let last: Vec<i32> = reader.samples_for_channel(channels - 1).unwrap().collect::<Result<_, _>>().unwrap();
+    let step = channels as usize;
+    assert!(last.iter().eq(expected[step - 1..].iter().step_by(step)));
+}
+
+#[test]
+fn requantizer_rounds_dithers_and_applies_gain() {
+    use ape_rs::dither::{DitherOptions, NoiseShaping, Requantizer};
+
+    let ramp: Vec<i32> = (-40_000..40_000).map(|i| i * 200 + 17).collect();
+    let exact = |s: i32| (s as f64 / 256.0).round().clamp(-32768.0, 32767.0);
+    let convert = |options| {
+        let mut samples = ramp.clone();
+        Requantizer::new(2, 24, 16, options).process(&mut samples);
+        samples
+    };
+
+    // Without dither: nearest value
+    let plain = convert(DitherOptions {
+        dither: false,
+        ..DitherOptions::default()
+    });
+    assert!(ramp.iter().zip(&plain).all(|(&s, &q)| q as f64 == exact(s)));
+
+    // TPDF dither stays within 1.5 LSB with no DC offset, and is
+    // reproducible from the seed
+    for shaping in [NoiseShaping::None, NoiseShaping::FirstOrder] {
+        let options = DitherOptions {
+            noise_shaping: shaping,
+            ..DitherOptions::default()
+        };
+        let dithered = convert(options);
+        assert!(dithered == convert(options));
+        let errors: Vec<f64> = ramp.iter().zip(&dithered).map(|(&s, &q)| q as f64 - s as f64 / 256.0).collect();
+        let bound = if shaping == NoiseShaping::None { 1.5 } else { 3.0 };
+        assert!(errors.iter().all(|e| e.abs() <= bound), "{shaping:?}");
+        assert!((errors.iter().sum::<f64>() / errors.len() as f64).abs() < 0.05);
+        assert!(dithered != plain);
+    }
+
+    // -6.02 dB halves, +60 dB clamps
+    let mut half = [32_000, -32_000];
+    let gain = |gain_db| DitherOptions {
+        gain_db,
+        dither: false,
+        ..DitherOptions::default()
+    };
+    Requantizer::new(1, 16, 16, gain(-6.0206)).process(&mut half);
+    assert_eq!(half, [16_000, -16_000]);
+    let mut loud = [1000, -1000];
+    Requantizer::new(1, 16, 16, gain(60.0)).process(&mut loud);
+    assert_eq!(loud, [32767, -32768]);
+
+    // No loss of depth: exact
+    let mut samples = [-128, 0, 127];
+    Requantizer::new(1, 8, 16, DitherOptions::default()).process(&mut samples);
+    assert_eq!(samples, [-32768, 0, 32512]);
+}
+
+#[test]
+fn samples_i16_passes_16_bit_through() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let samples: Vec<i16> = reader.samples_i16().collect::<Result<_, _>>().unwrap();
+    assert!(samples.iter().map(|&s| s as i32).eq(expected));
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn samples_i16_dithers_24_bit() {
+    use ape_rs::testgen::TestStream;
+
+    let stream = TestStream {
+        bits_per_sample: 24,
+        ..TestStream::default()
+    };
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    let samples: Vec<i16> = reader.samples_i16().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples.len(), stream.samples().len());
+    assert!(stream.samples().iter().zip(&samples).all(|(&s, &q)| (q as f64 - s as f64 / 256.0).abs() <= 1.5));
+}
+
+#[test]
+fn sample_conversions_round_trip() {
+    use ape_rs::convert::{self, PcmLayout};
+
+    // Integer <-> float is exact up to 24 bits, and clamps at full scale
+    for bits in [8, 16, 24] {
+        let range = convert::sample_range(bits);
+        let step = if bits == 24 { 97 } else { 1 };
+        for s in range.clone().step_by(step).chain([*range.end()]) {
+            let x = convert::to_f32(s, bits);
+            assert!((-1.0..1.0).contains(&x));
+            assert_eq!(convert::from_f32(x, bits), s);
+            assert_eq!(convert::from_f64(convert::to_f64(s, bits), bits), s);
+        }
+        assert_eq!(convert::from_f64(1.0, bits), *range.end());
+        assert_eq!(convert::from_f64(-2.0, bits), *range.start());
+        assert_eq!(convert::from_f64(f64::NAN, bits), 0);
+    }
+
+    // Widening is exact and narrowing truncates
+    assert_eq!(convert::to_i16(-128, 8), -32768);
+    assert_eq!(convert::to_i24(32767, 16), 32767 << 8);
+    assert_eq!(convert::to_i16(convert::to_i24(-1234, 16), 24), -1234);
+    assert_eq!(convert::to_i16(0x7f_ffff, 24), 32767);
+    assert_eq!(convert::to_i16(-1, 24), -1);
+    assert_eq!(convert::clamp(1 << 40, 16), 32767);
+
+    // Packing and unpacking in every layout and container size
+    let samples = [-8_388_608, -1, 0, 1, 8_388_607];
+    for bits in [8, 16, 24, 32] {
+        let samples: Vec<i32> = samples.iter().map(|&s| convert::rescale(s, 24, bits)).collect();
+        for layout in [PcmLayout::Wav, PcmLayout::Flac, PcmLayout::Aiff] {
+            let n = convert::bytes_per_sample(bits);
+            let mut bytes = Vec::new();
+            convert::pack(&samples, n, layout, &mut bytes);
+            assert_eq!(bytes.len(), samples.len() * n);
+            let mut unpacked = Vec::new();
+            convert::unpack(&bytes, n, layout, &mut unpacked);
+            assert_eq!(unpacked, samples, "{bits} bits, {layout:?}");
+        }
+    }
+    let mut bytes = Vec::new();
+    convert::pack(&[-2, 0x1234], 2, PcmLayout::Aiff, &mut bytes);
+    assert_eq!(bytes, [0xff, 0xfe, 0x12, 0x34]);
+    convert::pack(&[-128], 1, PcmLayout::Wav, &mut bytes);
+    assert_eq!(bytes[4], 0);
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let floats: Vec<f32> = reader.samples_f32().collect::<Result<_, _>>().unwrap();
+    assert!(floats.iter().map(|&x| convert::from_f32(x, 16)).eq(expected));
+}
+
+#[test]
+fn decode_at_most_respects_the_budget() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let channels = reader.info().channels as usize;
+    let mut out = Vec::new();
+    let mut calls = 0;
+    loop {
+        let before = out.len();
+        let blocks = reader.decode_at_most(50_000, &mut out).unwrap();
+        assert_eq!((out.len() - before) / channels, blocks);
+        if blocks == 0 {
+            break;
+        }
+        assert!(blocks <= 50_000);
+        calls += 1;
+    }
+    assert_eq!(calls, (expected.len() / channels).div_ceil(50_000));
+    assert!(out == expected);
+    assert_eq!(reader.decode_at_most(10, &mut out).unwrap(), 0);
+}
+
+#[test]
+fn read_samples_fills_any_slice() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    // A partly read frame is finished first
+    let mut out: Vec<i32> = reader.samples().take(3).collect::<Result<_, _>>().unwrap();
+    let mut buf = [0i32; 100_003];
+    loop {
+        let n = reader.read_samples(&mut buf).unwrap();
+        out.extend_from_slice(&buf[..n]);
+        if n < buf.len() {
+            break;
+        }
+    }
+    assert!(out == expected);
+    assert_eq!(reader.read_samples(&mut buf).unwrap(), 0);
+    assert_eq!(reader.read_samples(&mut []).unwrap(), 0);
+}
+
+#[test]
+fn load_returns_the_whole_stream() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let path = std::env::temp_dir().join(format!("ape-rs-load-{}.ape", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+    let audio = ape_rs::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let audio = audio.unwrap();
+    assert!(audio.samples == expected);
+    assert_eq!(audio.info.total_samples, expected.len() as u64);
+    let blocks = audio.blocks() as u64;
+    let rate = audio.info.sample_rate as u64;
+    assert_eq!(audio.duration.as_nanos(), (blocks * 1_000_000_000 / rate) as u128);
+    let planar = audio.planar();
+    assert_eq!(planar.len(), audio.info.channels as usize);
+    assert!(planar.iter().all(|ch| ch.len() as u64 == blocks));
+    assert_eq!(audio.channel(0).unwrap(), planar[0]);
+    assert!(audio.channel(audio.info.channels).is_none());
+    assert!(ape_rs::load(std::env::temp_dir().join("ape-rs-no-such-file.ape")).is_err());
+}
+
+#[test]
+fn decode_with_pushes_chunks_until_break() {
+    use std::ops::ControlFlow;
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let _: Vec<i32> = reader.samples().take(10).collect::<Result<_, _>>().unwrap();
+    let mut pushed = Vec::new();
+    let done = reader.decode_with(|chunk| -> ControlFlow<()> {
+        pushed.extend_from_slice(chunk);
+        ControlFlow::Continue(())
+    });
+    assert_eq!(done.unwrap(), ControlFlow::Continue(()));
+    assert!(pushed[..] == expected[10..]);
+
+    // Breaking stops after that chunk and returns its value
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let mut chunks = 0;
+    let stopped = reader.decode_with(|chunk| {
+        chunks += 1;
+        if chunks == 2 { ControlFlow::Break(chunk.len()) } else { ControlFlow::Continue(()) }
+    });
+    assert_eq!(stopped.unwrap(), ControlFlow::Break(expected.len() / 3));
+    assert_eq!(reader.samples().count(), expected.len() / 3);
+}
+
+#[test]
+fn left_justified_samples_fill_the_top_bits() {
+    use ape_rs::convert;
+
+    assert_eq!(convert::left_justify(-8_388_608, 24), i32::MIN);
+    assert_eq!(convert::left_justify(8_388_607, 24), 0x7fff_ff00);
+    assert_eq!(convert::left_justify(-1, 16), -65536);
+    assert_eq!(convert::left_justify(127, 8), 0x7f00_0000);
+    assert_eq!(convert::left_justify(-5, 32), -5);
+    let mut samples = [1, -1, 0x7fff];
+    convert::left_justify_in_place(&mut samples, 16);
+    assert_eq!(samples, [1 << 16, -1 << 16, 0x7fff_0000]);
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let justified: Vec<i32> = reader.samples_left_justified().collect::<Result<_, _>>().unwrap();
+    assert!(justified.iter().map(|&s| s >> 16).eq(expected.iter().copied()));
+    assert!(justified.iter().all(|&s| s & 0xffff == 0));
+}
+
+#[cfg(feature = "rubato")]
+#[test]
+fn resampled_stream_keeps_length_and_level() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let input: Vec<f32> = reader.samples_f32().collect::<Result<_, _>>().unwrap();
+    let rms = |x: &[f32]| (x.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / x.len() as f64).sqrt();
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let rate = reader.info().sample_rate as u64;
+    assert_eq!(reader.resampled(0).err().unwrap().kind(), ErrorKind::FormatMismatch);
+    let resampled = reader.resampled(48_000).unwrap();
+    assert_eq!(resampled.sample_rate(), 48_000);
+    let output: Vec<f32> = resampled.collect::<Result<_, _>>().unwrap();
+    assert_eq!(output.len() as u64, (input.len() as u64 * 48_000).div_ceil(rate));
+    assert!((rms(&output) / rms(&input) - 1.0).abs() < 0.02);
+    assert!(reader.samples().next().is_none());
+
+    // No delay: output sample k is the input at time k / 48 kHz
+    let channels = reader.info().channels as usize;
+    let error: Vec<f32> = (1000..output.len() / channels - 1000)
+        .map(|k| {
+            let t = k as f64 * rate as f64 / 48_000.0;
+            let (i, frac) = (t as usize, (t - t.floor()) as f32);
+            let at = |i: usize| input[i * channels];
+            output[k * channels] - (at(i) * (1.0 - frac) + at(i + 1) * frac)
+        })
+        .collect();
+    assert!(rms(&error) < rms(&input) * 0.02);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn pipeline_runs_stages_into_sinks() {
+    use ape_rs::pipeline::{ExtractChannel, PcmFormat, Requantize, ToMono, WavSink};
+    use ape_rs::testgen::TestStream;
+
+    let stream = TestStream { bits_per_sample: 24, ..TestStream::default() };
+    let data = stream.encode().unwrap();
+    let open = || ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+
+    let expected: Vec<i32> = open().mono_samples().collect::<Result<_, _>>().unwrap();
+    let mut mono = Vec::new();
+    let format = open().pipeline().stage(ToMono::new()).run(&mut mono).unwrap();
+    assert_eq!(format, PcmFormat { sample_rate: 44100, channels: 1, bits_per_sample: 24 });
+    assert_eq!(mono, expected);
+
+    let expected: Vec<i32> = open().samples_for_channel(1).unwrap().collect::<Result<_, _>>().unwrap();
+    let mut right = Vec::new();
+    open().pipeline().stage(ExtractChannel::new(1)).run(&mut right).unwrap();
+    assert_eq!(right, expected);
+
+    // Formats are checked before anything is decoded
+    let mut reader = open();
+    let err = reader.pipeline().stage(ExtractChannel::new(2)).run(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FormatMismatch);
+    assert_eq!(reader.samples().count(), stream.samples().len());
+
+    // Requantized to 16 bits into a WAV file
+    let expected: Vec<i16> = open().samples_i16().collect::<Result<_, _>>().unwrap();
+    let mut sink = WavSink::new(std::io::Cursor::new(Vec::new()));
+    let stage = Requantize::new(16, ape_rs::dither::DitherOptions::default());
+    open().pipeline().stage(stage).run(&mut sink).unwrap();
+    let wav = sink.into_inner().into_inner();
+    let data_len = expected.len() as u32 * 2;
+    assert_eq!(wav.len(), 44 + data_len as usize);
+    assert_eq!(&wav[4..8], &(36 + data_len).to_le_bytes());
+    assert_eq!(&wav[22..24], &2u16.to_le_bytes());
+    assert_eq!(&wav[34..36], &16u16.to_le_bytes());
+    assert_eq!(&wav[40..44], &data_len.to_le_bytes());
+    let written: Vec<i16> = wav[44..].chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+    assert_eq!(written, expected);
+
+    // An odd-sized data chunk is padded, after whatever the writer held
+    let odd = TestStream { bits_per_sample: 24, blocks: 10_001, ..TestStream::default() };
+    let expected: Vec<i32> = ApeReader::new(std::io::Cursor::new(odd.encode().unwrap()))
+        .unwrap()
+        .mono_samples()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let mut out = std::io::Cursor::new(b"prefix".to_vec());
+    out.set_position(6);
+    let mut sink = WavSink::new(out);
+    let mut reader = ApeReader::new(std::io::Cursor::new(odd.encode().unwrap())).unwrap();
+    reader.pipeline().stage(ToMono::new()).run(&mut sink).unwrap();
+    let file = sink.into_inner().into_inner();
+    let wav = &file[6..];
+    let data_len = 3 * 10_001u32;
+    assert_eq!(wav.len(), 44 + data_len as usize + 1);
+    assert_eq!((&wav[4..8], &wav[40..44]), (&(36 + data_len + 1).to_le_bytes()[..], &data_len.to_le_bytes()[..]));
+    assert_eq!(wav.last(), Some(&0));
+    let mut parsed = ape_rs::wav_reader::WavReader::new(wav).unwrap();
+    assert_eq!(parsed.read_all_samples().unwrap(), expected);
+    assert!(parsed.into_trailer().unwrap() == [0]);
+}
+
+#[cfg(feature = "rubato")]
+#[test]
+fn pipeline_resample_matches_resampled() {
+    use ape_rs::pipeline::Resample;
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let expected: Vec<i32> = reader
+        .resampled(48_000)
+        .unwrap()
+        .map(|s| ape_rs::convert::from_f32(s.unwrap(), 16))
+        .collect();
+
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let mut output = Vec::new();
+    let format = reader.pipeline().stage(Resample::new(48_000)).run(&mut output).unwrap();
+    assert_eq!(format.sample_rate, 48_000);
+    assert_eq!(output, expected);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn gapless_metadata_round_trips_and_trims() {
+    use ape_rs::gapless::{Gapless, apev2_text_items};
+    use ape_rs::testgen::TestStream;
+
+    let itunes = " 00000000 00000840 000001CA 00000000003F1F36 00000000 00000000";
+    assert_eq!(Gapless::parse_itunsmpb(itunes), Some(Gapless { delay: 0x840, padding: 0x1ca }));
+    assert_eq!(Gapless::parse_itunsmpb(" 00000000 0000084"), None);
+    let gapless = Gapless { delay: 1000, padding: 500 };
+    assert_eq!(Gapless::parse_itunsmpb(&gapless.to_itunsmpb(10_000)), Some(gapless));
+    assert!(gapless.to_itunsmpb(10_000).contains(" 0000000000002134 "));
+
+    // The tag is found behind an ID3v1 tag too
+    let mut tags = gapless.apev2_tag(10_000);
+    tags.extend_from_slice(&[b'T', b'A', b'G'].into_iter().chain([0; 125]).collect::<Vec<_>>());
+    assert_eq!(apev2_text_items(&tags), [("iTunSMPB".to_string(), gapless.to_itunsmpb(10_000))]);
+    assert!(apev2_text_items(&tags[..40]).is_empty());
+
+    let stream = TestStream { gapless: Some(gapless), ..TestStream::default() };
+    let data = stream.encode().unwrap();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert_eq!(reader.gapless().unwrap(), Some(gapless));
+    assert_eq!(reader.info().total_samples, 20_000);
+    let mut track = reader.into_gapless().unwrap();
+    assert_eq!(track.gapless(), gapless);
+    assert_eq!(track.info().total_samples, 2 * 8500);
+    let samples: Vec<i32> = track.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == stream.samples()[2 * 1000..2 * 9500]);
+    assert!(track.into_inner().warnings().is_empty());
+
+    // Without an item nothing is trimmed
+    let stream = TestStream::default();
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    assert_eq!(reader.gapless().unwrap(), None);
+    let mut track = reader.into_gapless().unwrap();
+    assert_eq!(track.samples().count(), stream.samples().len());
+
+    // Nor with one claiming more blocks than the stream has
+    let bad = Gapless { delay: 6000, padding: 5000 };
+    let mut data = stream.encode().unwrap();
+    data.extend_from_slice(&bad.apev2_tag(10_000));
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert_eq!(reader.gapless().unwrap(), None);
+}
+
+#[test]
+fn apev2_text_items_ignores_malformed_footers() {
+    use ape_rs::gapless::{Gapless, apev2_text_items};
+
+    let tag = Gapless { delay: 1000, padding: 500 }.apev2_tag(10_000);
+    let footer_at = tag.len() - 32;
+    let with_size = |size: u32, flags: u32| {
+        let mut tag = tag.clone();
+        tag[footer_at + 12..footer_at + 16].copy_from_slice(&size.to_le_bytes());
+        tag[footer_at + 20..footer_at + 24].copy_from_slice(&flags.to_le_bytes());
+        tag
+    };
+    for (size, flags) in [(0, 0), (0, 1 << 31), (31, 1 << 31), (u32::MAX, 0), (tag.len() as u32, 1 << 31)] {
+        assert!(apev2_text_items(&with_size(size, flags)).is_empty(), "size {size}, flags {flags:#x}");
+    }
+    // A footer alone, with no room for the header it claims
+    assert!(apev2_text_items(&tag[footer_at..]).is_empty());
+    assert_eq!(apev2_text_items(&tag).len(), 1);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn frame_index_sidecar_reopens_and_records_damage() {
+    use ape_rs::frame_index::{FrameCheck, FrameIndex};
+    use ape_rs::testgen::TestStream;
+
+    let stream = TestStream { blocks: 4096 * 2 + 100, ..TestStream::default() };
+    let data = stream.encode().unwrap();
+    let mut damaged = data.clone();
+    let header = ape_rs::parse_header_slice(&data).unwrap();
+    damaged[header.frame_position(1).unwrap() as usize + 100] ^= 0x40;
+
+    // Verifying leaves the reader where it was
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged.clone())).unwrap();
+    let first: Vec<i32> = reader.samples().take(10).collect::<Result<_, _>>().unwrap();
+    let index = reader.verified_frame_index().unwrap();
+    let next: Vec<i32> = reader.samples().take(10).collect::<Result<_, _>>().unwrap();
+    assert_eq!([first, next].concat(), stream.samples()[..20]);
+    let checks: Vec<FrameCheck> = index.frames.iter().map(|f| f.check).collect();
+    assert_eq!(checks, [FrameCheck::Ok, FrameCheck::Damaged, FrameCheck::Ok]);
+    assert_eq!(index.damaged_frames().collect::<Vec<_>>(), [1]);
+    assert_eq!(index.frame_check(2), Some(FrameCheck::Ok));
+    assert_eq!(index.frame_check(3), None);
+    assert!(!index.is_verified());
+    assert_eq!(index.frames[2].first_block, 2 * 4096);
+    assert_eq!(index.frames[2].offset, header.frame_position(2).unwrap());
+
+    // Through a sidecar file, and open without parsing the header
+    let path = std::env::temp_dir().join(format!("ape-rs-index-{}.ape", std::process::id()));
+    let sidecar = FrameIndex::sidecar_path(&path);
+    assert!(sidecar.to_string_lossy().ends_with(".ape.apeidx"));
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let index = reader.verified_frame_index().unwrap();
+    assert!(index.is_verified());
+    index.save(&sidecar).unwrap();
+    let loaded = FrameIndex::load(&sidecar).unwrap();
+    std::fs::remove_file(&sidecar).unwrap();
+    assert_eq!(loaded.to_bytes(), index.to_bytes());
+    assert_eq!(loaded.frames, index.frames);
+    let mut reader = ApeReaderBuilder::new().build_with_index(std::io::Cursor::new(data.clone()), &loaded).unwrap();
+    assert_eq!(reader.info().to_string(), ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().info().to_string());
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(samples == stream.samples());
+
+    // An index does not open another file, nor decode from damaged bytes
+    let other = TestStream::default().encode().unwrap();
+    let err = ApeReaderBuilder::new().build_with_index(std::io::Cursor::new(other), &loaded).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidSeekTable);
+    let bytes = index.to_bytes();
+    assert_eq!(FrameIndex::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err().kind(), ErrorKind::InvalidSeekTable);
+    let mut bytes = index.to_bytes();
+    let last = bytes.len() - 17;
+    bytes[last] ^= 1;
+    assert_eq!(FrameIndex::from_bytes(&bytes).unwrap_err().kind(), ErrorKind::InvalidSeekTable);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn seek_to_time_lands_on_block_boundaries() {
+    use ape_rs::testgen::TestStream;
+    use std::time::Duration;
+
+    let stream = TestStream::default();
+    let expected = stream.samples();
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    reader.samples().take(3).for_each(drop);
+
+    for block in [0u64, 1, 4095, 4096, 4097, 9999] {
+        // The block's start time, truncated to the nanosecond
+        let time = Duration::from_nanos(block * 1_000_000_000 / 44_100);
+        assert_eq!(reader.seek_to_time(time).unwrap(), 2 * block, "block {block}");
+        let next: Vec<i32> = reader.samples().take(5).collect::<Result<_, _>>().unwrap();
+        let at = 2 * block as usize;
+        assert_eq!(next, expected[at..(at + 5).min(expected.len())], "block {block}");
+    }
+
+    // Nearest block: 1.5 s is block 66150, just under it rounds up
+    let mut reader = ApeReader::new(std::io::Cursor::new(TestStream { blocks: 70_000, ..stream }.encode().unwrap())).unwrap();
+    assert_eq!(reader.seek_to_time(Duration::from_millis(1500)).unwrap(), 2 * 66_150);
+    assert_eq!(reader.seek_to_time(Duration::from_nanos(1_499_999_990)).unwrap(), 2 * 66_150);
+    assert_eq!(reader.seek_to_time(Duration::from_secs(3600)).unwrap(), 2 * 70_000);
+    assert!(reader.samples().next().is_none());
+    assert_eq!(reader.seek_to_time(Duration::ZERO).unwrap(), 0);
+    assert_eq!(reader.samples().count(), 2 * 70_000);
+}
+
+#[test]
+fn rewind_allows_another_pass() {
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    let first: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(reader.samples().next().is_none());
+    reader.rewind().unwrap();
+    let second: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(first == expected && second == expected);
+
+    // From mid-frame, and through other decoding methods
+    reader.rewind().unwrap();
+    reader.samples().take(1000).for_each(drop);
+    reader.rewind().unwrap();
+    let mut buf = vec![0; expected.len() + 1];
+    assert_eq!(reader.read_samples(&mut buf).unwrap(), expected.len());
+    assert!(buf[..expected.len()] == expected);
+    reader.rewind().unwrap();
+    assert_eq!(reader.decoded_frames().count(), 2);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn seek_to_frame_and_frame_accessors() {
+    use ape_rs::testgen::TestStream;
+
+    let stream = TestStream::default();
+    let expected = stream.samples();
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    assert_eq!((reader.frame_count(), reader.blocks_per_frame(), reader.current_frame()), (3, 4096, 0));
+
+    reader.seek_to_frame(1).unwrap();
+    assert_eq!(reader.current_frame(), 1);
+    let frame: Vec<i32> = reader.samples().take(2 * 4096).collect::<Result<_, _>>().unwrap();
+    assert!(frame == expected[2 * 4096..2 * 8192]);
+    assert_eq!(reader.current_frame(), 2);
+    reader.samples().next().unwrap().unwrap();
+    assert_eq!(reader.current_frame(), 2);
+
+    reader.seek_to_frame(0).unwrap();
+    assert_eq!(reader.samples().count(), expected.len());
+    assert_eq!(reader.current_frame(), 3);
+    reader.seek_to_frame(2).unwrap();
+    assert!(reader.samples().collect::<Result<Vec<_>, _>>().unwrap() == expected[2 * 8192..]);
+    reader.seek_to_frame(99).unwrap();
+    assert_eq!(reader.current_frame(), 3);
+    assert!(reader.samples().next().is_none());
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn playback_position_tracks_samples_read() {
+    use ape_rs::testgen::TestStream;
+    use std::time::Duration;
+
+    let stream = TestStream::default();
+    let mut reader = ApeReader::new(std::io::Cursor::new(stream.encode().unwrap())).unwrap();
+    assert_eq!((reader.sample_position(), reader.time_position()), (0, Duration::ZERO));
+
+    reader.samples().take(2 * 4410 + 1).for_each(drop);
+    assert_eq!(reader.sample_position(), 2 * 4410 + 1);
+    assert_eq!(reader.time_position(), Duration::from_millis(100));
+
+    // Across frame boundaries and seeks
+    let mut buf = vec![0; 2 * 5000];
+    reader.read_samples(&mut buf).unwrap();
+    assert_eq!(reader.sample_position(), 2 * 9410 + 1);
+    reader.seek_to_time(Duration::from_millis(50)).unwrap();
+    assert_eq!(reader.sample_position(), 2 * 2205);
+    reader.seek_to_frame(2).unwrap();
+    assert_eq!(reader.sample_position(), 2 * 8192);
+
+    reader.samples().for_each(drop);
+    assert_eq!(reader.sample_position(), reader.total_samples());
+    assert_eq!(reader.time_position(), Duration::from_nanos(10_000 * 1_000_000_000 / 44_100));
+    reader.rewind().unwrap();
+    assert_eq!(reader.sample_position(), 0);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn skipping_samples_decodes_only_the_landing_frame() {
+    use ape_rs::testgen::TestStream;
+
+    let stream = TestStream::default();
+    let expected = stream.samples();
+    let data = stream.encode().unwrap();
+    let mut reader = ApeReaderBuilder::new().frame_cache(4).build(std::io::Cursor::new(data)).unwrap();
+
+    // Within the buffered frame, then across a whole frame
+    let mut samples = reader.samples();
+    assert_eq!(samples.next().unwrap().unwrap(), expected[0]);
+    assert_eq!(samples.skip_samples(99).unwrap(), 99);
+    assert_eq!(samples.next().unwrap().unwrap(), expected[100]);
+    assert_eq!(samples.nth(2 * 8192).unwrap().unwrap(), expected[2 * 8192 + 101]);
+    assert_eq!(reader.frame_cache_stats().unwrap().misses, 2);
+    assert_eq!(reader.sample_position(), 2 * 8192 + 102);
+
+    // `skip` goes through `nth`
+    reader.rewind().unwrap();
+    let tail: Vec<i32> = reader.samples().skip(2 * 8192 + 7).collect::<Result<_, _>>().unwrap();
+    assert!(tail == expected[2 * 8192 + 7..]);
+
+    // Frame-aligned skips decode nothing until read
+    reader.rewind().unwrap();
+    let misses = reader.frame_cache_stats().unwrap().misses;
+    assert_eq!(reader.samples().skip_samples(8192).unwrap(), 8192);
+    assert_eq!(reader.frame_cache_stats().unwrap().misses, misses);
+    assert_eq!(reader.current_frame(), 1);
+
+    // Past the end
+    assert_eq!(reader.samples().skip_samples(u64::MAX).unwrap(), expected.len() as u64 - 8192);
+    assert_eq!(reader.samples().skip_samples(5).unwrap(), 0);
+    assert!(reader.samples().next().is_none());
+    reader.rewind().unwrap();
+    assert!(reader.samples().nth(expected.len()).is_none());
+    reader.rewind().unwrap();
+    assert_eq!(reader.samples().nth(expected.len() - 1).unwrap().unwrap(), expected[expected.len() - 1]);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn samples_size_hint_bounds_what_is_yielded() {
+    use ape_rs::testgen::TestStream;
+
+    let stream = TestStream::default();
+    let total = stream.samples().len();
+    let data = stream.encode().unwrap();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+
+    let mut samples = reader.samples();
+    assert_eq!(samples.size_hint(), (total, Some(total)));
+    samples.next().unwrap().unwrap();
+    assert_eq!(samples.size_hint(), (total - 1, Some(total - 1)));
+    samples.skip_samples(8191).unwrap();
+    assert_eq!(samples.size_hint(), (total - 8192, Some(total - 8192)));
+    assert_eq!(samples.by_ref().count(), total - 8192);
+    assert_eq!(samples.size_hint(), (0, Some(0)));
+    assert!(samples.next().is_none());
+
+    // Size hints before each item, and the items themselves
+    fn hinted(samples: &mut ape_rs::ApeSamples<'_, std::io::Cursor<Vec<u8>>>) -> Vec<((usize, Option<usize>), bool)> {
+        let mut out = Vec::new();
+        loop {
+            let hint = samples.size_hint();
+            let Some(item) = samples.next() else { break };
+            out.push((hint, item.is_ok()));
+        }
+        assert_eq!(samples.size_hint(), (0, Some(0)));
+        out
+    }
+    let header = ape_rs::parse_header_slice(&data).unwrap();
+    let mut damaged = data;
+    damaged[header.frame_position(1).unwrap() as usize + 100] ^= 0x40;
+    let open = |mode| {
+        ApeReaderBuilder::new()
+            .frame_error_mode(mode)
+            .build(std::io::Cursor::new(damaged.clone()))
+            .unwrap()
+    };
+
+    // Concealed frames keep the hint exact
+    let items = hinted(&mut open(FrameErrorMode::Silence).samples());
+    assert_eq!(items.len(), total);
+    for (i, ((lower, upper), ok)) in items.into_iter().enumerate() {
+        assert!(ok && lower == total - i && upper == Some(lower), "item {i}: hint ({lower}, {upper:?})");
+    }
+
+    // An error takes the place of the damaged frame; the hint is exact
+    // again after it
+    let items = hinted(&mut open(FrameErrorMode::Strict).samples());
+    let error = items.iter().position(|(_, ok)| !ok).unwrap();
+    assert_eq!(error, 8192);
+    assert_eq!(items.len(), total - 8192 + 1);
+    for (i, ((lower, upper), _)) in items.iter().enumerate().skip(error + 1) {
+        assert!(*lower == items.len() - i && *upper == Some(*lower), "item {i}: hint ({lower}, {upper:?})");
+    }
+
+    // A dropped frame yields fewer samples than the upper bound
+    let items = hinted(&mut open(FrameErrorMode::Skip).samples());
+    assert_eq!(items.len(), total - 8192);
+    for (i, ((lower, upper), ok)) in items.iter().enumerate() {
+        let rest = items.len() - i;
+        assert!(*ok && *lower <= rest && rest <= upper.unwrap(), "item {i}: {rest} left, hint ({lower}, {upper:?})");
+    }
+}
+
+// ── Shared readers ─────────────────────────────────────────────────
+
+#[test]
+fn shared_reader_cursors_decode_concurrently() {
+    use ape_rs::shared::SharedApeReader;
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let file = SharedApeReader::new(data).unwrap();
+    assert_eq!(file.info().total_samples, expected.len() as u64);
+
+    let starts = [0u64, 1000, 80_000, expected.len() as u64];
+    std::thread::scope(|s| {
+        for start in starts {
+            let (file, expected) = (file.clone(), &expected);
+            s.spawn(move || {
+                let mut reader = file.cursor_at(start).unwrap();
+                let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+                assert!(samples == expected[start as usize..], "cursor at block {start}");
+            });
+        }
+    });
+
+    // Cursors are independent of each other
+    let mut a = file.cursor().unwrap();
+    let mut b = file.cursor_with(ApeReaderBuilder::new().frame_error_mode(FrameErrorMode::Skip)).unwrap();
+    let first: Vec<i32> = a.samples().take(10).collect::<Result<_, _>>().unwrap();
+    let all: Vec<i32> = b.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(first, expected[..10]);
+    assert_eq!(all, expected);
+}
+
+#[test]
+fn decode_next_frame_into_reuses_caller_buffer() {
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+
+    // The rest of a partly consumed frame comes first
+    let head: Vec<i32> = reader.samples().take(5).collect::<Result<_, _>>().unwrap();
+    let mut out = Vec::with_capacity(expected.len());
+    let ptr = out.as_ptr();
+    let mut all = head;
+    while reader.decode_next_frame_into(&mut out).unwrap() {
+        assert!(!out.is_empty());
+        assert_eq!(out.as_ptr(), ptr, "buffer was reallocated");
+        all.extend_from_slice(&out);
+    }
+    assert!(out.is_empty());
+    assert_eq!(all, expected);
+    assert!(!reader.decode_next_frame_into(&mut out).unwrap());
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn open_mmap_decodes_mapped_file() {
+    use ape_rs::shared::SharedApeReader;
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let path = std::env::temp_dir().join(format!("ape-rs-mmap-{}.ape", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+
+    let mut reader = ApeReader::open_mmap(&path).unwrap();
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, expected);
+
+    let shared = SharedApeReader::open_mmap(&path).unwrap();
+    let samples: Vec<i32> = shared.cursor_at(100).unwrap().samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, expected[100..]);
+    drop((reader, shared));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn block_positions_map_to_bytes() {
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let header = reader.header();
+    let bpf = header.header.blocks_per_frame as u64;
+    let total = header.total_blocks();
+    let frames: Vec<_> = reader.frames().collect();
+
+    assert_eq!(header.block_byte_offset(0), Some(frames[0].offset));
+    assert_eq!(header.block_byte_offset(bpf), Some(frames[1].offset));
+    let mid = header.block_byte_offset(bpf / 2).unwrap();
+    assert!(frames[0].offset < mid && mid < frames[1].offset);
+    assert_eq!(header.block_byte_offset(total), Some(header.frame_data_end()));
+    assert_eq!(header.block_byte_offset(total + 1), None);
+
+    let frame = reader.frame_at_block(bpf + 10).unwrap();
+    assert_eq!((frame.index, frame.first_block), (1, bpf));
+    assert!(reader.frame_at_block(total).is_none());
+
+    assert_eq!(header.decodable_blocks(0), 0);
+    assert_eq!(header.decodable_blocks(frames[1].offset - 8), 0);
+    assert_eq!(header.decodable_blocks(frames[1].offset + 4), bpf);
+    assert_eq!(header.decodable_blocks(data.len() as u64), total);
+}
+
+#[test]
+fn crc_module_matches_stored_frame_crc() {
+    use ape_rs::crc;
+
+    assert_eq!(crc::crc32(b""), 0);
+    assert_eq!(crc::crc32(b"The quick brown fox jumps over the lazy dog"), 0x414F_A339);
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let samples = decode_all(data.clone());
+    let reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let stored = le32_at(&data, reader.header().data_offset as usize) & 0x7FFF_FFFF;
+    assert_eq!(crc::frame_crc(&samples, 16), stored);
+}
+
+#[test]
+fn error_codes_are_stable() {
+    assert_eq!(ErrorKind::from_code(0), None);
+    assert_eq!(ErrorKind::from_code(18), None);
+    for code in 1..=17 {
+        let kind = ErrorKind::from_code(code).unwrap();
+        assert_eq!(kind.code(), code);
+    }
+    assert_eq!(ErrorKind::InvalidMagic.code(), 1);
+    assert_eq!(ErrorKind::CrcMismatch.code(), 6);
+    assert_eq!(ErrorKind::Md5Mismatch.code(), 16);
+    assert_eq!(ErrorKind::ArithmeticOverflow.code(), 17);
+
+    // Errors report their kind's code
+    let err = ApeReader::new(std::io::Cursor::new(b"RIFF....".to_vec())).err().unwrap();
+    assert_eq!(err.code(), ErrorKind::InvalidMagic.code());
+}
+
+#[test]
+fn reader_accessors_match_info() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    let info = reader.info();
+    assert_eq!(
+        (reader.sample_rate(), reader.channels(), reader.bits_per_sample()),
+        (info.sample_rate, info.channels, info.bits_per_sample)
+    );
+    assert_eq!((reader.total_samples(), reader.compression_level()), (info.total_samples, info.compression_level));
+    assert_eq!((reader.sample_rate(), reader.channels(), reader.compression_level()), (44100, 1, 4000));
+}
+
+#[test]
+fn errors_clone_and_compare() {
+    let err = ApeReader::new(std::io::Cursor::new(b"RIFF....".to_vec())).err().unwrap();
+    assert_eq!(err.clone(), err);
+    assert_eq!(err, ApeError::InvalidMagic);
+    assert_ne!(err, ApeError::InvalidSeekTable);
+
+    // I/O errors keep their kind and message
+    let err = ApeReader::open("tests/data/no-such-file.ape").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(err.io_kind(), Some(std::io::ErrorKind::NotFound));
+    let ApeError::Io(io) = err.clone() else { panic!("{err:?}") };
+    assert_eq!(std::io::Error::from(io.clone()).kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().contains(&io.message));
+    assert_eq!(ApeError::InvalidMagic.io_kind(), None);
+
+    // and cross threads
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || tx.send(err).unwrap());
+    assert_eq!(rx.recv().unwrap().io_kind(), Some(std::io::ErrorKind::NotFound));
+}
+
+// ── Parallel decoding ──────────────────────────────────────────────
+
+#[test]
+fn decode_many_reports_every_file() {
+    use ape_rs::parallel::{BatchOptions, decode_many};
+
+    let Some(data) = first_frames_of_test_file(2) else { return };
+    let expected = decode_all(data.clone());
+    let dir = std::env::temp_dir().join(format!("ape-rs-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut paths = Vec::new();
+    for i in 0..5 {
+        let path = dir.join(format!("{i}.ape"));
+        std::fs::write(&path, if i == 3 { &b"not an ape file"[..] } else { &data[..] }).unwrap();
+        paths.push(path);
+    }
+    paths.push(dir.join("missing.ape"));
+
+    let options = BatchOptions {
+        parallelism: 2.into(),
+        ..BatchOptions::default()
+    };
+    let mut seen = vec![false; paths.len()];
+    decode_many(&paths, &options, |item| {
+        assert_eq!(item.path, paths[item.index]);
+        assert!(!std::mem::replace(&mut seen[item.index], true));
+        match item.index {
+            3 => assert_eq!(item.result.unwrap_err().kind(), ErrorKind::InvalidMagic),
+            5 => assert_eq!(item.result.unwrap_err().kind(), ErrorKind::Io),
+            _ => assert!(item.result.unwrap().samples == expected),
+        }
+    });
+    assert!(seen.iter().all(|&s| s));
+
+    // A file without a seek table opens only with layout recovery
+    let mut damaged = data.clone();
+    let (seek_table_bytes, header_data_bytes) = (le32_at(&damaged, 16), le32_at(&damaged, 20));
+    put_le32(&mut damaged, 16, 0);
+    put_le32(&mut damaged, 20, header_data_bytes + seek_table_bytes);
+    std::fs::write(&paths[0], &damaged).unwrap();
+    for recover in [false, true] {
+        let options = BatchOptions {
+            builder: ApeReaderBuilder::new().recover_frame_layout(recover),
+            ..BatchOptions::default()
+        };
+        decode_many(&paths[..1], &options, |item| match recover {
+            false => assert_eq!(item.result.unwrap_err().kind(), ErrorKind::InvalidHeader),
+            true => assert!(item.result.unwrap().samples == expected),
+        });
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parallel_decode_matches_sequential() {
+    let Some(data) = first_frames_of_test_file(4) else { return };
+    let sequential = |data: &[u8], mode| {
+        let mut wav = Vec::new();
+        let mut reader = ApeReaderBuilder::new().frame_error_mode(mode).build(std::io::Cursor::new(data.to_vec())).unwrap();
+        reader.write_wav(&mut wav).unwrap();
+        wav[44..].to_vec()
+    };
+    let parallel = |data: &[u8], mode, threads| {
+        let mut pcm = Vec::new();
+        let mut reader = ApeReaderBuilder::new().frame_error_mode(mode).build(std::io::Cursor::new(data.to_vec())).unwrap();
+        reader.decode_parallel_into(&mut pcm, threads).unwrap();
+        assert!(reader.samples().next().is_none());
+        (pcm, reader.warnings().len())
+    };
+
+    let expected = sequential(&data, FrameErrorMode::Strict);
+    for threads in [0, 1, 3] {
+        assert!(parallel(&data, FrameErrorMode::Strict, threads).0 == expected);
+    }
+
+    // Continues from a partly consumed frame
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let head: Vec<i32> = reader.samples().take(3).collect::<Result<_, _>>().unwrap();
+    let mut pcm: Vec<u8> = head.iter().flat_map(|&s| (s as i16).to_le_bytes()).collect();
+    reader.decode_parallel_into(&mut pcm, 2).unwrap();
+    assert!(pcm == expected);
+
+    // A damaged frame is handled as the sequential decoder handles it
+    let mut damaged = data.clone();
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(data)).unwrap();
+    damaged[header.frame_position(2).unwrap() as usize + 100] ^= 0x40;
+    let expected = sequential(&damaged, FrameErrorMode::Silence);
+    assert_eq!(parallel(&damaged, FrameErrorMode::Silence, 2), (expected, 1));
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged)).unwrap();
+    assert!(reader.decode_parallel_into(std::io::sink(), 2).is_err());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_apis_run_on_a_rayon_pool() {
+    use ape_rs::parallel::{BatchOptions, Parallelism, decode_many};
+    use std::sync::Arc;
+
+    let Some(data) = first_frames_of_test_file(3) else { return };
+    let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+    let mut wav = Vec::new();
+    ApeReader::new(std::io::Cursor::new(data.clone())).unwrap().write_wav(&mut wav).unwrap();
+
+    let mut pcm = Vec::new();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    reader.decode_parallel_into(&mut pcm, Arc::clone(&pool)).unwrap();
+    assert!(pcm[..] == wav[44..]);
+
+    // Called from inside the pool, which then has one thread to spare
+    let mut pcm = Vec::new();
+    pool.install(|| {
+        let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+        reader.decode_parallel_into(&mut pcm, Parallelism::Rayon(Arc::clone(&pool))).unwrap();
+    });
+    assert!(pcm[..] == wav[44..]);
+
+    let path = std::env::temp_dir().join(format!("ape-rs-rayon-{}.ape", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+    let options = BatchOptions {
+        parallelism: pool.into(),
+        ..BatchOptions::default()
+    };
+    let mut decoded = 0;
+    decode_many(&[&path, &path, &path], &options, |item| {
+        assert!(item.result.unwrap().samples.len() * 2 == wav.len() - 44);
+        decoded += 1;
+    });
+    assert_eq!(decoded, 3);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn reset_for_decodes_the_next_file() {
+    let (Some(one), Some(three)) = (first_frames_of_test_file(1), first_frames_of_test_file(3)) else { return };
+    let mut reader = ApeReaderBuilder::new()
+        .frame_error_mode(FrameErrorMode::Silence)
+        .build(std::io::Cursor::new(one.clone()))
+        .unwrap();
+    let _ = reader.samples().take(10).count();
+
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(three.clone())).unwrap();
+    reader.reset_for(header, std::io::Cursor::new(three.clone())).unwrap();
+    assert_eq!(reader.frame_count(), 3);
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples, decode_all(three));
+    assert_eq!(reader.info().total_samples, samples.len() as u64);
+
+    // The frame error mode carries over
+    let mut damaged = one.clone();
+    let header = ape_rs::parse_header(&mut std::io::Cursor::new(one.clone())).unwrap();
+    damaged[header.data_offset as usize + 100] ^= 0x40;
+    reader.reset_for(header.clone(), std::io::Cursor::new(damaged)).unwrap();
+    let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(samples.len() as u64, header.total_samples());
+    assert_eq!(reader.warnings().len(), 1);
+
+    let mut bad = header;
+    bad.header.channels = 5;
+    assert_eq!(reader.reset_for(bad, std::io::Cursor::new(one)).unwrap_err().kind(), ErrorKind::InvalidHeader);
+}
+
+#[cfg(feature = "strict-math")]
+#[test]
+fn strict_math_reports_predictor_overflow() {
+    use ape_rs::packet::PacketDecoder;
+
+    // A one-block stereo Fast frame whose residuals are both i32::MAX, so
+    // rebuilding the right channel from X and Y overflows.
+    const FRAME: [u8; 24] = [
+        0, 0, 0, 0, 0, 255, 255, 0, 254, 255, 255, 127, 63, 128, 255, 255, 64, 255, 255, 255, 0, 0, 0, 0,
+    ];
+    let mut decoder = PacketDecoder::new(2, 16, 1000).unwrap();
+    let err = decoder.decode_packet(&FRAME, 0, 1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ArithmeticOverflow);
+    assert!(err.is_recoverable());
+}
+
+#[cfg(all(feature = "debug-dump", feature = "testgen"))]
+#[test]
+fn debug_dump_traces_each_frame() {
+    use ape_rs::packet::PacketDecoder;
+    use ape_rs::testgen::{Signal, TestStream};
+
+    let stream = TestStream {
+        compression_level: 3000,
+        signal: Signal::Noise { amplitude: 0.5, seed: 7 },
+        ..TestStream::default()
+    };
+    let data = stream.encode().unwrap();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let frame = reader.read_raw_frame(0).unwrap().unwrap();
+
+    let mut decoder = PacketDecoder::new(2, 16, 3000).unwrap();
+    decoder.decode_packet(&frame.data, frame.info.skip, frame.info.blocks).unwrap();
+    assert!(decoder.take_stats().is_none(), "off by default");
+    decoder.record_stats(true);
+    let pcm = decoder.decode_packet(&frame.data, frame.info.skip, frame.info.blocks).unwrap();
+    assert_eq!(pcm.len(), 2 * 4096);
+    let stats = decoder.take_stats().unwrap();
+    assert_eq!((stats.blocks, stats.decoded, stats.channels.len()), (4096, 4096, 2));
+    for channel in &stats.channels {
+        let blocks: Vec<u32> = channel.trace.iter().map(|p| p.block).collect();
+        assert_eq!(blocks, [0, 4095]);
+        assert!(channel.k_min <= channel.k_max);
+        let last = channel.trace.last().unwrap();
+        assert_eq!(last.filter_avg.len(), 1);
+        assert!(last.filter_avg.iter().all(|&avg| avg > 0), "{last:?}");
+    }
+
+    // A damaged frame is dumped up to where it failed, then the error
+    let mut damaged = data;
+    let offset = reader.header().frame_position(1).unwrap() as usize + 100;
+    damaged[offset] ^= 0x40;
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged)).unwrap();
+    let mut dump = Vec::new();
+    reader.dump_decode_state(&mut dump).unwrap();
+    let dump = String::from_utf8(dump).unwrap();
+    assert!(dump.starts_with("frame 0: 4096 of 4096 blocks decoded\n  Y: k "));
+    assert!(dump.contains("\nframe 1: "));
+    assert!(dump.contains("\n  error: "));
+    assert!(dump.contains("\nframe 2: 1808 of 1808 blocks decoded\n"));
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn frame_cache_serves_repeated_seeks() {
+    use ape_rs::frame_cache::FrameCacheStats;
+
+    let data = ape_rs::testgen::TestStream::default().encode().unwrap();
+    let expected = decode_all(data.clone());
+    let mut reader = ApeReaderBuilder::new().frame_cache(2).build(std::io::Cursor::new(data.clone())).unwrap();
+    let start = reader.snapshot();
+    let mut samples = reader.samples();
+    let head: Vec<i32> = samples.by_ref().take(9000).collect::<Result<_, _>>().unwrap();
+    let mid = reader.snapshot();
+    let rest: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!([head, rest].concat() == expected);
+    assert_eq!(reader.frame_cache_stats(), Some(FrameCacheStats { hits: 0, misses: 3, frames: 2, capacity: 2 }));
+
+    // Frames 1 and 2 come from the cache; frame 0 was evicted
+    for _ in 0..2 {
+        reader.restore(&mid).unwrap();
+        let rest: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+        assert!(rest == expected[9000..]);
+    }
+    assert_eq!(reader.frame_cache_stats().unwrap().hits, 4);
+    reader.restore(&start).unwrap();
+    let all: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert!(all == expected);
+    let stats = reader.frame_cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses), (4, 6));
+
+    // Damaged frames are never cached
+    let mut damaged = data;
+    damaged[reader.header().frame_position(1).unwrap() as usize + 100] ^= 0x40;
+    let mut reader = ApeReaderBuilder::new()
+        .frame_cache(4)
+        .frame_error_mode(FrameErrorMode::Silence)
+        .build(std::io::Cursor::new(damaged))
+        .unwrap();
+    for _ in 0..2 {
+        reader.restore(&start).unwrap();
+        assert_eq!(reader.samples().count(), expected.len());
+    }
+    assert_eq!(reader.frame_cache_stats().unwrap().frames, 2);
+    assert_eq!(reader.warnings().len(), 2);
+
+    reader.set_frame_cache(0);
+    assert_eq!(reader.frame_cache_stats(), None);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn report_sums_up_a_damaged_file() {
+    let stream = ape_rs::testgen::TestStream::default();
+    let data = stream.encode().unwrap();
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    let report = reader.report().unwrap();
+    assert!(report.verification.is_ok());
+    assert_eq!(report.verification.frames, 3);
+    assert_eq!(report.verification.file_md5, Some(true));
+    assert_eq!(report.stats.blocks, 10_000);
+    assert!(report.stats.compression_ratio > 0.0 && report.stats.compression_ratio < 1.0);
+
+    // In Strict mode as in the others, a damaged frame is listed
+    let mut damaged = data;
+    let offset = reader.header().frame_position(1).unwrap() as usize + 100;
+    damaged[offset] ^= 0x40;
+    let mut reader = ApeReader::new(std::io::Cursor::new(damaged)).unwrap();
+    let report = reader.report().unwrap();
+    assert!(!report.verification.is_ok());
+    assert_eq!(report.verification.damaged_frames, [1]);
+    assert_eq!(report.verification.frames, 3);
+    assert_eq!(report.verification.file_md5, Some(false));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["verification"]["damaged_frames"], serde_json::json!([1]));
+        assert_eq!(json["info"]["sample_rate"], 44100);
+        let back: ape_rs::report::Report = serde_json::from_value(json).unwrap();
+        assert_eq!(back.verification, report.verification);
+    }
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn layout_maps_every_byte() {
+    use ape_rs::layout::RegionKind;
+
+    let mut data = ape_rs::testgen::TestStream::default().encode().unwrap();
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(128, 0);
+    data.extend_from_slice(&id3v1);
+    let mut reader = ApeReader::new(std::io::Cursor::new(data.clone())).unwrap();
+    reader.samples().next();
+    let regions = reader.layout().unwrap();
+
+    let kinds: Vec<RegionKind> = regions.iter().map(|r| r.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            RegionKind::Descriptor,
+            RegionKind::Header,
+            RegionKind::SeekTable,
+            RegionKind::HeaderData,
+            RegionKind::Frame(0),
+            RegionKind::Frame(1),
+            RegionKind::Frame(2),
+            RegionKind::Tags,
+        ]
+    );
+    // Contiguous from the first byte to the last
+    assert_eq!(regions[0].offset, 0);
+    assert!(regions.windows(2).all(|w| w[0].end() == w[1].offset));
+    assert_eq!(regions.last().unwrap().end(), data.len() as u64);
+    assert_eq!(regions.last().unwrap().len, 128);
+    for (region, frame) in regions[4..7].iter().zip(reader.frames()) {
+        assert_eq!((region.offset, region.len), (frame.offset, frame.size));
+    }
+    // Reading the layout does not disturb decoding
+    assert_eq!(reader.samples().count() as u64, reader.info().total_samples - 1);
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn sidecars_round_trip_and_verify() {
+    use ape_rs::digest::{HashAlgorithm, PcmHasher, PcmLayout};
+    use ape_rs::sidecar::{self, CheckResult, SidecarFormat};
+    use ape_rs::testgen::TestStream;
+
+    let dir = std::env::temp_dir().join(format!("ape-rs-sidecar-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let streams = [TestStream::default(), TestStream { channels: 1, bits_per_sample: 8, ..TestStream::default() }];
+    let mut paths = Vec::new();
+    for (i, stream) in streams.iter().enumerate() {
+        let path = dir.join(format!("{i:02} track.ape"));
+        std::fs::write(&path, stream.encode().unwrap()).unwrap();
+        paths.push(path);
+    }
+
+    let entries = sidecar::generate(&paths).unwrap();
+    assert_eq!(entries[1].name, "01 track.ape");
+    for (entry, stream) in entries.iter().zip(&streams) {
+        // The same MD5 a FLAC file of the audio would store
+        let mut hasher = PcmHasher::new(HashAlgorithm::Md5, stream.bits_per_sample, PcmLayout::Flac);
+        hasher.update(&stream.samples());
+        assert_eq!(entry.md5[..], hasher.finish().bytes[..]);
+    }
+
+    for format in [SidecarFormat::Ffp, SidecarFormat::Md5] {
+        let mut text = Vec::new();
+        sidecar::write(&entries, format, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(sidecar::parse(&format!("; comment\n\n{text}")), entries);
+        let path = dir.join(format!("album.{}", format.extension()));
+        assert_eq!(SidecarFormat::from_path(&path), Some(format));
+        std::fs::write(&path, text).unwrap();
+        assert!(sidecar::verify(&path).unwrap().iter().all(|c| c.is_ok()));
+    }
+    assert_eq!(sidecar::parse("0123456789ABCDEF0123456789abcdef *x.ape")[0].name, "x.ape");
+
+    // A changed file and a missing one fail
+    std::fs::write(&paths[0], streams[1].encode().unwrap()).unwrap();
+    std::fs::remove_file(&paths[1]).unwrap();
+    let checks = sidecar::verify(dir.join("album.ffp")).unwrap();
+    assert!(matches!(checks[0].result, CheckResult::Mismatch { actual } if actual == entries[1].md5));
+    assert!(matches!(&checks[1].result, CheckResult::Failed(e) if e.kind() == ErrorKind::Io));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "testgen")]
+#[test]
+fn scan_dir_indexes_a_tree() {
+    use ape_rs::parallel::scan_dir;
+    use ape_rs::testgen::TestStream;
+
+    let dir = std::env::temp_dir().join(format!("ape-rs-scan-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("album/disc 2")).unwrap();
+    let stream = TestStream::default();
+    let data = stream.encode().unwrap();
+    std::fs::write(dir.join("album/01.ape"), &data).unwrap();
+    std::fs::write(dir.join("album/disc 2/01.APE"), &data).unwrap();
+    std::fs::write(dir.join("album/cover.jpg"), b"not audio").unwrap();
+    std::fs::write(dir.join("broken.ape"), b"MAC nonsense").unwrap();
+
+    let items = scan_dir(&dir, 2);
+    let names: Vec<_> = items.iter().map(|i| i.path.strip_prefix(&dir).unwrap().to_path_buf()).collect();
+    assert_eq!(
+        names,
+        [Path::new("album/01.ape"), Path::new("album/disc 2/01.APE"), Path::new("broken.ape")]
+    );
+    for item in &items[..2] {
+        let file = item.result.as_ref().unwrap();
+        assert_eq!(file.info.total_samples, stream.blocks * 2);
+        assert_eq!(file.file_size, data.len() as u64);
+        assert_eq!(file.duration, std::time::Duration::from_nanos(10_000 * 1_000_000_000 / 44_100));
+        assert!(file.metadata.is_empty());
+    }
+    assert!(items[2].result.is_err());
+
+    // A missing root is reported, not a panic
+    let items = scan_dir(dir.join("missing"), 0);
+    assert_eq!(items[0].result.as_ref().unwrap_err().kind(), ErrorKind::Io);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn boxed_sources_share_one_reader_type() {
+    use ape_rs::{BoxedApeReader, ReadSeek};
+
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let expected = decode_all(data.clone());
+    let path = std::env::temp_dir().join(format!("ape-rs-boxed-{}.ape", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+    let sources: Vec<Box<dyn ReadSeek + Send>> = vec![
+        Box::new(std::io::Cursor::new(data)),
+        Box::new(std::io::BufReader::new(std::fs::File::open(&path).unwrap())),
+    ];
+    let readers: Vec<BoxedApeReader> = sources.into_iter().map(|s| ApeReader::from_boxed(s).unwrap()).collect();
+    for mut reader in readers {
+        let samples: Vec<i32> = reader.samples().collect::<Result<_, _>>().unwrap();
+        assert_eq!(samples, expected);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn debug_and_display_summarize() {
+    let Some(data) = first_frames_of_test_file(1) else { return };
+    let mut reader = ApeReader::new(std::io::Cursor::new(data)).unwrap();
+    assert_eq!(
+        reader.info().to_string(),
+        "44100 Hz, 16-bit mono, 0:06.687 (294912 blocks), Extra High (4000), format 3.99"
+    );
+
+    let samples = reader.samples();
+    assert!(format!("{samples:?}").starts_with("ApeSamples { sample_index: 0, total_samples: 294912"));
+    reader.samples().next().unwrap().unwrap();
+    let debug = format!("{reader:?}");
+    assert!(debug.starts_with("ApeReader { info: ApeInfo { sample_rate: 44100"), "{debug}");
+    assert!(debug.contains("buffer: SampleBuffer { len: 294912, pos: 1"), "{debug}");
+    assert!(debug.contains("PacketDecoder { channels: 1, bits_per_sample: 16, compression_level: 4000"), "{debug}");
+    // Summaries, not sample dumps
+    assert!(debug.len() < 1000, "{debug}");
 }
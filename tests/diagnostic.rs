@@ -1,3 +1,5 @@
+#![cfg(feature = "std")]
+
 use ape_rs::ApeReader;
 use std::path::Path;
 